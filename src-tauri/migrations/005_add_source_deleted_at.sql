@@ -0,0 +1,6 @@
+-- 文献源软删除
+-- deleted_at 非空表示已移入回收站
+
+ALTER TABLE sources ADD COLUMN deleted_at INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_sources_deleted_at ON sources(deleted_at);
@@ -0,0 +1,6 @@
+-- 卡片置顶
+-- pinned = 1 的卡片在列表中优先显示
+
+ALTER TABLE cards ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX IF NOT EXISTS idx_cards_pinned ON cards(pinned);
@@ -0,0 +1,12 @@
+-- RAG 索引元数据
+-- 记录每个文献源生成现有向量时使用的分块参数和向量模型
+
+CREATE TABLE IF NOT EXISTS source_index_meta (
+    source_id TEXT PRIMARY KEY,
+    chunk_size INTEGER NOT NULL,
+    chunk_overlap INTEGER NOT NULL,
+    embedding_model TEXT NOT NULL,
+    chunk_count INTEGER NOT NULL,
+    indexed_at INTEGER NOT NULL,
+    FOREIGN KEY (source_id) REFERENCES sources(id) ON DELETE CASCADE
+);
@@ -0,0 +1,4 @@
+-- 卡片字数
+-- 写入时根据纯文本预先计算，卡片列表无需加载正文
+
+ALTER TABLE cards ADD COLUMN word_count INTEGER NOT NULL DEFAULT 0;
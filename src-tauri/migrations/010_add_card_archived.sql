@@ -0,0 +1,6 @@
+-- 卡片归档
+-- archived = 1 的卡片默认不出现在卡片列表、图谱和搜索结果中，链接保持不变
+
+ALTER TABLE cards ADD COLUMN archived INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX IF NOT EXISTS idx_cards_archived ON cards(archived);
@@ -0,0 +1,6 @@
+-- 文献源来源（manual / epub / web / pdf / bibtex）
+-- 记录文献源是通过哪种途径加入资料库的，已有文献源统一回填为 manual
+
+ALTER TABLE sources ADD COLUMN source_origin TEXT;
+
+UPDATE sources SET source_origin = 'manual' WHERE source_origin IS NULL;
@@ -0,0 +1,4 @@
+-- 间隔重复复习
+-- review 为 JSON 格式的 SM-2 复习状态（dueAt/interval/ease/reps），NULL 表示未加入复习
+
+ALTER TABLE cards ADD COLUMN review TEXT;
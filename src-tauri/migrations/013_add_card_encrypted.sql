@@ -0,0 +1,4 @@
+-- 卡片加密
+-- encrypted = 1 时 content 为 XChaCha20-Poly1305 密文，plain_text、preview 和 links 留空
+
+ALTER TABLE cards ADD COLUMN encrypted INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,14 @@
+-- 高亮全文检索
+-- trigram 分词支持中文子串匹配；highlight_id 对应 highlights.id，只用于关联不参与检索
+-- 索引由代码在增删改高亮时同步维护，下面的回填语句可重复执行
+
+CREATE VIRTUAL TABLE IF NOT EXISTS highlights_fts USING fts5(
+    highlight_id UNINDEXED,
+    content,
+    note,
+    tokenize = 'trigram'
+);
+
+INSERT INTO highlights_fts (highlight_id, content, note)
+SELECT id, content, COALESCE(note, '') FROM highlights
+WHERE id NOT IN (SELECT highlight_id FROM highlights_fts);
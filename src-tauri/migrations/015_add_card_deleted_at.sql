@@ -0,0 +1,6 @@
+-- 卡片软删除
+-- deleted_at 非空表示已移入回收站，不出现在任何卡片查询中
+
+ALTER TABLE cards ADD COLUMN deleted_at INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_cards_deleted_at ON cards(deleted_at);
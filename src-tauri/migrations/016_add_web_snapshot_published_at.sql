@@ -0,0 +1,3 @@
+-- 网页快照的文章发布时间（毫秒），从 meta 标签或 JSON-LD 中提取
+
+ALTER TABLE web_snapshots ADD COLUMN published_at INTEGER;
@@ -0,0 +1,4 @@
+-- 网页快照的词数（中文按分词计）和预计阅读时间（分钟）
+
+ALTER TABLE web_snapshots ADD COLUMN word_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE web_snapshots ADD COLUMN reading_minutes INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,421 @@
+//! 向量近似最近邻索引（HNSW）
+//! 分层图只访问少量节点即可找到候选，删除采用标记方式，删除过多时由调用方重建
+
+use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// 上层每个节点保留的邻居数（第 0 层为 2 倍）
+const DEFAULT_M: usize = 16;
+
+/// 构建时每层的候选集大小
+const DEFAULT_EF_CONSTRUCTION: usize = 100;
+
+/// 层数上限
+const MAX_LEVEL: usize = 16;
+
+/// 搜索候选：距离越小越近
+#[derive(Debug, Clone, Copy)]
+struct Candidate {
+    dist: f32,
+    node: u32,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.dist
+            .total_cmp(&other.dist)
+            .then(self.node.cmp(&other.node))
+    }
+}
+
+/// HNSW 索引，向量归一化后以余弦距离（1 - 点积）比较
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HnswIndex {
+    dimension: usize,
+    m: usize,
+    ef_construction: usize,
+    /// 节点 -> 分块 ID
+    ids: Vec<String>,
+    vectors: Vec<Vec<f32>>,
+    /// 节点 -> 层 -> 邻居
+    links: Vec<Vec<Vec<u32>>>,
+    deleted: Vec<bool>,
+    entry: Option<u32>,
+    max_level: usize,
+    /// 分块 ID -> 未删除的节点，加载后重建
+    #[serde(skip)]
+    lookup: HashMap<String, u32>,
+}
+
+impl HnswIndex {
+    pub fn new(dimension: usize) -> Self {
+        Self {
+            dimension,
+            m: DEFAULT_M,
+            ef_construction: DEFAULT_EF_CONSTRUCTION,
+            ids: Vec::new(),
+            vectors: Vec::new(),
+            links: Vec::new(),
+            deleted: Vec::new(),
+            entry: None,
+            max_level: 0,
+            lookup: HashMap::new(),
+        }
+    }
+
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    /// 未删除的向量数
+    pub fn len(&self) -> usize {
+        self.lookup.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lookup.is_empty()
+    }
+
+    /// 标记删除的节点多于有效节点时需要重建
+    pub fn needs_rebuild(&self) -> bool {
+        self.ids.len() - self.len() > self.len()
+    }
+
+    /// 插入或替换向量
+    pub fn insert(&mut self, id: &str, vector: &[f32]) -> Result<(), String> {
+        if vector.len() != self.dimension {
+            return Err(format!(
+                "vector dimension {} does not match index dimension {}",
+                vector.len(),
+                self.dimension
+            ));
+        }
+        self.remove(id);
+
+        let node = self.ids.len() as u32;
+        let level = self.random_level();
+        self.ids.push(id.to_string());
+        self.vectors.push(normalize(vector));
+        self.links.push(vec![Vec::new(); level + 1]);
+        self.deleted.push(false);
+        self.lookup.insert(id.to_string(), node);
+
+        let Some(mut entry) = self.entry else {
+            self.entry = Some(node);
+            self.max_level = level;
+            return Ok(());
+        };
+
+        let query = self.vectors[node as usize].clone();
+        for layer in (level + 1..=self.max_level).rev() {
+            entry = self.search_layer(&query, &[entry], 1, layer)[0].node;
+        }
+
+        let mut entries = vec![entry];
+        for layer in (0..=level.min(self.max_level)).rev() {
+            let found = self.search_layer(&query, &entries, self.ef_construction, layer);
+            let neighbors: Vec<u32> = found
+                .iter()
+                .take(self.max_links(layer))
+                .map(|c| c.node)
+                .collect();
+            for &neighbor in &neighbors {
+                self.connect(neighbor, node, layer);
+            }
+            self.links[node as usize][layer] = neighbors;
+            entries = found.into_iter().map(|c| c.node).collect();
+        }
+
+        if level > self.max_level {
+            self.entry = Some(node);
+            self.max_level = level;
+        }
+        Ok(())
+    }
+
+    /// 标记删除，节点仍保留在图中用于导航；不存在时返回 false
+    pub fn remove(&mut self, id: &str) -> bool {
+        match self.lookup.remove(id) {
+            Some(node) => {
+                self.deleted[node as usize] = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 查找最相近的 k 个向量，返回 (分块 ID, 余弦相似度)，按相似度从高到低排列
+    ///
+    /// `ef` 为搜索时的候选集大小，越大越准确也越慢
+    pub fn search(&self, query: &[f32], k: usize, ef: usize) -> Vec<(String, f32)> {
+        if k == 0 || query.len() != self.dimension {
+            return Vec::new();
+        }
+        let Some(mut entry) = self.entry else {
+            return Vec::new();
+        };
+
+        let query = normalize(query);
+        for layer in (1..=self.max_level).rev() {
+            entry = self.search_layer(&query, &[entry], 1, layer)[0].node;
+        }
+        self.search_layer(&query, &[entry], ef.max(k), 0)
+            .into_iter()
+            .filter(|c| !self.deleted[c.node as usize])
+            .take(k)
+            .map(|c| (self.ids[c.node as usize].clone(), 1.0 - c.dist))
+            .collect()
+    }
+
+    /// 写入索引文件（先写临时文件再替换，避免中断后留下半个文件）
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let bytes = bincode::serialize(self).map_err(|e| format!("Failed to serialize index: {}", e))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create index directory: {}", e))?;
+        }
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, bytes).map_err(|e| format!("Failed to write index file: {}", e))?;
+        fs::rename(&tmp, path).map_err(|e| format!("Failed to replace index file: {}", e))
+    }
+
+    /// 读取索引文件
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| format!("Failed to read index file: {}", e))?;
+        let mut index: Self =
+            bincode::deserialize(&bytes).map_err(|e| format!("Invalid index file: {}", e))?;
+        index.lookup = index
+            .ids
+            .iter()
+            .enumerate()
+            .filter(|(node, _)| !index.deleted[*node])
+            .map(|(node, id)| (id.clone(), node as u32))
+            .collect();
+        Ok(index)
+    }
+
+    /// 在单层上做贪心扩展搜索，返回最近的至多 ef 个节点（由近到远）
+    fn search_layer(&self, query: &[f32], entries: &[u32], ef: usize, layer: usize) -> Vec<Candidate> {
+        let mut visited: HashSet<u32> = entries.iter().copied().collect();
+        let mut candidates = BinaryHeap::new();
+        let mut results = BinaryHeap::new();
+        for &node in entries {
+            let candidate = Candidate {
+                dist: self.distance(query, node),
+                node,
+            };
+            candidates.push(Reverse(candidate));
+            results.push(candidate);
+        }
+        while results.len() > ef {
+            results.pop();
+        }
+
+        while let Some(Reverse(current)) = candidates.pop() {
+            let worst = results.peek().map_or(f32::INFINITY, |c| c.dist);
+            if current.dist > worst && results.len() >= ef {
+                break;
+            }
+            for &neighbor in self.neighbors(current.node, layer) {
+                if !visited.insert(neighbor) {
+                    continue;
+                }
+                let dist = self.distance(query, neighbor);
+                let worst = results.peek().map_or(f32::INFINITY, |c| c.dist);
+                if results.len() < ef || dist < worst {
+                    let candidate = Candidate { dist, node: neighbor };
+                    candidates.push(Reverse(candidate));
+                    results.push(candidate);
+                    if results.len() > ef {
+                        results.pop();
+                    }
+                }
+            }
+        }
+
+        results.into_sorted_vec()
+    }
+
+    /// 把 `neighbor` 加入 `node` 的邻居，超出上限时只保留最近的
+    fn connect(&mut self, node: u32, neighbor: u32, layer: usize) {
+        let max_links = self.max_links(layer);
+        let mut links = std::mem::take(&mut self.links[node as usize][layer]);
+        links.push(neighbor);
+        if links.len() > max_links {
+            let base = &self.vectors[node as usize];
+            links.sort_by(|&a, &b| {
+                cosine_distance(base, &self.vectors[a as usize])
+                    .total_cmp(&cosine_distance(base, &self.vectors[b as usize]))
+            });
+            links.truncate(max_links);
+        }
+        self.links[node as usize][layer] = links;
+    }
+
+    fn neighbors(&self, node: u32, layer: usize) -> &[u32] {
+        self.links[node as usize]
+            .get(layer)
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    fn distance(&self, query: &[f32], node: u32) -> f32 {
+        cosine_distance(query, &self.vectors[node as usize])
+    }
+
+    fn max_links(&self, layer: usize) -> usize {
+        if layer == 0 {
+            self.m * 2
+        } else {
+            self.m
+        }
+    }
+
+    /// 按指数分布随机选择节点层数
+    fn random_level(&self) -> usize {
+        let mult = 1.0 / (self.m as f64).ln();
+        let r: f64 = rand::random();
+        ((-(1.0 - r).ln() * mult).floor() as usize).min(MAX_LEVEL)
+    }
+}
+
+/// 归一化向量，零向量原样返回
+fn normalize(vector: &[f32]) -> Vec<f32> {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter().map(|x| x / norm).collect()
+    } else {
+        vector.to_vec()
+    }
+}
+
+/// 已归一化向量之间的余弦距离
+fn cosine_distance(a: &[f32], b: &[f32]) -> f32 {
+    1.0 - a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn random_vectors(count: usize, dimension: usize, seed: u64) -> Vec<Vec<f32>> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..count)
+            .map(|_| (0..dimension).map(|_| rng.gen_range(-1.0..1.0)).collect())
+            .collect()
+    }
+
+    /// 线性扫描得到的精确前 k 个
+    fn exact_top_k(vectors: &[Vec<f32>], query: &[f32], k: usize) -> Vec<String> {
+        let query = normalize(query);
+        let mut scored: Vec<(usize, f32)> = vectors
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (i, cosine_distance(&query, &normalize(v))))
+            .collect();
+        scored.sort_by(|a, b| a.1.total_cmp(&b.1));
+        scored.into_iter().take(k).map(|(i, _)| i.to_string()).collect()
+    }
+
+    fn build(vectors: &[Vec<f32>]) -> HnswIndex {
+        let mut index = HnswIndex::new(vectors[0].len());
+        for (i, vector) in vectors.iter().enumerate() {
+            index.insert(&i.to_string(), vector).unwrap();
+        }
+        index
+    }
+
+    fn recall(index: &HnswIndex, vectors: &[Vec<f32>], queries: &[Vec<f32>], k: usize, ef: usize) -> f64 {
+        let mut hits = 0;
+        for query in queries {
+            let exact: HashSet<String> = exact_top_k(vectors, query, k).into_iter().collect();
+            hits += index
+                .search(query, k, ef)
+                .into_iter()
+                .filter(|(id, _)| exact.contains(id))
+                .count();
+        }
+        hits as f64 / (queries.len() * k) as f64
+    }
+
+    #[test]
+    fn test_search_recall() {
+        let vectors = random_vectors(2000, 32, 1);
+        let queries = random_vectors(50, 32, 2);
+        let index = build(&vectors);
+        assert_eq!(index.len(), 2000);
+        assert!(recall(&index, &vectors, &queries, 10, 64) >= 0.9);
+
+        // 向量自身总是最近的
+        let results = index.search(&vectors[42], 1, 64);
+        assert_eq!(results[0].0, "42");
+        assert!((results[0].1 - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_remove_replace_and_reload() {
+        let vectors = random_vectors(300, 8, 3);
+        let mut index = build(&vectors);
+
+        assert!(index.remove("7"));
+        assert!(!index.remove("7"));
+        assert!(index.search(&vectors[7], 5, 32).iter().all(|(id, _)| id != "7"));
+
+        // 同一 ID 再次插入视为替换
+        index.insert("8", &vectors[9]).unwrap();
+        assert_eq!(index.len(), 299);
+        assert!(index.insert("x", &[1.0, 2.0]).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("index.hnsw");
+        index.save(&path).unwrap();
+        let loaded = HnswIndex::load(&path).unwrap();
+        assert_eq!(loaded.len(), 299);
+        assert_eq!(loaded.dimension(), 8);
+        assert_eq!(loaded.search(&vectors[100], 3, 32), index.search(&vectors[100], 3, 32));
+        assert!(!loaded.needs_rebuild());
+    }
+
+    /// 较大的索引上查询明显快于线性扫描，召回率不下降
+    #[test]
+    fn test_search_faster_than_linear_scan() {
+        use std::time::Instant;
+
+        let vectors = random_vectors(10_000, 64, 4);
+        let queries = random_vectors(20, 64, 5);
+        let index = build(&vectors);
+
+        let started = Instant::now();
+        for query in &queries {
+            index.search(query, 10, 64);
+        }
+        let hnsw = started.elapsed();
+
+        let started = Instant::now();
+        for query in &queries {
+            exact_top_k(&vectors, query, 10);
+        }
+        let linear = started.elapsed();
+
+        assert!(hnsw < linear, "hnsw {:?} vs linear scan {:?}", hnsw, linear);
+        assert!(recall(&index, &vectors, &queries, 10, 64) >= 0.8);
+    }
+}
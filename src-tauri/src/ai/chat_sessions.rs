@@ -0,0 +1,276 @@
+//! AI 对话会话模块
+//! 在 SQLite 中保存多轮对话
+
+use crate::db::Database;
+use crate::models::is_cjk;
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use std::sync::Arc;
+use thiserror::Error;
+use uuid::Uuid;
+
+/// 根据首条用户消息生成标题时截取的字符数
+const SESSION_TITLE_CHARS: usize = 50;
+
+#[derive(Debug, Error)]
+pub enum ChatSessionError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Session not found: {0}")]
+    NotFound(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: String, // "user", "assistant", "system"
+    pub content: String,
+}
+
+/// 会话概要
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatSession {
+    pub id: String,
+    pub title: String,
+    pub message_count: usize,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// 已保存的消息
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredChatMessage {
+    pub id: String,
+    pub role: String,
+    pub content: String,
+    pub created_at: i64,
+}
+
+/// 会话及其全部消息
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatSessionDetail {
+    #[serde(flatten)]
+    pub session: ChatSession,
+    pub messages: Vec<StoredChatMessage>,
+}
+
+/// 对话会话存储
+pub struct ChatSessionStore {
+    db: Arc<Database>,
+}
+
+impl ChatSessionStore {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+
+    /// 创建会话；未指定标题时以首条用户消息作为标题
+    pub async fn create_session(&self, title: Option<&str>) -> Result<ChatSession, ChatSessionError> {
+        let now = chrono::Utc::now().timestamp_millis();
+        let session = ChatSession {
+            id: Uuid::new_v4().to_string(),
+            title: title.map(str::trim).unwrap_or_default().to_string(),
+            message_count: 0,
+            created_at: now,
+            updated_at: now,
+        };
+
+        sqlx::query("INSERT INTO chat_sessions (id, title, created_at, updated_at) VALUES (?, ?, ?, ?)")
+            .bind(&session.id)
+            .bind(&session.title)
+            .bind(session.created_at)
+            .bind(session.updated_at)
+            .execute(self.db.pool())
+            .await?;
+
+        Ok(session)
+    }
+
+    /// 追加一条消息
+    pub async fn append_message(
+        &self,
+        session_id: &str,
+        message: &ChatMessage,
+    ) -> Result<StoredChatMessage, ChatSessionError> {
+        let now = chrono::Utc::now().timestamp_millis();
+        let title = if message.role == "user" {
+            message.content.trim().chars().take(SESSION_TITLE_CHARS).collect()
+        } else {
+            String::new()
+        };
+
+        let updated = sqlx::query(
+            "UPDATE chat_sessions SET updated_at = ?,
+                title = CASE WHEN title = '' THEN ? ELSE title END
+             WHERE id = ?",
+        )
+        .bind(now)
+        .bind(&title)
+        .bind(session_id)
+        .execute(self.db.pool())
+        .await?;
+        if updated.rows_affected() == 0 {
+            return Err(ChatSessionError::NotFound(session_id.to_string()));
+        }
+
+        let stored = StoredChatMessage {
+            id: Uuid::new_v4().to_string(),
+            role: message.role.clone(),
+            content: message.content.clone(),
+            created_at: now,
+        };
+        sqlx::query(
+            "INSERT INTO chat_messages (id, session_id, role, content, created_at) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(&stored.id)
+        .bind(session_id)
+        .bind(&stored.role)
+        .bind(&stored.content)
+        .bind(stored.created_at)
+        .execute(self.db.pool())
+        .await?;
+
+        Ok(stored)
+    }
+
+    /// 获取会话及其消息（按时间顺序）
+    pub async fn get_session(&self, session_id: &str) -> Result<Option<ChatSessionDetail>, ChatSessionError> {
+        let Some(row) = sqlx::query(
+            "SELECT s.id, s.title, COUNT(m.id), s.created_at, s.updated_at
+             FROM chat_sessions s LEFT JOIN chat_messages m ON m.session_id = s.id
+             WHERE s.id = ? GROUP BY s.id",
+        )
+        .bind(session_id)
+        .fetch_optional(self.db.pool())
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        // rowid 保证同一毫秒内写入的消息顺序
+        let messages = sqlx::query(
+            "SELECT id, role, content, created_at FROM chat_messages
+             WHERE session_id = ? ORDER BY created_at, rowid",
+        )
+        .bind(session_id)
+        .fetch_all(self.db.pool())
+        .await?
+        .into_iter()
+        .map(|row| StoredChatMessage {
+            id: row.get(0),
+            role: row.get(1),
+            content: row.get(2),
+            created_at: row.get(3),
+        })
+        .collect();
+
+        Ok(Some(ChatSessionDetail {
+            session: Self::row_to_session(&row),
+            messages,
+        }))
+    }
+
+    /// 列出所有会话（最近活跃的在前）
+    pub async fn list_sessions(&self) -> Result<Vec<ChatSession>, ChatSessionError> {
+        let rows = sqlx::query(
+            "SELECT s.id, s.title, COUNT(m.id), s.created_at, s.updated_at
+             FROM chat_sessions s LEFT JOIN chat_messages m ON m.session_id = s.id
+             GROUP BY s.id ORDER BY s.updated_at DESC",
+        )
+        .fetch_all(self.db.pool())
+        .await?;
+
+        Ok(rows.iter().map(Self::row_to_session).collect())
+    }
+
+    /// 删除会话（消息级联删除）
+    pub async fn delete_session(&self, session_id: &str) -> Result<(), ChatSessionError> {
+        sqlx::query("DELETE FROM chat_messages WHERE session_id = ?")
+            .bind(session_id)
+            .execute(self.db.pool())
+            .await?;
+        sqlx::query("DELETE FROM chat_sessions WHERE id = ?")
+            .bind(session_id)
+            .execute(self.db.pool())
+            .await?;
+        Ok(())
+    }
+
+    fn row_to_session(row: &sqlx::sqlite::SqliteRow) -> ChatSession {
+        ChatSession {
+            id: row.get(0),
+            title: row.get(1),
+            message_count: row.get::<i64, _>(2) as usize,
+            created_at: row.get(3),
+            updated_at: row.get(4),
+        }
+    }
+}
+
+/// 粗略估算 token 数：中日韩文字约每字 1 token，其他非 ASCII 文字约每 2 个字符 1 token，
+/// ASCII 约每 4 个字符 1 token
+pub fn estimate_tokens(text: &str) -> usize {
+    let (mut cjk, mut other, mut ascii) = (0usize, 0usize, 0usize);
+    for c in text.chars() {
+        if is_cjk(c) {
+            cjk += 1;
+        } else if c.is_ascii() {
+            ascii += 1;
+        } else {
+            other += 1;
+        }
+    }
+    cjk + other.div_ceil(2) + ascii.div_ceil(4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn message(role: &str, content: &str) -> ChatMessage {
+        ChatMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens("卡片abcd"), 3);
+        assert_eq!(estimate_tokens("カード、카드"), 6);
+        assert_eq!(estimate_tokens("привет"), 3);
+    }
+
+    #[tokio::test]
+    async fn test_session_roundtrip() {
+        let dir = tempdir().unwrap();
+        let db = Arc::new(Database::open(&dir.path().join("zentri.db")).await.unwrap());
+        let store = ChatSessionStore::new(db);
+
+        let session = store.create_session(None).await.unwrap();
+        store
+            .append_message(&session.id, &message("user", "What is a zettel?"))
+            .await
+            .unwrap();
+        store
+            .append_message(&session.id, &message("assistant", "A note."))
+            .await
+            .unwrap();
+
+        let detail = store.get_session(&session.id).await.unwrap().unwrap();
+        assert_eq!(detail.session.title, "What is a zettel?");
+        assert_eq!(detail.session.message_count, 2);
+        assert_eq!(detail.messages[1].role, "assistant");
+        assert_eq!(store.list_sessions().await.unwrap().len(), 1);
+
+        store.delete_session(&session.id).await.unwrap();
+        assert!(store.get_session(&session.id).await.unwrap().is_none());
+        assert!(store
+            .append_message(&session.id, &message("user", "hi"))
+            .await
+            .is_err());
+    }
+}
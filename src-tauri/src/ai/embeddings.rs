@@ -2,6 +2,8 @@
 //! 使用 llama-server 的 embedding 接口进行文本向量化
 
 use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -10,10 +12,66 @@ pub enum EmbeddingError {
     Network(String),
     #[error("Invalid response: {0}")]
     InvalidResponse(String),
+    #[error("HTTP {status}: {body}")]
+    Http { status: u16, body: String },
     #[error("Server not available")]
     ServerNotAvailable,
 }
 
+impl EmbeddingError {
+    /// 超时、连接失败和 5xx 属于瞬时错误，值得重试；4xx 和响应格式错误直接失败
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            EmbeddingError::Network(_) => true,
+            EmbeddingError::Http { status, .. } => *status >= 500,
+            _ => false,
+        }
+    }
+}
+
+/// 瞬时失败的重试策略：每次重试前的等待时间从 `initial_backoff` 开始翻倍，不超过 `max_backoff`
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// 最大重试次数（不含首次请求）
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(4),
+        }
+    }
+}
+
+/// vault 配置中保存向量化重试策略的键，值形如 `{"maxRetries": 3, "initialBackoffMs": 250, "maxBackoffMs": 4000}`
+pub const RETRY_POLICY_SETTING: &str = "aiEmbeddingRetry";
+
+impl RetryPolicy {
+    /// 从 vault 配置读取重试策略，缺失的项使用默认值
+    pub fn from_vault(vault_path: &Path) -> Self {
+        let defaults = Self::default();
+        let setting = crate::vault::read_setting(vault_path, RETRY_POLICY_SETTING);
+        let get = |key: &str| setting.as_ref().and_then(|s| s.get(key)).and_then(serde_json::Value::as_u64);
+        Self {
+            max_retries: get("maxRetries").map_or(defaults.max_retries, |n| n.min(u64::from(u32::MAX)) as u32),
+            initial_backoff: get("initialBackoffMs").map_or(defaults.initial_backoff, Duration::from_millis),
+            max_backoff: get("maxBackoffMs").map_or(defaults.max_backoff, Duration::from_millis),
+        }
+    }
+
+    /// 第 `attempt` 次重试（从 0 开始）前的等待时间
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_backoff)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct EmbeddingRequest {
     input: Vec<String>,
@@ -43,6 +101,7 @@ struct EmbeddingUsage {
 pub struct EmbeddingService {
     base_url: String,
     model: String,
+    retry: RetryPolicy,
 }
 
 impl EmbeddingService {
@@ -50,18 +109,42 @@ impl EmbeddingService {
         Self {
             base_url: format!("http://127.0.0.1:{}", port),
             model: "text-embedding".to_string(), // llama-server 的默认 embedding 模型名
+            retry: RetryPolicy::default(),
         }
     }
 
+    /// 使用自定义重试策略
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
     /// 对单个文本进行向量化
     pub async fn embed(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
         let embeddings = self.embed_batch(&[text.to_string()]).await?;
         Ok(embeddings.into_iter().next().unwrap_or_default())
     }
 
-    /// 批量向量化
+    /// 批量向量化，瞬时失败（如模型加载期间的 503）按重试策略退避重试
     pub async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
         let client = reqwest::Client::new();
+        let mut attempt = 0;
+        loop {
+            match self.request_embeddings(&client, texts).await {
+                Err(e) if e.is_retryable() && attempt < self.retry.max_retries => {
+                    tokio::time::sleep(self.retry.backoff(attempt)).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn request_embeddings(
+        &self,
+        client: &reqwest::Client,
+        texts: &[String],
+    ) -> Result<Vec<Vec<f32>>, EmbeddingError> {
         let url = format!("{}/v1/embeddings", self.base_url);
 
         let request = EmbeddingRequest {
@@ -76,12 +159,12 @@ impl EmbeddingService {
             .await
             .map_err(|e| EmbeddingError::Network(e.to_string()))?;
 
-        if !response.status().is_success() {
-            return Err(EmbeddingError::InvalidResponse(format!(
-                "HTTP {}: {}",
-                response.status(),
-                response.text().await.unwrap_or_default()
-            )));
+        let status = response.status();
+        if !status.is_success() {
+            return Err(EmbeddingError::Http {
+                status: status.as_u16(),
+                body: response.text().await.unwrap_or_default(),
+            });
         }
 
         let embedding_response: EmbeddingResponse = response
@@ -114,6 +197,108 @@ impl EmbeddingService {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// 依次返回给定状态码的最小 HTTP 服务，返回端口和已收到的请求数
+    async fn mock_server(statuses: Vec<u16>) -> (u16, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        tokio::spawn(async move {
+            for status in statuses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                counter.fetch_add(1, Ordering::SeqCst);
+                read_request(&mut socket).await;
+                let body = if status == 200 {
+                    r#"{"data":[{"embedding":[0.5,0.25],"index":0}],"model":"m","usage":{"prompt_tokens":1,"total_tokens":1}}"#
+                } else {
+                    "loading model"
+                };
+                let response = format!(
+                    "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (port, hits)
+    }
 
+    /// 读完请求头和 Content-Length 指定的请求体
+    async fn read_request(socket: &mut tokio::net::TcpStream) {
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 4096];
+        loop {
+            let n = socket.read(&mut chunk).await.unwrap();
+            if n == 0 {
+                return;
+            }
+            buf.extend_from_slice(&chunk[..n]);
+            let text = String::from_utf8_lossy(&buf);
+            if let Some(header_end) = text.find("\r\n\r\n") {
+                let content_length = text[..header_end]
+                    .lines()
+                    .find_map(|l| l.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().to_string()))
+                    .and_then(|v| v.parse::<usize>().ok())
+                    .unwrap_or(0);
+                if buf.len() >= header_end + 4 + content_length {
+                    return;
+                }
+            }
+        }
+    }
 
+    fn fast_retry() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(5),
+        }
+    }
 
+    #[tokio::test]
+    async fn test_embed_retries_transient_failures() {
+        let (port, hits) = mock_server(vec![503, 503, 200]).await;
+        let service = EmbeddingService::new(port).with_retry_policy(fast_retry());
+
+        let embedding = service.embed("hello").await.unwrap();
+        assert_eq!(embedding, vec![0.5, 0.25]);
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_retry_policy_from_vault() {
+        let vault = tempfile::tempdir().unwrap();
+        assert_eq!(RetryPolicy::from_vault(vault.path()).max_retries, RetryPolicy::default().max_retries);
+
+        crate::vault::write_setting(
+            vault.path(),
+            RETRY_POLICY_SETTING,
+            serde_json::json!({ "maxRetries": 0, "initialBackoffMs": 10 }),
+        )
+        .unwrap();
+        let policy = RetryPolicy::from_vault(vault.path());
+        assert_eq!(policy.max_retries, 0);
+        assert_eq!(policy.initial_backoff, Duration::from_millis(10));
+        assert_eq!(policy.max_backoff, RetryPolicy::default().max_backoff);
+    }
+
+    #[tokio::test]
+    async fn test_embed_fails_fast_on_client_error() {
+        let (port, hits) = mock_server(vec![400, 200]).await;
+        let service = EmbeddingService::new(port).with_retry_policy(fast_retry());
+
+        let err = service.embed("hello").await.unwrap_err();
+        assert!(matches!(err, EmbeddingError::Http { status: 400, .. }));
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+}
@@ -1,36 +1,74 @@
 //! AI 管理器
 //! 统一管理 Sidecar、模型和 RAG 服务
 
-use crate::ai::{SidecarManager, ModelManager, RAGService};
+use crate::ai::chat_sessions::estimate_tokens;
+use crate::ai::embeddings::RetryPolicy;
+use crate::ai::{ChatMessage, ChatSessionStore, SidecarManager, ModelManager, RAGService};
 use crate::db::Database;
+use serde::Serialize;
 use std::sync::Arc;
 use std::sync::Mutex;
 
+/// llama-server 未报告上下文长度时使用的默认值
+pub const DEFAULT_CONTEXT_SIZE: usize = 4096;
+
+/// 为模型回复预留的 token 数
+pub const COMPLETION_RESERVE: usize = 1024;
+
+/// 每条消息在聊天模板中的额外开销（角色标记、分隔符）
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+
+/// 上下文占用情况（估算值），返回给前端展示
+#[derive(Debug, Clone, Serialize)]
+pub struct ContextUsage {
+    pub estimated_tokens: usize,
+    pub context_size: usize,
+    pub reserved_for_completion: usize,
+    /// 为放入上下文而丢弃的旧消息数
+    pub dropped_messages: usize,
+}
+
+/// 裁剪到上下文窗口内的消息
+#[derive(Debug, Clone)]
+pub struct FittedContext {
+    pub messages: Vec<ChatMessage>,
+    pub usage: ContextUsage,
+}
+
 /// AI 管理器
 pub struct AIManager {
     sidecar: Arc<SidecarManager>,
     models: Arc<ModelManager>,
     rag: Arc<Mutex<Option<Arc<RAGService>>>>,
+    chat_sessions: Arc<ChatSessionStore>,
     db: Arc<Database>,
     port: Arc<Mutex<u16>>,
+    context_size: Arc<Mutex<usize>>,
     vault_path: Arc<Mutex<Option<std::path::PathBuf>>>,
+    /// 向量化请求的重试策略，取自 vault 配置
+    retry_policy: Arc<Mutex<RetryPolicy>>,
 }
 
 impl AIManager {
     pub fn new(db: Arc<Database>, vault_path: Option<std::path::PathBuf>) -> Result<Self, String> {
         let models = ModelManager::new().map_err(|e| e.to_string())?;
-        
+        let retry_policy = vault_path.as_deref().map(RetryPolicy::from_vault).unwrap_or_default();
+
         Ok(Self {
             sidecar: Arc::new(SidecarManager::new()),
             models: Arc::new(models),
             rag: Arc::new(Mutex::new(None)),
+            chat_sessions: Arc::new(ChatSessionStore::new(db.clone())),
             db,
             port: Arc::new(Mutex::new(8080)),
+            context_size: Arc::new(Mutex::new(DEFAULT_CONTEXT_SIZE)),
             vault_path: Arc::new(Mutex::new(vault_path)),
+            retry_policy: Arc::new(Mutex::new(retry_policy)),
         })
     }
 
     pub fn set_vault_path(&self, vault_path: Option<std::path::PathBuf>) {
+        *self.retry_policy.lock().unwrap() = vault_path.as_deref().map(RetryPolicy::from_vault).unwrap_or_default();
         *self.vault_path.lock().unwrap() = vault_path;
         // 重置 RAG 服务以使用新的 vault_path
         let mut rag_guard = self.rag.lock().unwrap();
@@ -45,12 +83,18 @@ impl AIManager {
         self.models.clone()
     }
 
+    pub fn get_chat_sessions(&self) -> Arc<ChatSessionStore> {
+        self.chat_sessions.clone()
+    }
+
     pub fn get_rag(&self) -> Arc<RAGService> {
         let mut rag_guard = self.rag.lock().unwrap();
         if rag_guard.is_none() {
             let port = *self.port.lock().unwrap();
             let vault_path = self.vault_path.lock().unwrap().clone();
-            let rag_service = Arc::new(RAGService::new(self.db.clone(), port, vault_path));
+            let retry_policy = self.retry_policy.lock().unwrap().clone();
+            let rag_service =
+                Arc::new(RAGService::new(self.db.clone(), port, vault_path).with_retry_policy(retry_policy));
             *rag_guard = Some(rag_service.clone());
             rag_service
         } else {
@@ -68,5 +112,126 @@ impl AIManager {
     pub fn get_port(&self) -> u16 {
         *self.port.lock().unwrap()
     }
+
+    /// 设置当前模型的上下文长度（启动 llama-server 后从 /props 读取）
+    pub fn set_context_size(&self, context_size: usize) {
+        *self.context_size.lock().unwrap() = context_size;
+    }
+
+    pub fn get_context_size(&self) -> usize {
+        *self.context_size.lock().unwrap()
+    }
+
+    /// 提示词可用的 token 预算（上下文长度减去回复预留）
+    pub fn prompt_budget(&self) -> usize {
+        self.get_context_size().saturating_sub(COMPLETION_RESERVE)
+    }
+
+    /// 按当前模型的上下文长度裁剪消息
+    pub fn fit_to_context(&self, messages: Vec<ChatMessage>) -> Result<FittedContext, String> {
+        fit_to_context(messages, self.get_context_size(), COMPLETION_RESERVE)
+    }
+}
+
+/// 从 llama-server 的 /props 接口读取上下文长度，服务器未就绪时返回 None
+pub async fn fetch_context_size(port: u16) -> Option<usize> {
+    let url = format!("http://127.0.0.1:{}/props", port);
+    let props: serde_json::Value = reqwest::Client::new()
+        .get(&url)
+        .timeout(std::time::Duration::from_secs(2))
+        .send()
+        .await
+        .ok()?
+        .json()
+        .await
+        .ok()?;
+
+    props["default_generation_settings"]["n_ctx"]
+        .as_u64()
+        .or_else(|| props["n_ctx"].as_u64())
+        .filter(|n| *n > 0)
+        .map(|n| n as usize)
+}
+
+fn message_tokens(message: &ChatMessage) -> usize {
+    estimate_tokens(&message.content) + MESSAGE_OVERHEAD_TOKENS
+}
+
+/// 将消息裁剪到上下文窗口内：保留所有 system 消息和最新一条消息，从最早的对话开始丢弃。
+/// 保留下来的消息仍然放不下时返回错误，而不是让 llama-server 截断提示词
+pub fn fit_to_context(
+    messages: Vec<ChatMessage>,
+    ctx_size: usize,
+    reserve_for_completion: usize,
+) -> Result<FittedContext, String> {
+    let budget = ctx_size.saturating_sub(reserve_for_completion);
+    let mut total: usize = messages.iter().map(message_tokens).sum();
+    let last = messages.len().saturating_sub(1);
+    let mut keep = vec![true; messages.len()];
+    let mut dropped = 0;
+
+    for (i, message) in messages.iter().enumerate() {
+        if total <= budget {
+            break;
+        }
+        if message.role == "system" || i == last {
+            continue;
+        }
+        keep[i] = false;
+        total -= message_tokens(message);
+        dropped += 1;
+    }
+
+    if total > budget {
+        return Err(format!(
+            "Prompt is too long for the model context: ~{} tokens, {} available ({} context, {} reserved for the reply)",
+            total, budget, ctx_size, reserve_for_completion
+        ));
+    }
+
+    let messages = messages
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(message, keep)| keep.then_some(message))
+        .collect();
+
+    Ok(FittedContext {
+        messages,
+        usage: ContextUsage {
+            estimated_tokens: total,
+            context_size: ctx_size,
+            reserved_for_completion: reserve_for_completion,
+            dropped_messages: dropped,
+        },
+    })
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: &str, content: &str) -> ChatMessage {
+        ChatMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_fit_to_context_keeps_system_and_latest() {
+        let messages = vec![
+            message("system", "be brief"),
+            message("user", &"a".repeat(400)),
+            message("assistant", &"b".repeat(400)),
+            message("user", "latest question"),
+        ];
+        let fitted = fit_to_context(messages.clone(), 200, 80).unwrap();
+        let roles: Vec<_> = fitted.messages.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, vec!["system", "assistant", "user"]);
+        assert_eq!(fitted.messages[2].content, "latest question");
+        assert_eq!(fitted.usage.dropped_messages, 1);
+        assert!(fitted.usage.estimated_tokens <= 120);
+
+        assert!(fit_to_context(messages, 40, 30).is_err());
+    }
+}
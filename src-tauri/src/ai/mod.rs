@@ -3,12 +3,18 @@
 
 pub mod sidecar;
 pub mod models;
+pub mod ann;
 pub mod embeddings;
 pub mod rag;
 pub mod manager;
+pub mod chat_sessions;
 
-pub use manager::AIManager;
+pub use manager::{AIManager, ContextUsage};
 pub use sidecar::SidecarManager;
-pub use models::{ModelManager, ModelInfo, get_available_models};
+pub use models::{
+    ModelManager, ModelInfo, ProgressThrottle, annotate_recommendations, get_available_models,
+    PROGRESS_THROTTLE_INTERVAL,
+};
 pub use rag::RAGService;
+pub use chat_sessions::{estimate_tokens, ChatMessage, ChatSessionStore};
 
@@ -1,13 +1,19 @@
 //! 模型管理
 //! 负责模型的下载、验证、存储和列表管理
 
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::fs;
-use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::io::{Read, Write};
+use std::time::{Duration, Instant};
 use dirs::data_dir;
+use sha2::{Digest, Sha256};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use futures_util::StreamExt;
+use crate::ai::sidecar::HardwareInfo;
 
 #[derive(Debug, Error)]
 pub enum ModelError {
@@ -19,6 +25,10 @@ pub enum ModelError {
     Io(#[from] std::io::Error),
     #[error("Network error: {0}")]
     Network(String),
+    #[error("Invalid manifest: {0}")]
+    InvalidManifest(String),
+    #[error("Download cancelled: {0}")]
+    Cancelled(String),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,6 +38,161 @@ pub struct ModelInfo {
     pub size: u64, // bytes
     pub url: String,
     pub description: Option<String>,
+    /// 文件 SHA-256 (十六进制)
+    #[serde(default)]
+    pub sha256: Option<String>,
+    /// 建议的最小内存 (GB)
+    #[serde(default)]
+    pub min_ram_gb: Option<f32>,
+    /// 是否推荐在本机运行（由 annotate_recommendations 填充）
+    #[serde(default)]
+    pub recommended_for_this_machine: bool,
+}
+
+/// 远程模型清单
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelManifest {
+    pub version: u32,
+    pub models: Vec<ModelInfo>,
+}
+
+/// 当前支持的清单版本
+const MANIFEST_VERSION: u32 = 1;
+
+/// 本地缓存的远程清单文件名
+const CATALOG_CACHE_FILE: &str = "catalog.json";
+
+impl ModelManifest {
+    /// 校验清单结构，不合法的清单整体拒绝
+    pub fn validate(&self) -> Result<(), ModelError> {
+        if self.version > MANIFEST_VERSION {
+            return Err(ModelError::InvalidManifest(format!(
+                "unsupported version {}",
+                self.version
+            )));
+        }
+
+        let mut ids = std::collections::HashSet::new();
+        for model in &self.models {
+            if model.id.trim().is_empty() || model.name.trim().is_empty() {
+                return Err(ModelError::InvalidManifest("model id and name are required".to_string()));
+            }
+            if model.id.contains(['/', '\\']) || model.id.contains("..") {
+                return Err(ModelError::InvalidManifest(format!("invalid model id: {}", model.id)));
+            }
+            if !ids.insert(model.id.as_str()) {
+                return Err(ModelError::InvalidManifest(format!("duplicate model id: {}", model.id)));
+            }
+            if !model.url.starts_with("https://") {
+                return Err(ModelError::InvalidManifest(format!("model {} must use https", model.id)));
+            }
+            if model.size == 0 {
+                return Err(ModelError::InvalidManifest(format!("model {} has no size", model.id)));
+            }
+            if let Some(hash) = &model.sha256 {
+                if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(ModelError::InvalidManifest(format!("model {} has invalid sha256", model.id)));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 合并模型列表：远程条目覆盖同 ID 的内置条目
+pub fn merge_models(builtin: Vec<ModelInfo>, remote: Vec<ModelInfo>) -> Vec<ModelInfo> {
+    let mut merged = builtin;
+    for model in remote {
+        match merged.iter_mut().find(|m| m.id == model.id) {
+            Some(existing) => *existing = model,
+            None => merged.push(model),
+        }
+    }
+    merged
+}
+
+/// 按本机内存标注推荐模型；内存未知时一律不推荐
+pub fn annotate_recommendations(models: &mut [ModelInfo], hardware: &HardwareInfo) {
+    for model in models.iter_mut() {
+        model.recommended_for_this_machine = match (hardware.total_ram_gb, model.min_ram_gb) {
+            (Some(total), Some(min)) => total >= min,
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+    }
+}
+
+/// 流式计算文件的 SHA-256（十六进制小写），不把整个文件读入内存
+pub fn file_sha256(path: &Path) -> Result<String, ModelError> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1024 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// 校验模型文件的大小和 SHA-256，清单未提供校验值时只检查大小
+pub fn verify_model_file(path: &Path, model_info: &ModelInfo) -> Result<(), ModelError> {
+    if !path.exists() {
+        return Err(ModelError::NotFound(model_info.id.clone()));
+    }
+    let size = fs::metadata(path)?.len();
+    if size != model_info.size {
+        return Err(ModelError::DownloadFailed(format!(
+            "File size mismatch: expected {}, got {}",
+            model_info.size, size
+        )));
+    }
+    if let Some(expected) = &model_info.sha256 {
+        let actual = file_sha256(path)?;
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(ModelError::DownloadFailed(format!(
+                "Checksum mismatch: expected {}, got {}",
+                expected, actual
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// 下载进度事件的最小间隔
+pub const PROGRESS_THROTTLE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// 下载进度节流：两次上报之间至少间隔 `interval`，下载完成时总是上报
+#[derive(Debug)]
+pub struct ProgressThrottle {
+    interval: Duration,
+    last: Option<Instant>,
+}
+
+impl ProgressThrottle {
+    pub fn new(interval: Duration) -> Self {
+        Self { interval, last: None }
+    }
+
+    /// 判断本次进度是否需要上报，需要时记录上报时间
+    pub fn should_emit(&mut self, now: Instant, downloaded: u64, total: u64) -> bool {
+        let due = match self.last {
+            Some(last) => now.duration_since(last) >= self.interval,
+            None => true,
+        };
+        if due || downloaded >= total {
+            self.last = Some(now);
+            true
+        } else {
+            false
+        }
+    }
 }
 
 /// 预定义的模型列表
@@ -39,6 +204,9 @@ pub fn get_available_models() -> Vec<ModelInfo> {
             size: 4_000_000_000, // ~4GB
             url: "https://huggingface.co/Qwen/Qwen2.5-7B-Instruct-GGUF/resolve/main/qwen2.5-7b-instruct-q4_k_m.gguf".to_string(),
             description: Some("推荐模型，平衡性能和资源占用".to_string()),
+            sha256: None,
+            min_ram_gb: Some(8.0),
+            recommended_for_this_machine: false,
         },
         ModelInfo {
             id: "qwen2.5-1.5b-int4".to_string(),
@@ -46,13 +214,37 @@ pub fn get_available_models() -> Vec<ModelInfo> {
             size: 1_000_000_000, // ~1GB
             url: "https://huggingface.co/Qwen/Qwen2.5-1.5B-Instruct-GGUF/resolve/main/qwen2.5-1.5b-instruct-q4_k_m.gguf".to_string(),
             description: Some("轻量级模型，适合低配置设备".to_string()),
+            sha256: None,
+            min_ram_gb: Some(4.0),
+            recommended_for_this_machine: false,
         },
     ]
 }
 
+/// 下载的取消信号：置位标记并唤醒正在等待数据的下载循环
+#[derive(Default)]
+struct CancelSignal {
+    cancelled: AtomicBool,
+    notify: tokio::sync::Notify,
+}
+
+impl CancelSignal {
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        // notify_one 在没有等待者时保留许可，之后的 notified() 会立即返回
+        self.notify.notify_one();
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
 /// 模型管理器
 pub struct ModelManager {
     models_dir: PathBuf,
+    /// 进行中的下载及其取消信号（按模型 ID）
+    downloads: Mutex<HashMap<String, Arc<CancelSignal>>>,
 }
 
 impl ModelManager {
@@ -65,11 +257,17 @@ impl ModelManager {
             .join("zentri")
             .join("models");
 
+        Self::with_models_dir(app_data_dir)
+    }
+
+    /// 使用指定的模型存储目录
+    pub fn with_models_dir(models_dir: PathBuf) -> Result<Self, ModelError> {
         // 确保目录存在
-        fs::create_dir_all(&app_data_dir)?;
+        fs::create_dir_all(&models_dir)?;
 
         Ok(Self {
-            models_dir: app_data_dir,
+            models_dir,
+            downloads: Mutex::new(HashMap::new()),
         })
     }
 
@@ -78,6 +276,44 @@ impl ModelManager {
         &self.models_dir
     }
 
+    /// 获取完整模型目录：内置列表 + 本地缓存的远程清单
+    pub fn get_catalog(&self) -> Vec<ModelInfo> {
+        let cached = fs::read_to_string(self.models_dir.join(CATALOG_CACHE_FILE))
+            .ok()
+            .and_then(|s| serde_json::from_str::<ModelManifest>(&s).ok())
+            .filter(|m| m.validate().is_ok())
+            .map(|m| m.models)
+            .unwrap_or_default();
+        merge_models(get_available_models(), cached)
+    }
+
+    /// 从远程 JSON 清单拉取模型列表，校验后缓存到本地
+    pub async fn fetch_remote_models(&self, url: &str) -> Result<Vec<ModelInfo>, ModelError> {
+        let response = reqwest::Client::new()
+            .get(url)
+            .timeout(std::time::Duration::from_secs(15))
+            .send()
+            .await
+            .map_err(|e| ModelError::Network(e.to_string()))?;
+
+        if !response.status().is_success() {
+            return Err(ModelError::Network(format!("HTTP error: {}", response.status())));
+        }
+
+        let body = response
+            .text()
+            .await
+            .map_err(|e| ModelError::Network(e.to_string()))?;
+        let manifest: ModelManifest = serde_json::from_str(&body)
+            .map_err(|e| ModelError::InvalidManifest(e.to_string()))?;
+        manifest.validate()?;
+
+        // 只缓存校验通过的清单
+        fs::write(self.models_dir.join(CATALOG_CACHE_FILE), &body)?;
+
+        Ok(merge_models(get_available_models(), manifest.models))
+    }
+
     /// 获取模型文件路径
     pub fn get_model_path(&self, model_id: &str) -> PathBuf {
         self.models_dir.join(format!("{}.gguf", model_id))
@@ -109,11 +345,46 @@ impl ModelManager {
         Ok(models)
     }
 
-    /// 下载模型（支持断点续传）
+    /// 下载模型（支持断点续传），可通过 `cancel_download` 取消
     pub async fn download_model(
         &self,
         model_info: &ModelInfo,
         on_progress: Option<Box<dyn Fn(u64, u64) + Send>>,
+    ) -> Result<PathBuf, ModelError> {
+        let cancelled = Arc::new(CancelSignal::default());
+        self.downloads
+            .lock()
+            .unwrap()
+            .insert(model_info.id.clone(), cancelled.clone());
+
+        let result = self.download_to_file(model_info, on_progress, &cancelled).await;
+
+        let mut downloads = self.downloads.lock().unwrap();
+        if downloads
+            .get(&model_info.id)
+            .is_some_and(|flag| Arc::ptr_eq(flag, &cancelled))
+        {
+            downloads.remove(&model_info.id);
+        }
+        result
+    }
+
+    /// 取消进行中的下载，已下载的部分保留以便之后续传；没有进行中的下载时返回 false
+    pub fn cancel_download(&self, model_id: &str) -> bool {
+        match self.downloads.lock().unwrap().get(model_id) {
+            Some(signal) => {
+                signal.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    async fn download_to_file(
+        &self,
+        model_info: &ModelInfo,
+        on_progress: Option<Box<dyn Fn(u64, u64) + Send>>,
+        cancelled: &CancelSignal,
     ) -> Result<PathBuf, ModelError> {
         let model_path = self.get_model_path(&model_info.id);
         
@@ -121,8 +392,12 @@ impl ModelManager {
         if model_path.exists() {
             let metadata = fs::metadata(&model_path)?;
             if metadata.len() == model_info.size {
-                // 文件已完整下载
-                return Ok(model_path);
+                // 大小一致但校验失败的文件无法续传，删除后重新下载
+                match self.verify_model(model_info).await {
+                    Ok(()) => return Ok(model_path),
+                    Err(ModelError::DownloadFailed(_)) => fs::remove_file(&model_path)?,
+                    Err(e) => return Err(e),
+                }
             }
         }
 
@@ -168,7 +443,17 @@ impl ModelManager {
         let mut stream = response.bytes_stream();
         let total_size = model_info.size;
 
-        while let Some(chunk) = stream.next().await {
+        loop {
+            // 服务器停住不发数据时也要能及时取消，不能只在收到数据后检查
+            let chunk = tokio::select! {
+                chunk = stream.next() => chunk,
+                _ = cancelled.notify.notified() => {
+                    return Err(ModelError::Cancelled(model_info.id.clone()));
+                }
+            };
+            let Some(chunk) = chunk else {
+                break;
+            };
             let chunk = chunk.map_err(|e| ModelError::Network(e.to_string()))?;
             file.write_all(&chunk)?;
             downloaded_bytes += chunk.len() as u64;
@@ -177,20 +462,35 @@ impl ModelManager {
             if let Some(ref callback) = on_progress {
                 callback(downloaded_bytes, total_size);
             }
+
+            // 取消时保留已写入的部分，下次通过 Range 请求续传
+            if cancelled.is_cancelled() {
+                return Err(ModelError::Cancelled(model_info.id.clone()));
+            }
         }
 
-        // 验证文件大小
-        let final_size = fs::metadata(&model_path)?.len();
-        if final_size != model_info.size {
-            return Err(ModelError::DownloadFailed(format!(
-                "File size mismatch: expected {}, got {}",
-                model_info.size, final_size
-            )));
+        // 验证文件大小和校验值，校验失败的文件删除，避免下次被当作已下载
+        drop(file);
+        if let Err(e) = self.verify_model(model_info).await {
+            if let ModelError::DownloadFailed(_) = e {
+                let _ = fs::remove_file(&model_path);
+            }
+            return Err(e);
         }
 
         Ok(model_path)
     }
 
+    /// 重新校验已下载的模型文件（大小和 SHA-256）
+    pub async fn verify_model(&self, model_info: &ModelInfo) -> Result<(), ModelError> {
+        let path = self.get_model_path(&model_info.id);
+        let model_info = model_info.clone();
+        // 大文件哈希耗时较长，放到阻塞线程池中执行
+        tokio::task::spawn_blocking(move || verify_model_file(&path, &model_info))
+            .await
+            .map_err(|e| ModelError::Io(std::io::Error::other(e.to_string())))?
+    }
+
     /// 删除模型文件
     pub fn delete_model(&self, model_id: &str) -> Result<(), ModelError> {
         let model_path = self.get_model_path(model_id);
@@ -216,3 +516,192 @@ impl Default for ModelManager {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(id: &str, url: &str) -> ModelInfo {
+        ModelInfo {
+            id: id.to_string(),
+            name: id.to_string(),
+            size: 1,
+            url: url.to_string(),
+            description: None,
+            sha256: None,
+            min_ram_gb: None,
+            recommended_for_this_machine: false,
+        }
+    }
+
+    #[test]
+    fn test_manifest_validation() {
+        let ok = ModelManifest {
+            version: 1,
+            models: vec![model("a", "https://example.com/a.gguf")],
+        };
+        assert!(ok.validate().is_ok());
+
+        let http = ModelManifest {
+            version: 1,
+            models: vec![model("a", "http://example.com/a.gguf")],
+        };
+        assert!(http.validate().is_err());
+
+        let traversal = ModelManifest {
+            version: 1,
+            models: vec![model("../a", "https://example.com/a.gguf")],
+        };
+        assert!(traversal.validate().is_err());
+    }
+
+    #[test]
+    fn test_verify_model_file_checksum() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tiny.gguf");
+        fs::write(&path, b"abc").unwrap();
+        assert_eq!(
+            file_sha256(&path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        let mut info = model("tiny", "https://example.com/tiny.gguf");
+        info.size = 3;
+        assert!(verify_model_file(&path, &info).is_ok());
+
+        info.sha256 = Some("BA7816BF8F01CFEA414140DE5DAE2223B00361A396177A9CB410FF61F20015AD".to_string());
+        assert!(verify_model_file(&path, &info).is_ok());
+
+        // 同样大小但内容损坏
+        fs::write(&path, b"abd").unwrap();
+        assert!(matches!(verify_model_file(&path, &info), Err(ModelError::DownloadFailed(_))));
+
+        info.size = 4;
+        assert!(matches!(verify_model_file(&path, &info), Err(ModelError::DownloadFailed(_))));
+
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(verify_model_file(&path, &info), Err(ModelError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_progress_throttle() {
+        let mut throttle = ProgressThrottle::new(Duration::from_millis(250));
+        let start = Instant::now();
+        assert!(throttle.should_emit(start, 1, 100));
+        assert!(!throttle.should_emit(start + Duration::from_millis(100), 2, 100));
+        assert!(!throttle.should_emit(start + Duration::from_millis(249), 3, 100));
+        assert!(throttle.should_emit(start + Duration::from_millis(250), 4, 100));
+        assert!(!throttle.should_emit(start + Duration::from_millis(300), 5, 100));
+        // 下载完成时不受节流限制
+        assert!(throttle.should_emit(start + Duration::from_millis(301), 100, 100));
+    }
+
+    /// 先返回 `partial` 字节然后停住的下载服务（声明的长度为 `size`）
+    async fn stalling_server(size: usize, partial: usize) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 1024];
+            let _ = socket.read(&mut request).await;
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: {}\r\n\r\n",
+                size
+            );
+            socket.write_all(head.as_bytes()).await.unwrap();
+            socket.write_all(&vec![7u8; partial]).await.unwrap();
+            socket.flush().await.unwrap();
+            tokio::time::sleep(std::time::Duration::from_secs(10)).await;
+        });
+        format!("http://{}/model.gguf", addr)
+    }
+
+    #[tokio::test]
+    async fn test_cancel_download_keeps_partial_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = Arc::new(ModelManager::with_models_dir(dir.path().to_path_buf()).unwrap());
+        let mut info = model("partial", &stalling_server(1000, 100).await);
+        info.size = 1000;
+
+        let canceller = manager.clone();
+        let on_progress: Box<dyn Fn(u64, u64) + Send> = Box::new(move |downloaded, _| {
+            if downloaded >= 100 {
+                canceller.cancel_download("partial");
+            }
+        });
+        let result = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            manager.download_model(&info, Some(on_progress)),
+        )
+        .await
+        .expect("download was not cancelled");
+
+        assert!(matches!(result, Err(ModelError::Cancelled(_))));
+        assert_eq!(fs::metadata(manager.get_model_path("partial")).unwrap().len(), 100);
+        // 下载结束后不再登记
+        assert!(!manager.cancel_download("partial"));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_download_while_stalled() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = Arc::new(ModelManager::with_models_dir(dir.path().to_path_buf()).unwrap());
+        let mut info = model("stalled", &stalling_server(1000, 100).await);
+        info.size = 1000;
+
+        // 等前 100 字节写完、下载循环停在等待数据时，从另一个任务取消
+        let download = tokio::spawn({
+            let manager = manager.clone();
+            async move { manager.download_model(&info, None).await }
+        });
+        let path = manager.get_model_path("stalled");
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while fs::metadata(&path).map(|m| m.len()).unwrap_or(0) < 100 {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("partial data never arrived");
+        assert!(manager.cancel_download("stalled"));
+
+        let result = tokio::time::timeout(std::time::Duration::from_secs(5), download)
+            .await
+            .expect("download was not cancelled")
+            .unwrap();
+        assert!(matches!(result, Err(ModelError::Cancelled(_))));
+        assert_eq!(fs::metadata(&path).unwrap().len(), 100);
+    }
+
+    #[test]
+    fn test_merge_models_overrides_builtin() {
+        let merged = merge_models(
+            vec![model("a", "https://old"), model("b", "https://b")],
+            vec![model("a", "https://new"), model("c", "https://c")],
+        );
+        assert_eq!(merged.len(), 3);
+        assert_eq!(merged[0].url, "https://new");
+    }
+
+    #[test]
+    fn test_annotate_recommendations() {
+        let mut models = vec![model("small", "https://s"), model("big", "https://b")];
+        models[0].min_ram_gb = Some(4.0);
+        models[1].min_ram_gb = Some(16.0);
+
+        let mut hardware = HardwareInfo {
+            total_ram_gb: Some(8.0),
+            available_ram_gb: None,
+            cpu_cores: Some(8),
+            gpu_backend: None,
+        };
+        annotate_recommendations(&mut models, &hardware);
+        assert!(models[0].recommended_for_this_machine);
+        assert!(!models[1].recommended_for_this_machine);
+
+        hardware.total_ram_gb = None;
+        annotate_recommendations(&mut models, &hardware);
+        assert!(!models[0].recommended_for_this_machine);
+    }
+}
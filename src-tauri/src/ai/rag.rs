@@ -1,11 +1,19 @@
 //! RAG (检索增强生成) 模块
 //! 实现向量索引、相似度搜索和 RAG Prompt 构建
 
-use crate::ai::embeddings::{EmbeddingService, EmbeddingError};
+use crate::ai::ann::HnswIndex;
+use crate::ai::embeddings::{EmbeddingService, EmbeddingError, RetryPolicy};
 use crate::db::Database;
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize};
 use sqlx::Row;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
 use std::fs;
-use std::sync::Arc;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -18,13 +26,234 @@ pub enum RAGError {
     SourceNotFound(String),
     #[error("Serialization error: {0}")]
     Serialization(String),
+    #[error("Rerank error: {0}")]
+    Rerank(String),
+    #[error("Incompatible embeddings: {0}")]
+    Incompatible(String),
 }
 
+/// 相关性打分器（用于重排序）
+pub trait RelevanceScorer: Send + Sync {
+    /// 返回 0-10 的相关性分数
+    fn score<'a>(&'a self, query: &'a str, passage: &'a str) -> BoxFuture<'a, Result<f32, RAGError>>;
+}
+
+/// 基于聊天模型的打分器（cross-encoder 式 Prompt）
+pub struct ChatRelevanceScorer {
+    base_url: String,
+}
+
+impl ChatRelevanceScorer {
+    pub fn new(port: u16) -> Self {
+        Self {
+            base_url: format!("http://127.0.0.1:{}", port),
+        }
+    }
+
+    /// 从模型回复中解析分数
+    fn parse_score(reply: &str) -> f32 {
+        let number: String = reply
+            .trim()
+            .chars()
+            .skip_while(|c| !c.is_ascii_digit())
+            .take_while(|c| c.is_ascii_digit() || *c == '.')
+            .collect();
+        number.parse::<f32>().unwrap_or(0.0).clamp(0.0, 10.0)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ScoreMessage {
+    role: String,
+    content: String,
+}
+
+#[derive(Serialize)]
+struct ScoreRequest {
+    model: String,
+    messages: Vec<ScoreMessage>,
+    temperature: f32,
+    max_tokens: u32,
+    stream: bool,
+}
+
+#[derive(Deserialize)]
+struct ScoreResponse {
+    choices: Vec<ScoreChoice>,
+}
+
+#[derive(Deserialize)]
+struct ScoreChoice {
+    message: ScoreMessage,
+}
+
+impl RelevanceScorer for ChatRelevanceScorer {
+    fn score<'a>(&'a self, query: &'a str, passage: &'a str) -> BoxFuture<'a, Result<f32, RAGError>> {
+        Box::pin(async move {
+            let prompt = format!(
+                "请评估下面的文本片段与问题的相关程度，给出 0 到 10 的整数分数（10 表示完全相关）。只输出数字。\n\n问题：{}\n\n文本片段：{}\n\n分数：",
+                query, passage
+            );
+            let request = ScoreRequest {
+                model: "local-model".to_string(),
+                messages: vec![ScoreMessage {
+                    role: "user".to_string(),
+                    content: prompt,
+                }],
+                temperature: 0.0,
+                max_tokens: 4,
+                stream: false,
+            };
+
+            let response: ScoreResponse = reqwest::Client::new()
+                .post(format!("{}/v1/chat/completions", self.base_url))
+                .json(&request)
+                .send()
+                .await
+                .map_err(|e| RAGError::Rerank(format!("Network error: {}", e)))?
+                .json()
+                .await
+                .map_err(|e| RAGError::Rerank(format!("Parse error: {}", e)))?;
+
+            let reply = response
+                .choices
+                .first()
+                .map(|c| c.message.content.as_str())
+                .unwrap_or("");
+            Ok(Self::parse_score(reply))
+        })
+    }
+}
+
+/// 分块参数
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkConfig {
+    /// 每块的目标字符数
+    pub chunk_size: usize,
+    /// 相邻块之间重叠的字符数
+    pub overlap: usize,
+}
+
+impl Default for ChunkConfig {
+    fn default() -> Self {
+        Self {
+            chunk_size: 500,
+            overlap: 0,
+        }
+    }
+}
+
+/// 生成某文献源现有向量时使用的参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceIndexMeta {
+    pub source_id: String,
+    pub chunk_config: ChunkConfig,
+    pub embedding_model: String,
+    pub chunk_count: usize,
+    pub indexed_at: i64,
+}
+
+/// 重新索引结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ReindexResult {
+    pub chunk_count: usize,
+    /// 分块参数或向量模型发生变化，已清空旧向量后重建
+    pub config_changed: bool,
+}
+
+/// 文献源的 RAG 索引覆盖情况
+#[derive(Debug, Clone, Serialize)]
+pub struct RagCoverage {
+    pub source_id: String,
+    pub chunk_count: usize,
+    /// 旧版本索引的文献源没有记录
+    pub index_meta: Option<SourceIndexMeta>,
+}
+
+/// 向量导出文件的格式版本
+const EMBEDDINGS_EXPORT_VERSION: u32 = 1;
+
+/// 导出文件中的单个分块
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ExportedChunk {
+    id: String,
+    source_id: String,
+    content: String,
+    created_at: i64,
+    vector: Vec<f32>,
+}
+
+/// 向量导出文件：分块向量、各文献源的索引参数和导出时的模型信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct EmbeddingsExport {
+    version: u32,
+    embedding_model: String,
+    dimension: usize,
+    exported_at: i64,
+    index_meta: Vec<SourceIndexMeta>,
+    chunks: Vec<ExportedChunk>,
+}
+
+/// 向量导出/导入结果
+#[derive(Debug, Clone, Serialize)]
+pub struct EmbeddingsTransfer {
+    pub embedding_model: String,
+    pub dimension: usize,
+    pub source_count: usize,
+    pub chunk_count: usize,
+    /// 导入时当前库中不存在的文献源，其向量被跳过
+    pub skipped_sources: Vec<String>,
+}
+
+/// 卡片中的一段文本，start/end 为在卡片纯文本中的字符偏移（左闭右开）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CardSpan {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+/// 单卡问答结果
+#[derive(Debug, Clone, Serialize)]
+pub struct AnswerWithSpans {
+    pub answer: String,
+    /// 回答引用的原文片段，供编辑器高亮
+    pub spans: Vec<CardSpan>,
+}
+
+/// 单卡问答时每次取回的片段数
+const CARD_QA_SPAN_LIMIT: usize = 3;
+
+/// 卡片的按需向量索引，卡片修改后失效
+struct CardIndex {
+    modified_at: i64,
+    chunks: Vec<(CardSpan, Vec<f32>)>,
+}
+
+/// 重排序分数缓存：(query_hash, chunk_id) -> score
+type RerankCache = Mutex<HashMap<(u64, String), f32>>;
+
+/// ANN 索引文件名（位于 derived/embeddings/）
+const ANN_INDEX_FILE: &str = "index.hnsw";
+
+/// ANN 取候选的倍数，候选再按精确余弦相似度重新打分
+const ANN_CANDIDATE_FACTOR: usize = 4;
+
+/// ANN 检索的最小候选集大小
+const ANN_EF_SEARCH: usize = 64;
+
 /// RAG 服务
 pub struct RAGService {
     db: Arc<Database>,
     embedding_service: EmbeddingService,
     vault_path: Option<std::path::PathBuf>,
+    scorer: Arc<dyn RelevanceScorer>,
+    rerank_cache: RerankCache,
+    /// 单卡问答的向量缓存：card_id -> 索引
+    card_indexes: Mutex<HashMap<String, Arc<CardIndex>>>,
+    /// 全部分块的 ANN 索引，不存在时相似度搜索回退到线性扫描
+    ann: Mutex<Option<HnswIndex>>,
+    ann_loaded: AtomicBool,
 }
 
 impl RAGService {
@@ -33,13 +262,34 @@ impl RAGService {
             db,
             embedding_service: EmbeddingService::new(embedding_port),
             vault_path,
+            scorer: Arc::new(ChatRelevanceScorer::new(embedding_port)),
+            rerank_cache: Mutex::new(HashMap::new()),
+            card_indexes: Mutex::new(HashMap::new()),
+            ann: Mutex::new(None),
+            ann_loaded: AtomicBool::new(false),
         }
     }
 
-    /// 索引文献源内容
-    pub async fn index_source(&self, source_id: &str, content: &str) -> Result<(), RAGError> {
-        // 将内容分块（简单实现：按段落分割）
-        let chunks = Self::chunk_text(content, 500); // 每块约 500 字符
+    /// 向量化请求使用自定义重试策略
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.embedding_service = self.embedding_service.with_retry_policy(retry);
+        self
+    }
+
+    /// 索引文献源内容，每完成一块回调 (已完成, 总数)，返回总块数
+    ///
+    /// 完成后清理上次索引遗留的多余分块，并记录本次使用的分块参数和向量模型
+    pub async fn index_source(
+        &self,
+        source_id: &str,
+        content: &str,
+        config: &ChunkConfig,
+        embedding_model: &str,
+        on_progress: Option<Box<dyn Fn(usize, usize) + Send + Sync>>,
+    ) -> Result<usize, RAGError> {
+        let started_at = chrono::Utc::now().timestamp_millis();
+        let chunks = Self::chunk_text(content, config);
+        let total = chunks.len();
 
         for (index, chunk) in chunks.iter().enumerate() {
             // 向量化
@@ -47,24 +297,188 @@ impl RAGService {
 
             // 存储到数据库
             self.store_embedding(source_id, index, chunk, &embedding).await?;
+
+            if let Some(ref callback) = on_progress {
+                callback(index + 1, total);
+            }
+        }
+
+        // 本次未覆盖到的旧分块已过期
+        let stale: Vec<String> = sqlx::query_scalar(
+            "SELECT id FROM embeddings WHERE source_id = ? AND created_at < ?",
+        )
+        .bind(source_id)
+        .bind(started_at)
+        .fetch_all(self.db.pool())
+        .await?;
+        self.remove_embeddings(&stale).await?;
+        self.sync_ann_index().await;
+
+        self.save_index_meta(&SourceIndexMeta {
+            source_id: source_id.to_string(),
+            chunk_config: config.clone(),
+            embedding_model: embedding_model.to_string(),
+            chunk_count: total,
+            indexed_at: chrono::Utc::now().timestamp_millis(),
+        })
+        .await?;
+
+        Ok(total)
+    }
+
+    /// 重新索引文献源；分块参数或向量模型与上次不同时先清空旧向量，避免混用
+    pub async fn reindex_source(
+        &self,
+        source_id: &str,
+        content: &str,
+        config: &ChunkConfig,
+        embedding_model: &str,
+        on_progress: Option<Box<dyn Fn(usize, usize) + Send + Sync>>,
+    ) -> Result<ReindexResult, RAGError> {
+        let config_changed = self
+            .get_index_meta(source_id)
+            .await?
+            .map(|meta| meta.chunk_config != *config || meta.embedding_model != embedding_model)
+            .unwrap_or(false);
+
+        if config_changed {
+            self.clear_source_index(source_id).await?;
         }
 
+        let chunk_count = self
+            .index_source(source_id, content, config, embedding_model, on_progress)
+            .await?;
+        Ok(ReindexResult {
+            chunk_count,
+            config_changed,
+        })
+    }
+
+    /// 删除文献源的全部向量和索引元数据
+    pub async fn clear_source_index(&self, source_id: &str) -> Result<(), RAGError> {
+        let ids: Vec<String> = sqlx::query_scalar("SELECT id FROM embeddings WHERE source_id = ?")
+            .bind(source_id)
+            .fetch_all(self.db.pool())
+            .await?;
+        self.remove_embeddings(&ids).await?;
+        self.sync_ann_index().await;
+
+        sqlx::query("DELETE FROM source_index_meta WHERE source_id = ?")
+            .bind(source_id)
+            .execute(self.db.pool())
+            .await?;
+        Ok(())
+    }
+
+    /// 删除指定分块的数据库记录和向量文件
+    async fn remove_embeddings(&self, ids: &[String]) -> Result<(), RAGError> {
+        self.load_ann_index().await?;
+        for id in ids {
+            if let Some(index) = self.ann.lock().unwrap().as_mut() {
+                index.remove(id);
+            }
+            sqlx::query("DELETE FROM embeddings WHERE id = ?")
+                .bind(id)
+                .execute(self.db.pool())
+                .await?;
+            if let Some(ref vault_path) = self.vault_path {
+                let embeddings_dir = vault_path.join("derived").join("embeddings");
+                let _ = fs::remove_file(embeddings_dir.join(format!("{}.bin", id)));
+                let _ = fs::remove_file(embeddings_dir.join(format!("{}.txt", id)));
+            }
+        }
+        Ok(())
+    }
+
+    /// 读取文献源的索引元数据
+    pub async fn get_index_meta(&self, source_id: &str) -> Result<Option<SourceIndexMeta>, RAGError> {
+        let row = sqlx::query(
+            "SELECT source_id, chunk_size, chunk_overlap, embedding_model, chunk_count, indexed_at
+             FROM source_index_meta WHERE source_id = ?",
+        )
+        .bind(source_id)
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        Ok(row.map(|row| Self::row_to_index_meta(&row)))
+    }
+
+    async fn save_index_meta(&self, meta: &SourceIndexMeta) -> Result<(), RAGError> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO source_index_meta
+             (source_id, chunk_size, chunk_overlap, embedding_model, chunk_count, indexed_at)
+             VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&meta.source_id)
+        .bind(meta.chunk_config.chunk_size as i64)
+        .bind(meta.chunk_config.overlap as i64)
+        .bind(&meta.embedding_model)
+        .bind(meta.chunk_count as i64)
+        .bind(meta.indexed_at)
+        .execute(self.db.pool())
+        .await?;
         Ok(())
     }
 
+    fn row_to_index_meta(row: &sqlx::sqlite::SqliteRow) -> SourceIndexMeta {
+        SourceIndexMeta {
+            source_id: row.get(0),
+            chunk_config: ChunkConfig {
+                chunk_size: row.get::<i64, _>(1) as usize,
+                overlap: row.get::<i64, _>(2) as usize,
+            },
+            embedding_model: row.get(3),
+            chunk_count: row.get::<i64, _>(4) as usize,
+            indexed_at: row.get(5),
+        }
+    }
+
+    /// 各文献源的向量数量及其索引参数
+    pub async fn get_coverage(&self) -> Result<Vec<RagCoverage>, RAGError> {
+        let rows = sqlx::query(
+            "SELECT source_id, COUNT(*) FROM embeddings GROUP BY source_id ORDER BY source_id",
+        )
+        .fetch_all(self.db.pool())
+        .await?;
+
+        let mut coverage = Vec::with_capacity(rows.len());
+        for row in rows {
+            let source_id: String = row.get(0);
+            let chunk_count = row.get::<i64, _>(1) as usize;
+            let index_meta = self.get_index_meta(&source_id).await?;
+            coverage.push(RagCoverage {
+                source_id,
+                chunk_count,
+                index_meta,
+            });
+        }
+        Ok(coverage)
+    }
+
     /// 相似度搜索
     pub async fn search_similar(
         &self,
         query: &str,
         limit: usize,
         source_id: Option<&str>,
+        rerank: bool,
     ) -> Result<Vec<SearchResult>, RAGError> {
         // 向量化查询
         let query_embedding = self.embedding_service.embed(query).await?;
 
+        // 未限定文献源时先从 ANN 索引取候选，再精确重新打分；没有索引时线性扫描
+        self.load_ann_index().await?;
+        let wanted = if rerank { limit * 3 } else { limit };
+        let candidate_ids = match source_id {
+            Some(_) => None,
+            None => self.ann_candidates(&query_embedding, wanted * ANN_CANDIDATE_FACTOR),
+        };
+
         // 从数据库检索元数据（异步）
         let pool = self.db.pool();
-        let rows = if let Some(sid) = source_id {
+        let rows = if let Some(ids) = candidate_ids {
+            self.embedding_rows(&ids).await?
+        } else if let Some(sid) = source_id {
             sqlx::query(
                 "SELECT id, source_id, content, vector FROM embeddings WHERE source_id = ? ORDER BY id"
             )
@@ -87,28 +501,8 @@ impl RAGService {
             let content: String = row.get(2);
             let vector_bytes_db: Vec<u8> = row.get(3);
             
-            // 从文件系统读取向量，如果不存在则使用数据库中的（向后兼容）
-            let stored_embedding: Vec<f32> = if let Some(ref vault_path) = self.vault_path {
-                let embedding_file = vault_path.join("derived").join("embeddings").join(format!("{}.bin", id));
-                if embedding_file.exists() {
-                    let vector_bytes = fs::read(&embedding_file)
-                        .map_err(|e| RAGError::Serialization(format!("Failed to read embedding file: {}", e)))?;
-                    bincode::deserialize(&vector_bytes)
-                        .map_err(|e| RAGError::Serialization(format!("Failed to deserialize vector: {}", e)))?
-                } else if !vector_bytes_db.is_empty() {
-                    // 向后兼容：从数据库读取（旧数据）
-                    bincode::deserialize(&vector_bytes_db)
-                        .map_err(|e| RAGError::Serialization(format!("Failed to deserialize vector: {}", e)))?
-                } else {
-                    continue; // 跳过没有向量的记录
-                }
-            } else {
-                // 没有 vault_path，从数据库读取（向后兼容）
-                if vector_bytes_db.is_empty() {
-                    continue; // 跳过没有向量的记录
-                }
-                bincode::deserialize(&vector_bytes_db)
-                    .map_err(|e| RAGError::Serialization(format!("Failed to deserialize vector: {}", e)))?
+            let Some(stored_embedding) = self.load_vector(&id, &vector_bytes_db)? else {
+                continue; // 跳过没有向量的记录
             };
 
             // 计算相似度
@@ -122,13 +516,494 @@ impl RAGService {
             });
         }
 
-        // 按相似度排序并取前 limit 个
+        // 按相似度排序；需要重排序时先多取 3 倍候选
         search_results.sort_by(|a, b| b.similarity.partial_cmp(&a.similarity).unwrap_or(std::cmp::Ordering::Equal));
-        search_results.truncate(limit);
 
+        if rerank {
+            search_results.truncate(limit * 3);
+            return Self::rerank_results(
+                self.scorer.as_ref(),
+                &self.rerank_cache,
+                query,
+                search_results,
+                limit,
+            )
+            .await;
+        }
+
+        search_results.truncate(limit);
         Ok(search_results)
     }
 
+    /// 按 ID 读取分块记录
+    async fn embedding_rows(&self, ids: &[String]) -> Result<Vec<sqlx::sqlite::SqliteRow>, RAGError> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let sql = format!(
+            "SELECT id, source_id, content, vector FROM embeddings WHERE id IN ({}) ORDER BY id",
+            vec!["?"; ids.len()].join(", ")
+        );
+        let mut query = sqlx::query(&sql);
+        for id in ids {
+            query = query.bind(id);
+        }
+        Ok(query.fetch_all(self.db.pool()).await?)
+    }
+
+    /// 从 ANN 索引取最相近的 k 个分块 ID；索引不存在、为空或维度不符时返回 None
+    fn ann_candidates(&self, query_embedding: &[f32], k: usize) -> Option<Vec<String>> {
+        let guard = self.ann.lock().unwrap();
+        let index = guard.as_ref()?;
+        if index.is_empty() || index.dimension() != query_embedding.len() {
+            return None;
+        }
+        Some(
+            index
+                .search(query_embedding, k, ANN_EF_SEARCH.max(k))
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect(),
+        )
+    }
+
+    fn ann_index_path(&self) -> Option<PathBuf> {
+        self.vault_path
+            .as_ref()
+            .map(|p| p.join("derived").join("embeddings").join(ANN_INDEX_FILE))
+    }
+
+    /// 首次使用时加载 ANN 索引；与数据库中的向量数不一致（如上次未保存就退出）时丢弃
+    async fn load_ann_index(&self) -> Result<(), RAGError> {
+        if self.ann_loaded.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+        let Some(path) = self.ann_index_path().filter(|p| p.exists()) else {
+            return Ok(());
+        };
+        let index = tokio::task::spawn_blocking(move || HnswIndex::load(&path))
+            .await
+            .map_err(|e| RAGError::Serialization(e.to_string()))?;
+        let index = match index {
+            Ok(index) => index,
+            Err(e) => {
+                eprintln!("Failed to load vector index, falling back to linear search: {}", e);
+                return Ok(());
+            }
+        };
+
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM embeddings")
+            .fetch_one(self.db.pool())
+            .await?;
+        if index.len() as i64 == count {
+            *self.ann.lock().unwrap() = Some(index);
+        } else {
+            eprintln!(
+                "Vector index is out of date ({} vectors, {} stored), falling back to linear search",
+                index.len(),
+                count
+            );
+        }
+        Ok(())
+    }
+
+    /// 将 ANN 索引写回磁盘
+    fn save_ann_index(&self) {
+        let Some(path) = self.ann_index_path() else {
+            return;
+        };
+        if let Some(index) = self.ann.lock().unwrap().as_ref() {
+            if let Err(e) = index.save(&path) {
+                eprintln!("Failed to save vector index: {}", e);
+            }
+        }
+    }
+
+    /// 批量写入或删除后调用：保存 ANN 索引，索引不存在或删除过多时重建
+    async fn sync_ann_index(&self) {
+        let needs_rebuild = self
+            .ann
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_none_or(HnswIndex::needs_rebuild);
+        if !needs_rebuild {
+            self.save_ann_index();
+        } else if let Err(e) = self.rebuild_ann_index().await {
+            eprintln!("Failed to rebuild vector index: {}", e);
+        }
+    }
+
+    /// 从已存储的向量重建 ANN 索引并保存，返回索引的向量数
+    pub async fn rebuild_ann_index(&self) -> Result<usize, RAGError> {
+        let rows = sqlx::query("SELECT id, vector FROM embeddings ORDER BY id")
+            .fetch_all(self.db.pool())
+            .await?;
+        let mut vectors = Vec::with_capacity(rows.len());
+        for row in rows {
+            let id: String = row.get(0);
+            let vector_bytes_db: Vec<u8> = row.get(1);
+            if let Some(vector) = self.load_vector(&id, &vector_bytes_db)? {
+                vectors.push((id, vector));
+            }
+        }
+
+        let index = tokio::task::spawn_blocking(move || -> Result<Option<HnswIndex>, String> {
+            let Some(dimension) = vectors.first().map(|(_, v)| v.len()) else {
+                return Ok(None);
+            };
+            let mut index = HnswIndex::new(dimension);
+            for (id, vector) in &vectors {
+                index.insert(id, vector)?;
+            }
+            Ok(Some(index))
+        })
+        .await
+        .map_err(|e| RAGError::Serialization(e.to_string()))?
+        .map_err(RAGError::Incompatible)?;
+
+        let count = index.as_ref().map_or(0, HnswIndex::len);
+        let empty = index.is_none();
+        *self.ann.lock().unwrap() = index;
+        self.ann_loaded.store(true, Ordering::SeqCst);
+        if empty {
+            if let Some(path) = self.ann_index_path() {
+                let _ = fs::remove_file(path);
+            }
+        } else {
+            self.save_ann_index();
+        }
+        Ok(count)
+    }
+
+    /// 读取分块向量：优先读文件系统，不存在时使用数据库中的（向后兼容）
+    fn load_vector(&self, id: &str, vector_bytes_db: &[u8]) -> Result<Option<Vec<f32>>, RAGError> {
+        if let Some(ref vault_path) = self.vault_path {
+            let embedding_file = vault_path.join("derived").join("embeddings").join(format!("{}.bin", id));
+            if embedding_file.exists() {
+                let vector_bytes = fs::read(&embedding_file)
+                    .map_err(|e| RAGError::Serialization(format!("Failed to read embedding file: {}", e)))?;
+                return bincode::deserialize(&vector_bytes)
+                    .map(Some)
+                    .map_err(|e| RAGError::Serialization(format!("Failed to deserialize vector: {}", e)));
+            }
+        }
+        if vector_bytes_db.is_empty() {
+            return Ok(None);
+        }
+        bincode::deserialize(vector_bytes_db)
+            .map(Some)
+            .map_err(|e| RAGError::Serialization(format!("Failed to deserialize vector: {}", e)))
+    }
+
+    /// 将全部向量、索引参数和模型信息导出为单个文件，用于备份或迁移到其他设备
+    pub async fn export_embeddings(
+        &self,
+        dest: &std::path::Path,
+        embedding_model: &str,
+    ) -> Result<EmbeddingsTransfer, RAGError> {
+        let rows = sqlx::query(
+            "SELECT id, source_id, content, vector, created_at FROM embeddings ORDER BY id",
+        )
+        .fetch_all(self.db.pool())
+        .await?;
+
+        let mut chunks = Vec::with_capacity(rows.len());
+        for row in rows {
+            let id: String = row.get(0);
+            let vector_bytes_db: Vec<u8> = row.get(3);
+            let Some(vector) = self.load_vector(&id, &vector_bytes_db)? else {
+                continue;
+            };
+            chunks.push(ExportedChunk {
+                id,
+                source_id: row.get(1),
+                content: row.get(2),
+                created_at: row.get(4),
+                vector,
+            });
+        }
+
+        let dimension = chunks.first().map(|c| c.vector.len()).unwrap_or(0);
+        if let Some(chunk) = chunks.iter().find(|c| c.vector.len() != dimension) {
+            return Err(RAGError::Incompatible(format!(
+                "chunk {} has dimension {}, expected {}; reindex before exporting",
+                chunk.id,
+                chunk.vector.len(),
+                dimension
+            )));
+        }
+
+        let index_meta = sqlx::query(
+            "SELECT source_id, chunk_size, chunk_overlap, embedding_model, chunk_count, indexed_at
+             FROM source_index_meta ORDER BY source_id",
+        )
+        .fetch_all(self.db.pool())
+        .await?
+        .iter()
+        .map(Self::row_to_index_meta)
+        .collect();
+
+        let export = EmbeddingsExport {
+            version: EMBEDDINGS_EXPORT_VERSION,
+            embedding_model: embedding_model.to_string(),
+            dimension,
+            exported_at: chrono::Utc::now().timestamp_millis(),
+            index_meta,
+            chunks,
+        };
+        let bytes = bincode::serialize(&export)
+            .map_err(|e| RAGError::Serialization(format!("Failed to serialize embeddings: {}", e)))?;
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| RAGError::Serialization(format!("Failed to create export directory: {}", e)))?;
+        }
+        fs::write(dest, bytes)
+            .map_err(|e| RAGError::Serialization(format!("Failed to write export file: {}", e)))?;
+
+        Ok(EmbeddingsTransfer {
+            embedding_model: export.embedding_model,
+            dimension,
+            source_count: Self::distinct_sources(&export.chunks).len(),
+            chunk_count: export.chunks.len(),
+            skipped_sources: Vec::new(),
+        })
+    }
+
+    /// 从导出文件恢复向量
+    ///
+    /// 导出时的模型必须与当前模型一致，维度必须与库中已有向量一致；
+    /// 导入的文献源会先清空旧向量，当前库中不存在的文献源被跳过
+    pub async fn import_embeddings(
+        &self,
+        src: &std::path::Path,
+        embedding_model: &str,
+    ) -> Result<EmbeddingsTransfer, RAGError> {
+        let bytes = fs::read(src)
+            .map_err(|e| RAGError::Serialization(format!("Failed to read export file: {}", e)))?;
+        let export: EmbeddingsExport = bincode::deserialize(&bytes)
+            .map_err(|e| RAGError::Serialization(format!("Invalid embeddings export: {}", e)))?;
+
+        if export.version != EMBEDDINGS_EXPORT_VERSION {
+            return Err(RAGError::Incompatible(format!(
+                "unsupported export version {}",
+                export.version
+            )));
+        }
+        if export.embedding_model != embedding_model {
+            return Err(RAGError::Incompatible(format!(
+                "exported with model {}, current model is {}",
+                export.embedding_model, embedding_model
+            )));
+        }
+        if export.chunks.iter().any(|c| c.vector.len() != export.dimension) {
+            return Err(RAGError::Incompatible("export file contains vectors of mixed dimension".to_string()));
+        }
+        if let Some(current) = self.current_dimension().await? {
+            if current != export.dimension {
+                return Err(RAGError::Incompatible(format!(
+                    "exported vectors have dimension {}, existing vectors have {}",
+                    export.dimension, current
+                )));
+            }
+        }
+
+        let mut imported_sources = Vec::new();
+        let mut skipped_sources = Vec::new();
+        for source_id in Self::distinct_sources(&export.chunks) {
+            let exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM sources WHERE id = ?)")
+                .bind(&source_id)
+                .fetch_one(self.db.pool())
+                .await?;
+            if exists {
+                self.clear_source_index(&source_id).await?;
+                imported_sources.push(source_id);
+            } else {
+                skipped_sources.push(source_id);
+            }
+        }
+
+        let mut chunk_count = 0;
+        for chunk in &export.chunks {
+            if skipped_sources.contains(&chunk.source_id) {
+                continue;
+            }
+            self.write_embedding(&chunk.id, &chunk.source_id, &chunk.content, &chunk.vector, chunk.created_at)
+                .await?;
+            chunk_count += 1;
+        }
+        self.sync_ann_index().await;
+        for meta in &export.index_meta {
+            if imported_sources.contains(&meta.source_id) {
+                self.save_index_meta(meta).await?;
+            }
+        }
+
+        Ok(EmbeddingsTransfer {
+            embedding_model: export.embedding_model,
+            dimension: export.dimension,
+            source_count: imported_sources.len(),
+            chunk_count,
+            skipped_sources,
+        })
+    }
+
+    /// 库中已有向量的维度，没有向量时返回 None
+    async fn current_dimension(&self) -> Result<Option<usize>, RAGError> {
+        let rows = sqlx::query("SELECT id, vector FROM embeddings ORDER BY id")
+            .fetch_all(self.db.pool())
+            .await?;
+        for row in rows {
+            let id: String = row.get(0);
+            let vector_bytes_db: Vec<u8> = row.get(1);
+            if let Some(vector) = self.load_vector(&id, &vector_bytes_db)? {
+                return Ok(Some(vector.len()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// 分块涉及的文献源（保持首次出现的顺序）
+    fn distinct_sources(chunks: &[ExportedChunk]) -> Vec<String> {
+        let mut sources: Vec<String> = Vec::new();
+        for chunk in chunks {
+            if !sources.contains(&chunk.source_id) {
+                sources.push(chunk.source_id.clone());
+            }
+        }
+        sources
+    }
+
+    /// 使用打分器对候选结果重排序，保留前 limit 个
+    async fn rerank_results(
+        scorer: &dyn RelevanceScorer,
+        cache: &RerankCache,
+        query: &str,
+        candidates: Vec<SearchResult>,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, RAGError> {
+        let query_hash = {
+            let mut hasher = DefaultHasher::new();
+            query.hash(&mut hasher);
+            hasher.finish()
+        };
+
+        let mut scored = Vec::with_capacity(candidates.len());
+        for candidate in candidates {
+            let key = (query_hash, candidate.id.clone());
+            let cached = cache.lock().unwrap().get(&key).copied();
+            let score = match cached {
+                Some(score) => score,
+                None => {
+                    let score = scorer.score(query, &candidate.content).await?;
+                    cache.lock().unwrap().insert(key, score);
+                    score
+                }
+            };
+            scored.push((score, candidate));
+        }
+
+        // 模型分数优先，分数相同时按余弦相似度
+        scored.sort_by(|(sa, a), (sb, b)| {
+            sb.partial_cmp(sa)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(b.similarity.partial_cmp(&a.similarity).unwrap_or(std::cmp::Ordering::Equal))
+        });
+
+        Ok(scored.into_iter().take(limit).map(|(_, r)| r).collect())
+    }
+
+    /// 取回卡片中与问题最相关的片段，按在卡片中的位置排序
+    ///
+    /// 卡片首次被提问或修改后会按需向量化，结果缓存在内存中
+    pub async fn retrieve_card_spans(
+        &self,
+        card_id: &str,
+        modified_at: i64,
+        text: &str,
+        question: &str,
+    ) -> Result<Vec<CardSpan>, RAGError> {
+        let index = self.card_index(card_id, modified_at, text).await?;
+        let query_embedding = self.embedding_service.embed(question).await?;
+
+        let mut scored: Vec<(f32, &CardSpan)> = index
+            .chunks
+            .iter()
+            .map(|(span, embedding)| {
+                (EmbeddingService::cosine_similarity(&query_embedding, embedding), span)
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        let mut spans: Vec<CardSpan> = scored
+            .into_iter()
+            .take(CARD_QA_SPAN_LIMIT)
+            .map(|(_, span)| span.clone())
+            .collect();
+        spans.sort_by_key(|span| span.start);
+        Ok(spans)
+    }
+
+    /// 读取或构建卡片的向量索引
+    async fn card_index(
+        &self,
+        card_id: &str,
+        modified_at: i64,
+        text: &str,
+    ) -> Result<Arc<CardIndex>, RAGError> {
+        let cached = self.card_indexes.lock().unwrap().get(card_id).cloned();
+        if let Some(index) = cached.filter(|index| index.modified_at == modified_at) {
+            return Ok(index);
+        }
+
+        let mut chunks = Vec::new();
+        for span in Self::chunk_spans(text, ChunkConfig::default().chunk_size) {
+            let embedding = self.embedding_service.embed(&span.text).await?;
+            chunks.push((span, embedding));
+        }
+        let index = Arc::new(CardIndex { modified_at, chunks });
+        self.card_indexes
+            .lock()
+            .unwrap()
+            .insert(card_id.to_string(), index.clone());
+        Ok(index)
+    }
+
+    /// 构建单卡问答 Prompt，要求模型用 [n] 标注引用的片段
+    pub fn build_card_prompt(question: &str, spans: &[CardSpan]) -> String {
+        let mut prompt = String::from("你是一个知识助手。请只根据以下笔记片段回答用户的问题，");
+        prompt.push_str("并在句末用 [编号] 标注所依据的片段。\n\n");
+        prompt.push_str("笔记片段：\n");
+
+        for (i, span) in spans.iter().enumerate() {
+            prompt.push_str(&format!("[{}] {}\n", i + 1, span.text));
+        }
+
+        prompt.push_str("\n问题：");
+        prompt.push_str(question);
+        prompt.push_str("\n\n如果片段中没有相关信息，请说明。");
+
+        prompt
+    }
+
+    /// 挑出回答中以 [n] 引用的片段；回答没有引用标记时返回全部片段
+    pub fn cited_spans(answer: &str, spans: Vec<CardSpan>) -> Vec<CardSpan> {
+        let citation = regex::Regex::new(r"\[(\d+)\]").unwrap();
+        let cited: std::collections::HashSet<usize> = citation
+            .captures_iter(answer)
+            .filter_map(|c| c[1].parse::<usize>().ok())
+            .collect();
+        if cited.is_empty() {
+            return spans;
+        }
+        spans
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| cited.contains(&(i + 1)))
+            .map(|(_, span)| span)
+            .collect()
+    }
+
     /// 构建 RAG Prompt
     pub fn build_rag_prompt(query: &str, context: Vec<SearchResult>) -> String {
         let mut prompt = String::from("你是一个知识助手。请基于以下上下文回答用户的问题。\n\n");
@@ -154,7 +1029,21 @@ impl RAGService {
         embedding: &[f32],
     ) -> Result<(), RAGError> {
         let id = format!("{}_{}", source_id, chunk_index);
-        
+        self.write_embedding(&id, source_id, content, embedding, chrono::Utc::now().timestamp_millis())
+            .await
+    }
+
+    /// 写入分块向量文件和数据库记录
+    async fn write_embedding(
+        &self,
+        id: &str,
+        source_id: &str,
+        content: &str,
+        embedding: &[f32],
+        created_at: i64,
+    ) -> Result<(), RAGError> {
+        self.load_ann_index().await?;
+
         // 如果有 vault_path，保存到文件系统
         if let Some(ref vault_path) = self.vault_path {
             let embeddings_dir = vault_path.join("derived").join("embeddings");
@@ -184,36 +1073,93 @@ impl RAGService {
         };
         
         sqlx::query(
-            "INSERT OR REPLACE INTO embeddings (id, source_id, content, vector) 
-             VALUES (?, ?, ?, ?)"
+            "INSERT OR REPLACE INTO embeddings (id, source_id, content, vector, created_at) 
+             VALUES (?, ?, ?, ?, ?)"
         )
-        .bind(&id)
+        .bind(id)
         .bind(source_id)
         .bind(content)
         .bind(&vector_bytes)
+        .bind(created_at)
         .execute(self.db.pool())
         .await?;
 
+        // 增量加入 ANN 索引，维度变化（换了向量模型）时索引失效，直到重建前使用线性扫描
+        let mut ann = self.ann.lock().unwrap();
+        if let Some(Err(e)) = ann.as_mut().map(|index| index.insert(id, embedding)) {
+            eprintln!("Dropping vector index: {}", e);
+            *ann = None;
+        }
+
         Ok(())
     }
 
-    /// 文本分块（简单实现）
-    fn chunk_text(text: &str, chunk_size: usize) -> Vec<String> {
+    /// 按段落切分并记录字符偏移：卡片纯文本每个文本块一行，相邻段落累积到不超过 chunk_size，空行被跳过
+    fn chunk_spans(text: &str, chunk_size: usize) -> Vec<CardSpan> {
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        let mut current: Option<(usize, usize)> = None;
+        let mut offset = 0;
+
+        for paragraph in text.split('\n') {
+            let paragraph_start = offset;
+            offset += paragraph.len() + 1;
+            let trimmed = paragraph.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            let start = paragraph_start + (paragraph.len() - paragraph.trim_start().len());
+            let end = start + trimmed.len();
+
+            current = match current {
+                Some((s, e)) if end - s > chunk_size => {
+                    ranges.push((s, e));
+                    Some((start, end))
+                }
+                Some((s, _)) => Some((s, end)),
+                None => Some((start, end)),
+            };
+        }
+        ranges.extend(current);
+
+        ranges
+            .into_iter()
+            .map(|(start, end)| CardSpan {
+                start: text[..start].chars().count(),
+                end: text[..end].chars().count(),
+                text: text[start..end].to_string(),
+            })
+            .collect()
+    }
+
+    /// 文本分块：按段落累积到 chunk_size，新块以上一块末尾 overlap 个字符开头
+    fn chunk_text(text: &str, config: &ChunkConfig) -> Vec<String> {
         let mut chunks = Vec::new();
         let mut current_chunk = String::new();
+        // current_chunk 中是否有重叠部分以外的新内容
+        let mut has_new_content = false;
 
         for paragraph in text.split("\n\n") {
-            if current_chunk.len() + paragraph.len() > chunk_size && !current_chunk.is_empty() {
-                chunks.push(current_chunk.trim().to_string());
-                current_chunk = String::new();
+            if current_chunk.len() + paragraph.len() > config.chunk_size && has_new_content {
+                let finished = current_chunk.trim().to_string();
+                let tail_start = finished
+                    .char_indices()
+                    .rev()
+                    .nth(config.overlap.saturating_sub(1))
+                    .map(|(i, _)| i)
+                    .filter(|_| config.overlap > 0)
+                    .unwrap_or(finished.len());
+                current_chunk = finished[tail_start..].to_string();
+                chunks.push(finished);
+                has_new_content = false;
             }
             if !current_chunk.is_empty() {
                 current_chunk.push_str("\n\n");
             }
             current_chunk.push_str(paragraph);
+            has_new_content |= !paragraph.trim().is_empty();
         }
 
-        if !current_chunk.trim().is_empty() {
+        if has_new_content && !current_chunk.trim().is_empty() {
             chunks.push(current_chunk.trim().to_string());
         }
 
@@ -229,3 +1175,212 @@ pub struct SearchResult {
     pub similarity: f32,
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 只认包含 "answer" 的片段
+    struct MockScorer;
+
+    impl RelevanceScorer for MockScorer {
+        fn score<'a>(&'a self, _query: &'a str, passage: &'a str) -> BoxFuture<'a, Result<f32, RAGError>> {
+            Box::pin(async move { Ok(if passage.contains("answer") { 9.0 } else { 1.0 }) })
+        }
+    }
+
+    fn result(id: &str, content: &str, similarity: f32) -> SearchResult {
+        SearchResult {
+            id: id.to_string(),
+            source_id: "s".to_string(),
+            content: content.to_string(),
+            similarity,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rerank_reorders_results() {
+        let cache = Mutex::new(HashMap::new());
+        let candidates = vec![
+            result("a", "lexically similar noise", 0.9),
+            result("b", "more noise", 0.8),
+            result("c", "the real answer", 0.5),
+        ];
+
+        let reranked = RAGService::rerank_results(&MockScorer, &cache, "question", candidates, 2)
+            .await
+            .unwrap();
+
+        assert_eq!(reranked.len(), 2);
+        assert_eq!(reranked[0].id, "c");
+        assert_eq!(reranked[1].id, "a");
+        assert_eq!(cache.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_parse_score() {
+        assert_eq!(ChatRelevanceScorer::parse_score("8"), 8.0);
+        assert_eq!(ChatRelevanceScorer::parse_score("分数：7.5"), 7.5);
+        assert_eq!(ChatRelevanceScorer::parse_score("15"), 10.0);
+        assert_eq!(ChatRelevanceScorer::parse_score("无"), 0.0);
+    }
+
+    #[test]
+    fn test_chunk_text_with_overlap() {
+        let text = "aaaa\n\nbbbb\n\ncccc";
+        let plain = RAGService::chunk_text(text, &ChunkConfig { chunk_size: 6, overlap: 0 });
+        assert_eq!(plain, vec!["aaaa", "bbbb", "cccc"]);
+
+        let overlapped = RAGService::chunk_text(text, &ChunkConfig { chunk_size: 6, overlap: 2 });
+        assert_eq!(overlapped, vec!["aaaa", "aa\n\nbbbb", "bb\n\ncccc"]);
+    }
+
+    #[test]
+    fn test_chunk_spans_track_char_offsets() {
+        let text = "aaaa\n\n  bbbb\n\n\n\n中文";
+        let spans = RAGService::chunk_spans(text, 6);
+        let ranges: Vec<(usize, usize)> = spans.iter().map(|s| (s.start, s.end)).collect();
+        assert_eq!(ranges, vec![(0, 4), (8, 12), (16, 18)]);
+        assert_eq!(spans[2].text, "中文");
+        let chars: Vec<char> = text.chars().collect();
+        assert_eq!(chars[8..12].iter().collect::<String>(), "bbbb");
+
+        let merged = RAGService::chunk_spans(text, 100);
+        assert_eq!(merged.len(), 1);
+        assert_eq!((merged[0].start, merged[0].end), (0, 18));
+    }
+
+    #[test]
+    fn test_chunk_spans_from_tiptap_paragraphs() {
+        let content = r#"{"type":"doc","content":[
+            {"type":"heading","attrs":{"level":1},"content":[{"type":"text","text":"Entropy"}]},
+            {"type":"paragraph","content":[{"type":"text","text":"Heat flows "},{"type":"text","marks":[{"type":"bold"}],"text":"downhill"},{"type":"text","text":"."}]},
+            {"type":"bulletList","content":[{"type":"listItem","content":[{"type":"paragraph","content":[{"type":"text","text":"Disorder grows"}]}]}]},
+            {"type":"paragraph"}
+        ]}"#;
+        let text = crate::db::extract_plain_text_from_json(content).unwrap();
+        assert_eq!(text, "Entropy\nHeat flows downhill.\nDisorder grows");
+
+        let spans = RAGService::chunk_spans(&text, 10);
+        let texts: Vec<&str> = spans.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(texts, vec!["Entropy", "Heat flows downhill.", "Disorder grows"]);
+        assert_eq!((spans[1].start, spans[1].end), (8, 28));
+    }
+
+    #[test]
+    fn test_cited_spans() {
+        let span = |start: usize| CardSpan {
+            start,
+            end: start + 1,
+            text: "x".to_string(),
+        };
+        let spans = vec![span(0), span(10), span(20)];
+        let cited = RAGService::cited_spans("答案见 [3] 和 [1]。", spans.clone());
+        assert_eq!(cited, vec![span(0), span(20)]);
+        assert_eq!(RAGService::cited_spans("没有引用", spans.clone()), spans);
+    }
+
+    #[tokio::test]
+    async fn test_export_import_embeddings_round_trip() {
+        use crate::models::{CreateSourceRequest, SourceType};
+
+        let dir = tempfile::tempdir().unwrap();
+        let vault_path = dir.path().to_path_buf();
+        let db = Arc::new(Database::open(&vault_path.join("zentri.db")).await.unwrap());
+        let source = db
+            .create_source(CreateSourceRequest {
+                source_type: SourceType::Book,
+                title: "Book".to_string(),
+                author: None,
+                url: None,
+                cover: None,
+                description: None,
+                tags: vec![],
+                source_origin: None,
+            })
+            .await
+            .unwrap();
+
+        let rag = RAGService::new(db.clone(), 1, Some(vault_path.clone()));
+        rag.store_embedding(&source.id, 0, "first", &[1.0, 0.0, 0.5]).await.unwrap();
+        rag.store_embedding(&source.id, 1, "second", &[0.0, 1.0, 0.5]).await.unwrap();
+        rag.save_index_meta(&SourceIndexMeta {
+            source_id: source.id.clone(),
+            chunk_config: ChunkConfig::default(),
+            embedding_model: "bge-m3".to_string(),
+            chunk_count: 2,
+            indexed_at: 1,
+        })
+        .await
+        .unwrap();
+
+        let export_path = dir.path().join("backup").join("embeddings.bin");
+        let exported = rag.export_embeddings(&export_path, "bge-m3").await.unwrap();
+        assert_eq!(exported.chunk_count, 2);
+        assert_eq!(exported.dimension, 3);
+
+        // 模型不一致时拒绝导入，且不动已有数据
+        let err = rag.import_embeddings(&export_path, "other-model").await.unwrap_err();
+        assert!(matches!(err, RAGError::Incompatible(_)));
+        assert_eq!(rag.get_coverage().await.unwrap()[0].chunk_count, 2);
+
+        rag.clear_source_index(&source.id).await.unwrap();
+        assert!(rag.get_coverage().await.unwrap().is_empty());
+
+        let imported = rag.import_embeddings(&export_path, "bge-m3").await.unwrap();
+        assert_eq!(imported.chunk_count, 2);
+        assert!(imported.skipped_sources.is_empty());
+        let meta = rag.get_index_meta(&source.id).await.unwrap().unwrap();
+        assert_eq!(meta.chunk_count, 2);
+        let id = format!("{}_1", source.id);
+        assert_eq!(rag.load_vector(&id, &[]).unwrap(), Some(vec![0.0, 1.0, 0.5]));
+    }
+
+    #[tokio::test]
+    async fn test_ann_index_persists_and_updates() {
+        use crate::models::{CreateSourceRequest, SourceType};
+
+        let dir = tempfile::tempdir().unwrap();
+        let vault_path = dir.path().to_path_buf();
+        let db = Arc::new(Database::open(&vault_path.join("zentri.db")).await.unwrap());
+        let source = db
+            .create_source(CreateSourceRequest {
+                source_type: SourceType::Book,
+                title: "Book".to_string(),
+                author: None,
+                url: None,
+                cover: None,
+                description: None,
+                tags: vec![],
+                source_origin: None,
+            })
+            .await
+            .unwrap();
+        let chunk = |i: usize| format!("{}_{}", source.id, i);
+
+        let rag = RAGService::new(db.clone(), 1, Some(vault_path.clone()));
+        rag.store_embedding(&source.id, 0, "first", &[1.0, 0.0, 0.0]).await.unwrap();
+        rag.store_embedding(&source.id, 1, "second", &[0.0, 1.0, 0.0]).await.unwrap();
+        // 没有索引时回退到线性扫描
+        assert!(rag.ann_candidates(&[1.0, 0.0, 0.0], 1).is_none());
+
+        assert_eq!(rag.rebuild_ann_index().await.unwrap(), 2);
+        assert!(rag.ann_index_path().unwrap().exists());
+        assert_eq!(rag.ann_candidates(&[0.9, 0.1, 0.0], 1), Some(vec![chunk(0)]));
+
+        // 新写入的向量增量加入索引
+        rag.store_embedding(&source.id, 2, "third", &[0.0, 0.0, 1.0]).await.unwrap();
+        assert_eq!(rag.ann_candidates(&[0.0, 0.1, 0.9], 1), Some(vec![chunk(2)]));
+        rag.save_ann_index();
+
+        // 重新打开时从磁盘加载
+        let reopened = RAGService::new(db.clone(), 1, Some(vault_path.clone()));
+        reopened.load_ann_index().await.unwrap();
+        assert_eq!(reopened.ann_candidates(&[0.0, 0.1, 0.9], 1), Some(vec![chunk(2)]));
+
+        // 清空后索引随之删除
+        reopened.clear_source_index(&source.id).await.unwrap();
+        assert!(reopened.ann_candidates(&[0.0, 0.1, 0.9], 1).is_none());
+        assert!(!reopened.ann_index_path().unwrap().exists());
+    }
+}
@@ -1,12 +1,14 @@
 //! Sidecar 进程管理
 //! 负责启动、停止和监控 llama-server 进程
 
-use std::path::PathBuf;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::sync::Arc;
 use tokio::sync::{mpsc, Mutex};
 use tokio::process::Command as TokioCommand;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use serde::Serialize;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -21,18 +23,203 @@ pub enum SidecarError {
     PortInUse(u16),
 }
 
+/// 进程事件（输出行写入日志缓冲区，不经过事件通道）
 #[derive(Debug, Clone)]
 pub enum CommandEvent {
-    Stdout(String),
-    Stderr(String),
     Terminated { code: Option<i32> },
 }
 
+/// 日志缓冲区保留的最大行数
+pub const SIDECAR_LOG_CAPACITY: usize = 2000;
+
+/// 输出流
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+/// 一行 sidecar 输出
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SidecarLogLine {
+    pub stream: LogStream,
+    pub line: String,
+    pub timestamp: i64,
+}
+
+/// 新日志行的回调（用于向前端推送实时日志）
+pub type LogListener = Arc<dyn Fn(&SidecarLogLine) + Send + Sync>;
+
+/// 环形日志缓冲区，写满后丢弃最旧的行
+#[derive(Clone)]
+pub struct LogBuffer {
+    lines: Arc<std::sync::Mutex<VecDeque<SidecarLogLine>>>,
+    capacity: usize,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            lines: Arc::new(std::sync::Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    pub fn push(&self, line: SidecarLogLine) {
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() == self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    pub fn clear(&self) {
+        self.lines.lock().unwrap().clear();
+    }
+
+    /// 最近的 `n` 行，按时间先后排列
+    pub fn tail(&self, n: usize) -> Vec<SidecarLogLine> {
+        let lines = self.lines.lock().unwrap();
+        lines.iter().skip(lines.len().saturating_sub(n)).cloned().collect()
+    }
+}
+
+/// 逐行读取子进程输出，写入日志缓冲区并通知监听者
+fn spawn_log_reader<R>(reader: R, stream: LogStream, logs: LogBuffer, on_log: Option<LogListener>)
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    tauri::async_runtime::spawn(async move {
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let entry = SidecarLogLine {
+                stream,
+                line,
+                timestamp: chrono::Utc::now().timestamp_millis(),
+            };
+            if let Some(on_log) = &on_log {
+                on_log(&entry);
+            }
+            logs.push(entry);
+        }
+    });
+}
+
+/// 本机硬件信息（检测失败的字段为 None）
+#[derive(Debug, Clone, Serialize)]
+pub struct HardwareInfo {
+    pub total_ram_gb: Option<f32>,
+    pub available_ram_gb: Option<f32>,
+    pub cpu_cores: Option<usize>,
+    /// 可用的 GPU 加速后端："metal" / "cuda" / "vulkan"
+    pub gpu_backend: Option<String>,
+}
+
+/// 有 GPU 后端时传给 `--n-gpu-layers` 的层数，超过模型层数时 llama-server 会卸载全部层
+const ALL_GPU_LAYERS: u32 = 999;
+
+impl HardwareInfo {
+    pub fn gpu_available(&self) -> bool {
+        self.gpu_backend.is_some()
+    }
+
+    /// 启动 llama-server 时卸载到 GPU 的层数：有 GPU 后端时全部卸载，否则只用 CPU
+    pub fn gpu_layers(&self) -> u32 {
+        if self.gpu_available() {
+            ALL_GPU_LAYERS
+        } else {
+            0
+        }
+    }
+}
+
+const BYTES_PER_GB: f32 = 1024.0 * 1024.0 * 1024.0;
+
+/// 检测本机硬件，用于判断模型能否流畅运行
+pub fn detect_hardware() -> HardwareInfo {
+    let (total_ram_gb, available_ram_gb) = detect_memory();
+    HardwareInfo {
+        total_ram_gb,
+        available_ram_gb,
+        cpu_cores: std::thread::available_parallelism().ok().map(|n| n.get()),
+        gpu_backend: detect_gpu_backend(),
+    }
+}
+
+/// 读取 /proc/meminfo（单位 kB）
+#[cfg(target_os = "linux")]
+fn detect_memory() -> (Option<f32>, Option<f32>) {
+    let Ok(meminfo) = std::fs::read_to_string("/proc/meminfo") else {
+        return (None, None);
+    };
+    let read_kb = |key: &str| {
+        meminfo
+            .lines()
+            .find(|line| line.starts_with(key))
+            .and_then(|line| line.split_whitespace().nth(1))
+            .and_then(|v| v.parse::<f32>().ok())
+            .map(|kb| kb * 1024.0 / BYTES_PER_GB)
+    };
+    (read_kb("MemTotal:"), read_kb("MemAvailable:"))
+}
+
+/// 通过 sysctl 读取物理内存；可用内存无可靠的简单来源，保守返回 None
+#[cfg(target_os = "macos")]
+fn detect_memory() -> (Option<f32>, Option<f32>) {
+    let total = std::process::Command::new("sysctl")
+        .args(["-n", "hw.memsize"])
+        .output()
+        .ok()
+        .and_then(|out| String::from_utf8(out.stdout).ok())
+        .and_then(|s| s.trim().parse::<f64>().ok())
+        .map(|bytes| (bytes / BYTES_PER_GB as f64) as f32);
+    (total, None)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn detect_memory() -> (Option<f32>, Option<f32>) {
+    (None, None)
+}
+
+/// 探测 sidecar 目录下的 ggml 后端库及相关环境变量
+fn detect_gpu_backend() -> Option<String> {
+    let libs: Vec<String> = SidecarManager::get_sidecar_path()
+        .ok()
+        .and_then(|p| p.parent().map(|d| d.to_path_buf()))
+        .and_then(|dir| std::fs::read_dir(dir).ok())
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.file_name().to_string_lossy().to_lowercase())
+                .collect()
+        })
+        .unwrap_or_default();
+    let has_lib = |backend: &str| libs.iter().any(|name| name.contains(backend));
+
+    if cfg!(target_os = "macos") && (has_lib("ggml-metal") || cfg!(target_arch = "aarch64")) {
+        return Some("metal".to_string());
+    }
+    // CUDA 后端还需要 NVIDIA 驱动
+    let nvidia_driver = std::env::var_os("CUDA_PATH").is_some()
+        || Path::new("/proc/driver/nvidia/version").exists();
+    if has_lib("ggml-cuda") && nvidia_driver {
+        return Some("cuda".to_string());
+    }
+    if has_lib("ggml-vulkan") {
+        return Some("vulkan".to_string());
+    }
+    None
+}
+
 /// Sidecar 管理器
 pub struct SidecarManager {
     child: Arc<Mutex<Option<tokio::process::Child>>>,
     port: Arc<Mutex<u16>>,
     model_path: Arc<Mutex<Option<PathBuf>>>,
+    /// 最近一次启动以来的 stdout/stderr 输出（进程退出后仍保留，便于排查崩溃）
+    logs: LogBuffer,
 }
 
 impl SidecarManager {
@@ -41,9 +228,15 @@ impl SidecarManager {
             child: Arc::new(Mutex::new(None)),
             port: Arc::new(Mutex::new(8080)),
             model_path: Arc::new(Mutex::new(None)),
+            logs: LogBuffer::new(SIDECAR_LOG_CAPACITY),
         }
     }
 
+    /// 最近的 `lines` 行服务器输出
+    pub fn recent_logs(&self, lines: usize) -> Vec<SidecarLogLine> {
+        self.logs.tail(lines)
+    }
+
     /// 检查端口是否可用
     fn check_port_available(port: u16) -> bool {
         use std::net::TcpListener;
@@ -130,11 +323,12 @@ impl SidecarManager {
         ))
     }
 
-    /// 启动 llama-server sidecar
+    /// 启动 llama-server sidecar，`on_log` 在每行输出时被调用
     pub async fn start(
         &self,
         model_path: PathBuf,
         port: Option<u16>,
+        on_log: Option<LogListener>,
     ) -> Result<(mpsc::Receiver<CommandEvent>, u16), SidecarError> {
         // 检查是否已经在运行
         if self.is_running().await {
@@ -194,6 +388,7 @@ impl SidecarManager {
 
         // 创建事件通道
         let (tx, rx) = mpsc::channel(100);
+        self.logs.clear();
 
         // 使用 tokio::process::Command 以便异步处理 I/O
         let mut cmd = TokioCommand::new(&sidecar_path);
@@ -206,6 +401,8 @@ impl SidecarManager {
             &actual_port.to_string(),
             "--host",
             "127.0.0.1",
+            "--n-gpu-layers",
+            &detect_hardware().gpu_layers().to_string(),
         ])
         .stdin(Stdio::null())
         .stdout(Stdio::piped())
@@ -239,30 +436,12 @@ impl SidecarManager {
         }
 
         // 异步监听进程输出
-        let tx_stdout = tx.clone();
-        let tx_stderr = tx.clone();
-
-        // 监听 stdout
-        tauri::async_runtime::spawn(async move {
-            let reader = BufReader::new(stdout);
-            let mut lines = reader.lines();
-            while let Ok(Some(line)) = lines.next_line().await {
-                let _ = tx_stdout.send(CommandEvent::Stdout(line)).await;
-            }
-        });
-
-        // 监听 stderr
-        tauri::async_runtime::spawn(async move {
-            let reader = BufReader::new(stderr);
-            let mut lines = reader.lines();
-            while let Ok(Some(line)) = lines.next_line().await {
-                let _ = tx_stderr.send(CommandEvent::Stderr(line)).await;
-            }
-        });
+        spawn_log_reader(stdout, LogStream::Stdout, self.logs.clone(), on_log.clone());
+        spawn_log_reader(stderr, LogStream::Stderr, self.logs.clone(), on_log);
 
         // 监听进程终止（不立即 take，而是等待进程退出后再清理）
         let child_clone2 = self.child.clone();
-        let tx_term = tx.clone();
+        let tx_term = tx;
         tauri::async_runtime::spawn(async move {
             // 等待进程退出（不立即 take，保持 child 在 self.child 中）
             loop {
@@ -342,3 +521,37 @@ impl Default for SidecarManager {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gpu_layers_follow_backend() {
+        let mut hardware = HardwareInfo {
+            total_ram_gb: None,
+            available_ram_gb: None,
+            cpu_cores: None,
+            gpu_backend: None,
+        };
+        assert_eq!(hardware.gpu_layers(), 0);
+        hardware.gpu_backend = Some("vulkan".to_string());
+        assert_eq!(hardware.gpu_layers(), ALL_GPU_LAYERS);
+    }
+
+    #[test]
+    fn test_log_buffer_keeps_latest_lines() {
+        let logs = LogBuffer::new(3);
+        for i in 0..5 {
+            logs.push(SidecarLogLine {
+                stream: LogStream::Stderr,
+                line: format!("line {}", i),
+                timestamp: i,
+            });
+        }
+
+        let lines: Vec<String> = logs.tail(10).into_iter().map(|l| l.line).collect();
+        assert_eq!(lines, vec!["line 2", "line 3", "line 4"]);
+        assert_eq!(logs.tail(1)[0].line, "line 4");
+    }
+}
@@ -38,6 +38,7 @@ pub struct EpubMetadata {
     pub publisher: Option<String>,
     pub publish_date: Option<String>,
     pub isbn: Option<String>,
+    pub language: Option<String>,
     pub cover_path: Option<String>,
     pub spine: Vec<SpineItem>,
 }
@@ -114,6 +115,11 @@ impl BookProcessor {
             duration: None,
             last_page: None,
             last_cfi: None,
+            language: metadata.language.as_deref().and_then(SourceMetadata::normalize_language),
+            content_format: SourceMetadata::content_format_from_path(file_name)
+                .or_else(|| Some("epub".to_string())),
+            word_count: None,
+            reading_minutes: None,
         };
 
         let create_req = CreateSourceRequest {
@@ -124,6 +130,7 @@ impl BookProcessor {
             cover: cover_path,
             description: metadata.description.clone(),
             tags: vec![],
+            source_origin: Some("epub".to_string()),
         };
 
         // 使用 services 层创建 source（异步）
@@ -135,7 +142,7 @@ impl BookProcessor {
                 handle.block_on(async {
                     services
                         .source
-                        .create(create_req_clone)
+                        .create(create_req_clone, Some(&state.indexer))
                         .await
                         .map_err(|e| BookProcessorError::DatabaseError(e.to_string()))
                 })
@@ -147,7 +154,7 @@ impl BookProcessor {
                     .block_on(async {
                         services
                             .source
-                            .create(create_req)
+                            .create(create_req, Some(&state.indexer))
                             .await
                             .map_err(|e| BookProcessorError::DatabaseError(e.to_string()))
                     })
@@ -174,7 +181,7 @@ impl BookProcessor {
                 handle.block_on(async {
                     services2
                         .source
-                        .update(&source.id, update_req_clone)
+                        .update(&source.id, update_req_clone, Some(&state.indexer))
                         .await
                         .map_err(|e| BookProcessorError::DatabaseError(e.to_string()))
                 })
@@ -185,7 +192,7 @@ impl BookProcessor {
                     .block_on(async {
                         services2
                             .source
-                            .update(&source.id, update_req)
+                            .update(&source.id, update_req, Some(&state.indexer))
                             .await
                             .map_err(|e| BookProcessorError::DatabaseError(e.to_string()))
                     })
@@ -260,6 +267,7 @@ impl BookProcessor {
             publisher: None,
             publish_date: None,
             isbn: None,
+            language: None,
             cover_path: None,
             spine: vec![],
         };
@@ -323,6 +331,14 @@ impl BookProcessor {
                 metadata.isbn = identifier_node.text().map(|s| s.trim().to_string());
             }
 
+            // 提取语言
+            if let Some(language_node) = metadata_node
+                .descendants()
+                .find(|n| n.tag_name().name() == "language")
+            {
+                metadata.language = language_node.text().map(|s| s.trim().to_string());
+            }
+
             // 查找封面
             if let Some(cover_meta) = metadata_node
                 .descendants()
@@ -1,17 +1,21 @@
 //! AI 相关命令
 //! 提供 AI 服务器管理、模型管理、聊天和 RAG 功能
 
-use crate::ai::{ModelInfo, get_available_models, sidecar::CommandEvent};
+use crate::ai::chat_sessions::{ChatSession, ChatSessionDetail, ChatSessionStore};
+use crate::ai::manager::fetch_context_size;
+use crate::ai::rag::{AnswerWithSpans, ChunkConfig, EmbeddingsTransfer, RAGService, RagCoverage, ReindexResult};
+use crate::ai::sidecar::{
+    detect_hardware, CommandEvent, HardwareInfo, LogListener, LogStream, SidecarLogLine,
+};
+pub use crate::ai::ChatMessage;
+use crate::ai::{
+    annotate_recommendations, estimate_tokens, get_available_models, ContextUsage, ModelInfo,
+    ProgressThrottle, PROGRESS_THROTTLE_INTERVAL,
+};
 use crate::state::AppState;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
-use tauri::State;
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ChatMessage {
-    pub role: String, // "user", "assistant", "system"
-    pub content: String,
-}
+use tauri::{AppHandle, Emitter, State};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ServerStatus {
@@ -20,9 +24,34 @@ pub struct ServerStatus {
     pub model_path: Option<String>,
 }
 
-/// 启动 AI 服务器
+/// sidecar 实时日志事件
+pub const SIDECAR_LOG_EVENT: &str = "sidecar-log";
+
+/// 模型下载进度事件
+pub const MODEL_DOWNLOAD_PROGRESS_EVENT: &str = "model-download-progress";
+
+/// 模型下载完成事件
+pub const MODEL_DOWNLOAD_COMPLETE_EVENT: &str = "model-download-complete";
+
+/// 模型下载失败事件
+pub const MODEL_DOWNLOAD_ERROR_EVENT: &str = "model-download-error";
+
+/// 模型下载进度
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelDownloadProgress {
+    pub model_id: String,
+    pub downloaded: u64,
+    pub total: u64,
+    pub percent: f64,
+}
+
+/// 启动失败时附带的最近输出行数
+const STARTUP_FAILURE_LOG_LINES: usize = 50;
+
+/// 启动 AI 服务器，运行期间的输出通过 `sidecar-log` 事件推送
 #[tauri::command]
 pub async fn ai_start_server(
+    app: AppHandle,
     state: State<'_, AppState>,
     modelId: String,
     port: Option<u16>,
@@ -43,8 +72,11 @@ pub async fn ai_start_server(
     }
 
     let sidecar = ai_manager.get_sidecar();
+    let on_log: LogListener = std::sync::Arc::new(move |line: &SidecarLogLine| {
+        let _ = app.emit(SIDECAR_LOG_EVENT, line);
+    });
     let (mut event_rx, actual_port) = sidecar
-        .start(model_path, port)
+        .start(model_path, port, Some(on_log))
         .await
         .map_err(|e| e.to_string())?;
 
@@ -55,30 +87,20 @@ pub async fn ai_start_server(
     
     // 验证进程是否还在运行
     if !sidecar.is_running().await {
-        // 收集错误输出（非阻塞方式）
-        let mut error_messages = Vec::new();
-        loop {
-            match event_rx.try_recv() {
-                Ok(event) => {
-                    match event {
-                        CommandEvent::Stderr(msg) => {
-                            error_messages.push(format!("[stderr] {}", msg));
-                        }
-                        CommandEvent::Stdout(msg) => {
-                            error_messages.push(format!("[stdout] {}", msg));
-                        }
-                        CommandEvent::Terminated { code } => {
-                            error_messages.push(format!("[exit] Process exited with code: {:?}", code));
-                        }
-                    }
-                }
-                Err(tokio::sync::mpsc::error::TryRecvError::Empty) => {
-                    break; // 没有更多消息
-                }
-                Err(tokio::sync::mpsc::error::TryRecvError::Disconnected) => {
-                    break; // 通道已关闭
-                }
-            }
+        // 收集错误输出：输出行取自日志缓冲区，退出码取自事件通道
+        let mut error_messages: Vec<String> = sidecar
+            .recent_logs(STARTUP_FAILURE_LOG_LINES)
+            .into_iter()
+            .map(|log| {
+                let stream = match log.stream {
+                    LogStream::Stdout => "stdout",
+                    LogStream::Stderr => "stderr",
+                };
+                format!("[{}] {}", stream, log.line)
+            })
+            .collect();
+        while let Ok(CommandEvent::Terminated { code }) = event_rx.try_recv() {
+            error_messages.push(format!("[exit] Process exited with code: {:?}", code));
         }
         
         let error_detail = if error_messages.is_empty() {
@@ -92,10 +114,33 @@ pub async fn ai_start_server(
             error_detail
         ));
     }
+
+    // 读取模型实际的上下文长度，服务器尚未就绪时沿用默认值
+    ai_manager.set_context_size(
+        fetch_context_size(actual_port)
+            .await
+            .unwrap_or(crate::ai::manager::DEFAULT_CONTEXT_SIZE),
+    );
     
     Ok(actual_port)
 }
 
+/// 获取 AI 服务器最近的输出（默认 200 行），用于排查模型加载失败等问题
+#[tauri::command]
+pub async fn ai_get_server_logs(
+    state: State<'_, AppState>,
+    lines: Option<usize>,
+) -> Result<Vec<SidecarLogLine>, String> {
+    let ai_manager = state
+        .ai_manager
+        .lock()
+        .unwrap()
+        .as_ref()
+        .ok_or("AI manager not initialized")?
+        .clone();
+    Ok(ai_manager.get_sidecar().recent_logs(lines.unwrap_or(200)))
+}
+
 /// 停止 AI 服务器
 #[tauri::command]
 pub async fn ai_stop_server(state: State<'_, AppState>) -> Result<(), String> {
@@ -144,10 +189,48 @@ pub async fn ai_check_status(state: State<'_, AppState>) -> Result<ServerStatus,
     })
 }
 
-/// 列出可用模型
+/// 获取本机硬件信息
+#[tauri::command]
+pub fn ai_hardware_info() -> HardwareInfo {
+    detect_hardware()
+}
+
+/// 列出可用模型（内置 + 已缓存的远程清单），并标注是否适合本机
+#[tauri::command]
+pub fn ai_list_models(state: State<'_, AppState>) -> Result<Vec<ModelInfo>, String> {
+    let ai_manager = state.ai_manager.lock().unwrap().as_ref().cloned();
+    let mut models = match ai_manager {
+        Some(ai_manager) => ai_manager.get_models().get_catalog(),
+        None => get_available_models(),
+    };
+    annotate_recommendations(&mut models, &detect_hardware());
+    Ok(models)
+}
+
+/// 从远程清单刷新模型目录，网络失败时回退到本地列表
 #[tauri::command]
-pub fn ai_list_models(_state: State<'_, AppState>) -> Result<Vec<ModelInfo>, String> {
-    Ok(get_available_models())
+pub async fn ai_refresh_model_catalog(
+    state: State<'_, AppState>,
+    url: String,
+) -> Result<Vec<ModelInfo>, String> {
+    let ai_manager = state
+        .ai_manager
+        .lock()
+        .unwrap()
+        .as_ref()
+        .ok_or("AI manager not initialized")?
+        .clone();
+
+    let model_manager = ai_manager.get_models();
+    let mut models = match model_manager.fetch_remote_models(&url).await {
+        Ok(models) => models,
+        Err(e) => {
+            eprintln!("Failed to refresh model catalog: {}", e);
+            model_manager.get_catalog()
+        }
+    };
+    annotate_recommendations(&mut models, &detect_hardware());
+    Ok(models)
 }
 
 /// 列出已下载的模型
@@ -165,9 +248,11 @@ pub fn ai_list_downloaded_models(state: State<'_, AppState>) -> Result<Vec<Strin
     model_manager.list_downloaded_models().map_err(|e| e.to_string())
 }
 
-/// 下载模型
+/// 下载模型，进度通过 `model-download-progress` 事件推送（节流），
+/// 结束时推送 `model-download-complete` 或 `model-download-error`
 #[tauri::command]
 pub async fn ai_download_model(
+    app: AppHandle,
     state: State<'_, AppState>,
     modelId: String,
 ) -> Result<String, String> {
@@ -182,18 +267,99 @@ pub async fn ai_download_model(
     let model_manager = ai_manager.get_models();
     
     // 查找模型信息
-    let model_info = get_available_models()
+    let model_info = model_manager
+        .get_catalog()
         .into_iter()
         .find(|m| m.id == modelId)
         .ok_or_else(|| format!("Model not found: {}", modelId))?;
 
+    let throttle = std::sync::Mutex::new(ProgressThrottle::new(PROGRESS_THROTTLE_INTERVAL));
+    let progress_app = app.clone();
+    let progress_model_id = modelId.clone();
+    let on_progress: Box<dyn Fn(u64, u64) + Send> = Box::new(move |downloaded: u64, total: u64| {
+        if !throttle
+            .lock()
+            .unwrap()
+            .should_emit(std::time::Instant::now(), downloaded, total)
+        {
+            return;
+        }
+        let percent = if total > 0 {
+            (downloaded as f64 / total as f64 * 100.0).min(100.0)
+        } else {
+            0.0
+        };
+        let _ = progress_app.emit(
+            MODEL_DOWNLOAD_PROGRESS_EVENT,
+            ModelDownloadProgress {
+                model_id: progress_model_id.clone(),
+                downloaded,
+                total,
+                percent,
+            },
+        );
+    });
+
     // 下载模型
-    let model_path = model_manager
-        .download_model(&model_info, None)
-        .await
-        .map_err(|e| e.to_string())?;
+    match model_manager.download_model(&model_info, Some(on_progress)).await {
+        Ok(model_path) => {
+            let model_path = model_path.to_string_lossy().to_string();
+            let _ = app.emit(
+                MODEL_DOWNLOAD_COMPLETE_EVENT,
+                serde_json::json!({ "model_id": modelId, "path": model_path }),
+            );
+            Ok(model_path)
+        }
+        Err(e) => {
+            let error = e.to_string();
+            let _ = app.emit(
+                MODEL_DOWNLOAD_ERROR_EVENT,
+                serde_json::json!({ "model_id": modelId, "error": error }),
+            );
+            Err(error)
+        }
+    }
+}
+
+/// 取消进行中的模型下载，已下载的部分保留，再次下载时续传
+#[tauri::command]
+pub fn ai_cancel_download(state: State<'_, AppState>, modelId: String) -> Result<bool, String> {
+    let ai_manager = state
+        .ai_manager
+        .lock()
+        .unwrap()
+        .as_ref()
+        .ok_or("AI manager not initialized")?
+        .clone();
 
-    Ok(model_path.to_string_lossy().to_string())
+    Ok(ai_manager.get_models().cancel_download(&modelId))
+}
+
+/// 重新校验已下载的模型文件（大小和 SHA-256）
+#[tauri::command]
+pub async fn ai_verify_model(
+    state: State<'_, AppState>,
+    modelId: String,
+) -> Result<(), String> {
+    let ai_manager = state
+        .ai_manager
+        .lock()
+        .unwrap()
+        .as_ref()
+        .ok_or("AI manager not initialized")?
+        .clone();
+
+    let model_manager = ai_manager.get_models();
+    let model_info = model_manager
+        .get_catalog()
+        .into_iter()
+        .find(|m| m.id == modelId)
+        .ok_or_else(|| format!("Model not found: {}", modelId))?;
+
+    model_manager
+        .verify_model(&model_info)
+        .await
+        .map_err(|e| e.to_string())
 }
 
 /// 设置活动模型
@@ -211,10 +377,14 @@ pub fn ai_set_active_model(
 }
 
 /// 基础聊天功能（通过 HTTP 调用 llama-server）
+///
+/// 传入 `session_id` 时，`messages` 只需包含本轮新消息：历史消息从会话中读取，
+/// 超出上下文预算的旧消息会被裁剪，本轮消息和回复写回会话
 #[tauri::command]
 pub async fn ai_chat(
     state: State<'_, AppState>,
     messages: Vec<ChatMessage>,
+    session_id: Option<String>,
 ) -> Result<String, String> {
     let ai_manager = state
         .ai_manager
@@ -231,6 +401,11 @@ pub async fn ai_chat(
         return Err("AI server is not running".to_string());
     }
 
+    let sessions = ai_manager.get_chat_sessions();
+    let new_messages = messages.clone();
+    let messages = with_session_history(&sessions, session_id.as_deref(), messages).await?;
+    let messages = ai_manager.fit_to_context(messages)?.messages;
+
     // 调用 llama-server 的 OpenAI 兼容 API
     let client = reqwest::Client::new();
     let url = format!("http://127.0.0.1:{}/v1/chat/completions", port);
@@ -268,7 +443,143 @@ pub async fn ai_chat(
         .await
         .map_err(|e| format!("Parse error: {}", e))?;
 
-    Ok(response.choices[0].message.content.clone())
+    let reply = response
+        .choices
+        .into_iter()
+        .next()
+        .ok_or("Empty response from AI server")?
+        .message;
+
+    // 只在模型成功回复后写入，失败的一轮不会留下没有回答的提问
+    if let Some(id) = &session_id {
+        for message in new_messages.iter().chain(std::iter::once(&reply)) {
+            sessions
+                .append_message(id, message)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(reply.content)
+}
+
+/// 会话消息在前、本轮消息在后，拼出发送给模型的完整消息列表
+async fn with_session_history(
+    sessions: &ChatSessionStore,
+    session_id: Option<&str>,
+    messages: Vec<ChatMessage>,
+) -> Result<Vec<ChatMessage>, String> {
+    let Some(id) = session_id else {
+        return Ok(messages);
+    };
+    let detail = sessions
+        .get_session(id)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Chat session not found: {}", id))?;
+    let history = detail.messages.into_iter().map(|m| ChatMessage {
+        role: m.role,
+        content: m.content,
+    });
+    Ok(history.chain(messages).collect())
+}
+
+/// 估算本轮对话的上下文占用，参数与 `ai_chat` 相同
+#[tauri::command]
+pub async fn ai_estimate_context(
+    state: State<'_, AppState>,
+    messages: Vec<ChatMessage>,
+    session_id: Option<String>,
+) -> Result<ContextUsage, String> {
+    let ai_manager = state
+        .ai_manager
+        .lock()
+        .unwrap()
+        .as_ref()
+        .ok_or("AI manager not initialized")?
+        .clone();
+
+    let sessions = ai_manager.get_chat_sessions();
+    let messages = with_session_history(&sessions, session_id.as_deref(), messages).await?;
+    Ok(ai_manager.fit_to_context(messages)?.usage)
+}
+
+/// 创建对话会话
+#[tauri::command]
+pub async fn create_chat_session(
+    state: State<'_, AppState>,
+    title: Option<String>,
+) -> Result<ChatSession, String> {
+    let ai_manager = state
+        .ai_manager
+        .lock()
+        .unwrap()
+        .as_ref()
+        .ok_or("AI manager not initialized")?
+        .clone();
+
+    ai_manager
+        .get_chat_sessions()
+        .create_session(title.as_deref())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 获取对话会话及其消息
+#[tauri::command]
+pub async fn get_chat_session(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<Option<ChatSessionDetail>, String> {
+    let ai_manager = state
+        .ai_manager
+        .lock()
+        .unwrap()
+        .as_ref()
+        .ok_or("AI manager not initialized")?
+        .clone();
+
+    ai_manager
+        .get_chat_sessions()
+        .get_session(&session_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 列出对话会话
+#[tauri::command]
+pub async fn list_chat_sessions(state: State<'_, AppState>) -> Result<Vec<ChatSession>, String> {
+    let ai_manager = state
+        .ai_manager
+        .lock()
+        .unwrap()
+        .as_ref()
+        .ok_or("AI manager not initialized")?
+        .clone();
+
+    ai_manager
+        .get_chat_sessions()
+        .list_sessions()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 删除对话会话
+#[tauri::command]
+pub async fn delete_chat_session(state: State<'_, AppState>, session_id: String) -> Result<(), String> {
+    let ai_manager = state
+        .ai_manager
+        .lock()
+        .unwrap()
+        .as_ref()
+        .ok_or("AI manager not initialized")?
+        .clone();
+
+    ai_manager
+        .get_chat_sessions()
+        .delete_session(&session_id)
+        .await
+        .map_err(|e| e.to_string())
 }
 
 /// 即时解释功能
@@ -294,7 +605,7 @@ pub async fn ai_explain_text(
         content: prompt,
     }];
 
-    ai_chat(state, messages).await
+    ai_chat(state, messages, None).await
 }
 
 /// RAG 查询
@@ -303,6 +614,7 @@ pub async fn ai_rag_query(
     state: State<'_, AppState>,
     query: String,
     sourceId: Option<String>,
+    rerank: Option<bool>,
 ) -> Result<String, String> {
     let ai_manager = state
         .ai_manager
@@ -316,13 +628,18 @@ pub async fn ai_rag_query(
     
     // 搜索相似内容
     let search_results = rag
-        .search_similar(&query, 5, sourceId.as_deref())
+        .search_similar(&query, 5, sourceId.as_deref(), rerank.unwrap_or(false))
         .await
         .map_err(|e| e.to_string())?;
 
-    // 构建 RAG Prompt（使用关联函数语法）
-    use crate::ai::rag::RAGService;
-    let prompt = RAGService::build_rag_prompt(&query, search_results);
+    // 构建 RAG Prompt（使用关联函数语法），超出上下文预算时从相关度最低的片段开始丢弃
+    let budget = ai_manager.prompt_budget();
+    let mut results = search_results;
+    let mut prompt = RAGService::build_rag_prompt(&query, results.clone());
+    while results.len() > 1 && estimate_tokens(&prompt) > budget {
+        results.pop();
+        prompt = RAGService::build_rag_prompt(&query, results.clone());
+    }
 
     // 调用聊天 API
     let messages = vec![ChatMessage {
@@ -330,12 +647,59 @@ pub async fn ai_rag_query(
         content: prompt,
     }];
 
-    ai_chat(state, messages).await
+    ai_chat(state, messages, None).await
+}
+
+/// 针对单张卡片提问，返回回答及其引用的原文位置
+#[tauri::command]
+pub async fn ai_ask_card(
+    state: State<'_, AppState>,
+    card_id: String,
+    question: String,
+) -> Result<AnswerWithSpans, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    let card = services
+        .card
+        .get_by_id(&card_id)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Card not found: {}", card_id))?;
+
+    let ai_manager = state
+        .ai_manager
+        .lock()
+        .unwrap()
+        .as_ref()
+        .ok_or("AI manager not initialized")?
+        .clone();
+
+    // 从正文重新提取纯文本，旧卡片保存的纯文本可能没有文本块之间的换行
+    let text = crate::db::extract_plain_text_from_json(&card.content).unwrap_or(card.plain_text);
+    let spans = ai_manager
+        .get_rag()
+        .retrieve_card_spans(&card.id, card.modified_at, &text, &question)
+        .await
+        .map_err(|e| e.to_string())?;
+    if spans.is_empty() {
+        return Err("Card has no content to answer from".to_string());
+    }
+
+    let messages = vec![ChatMessage {
+        role: "user".to_string(),
+        content: RAGService::build_card_prompt(&question, &spans),
+    }];
+    let answer = ai_chat(state, messages, None).await?;
+
+    Ok(AnswerWithSpans {
+        spans: RAGService::cited_spans(&answer, spans),
+        answer,
+    })
 }
 
 /// 索引文献源（用于 RAG）
 #[tauri::command]
 pub async fn ai_index_source(
+    app: AppHandle,
     state: State<'_, AppState>,
     sourceId: String,
     content: String,
@@ -349,8 +713,173 @@ pub async fn ai_index_source(
         .clone();
 
     let rag = ai_manager.get_rag();
-    rag.index_source(&sourceId, &content)
+    let embedding_model = embedding_model_name(&ai_manager.get_sidecar()).await;
+    let on_progress = index_progress_callback(&app, &sourceId);
+
+    let result = rag
+        .index_source(
+            &sourceId,
+            &content,
+            &ChunkConfig::default(),
+            &embedding_model,
+            Some(on_progress),
+        )
+        .await;
+
+    let _ = app.emit(
+        "rag-index-complete",
+        serde_json::json!({
+            "sourceId": sourceId,
+            "total": result.as_ref().ok(),
+            "error": result.as_ref().err().map(|e| e.to_string()),
+        }),
+    );
+
+    result.map(|_| ()).map_err(|e| e.to_string())
+}
+
+/// 按指定分块参数重新索引文献源，参数或向量模型变化时会先清空旧向量
+#[tauri::command]
+pub async fn ai_reindex_source(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    sourceId: String,
+    content: String,
+    chunkConfig: Option<ChunkConfig>,
+) -> Result<ReindexResult, String> {
+    let ai_manager = state
+        .ai_manager
+        .lock()
+        .unwrap()
+        .as_ref()
+        .ok_or("AI manager not initialized")?
+        .clone();
+
+    let rag = ai_manager.get_rag();
+    let embedding_model = embedding_model_name(&ai_manager.get_sidecar()).await;
+    let config = chunkConfig.unwrap_or_default();
+    if config.chunk_size == 0 || config.overlap >= config.chunk_size {
+        return Err("overlap must be smaller than a non-zero chunk_size".to_string());
+    }
+    let on_progress = index_progress_callback(&app, &sourceId);
+
+    let result = rag
+        .reindex_source(&sourceId, &content, &config, &embedding_model, Some(on_progress))
+        .await;
+
+    let _ = app.emit(
+        "rag-index-complete",
+        serde_json::json!({
+            "sourceId": sourceId,
+            "total": result.as_ref().ok().map(|r| r.chunk_count),
+            "error": result.as_ref().err().map(|e| e.to_string()),
+        }),
+    );
+
+    result.map_err(|e| e.to_string())
+}
+
+/// 各文献源的 RAG 索引覆盖情况
+#[tauri::command]
+pub async fn get_rag_coverage(state: State<'_, AppState>) -> Result<Vec<RagCoverage>, String> {
+    let ai_manager = state
+        .ai_manager
+        .lock()
+        .unwrap()
+        .as_ref()
+        .ok_or("AI manager not initialized")?
+        .clone();
+
+    ai_manager
+        .get_rag()
+        .get_coverage()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 从已存储的向量重建近似最近邻索引，返回索引的向量数
+#[tauri::command]
+pub async fn ai_rebuild_vector_index(state: State<'_, AppState>) -> Result<usize, String> {
+    let ai_manager = state
+        .ai_manager
+        .lock()
+        .unwrap()
+        .as_ref()
+        .ok_or("AI manager not initialized")?
+        .clone();
+
+    ai_manager
+        .get_rag()
+        .rebuild_ann_index()
         .await
         .map_err(|e| e.to_string())
 }
 
+/// 将向量索引导出为单个文件，用于备份或迁移
+#[tauri::command]
+pub async fn ai_export_embeddings(
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<EmbeddingsTransfer, String> {
+    let ai_manager = state
+        .ai_manager
+        .lock()
+        .unwrap()
+        .as_ref()
+        .ok_or("AI manager not initialized")?
+        .clone();
+
+    let embedding_model = embedding_model_name(&ai_manager.get_sidecar()).await;
+    ai_manager
+        .get_rag()
+        .export_embeddings(std::path::Path::new(&path), &embedding_model)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 从导出文件恢复向量索引，模型或维度与当前不一致时拒绝导入
+#[tauri::command]
+pub async fn ai_import_embeddings(
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<EmbeddingsTransfer, String> {
+    let ai_manager = state
+        .ai_manager
+        .lock()
+        .unwrap()
+        .as_ref()
+        .ok_or("AI manager not initialized")?
+        .clone();
+
+    let embedding_model = embedding_model_name(&ai_manager.get_sidecar()).await;
+    ai_manager
+        .get_rag()
+        .import_embeddings(std::path::Path::new(&path), &embedding_model)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 当前加载的向量模型名称（模型文件名），未加载时使用通用名称
+async fn embedding_model_name(sidecar: &crate::ai::sidecar::SidecarManager) -> String {
+    sidecar
+        .get_model_path()
+        .await
+        .and_then(|p| p.file_stem().map(|s| s.to_string_lossy().to_string()))
+        .unwrap_or_else(|| "text-embedding".to_string())
+}
+
+/// emit 只是投递事件，不会阻塞向量化循环
+fn index_progress_callback(
+    app: &AppHandle,
+    source_id: &str,
+) -> Box<dyn Fn(usize, usize) + Send + Sync> {
+    let progress_app = app.clone();
+    let progress_source_id = source_id.to_string();
+    Box::new(move |done: usize, total: usize| {
+        let _ = progress_app.emit(
+            "rag-index-progress",
+            serde_json::json!({ "sourceId": progress_source_id, "done": done, "total": total }),
+        );
+    })
+}
+
@@ -1,6 +1,7 @@
 //! 资源文件管理命令
 //! 处理图片等资源文件的上传、保存和管理
 
+use crate::models::HIGHLIGHT_IMAGE_DIR;
 use crate::state::AppState;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -41,13 +42,40 @@ pub fn save_image(
         .map_err(|e| format!("Failed to save image: {}", e))?;
 
     // 返回相对于 vault 的路径（用于存储和显示）
-    let relative_path = file_path
+    vault_relative_path(&vault_path, &file_path)
+}
+
+/// 计算相对于 vault 的路径（统一使用 `/` 分隔），用于存储和显示
+pub(crate) fn vault_relative_path(vault_path: &Path, path: &Path) -> Result<String, String> {
+    Ok(path
         .strip_prefix(vault_path)
         .map_err(|e| format!("Failed to compute relative path: {}", e))?
         .to_string_lossy()
-        .to_string();
+        .replace('\\', "/"))
+}
 
-    Ok(relative_path)
+/// 保存 PDF 区域高亮的截图，返回相对 vault 的路径
+#[tauri::command]
+pub fn save_highlight_image(
+    state: State<AppState>,
+    image_data: Vec<u8>,
+) -> Result<String, String> {
+    let vault_path = state
+        .vault_path
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or("Vault not initialized")?;
+
+    let highlights_dir = vault_path.join(HIGHLIGHT_IMAGE_DIR);
+    fs::create_dir_all(&highlights_dir)
+        .map_err(|e| format!("Failed to create highlights directory: {}", e))?;
+
+    let unique_filename = format!("{}.png", Uuid::new_v4());
+    fs::write(highlights_dir.join(&unique_filename), image_data)
+        .map_err(|e| format!("Failed to save highlight image: {}", e))?;
+
+    Ok(format!("{}/{}", HIGHLIGHT_IMAGE_DIR, unique_filename))
 }
 
 /// 读取图片文件
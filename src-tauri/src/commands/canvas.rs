@@ -1,9 +1,101 @@
 use crate::error::AppError;
-use crate::models::canvas::{Canvas, CanvasListItem};
+use crate::models::canvas::{node_refs, Canvas, CanvasListItem, NodeKind};
+use crate::services::Services;
 use crate::state::AppState;
 use crate::storage;
+use serde_json::{json, Value};
 use tauri::State;
 
+/// 解析引用型节点（高亮、网页快照）的内容，写入 `data.resolved` 供前端渲染
+async fn resolve_canvas_nodes(services: &Services, nodes: &mut Value) {
+    let refs = node_refs(nodes);
+    let Some(nodes) = nodes.as_array_mut() else {
+        return;
+    };
+
+    for node_ref in refs {
+        let resolved = match node_ref.kind {
+            NodeKind::Highlight => services
+                .highlight
+                .get_by_id(&node_ref.target_id)
+                .await
+                .ok()
+                .flatten()
+                .map(|h| {
+                    json!({
+                        "content": h.content,
+                        "note": h.note,
+                        "color": h.color,
+                        "sourceId": h.source_id,
+                    })
+                }),
+            NodeKind::Snapshot => services
+                .web_reader
+                .get_snapshot(&node_ref.target_id)
+                .await
+                .ok()
+                .flatten()
+                .map(|s| {
+                    json!({
+                        "title": s.title,
+                        "siteName": s.site_name,
+                        "excerpt": s.excerpt,
+                        "originalUrl": s.original_url,
+                    })
+                }),
+            _ => continue,
+        };
+
+        let node = nodes
+            .iter_mut()
+            .find(|n| n.get("id").and_then(|v| v.as_str()) == Some(node_ref.node_id.as_str()));
+        if let Some(data) = node.and_then(|n| n.get_mut("data")).and_then(|d| d.as_object_mut()) {
+            data.insert("resolved".to_string(), resolved.unwrap_or(Value::Null));
+        }
+    }
+}
+
+/// 移除运行时解析出的内容，避免把快照副本持久化到 Canvas 文件
+fn strip_resolved(nodes: &mut Value) {
+    if let Some(nodes) = nodes.as_array_mut() {
+        for node in nodes {
+            if let Some(data) = node.get_mut("data").and_then(|d| d.as_object_mut()) {
+                data.remove("resolved");
+            }
+        }
+    }
+}
+
+/// 校验引用型节点指向的卡片/高亮/快照存在
+async fn validate_canvas_nodes(services: &Services, nodes: &Value) -> Result<(), String> {
+    for node_ref in node_refs(nodes) {
+        let (exists, label) = match node_ref.kind {
+            NodeKind::Card => (
+                matches!(services.card.get_by_id(&node_ref.target_id).await, Ok(Some(_))),
+                "卡片",
+            ),
+            NodeKind::Highlight => (
+                matches!(services.highlight.get_by_id(&node_ref.target_id).await, Ok(Some(_))),
+                "高亮",
+            ),
+            NodeKind::Snapshot => (
+                matches!(services.web_reader.get_snapshot(&node_ref.target_id).await, Ok(Some(_))),
+                "网页快照",
+            ),
+            NodeKind::Text | NodeKind::Image => continue,
+        };
+
+        if !exists {
+            return Err(AppError::InvalidInput(format!(
+                "节点 {} 引用的{}不存在: {}",
+                node_ref.node_id, label, node_ref.target_id
+            ))
+            .to_string());
+        }
+    }
+    Ok(())
+}
+
 #[tauri::command]
 pub fn get_canvases(state: State<AppState>) -> Result<Vec<CanvasListItem>, String> {
     let vault_path = state
@@ -16,14 +108,19 @@ pub fn get_canvases(state: State<AppState>) -> Result<Vec<CanvasListItem>, Strin
 }
 
 #[tauri::command]
-pub fn get_canvas(state: State<AppState>, id: String) -> Result<Option<Canvas>, String> {
+pub async fn get_canvas(state: State<'_, AppState>, id: String) -> Result<Option<Canvas>, String> {
     let vault_path = state
         .vault_path
         .lock()
         .unwrap()
         .clone()
         .ok_or_else(|| AppError::VaultPathNotSet.to_string())?;
-    Ok(storage::read_canvas(&vault_path, &id))
+
+    let mut canvas = storage::read_canvas(&vault_path, &id);
+    if let (Some(canvas), Some(services)) = (canvas.as_mut(), state.get_services()) {
+        resolve_canvas_nodes(&services, &mut canvas.nodes).await;
+    }
+    Ok(canvas)
 }
 
 #[tauri::command]
@@ -44,11 +141,11 @@ pub fn create_canvas(state: State<AppState>, title: String) -> Result<Canvas, St
 }
 
 #[tauri::command]
-pub fn update_canvas(
-    state: State<AppState>,
+pub async fn update_canvas(
+    state: State<'_, AppState>,
     id: String,
     title: Option<String>,
-    nodes: Option<serde_json::Value>,
+    mut nodes: Option<serde_json::Value>,
     edges: Option<serde_json::Value>,
 ) -> Result<Canvas, String> {
     let vault_path = state
@@ -57,6 +154,13 @@ pub fn update_canvas(
         .unwrap()
         .clone()
         .ok_or_else(|| AppError::VaultPathNotSet.to_string())?;
+
+    if let Some(nodes) = nodes.as_mut() {
+        strip_resolved(nodes);
+        if let Some(services) = state.get_services() {
+            validate_canvas_nodes(&services, nodes).await?;
+        }
+    }
     
     storage::update_canvas(&vault_path, &id, title, nodes, edges)
         .map_err(|e| AppError::Storage(e).to_string())
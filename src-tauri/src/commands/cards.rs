@@ -1,15 +1,26 @@
 //! Card 相关命令
 
-use crate::models::{Card, CardType};
+use crate::graph::{LinkResolver, LinkTarget};
+use crate::models::{
+    Backlink, Card, CardListItem, CardListSort, CardMatch, CardRevision, CardTemplate, CardType,
+    FindOptions, ReplaceResult, ResolvedReference, TrashedCard, TypeChangeResult, UnlinkedMention,
+};
 use crate::state::AppState;
 use tauri::State;
 
-/// 获取所有卡片（包含完整内容）
+/// 获取所有卡片（包含完整内容），默认不含已归档卡片
 #[tauri::command]
-pub async fn get_cards(state: State<'_, AppState>) -> Result<Vec<Card>, String> {
+pub async fn get_cards(
+    state: State<'_, AppState>,
+    include_archived: Option<bool>,
+) -> Result<Vec<Card>, String> {
     println!("[DEBUG] command::get_cards called");
     let services = state.get_services().ok_or("Vault not initialized")?;
-    let cards = services.card.get_all().await.map_err(|e| e.to_string())?;
+    let cards = services
+        .card
+        .get_all_filtered(include_archived.unwrap_or(false))
+        .await
+        .map_err(|e| e.to_string())?;
     println!("[DEBUG] command::get_cards returning {} cards with full content", cards.len());
     Ok(cards)
 }
@@ -34,26 +45,39 @@ pub async fn get_card_by_path(state: State<'_, AppState>, path: String) -> Resul
     services.card.get_by_path(&path).await.map_err(|e| e.to_string())
 }
 
-/// 创建卡片
+/// 创建卡片，指定 `template_id` 时用模板生成初始内容
 #[tauri::command]
 pub async fn create_card(
     state: State<'_, AppState>,
     card_type: String,
     title: String,
     source_id: Option<String>,
+    template_id: Option<String>,
 ) -> Result<Card, String> {
     let ct = CardType::from_str(&card_type);
 
     // 使用服务层创建卡片
     let services = state.get_services().ok_or("Vault not initialized")?;
+    let content = template_id
+        .map(|id| services.card.render_template(&id, &title))
+        .transpose()
+        .map_err(|e| e.to_string())?;
     let indexer_ref: Option<&std::sync::Mutex<Option<crate::search::Indexer>>> = Some(&state.indexer);
+    let notify = |change| state.notify_card_change(change);
     services
         .card
-        .create(ct, &title, None, source_id.as_deref(), indexer_ref)
+        .create(ct, &title, content.as_deref(), source_id.as_deref(), indexer_ref, Some(&notify))
         .await
         .map_err(|e| e.to_string())
 }
 
+/// 列出可用的卡片模板
+#[tauri::command]
+pub async fn list_templates(state: State<'_, AppState>) -> Result<Vec<CardTemplate>, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services.card.list_templates().map_err(|e| e.to_string())
+}
+
 /// 更新卡片
 #[tauri::command]
 pub async fn update_card(
@@ -69,6 +93,7 @@ pub async fn update_card(
     
     let services = state.get_services().ok_or("Vault not initialized")?;
     let indexer_ref: Option<&std::sync::Mutex<Option<crate::search::Indexer>>> = Some(&state.indexer);
+    let notify = |change| state.notify_card_change(change);
     services
         .card
         .update(
@@ -78,15 +103,440 @@ pub async fn update_card(
             tags,
             ct,
             indexer_ref,
+            Some(&notify),
         )
         .await
         .map_err(|e| e.to_string())
 }
 
-/// 删除卡片
+/// 重命名卡片，并改写其他卡片中按旧标题指向它的 wiki 链接，返回被改写的卡片 ID
+#[tauri::command]
+pub async fn rename_card(
+    state: State<'_, AppState>,
+    id: String,
+    new_title: String,
+) -> Result<Vec<String>, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    let notify = |change| state.notify_card_change(change);
+    services
+        .card
+        .rename(&id, &new_title, Some(&state.indexer), Some(&notify))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 删除卡片（移入回收站）
 #[tauri::command]
 pub async fn delete_card(state: State<'_, AppState>, id: String) -> Result<(), String> {
     let services = state.get_services().ok_or("Vault not initialized")?;
     let indexer_ref: Option<&std::sync::Mutex<Option<crate::search::Indexer>>> = Some(&state.indexer);
-    services.card.delete(&id, indexer_ref).await.map_err(|e| e.to_string())
+    let notify = |change| state.notify_card_change(change);
+    services
+        .card
+        .delete(&id, indexer_ref, Some(&notify))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 从回收站恢复卡片
+#[tauri::command]
+pub async fn restore_card(state: State<'_, AppState>, id: String) -> Result<Option<Card>, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    let notify = |change| state.notify_card_change(change);
+    services
+        .card
+        .restore(&id, Some(&state.indexer), Some(&notify))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 将卡片导出为带 frontmatter 的 Markdown 文本
+#[tauri::command]
+pub async fn export_card_to_markdown(state: State<'_, AppState>, id: String) -> Result<String, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services.card.export_markdown(&id).await.map_err(|e| e.to_string())
+}
+
+/// 将所有卡片导出为 Markdown 文件（`<类型目录>/<id>.md`），返回导出数量；未解锁时跳过加密卡片
+#[tauri::command]
+pub async fn export_vault_to_markdown(state: State<'_, AppState>, dest: String) -> Result<usize, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    let cards = services.card.get_exportable().await.map_err(|e| e.to_string())?;
+    let dest = std::path::PathBuf::from(dest);
+    tokio::task::spawn_blocking(move || crate::storage::export_markdown_cards(&dest, &cards))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// 获取卡片的历史版本（新的在前）
+#[tauri::command]
+pub async fn get_card_revisions(
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<Vec<CardRevision>, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services.card.get_revisions(&id).await.map_err(|e| e.to_string())
+}
+
+/// 将卡片恢复到指定的历史版本
+#[tauri::command]
+pub async fn restore_card_revision(
+    state: State<'_, AppState>,
+    id: String,
+    timestamp: i64,
+) -> Result<Card, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    let notify = |change| state.notify_card_change(change);
+    services
+        .card
+        .restore_revision(&id, timestamp, Some(&state.indexer), Some(&notify))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 设置每张卡片保留的历史版本数量，0 表示不保存
+#[tauri::command]
+pub async fn set_card_revision_limit(state: State<'_, AppState>, limit: usize) -> Result<(), String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services.card.set_revision_limit(limit).await.map_err(|e| e.to_string())
+}
+
+/// 获取回收站中的卡片
+#[tauri::command]
+pub async fn list_trashed_cards(state: State<'_, AppState>) -> Result<Vec<TrashedCard>, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services.card.get_trashed().await.map_err(|e| e.to_string())
+}
+
+/// 清空回收站中超过指定天数的卡片（默认 30 天），返回删除数量
+#[tauri::command]
+pub async fn purge_trashed_cards(
+    state: State<'_, AppState>,
+    older_than_days: Option<u32>,
+) -> Result<u64, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services
+        .card
+        .purge_trashed(older_than_days.unwrap_or(crate::db::TRASH_RETENTION_DAYS))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 在所有卡片中重命名标签，返回受影响的卡片数量
+#[tauri::command]
+pub async fn rename_card_tag(
+    state: State<'_, AppState>,
+    old: String,
+    new: String,
+) -> Result<usize, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    let notify = |change| state.notify_card_change(change);
+    services
+        .card
+        .rename_tag(&old, &new, Some(&state.indexer), Some(&notify))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 从所有卡片中移除标签，返回受影响的卡片数量
+#[tauri::command]
+pub async fn delete_card_tag(state: State<'_, AppState>, tag: String) -> Result<usize, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    let notify = |change| state.notify_card_change(change);
+    services
+        .card
+        .delete_tag(&tag, Some(&state.indexer), Some(&notify))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 设置卡片置顶
+#[tauri::command]
+pub async fn set_card_pinned(
+    state: State<'_, AppState>,
+    id: String,
+    pinned: bool,
+) -> Result<Card, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    let notify = |change| state.notify_card_change(change);
+    services
+        .card
+        .set_pinned(&id, pinned, Some(&notify))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 加密或解密卡片正文（需要先解锁）
+#[tauri::command]
+pub async fn set_card_encrypted(
+    state: State<'_, AppState>,
+    id: String,
+    encrypted: bool,
+) -> Result<Card, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    let notify = |change| state.notify_card_change(change);
+    services
+        .card
+        .set_encrypted(&id, encrypted, Some(&state.indexer), Some(&notify))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 是否已设置加密口令
+#[tauri::command]
+pub async fn has_vault_passphrase(state: State<'_, AppState>) -> Result<bool, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services.card.has_passphrase().await.map_err(|e| e.to_string())
+}
+
+/// 首次设置加密口令，`confirmation` 须与口令一致；设置后即处于解锁状态
+#[tauri::command]
+pub async fn set_vault_passphrase(
+    state: State<'_, AppState>,
+    passphrase: String,
+    confirmation: String,
+) -> Result<(), String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services
+        .card
+        .set_passphrase(&passphrase, &confirmation)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 用已设置的口令解锁加密卡片；密钥只保存在内存中
+#[tauri::command]
+pub async fn unlock_vault(state: State<'_, AppState>, passphrase: String) -> Result<(), String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services
+        .card
+        .unlock(&passphrase, Some(&state.indexer))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 锁定加密卡片，丢弃内存中的密钥
+#[tauri::command]
+pub async fn lock_vault(state: State<'_, AppState>) -> Result<(), String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services
+        .card
+        .lock(Some(&state.indexer))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 加密卡片当前是否已解锁
+#[tauri::command]
+pub async fn is_vault_unlocked(state: State<'_, AppState>) -> Result<bool, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    Ok(services.card.is_unlocked())
+}
+
+/// 将卡片加入或移出间隔重复复习
+#[tauri::command]
+pub async fn set_card_review(
+    state: State<'_, AppState>,
+    id: String,
+    enabled: bool,
+) -> Result<Card, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    let notify = |change| state.notify_card_change(change);
+    services
+        .card
+        .set_review_enabled(&id, enabled, Some(&notify))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 记录复习评分（0-5，SM-2），返回带有下次复习时间的卡片
+#[tauri::command]
+pub async fn review_card(state: State<'_, AppState>, id: String, grade: u8) -> Result<Card, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    let notify = |change| state.notify_card_change(change);
+    services
+        .card
+        .review(&id, grade, Some(&notify))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 获取到期需要复习的卡片，最早到期的在前
+#[tauri::command]
+pub async fn get_due_cards(state: State<'_, AppState>) -> Result<Vec<CardListItem>, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services.card.get_due().await.map_err(|e| e.to_string())
+}
+
+/// 批量修改卡片类型（如将项目中的闪念笔记整体转为永久笔记），返回每张卡片的结果
+#[tauri::command]
+pub async fn batch_change_card_type(
+    state: State<'_, AppState>,
+    ids: Vec<String>,
+    card_type: CardType,
+) -> Result<Vec<TypeChangeResult>, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    let notify = |change| state.notify_card_change(change);
+    services
+        .card
+        .batch_change_type(&ids, card_type, Some(&state.indexer), Some(&notify))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 归档卡片
+#[tauri::command]
+pub async fn archive_card(state: State<'_, AppState>, id: String) -> Result<Card, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    let notify = |change| state.notify_card_change(change);
+    services
+        .card
+        .set_archived(&id, true, Some(&notify))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 取消归档卡片
+#[tauri::command]
+pub async fn unarchive_card(state: State<'_, AppState>, id: String) -> Result<Card, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    let notify = |change| state.notify_card_change(change);
+    services
+        .card
+        .set_archived(&id, false, Some(&notify))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 获取置顶卡片
+#[tauri::command]
+pub async fn get_pinned_cards(state: State<'_, AppState>) -> Result<Vec<Card>, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services.card.get_pinned().await.map_err(|e| e.to_string())
+}
+
+/// 获取卡片列表（不含正文，附带字数和阅读时间），默认不含已归档卡片
+#[tauri::command]
+pub async fn get_card_list(
+    state: State<'_, AppState>,
+    sort: Option<CardListSort>,
+    include_archived: Option<bool>,
+) -> Result<Vec<CardListItem>, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services
+        .card
+        .get_list(sort.unwrap_or_default(), include_archived.unwrap_or(false))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 在所有卡片中查找文本
+#[tauri::command]
+pub async fn find_in_cards(
+    state: State<'_, AppState>,
+    query: String,
+    options: Option<FindOptions>,
+) -> Result<Vec<CardMatch>, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services
+        .card
+        .find_in_cards(&query, &options.unwrap_or_default())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 在卡片中批量替换文本（dry_run 时仅预览）
+#[tauri::command]
+pub async fn replace_in_cards(
+    state: State<'_, AppState>,
+    query: String,
+    replacement: String,
+    options: Option<FindOptions>,
+    card_ids: Option<Vec<String>>,
+    dry_run: Option<bool>,
+) -> Result<Vec<ReplaceResult>, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    let notify = |change| state.notify_card_change(change);
+    services
+        .card
+        .replace_in_cards(
+            &query,
+            &replacement,
+            &options.unwrap_or_default(),
+            card_ids.as_deref(),
+            dry_run.unwrap_or(true),
+            Some(&state.indexer),
+            Some(&notify),
+        )
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 查找其他卡片中提及本卡片标题或别名、但尚未链接的位置
+#[tauri::command]
+pub async fn find_unlinked_mentions(
+    state: State<'_, AppState>,
+    card_id: String,
+) -> Result<Vec<UnlinkedMention>, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services
+        .card
+        .find_unlinked_mentions(&card_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 获取链接到该卡片的其他卡片及链接所在的上下文（用于反向链接面板）
+#[tauri::command]
+pub async fn get_backlinks_with_context(
+    state: State<'_, AppState>,
+    card_id: String,
+) -> Result<Vec<Backlink>, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services
+        .card
+        .get_backlinks_with_context(&card_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 解析卡片正文中引用的文献源、高亮和卡片
+#[tauri::command]
+pub async fn resolve_references(
+    state: State<'_, AppState>,
+    card_id: String,
+) -> Result<Vec<ResolvedReference>, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services
+        .card
+        .resolve_references(&card_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 获取卡片出链，并解析到实际卡片（与图谱相同的优先级：ID → 别名 → 标题）
+#[tauri::command]
+pub async fn get_outgoing_links(
+    state: State<'_, AppState>,
+    card_id: String,
+) -> Result<Vec<LinkTarget>, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    let card = services
+        .card
+        .get_by_id(&card_id)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Card not found: {}", card_id))?;
+
+    let cards: Vec<CardListItem> = services
+        .card
+        .get_all()
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+    let resolver = LinkResolver::new(&cards);
+
+    Ok(card.links.iter().map(|link| resolver.resolve_target(link)).collect())
 }
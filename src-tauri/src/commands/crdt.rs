@@ -1,10 +1,19 @@
 //! CRDT 相关命令
 //! 提供协作编辑、历史快照等功能的前端 API
 
-use crate::crdt::HistorySnapshot;
+use crate::crdt::{DiffGranularity, DiffOp, HistorySnapshot, PruneResult, PENDING_LOG_ERROR};
 use crate::state::AppState;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
 use serde::{Deserialize, Serialize};
-use tauri::State;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// CRDT 持久化失败时发送给前端的事件
+pub const CRDT_PERSIST_ERROR_EVENT: &str = "crdt-persist-error";
+
+/// 自动快照任务的检查间隔
+const AUTOSNAPSHOT_TICK: Duration = Duration::from_secs(30);
 
 /// 同步响应
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,6 +32,8 @@ pub struct SnapshotInfo {
     pub id: String,
     pub timestamp: i64,
     pub description: Option<String>,
+    /// 参与编辑的来源
+    pub origins: Vec<String>,
 }
 
 impl From<HistorySnapshot> for SnapshotInfo {
@@ -31,10 +42,34 @@ impl From<HistorySnapshot> for SnapshotInfo {
             id: s.id,
             timestamp: s.timestamp,
             description: s.description,
+            origins: s.origins,
         }
     }
 }
 
+/// 持久化失败事件内容
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrdtPersistError {
+    /// 出错的文档，flush_all 失败时为空
+    pub doc_id: Option<String>,
+    pub error: String,
+}
+
+/// 持久化失败时通知前端，错误照常返回给调用方
+fn emit_persist_error<T>(app: &AppHandle, doc_id: Option<&str>, result: Result<T, String>) -> Result<T, String> {
+    if let Err(error) = &result {
+        let _ = app.emit(
+            CRDT_PERSIST_ERROR_EVENT,
+            CrdtPersistError {
+                doc_id: doc_id.map(String::from),
+                error: error.clone(),
+            },
+        );
+    }
+    result
+}
+
 /// 获取文档的完整 CRDT 状态
 #[tauri::command]
 pub fn crdt_get_state(state: State<AppState>, doc_id: String) -> Result<String, String> {
@@ -58,15 +93,47 @@ pub fn crdt_get_state_vector(state: State<AppState>, doc_id: String) -> Result<S
 /// 应用来自前端的更新
 #[tauri::command]
 pub fn crdt_apply_update(
+    app: AppHandle,
     state: State<AppState>,
     doc_id: String,
     update: String,
+    origin: Option<String>,
 ) -> Result<(), String> {
     let crdt_guard = state.crdt.lock().unwrap();
     let crdt = crdt_guard.as_ref().ok_or("CRDT manager not initialized")?;
 
     let update_bytes = base64_decode(&update)?;
-    crdt.apply_update(&doc_id, &update_bytes)
+    let result = crdt.apply_update_with_origin(&doc_id, &update_bytes, origin.as_deref());
+    match result {
+        Err(e) if e.starts_with(PENDING_LOG_ERROR) => emit_persist_error(&app, Some(&doc_id), Err(e)),
+        other => other,
+    }
+}
+
+/// 撤销最近一组编辑，返回撤销后的完整状态
+#[tauri::command]
+pub fn crdt_undo(app: AppHandle, state: State<AppState>, doc_id: String) -> Result<String, String> {
+    let crdt_guard = state.crdt.lock().unwrap();
+    let crdt = crdt_guard.as_ref().ok_or("CRDT manager not initialized")?;
+
+    match crdt.undo(&doc_id) {
+        Err(e) if e.starts_with(PENDING_LOG_ERROR) => emit_persist_error(&app, Some(&doc_id), Err(e))?,
+        other => other?,
+    };
+    Ok(base64_encode(&crdt.get_full_state(&doc_id)))
+}
+
+/// 重做最近一次撤销，返回重做后的完整状态
+#[tauri::command]
+pub fn crdt_redo(app: AppHandle, state: State<AppState>, doc_id: String) -> Result<String, String> {
+    let crdt_guard = state.crdt.lock().unwrap();
+    let crdt = crdt_guard.as_ref().ok_or("CRDT manager not initialized")?;
+
+    match crdt.redo(&doc_id) {
+        Err(e) if e.starts_with(PENDING_LOG_ERROR) => emit_persist_error(&app, Some(&doc_id), Err(e))?,
+        other => other?,
+    };
+    Ok(base64_encode(&crdt.get_full_state(&doc_id)))
 }
 
 /// 获取增量更新 (从给定状态向量)
@@ -88,6 +155,7 @@ pub fn crdt_get_diff(
 /// 前端发送自己的状态向量和更新，后端返回缺失的更新
 #[tauri::command]
 pub fn crdt_sync(
+    app: AppHandle,
     state: State<AppState>,
     doc_id: String,
     client_state_vector: String,
@@ -99,7 +167,12 @@ pub fn crdt_sync(
     // 1. 如果客户端有更新，先应用
     if let Some(update) = client_update {
         let update_bytes = base64_decode(&update)?;
-        crdt.apply_update(&doc_id, &update_bytes)?;
+        match crdt.apply_update(&doc_id, &update_bytes) {
+            Err(e) if e.starts_with(PENDING_LOG_ERROR) => {
+                emit_persist_error(&app, Some(&doc_id), Err(e))?
+            }
+            other => other?,
+        }
     }
 
     // 2. 计算服务端需要发送给客户端的更新
@@ -117,20 +190,20 @@ pub fn crdt_sync(
 
 /// 保存文档到磁盘
 #[tauri::command]
-pub fn crdt_save(state: State<AppState>, doc_id: String) -> Result<(), String> {
+pub fn crdt_save(app: AppHandle, state: State<AppState>, doc_id: String) -> Result<(), String> {
     let crdt_guard = state.crdt.lock().unwrap();
     let crdt = crdt_guard.as_ref().ok_or("CRDT manager not initialized")?;
 
-    crdt.save_to_disk(&doc_id)
+    emit_persist_error(&app, Some(&doc_id), crdt.save_to_disk(&doc_id))
 }
 
 /// 保存所有脏文档
 #[tauri::command]
-pub fn crdt_flush_all(state: State<AppState>) -> Result<usize, String> {
+pub fn crdt_flush_all(app: AppHandle, state: State<AppState>) -> Result<usize, String> {
     let crdt_guard = state.crdt.lock().unwrap();
     let crdt = crdt_guard.as_ref().ok_or("CRDT manager not initialized")?;
 
-    crdt.flush_all()
+    emit_persist_error(&app, None, crdt.flush_all())
 }
 
 /// 创建历史快照
@@ -147,6 +220,38 @@ pub fn crdt_create_snapshot(
     Ok(snapshot.into())
 }
 
+/// 设置自动快照间隔（分钟），0 表示关闭
+#[tauri::command]
+pub fn crdt_set_autosnapshot_interval(state: State<AppState>, minutes: u64) -> Result<(), String> {
+    let crdt_guard = state.crdt.lock().unwrap();
+    let crdt = crdt_guard.as_ref().ok_or("CRDT manager not initialized")?;
+
+    crdt.set_autosnapshot_interval(minutes)
+}
+
+/// 后台按间隔为有修改的文档创建自动快照；切换 vault 后使用新的 CRDT 管理器及其间隔设置
+pub fn spawn_autosnapshot_task(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut last_run = Instant::now();
+        loop {
+            tokio::time::sleep(AUTOSNAPSHOT_TICK).await;
+            let crdt = app.state::<AppState>().crdt.lock().unwrap().clone();
+            let Some(interval) = crdt.as_ref().and_then(|c| c.autosnapshot_interval()) else {
+                continue;
+            };
+            if last_run.elapsed() < interval {
+                continue;
+            }
+            last_run = Instant::now();
+            if let Some(crdt) = crdt {
+                if let Err(e) = crdt.auto_snapshot() {
+                    eprintln!("Failed to create automatic snapshots: {}", e);
+                }
+            }
+        }
+    });
+}
+
 /// 获取快照列表
 #[tauri::command]
 pub fn crdt_list_snapshots(state: State<AppState>, doc_id: String) -> Result<Vec<SnapshotInfo>, String> {
@@ -157,6 +262,20 @@ pub fn crdt_list_snapshots(state: State<AppState>, doc_id: String) -> Result<Vec
     Ok(snapshots.into_iter().map(|s| s.into()).collect())
 }
 
+/// 清理历史快照：保留最近 keep_last 个，更早的每天保留一个，最多 keep_daily 天
+#[tauri::command]
+pub fn crdt_prune_snapshots(
+    state: State<AppState>,
+    doc_id: String,
+    keep_last: usize,
+    keep_daily: usize,
+) -> Result<PruneResult, String> {
+    let crdt_guard = state.crdt.lock().unwrap();
+    let crdt = crdt_guard.as_ref().ok_or("CRDT manager not initialized")?;
+
+    crdt.prune_snapshots(&doc_id, keep_last, keep_daily)
+}
+
 /// 恢复到指定快照
 #[tauri::command]
 pub fn crdt_restore_snapshot(
@@ -174,14 +293,29 @@ pub fn crdt_restore_snapshot(
     Ok(base64_encode(&full_state))
 }
 
+/// 比较两个快照，返回从 ts_a 到 ts_b 的文本差异（默认按行）
+#[tauri::command]
+pub fn crdt_diff_snapshots(
+    state: State<AppState>,
+    doc_id: String,
+    ts_a: i64,
+    ts_b: i64,
+    granularity: Option<DiffGranularity>,
+) -> Result<Vec<DiffOp>, String> {
+    let crdt_guard = state.crdt.lock().unwrap();
+    let crdt = crdt_guard.as_ref().ok_or("CRDT manager not initialized")?;
+
+    crdt.diff_snapshots(&doc_id, ts_a, ts_b, granularity.unwrap_or_default())
+}
+
 /// 卸载文档 (释放内存)
 #[tauri::command]
-pub fn crdt_unload(state: State<AppState>, doc_id: String) -> Result<(), String> {
+pub fn crdt_unload(app: AppHandle, state: State<AppState>, doc_id: String) -> Result<(), String> {
     let crdt_guard = state.crdt.lock().unwrap();
     let crdt = crdt_guard.as_ref().ok_or("CRDT manager not initialized")?;
 
-    // 先保存
-    crdt.save_to_disk(&doc_id)?;
+    // 先保存，失败时保留文档在内存中
+    emit_persist_error(&app, Some(&doc_id), crdt.save_to_disk(&doc_id))?;
     // 再卸载
     crdt.unload(&doc_id);
     Ok(())
@@ -190,89 +324,32 @@ pub fn crdt_unload(state: State<AppState>, doc_id: String) -> Result<(), String>
 // ============ 辅助函数 ============
 
 fn base64_encode(data: &[u8]) -> String {
-    use std::io::Write;
-    let mut encoder = base64_encoder();
-    encoder.write_all(data).unwrap();
-    encoder.into_inner()
+    BASE64.encode(data)
 }
 
+/// 严格解码，非法字符、长度或填充都会报错，避免把损坏的数据交给 `Update::decode_v1`
 fn base64_decode(s: &str) -> Result<Vec<u8>, String> {
-    // 简单的 base64 解码实现
-    let table = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
-    let mut result = Vec::new();
-    let mut buffer: u32 = 0;
-    let mut bits_collected = 0;
-
-    for c in s.bytes() {
-        if c == b'=' {
-            break;
-        }
-        let value = table.iter().position(|&x| x == c);
-        if let Some(v) = value {
-            buffer = (buffer << 6) | (v as u32);
-            bits_collected += 6;
-            if bits_collected >= 8 {
-                bits_collected -= 8;
-                result.push((buffer >> bits_collected) as u8);
-                buffer &= (1 << bits_collected) - 1;
-            }
-        }
-    }
-    Ok(result)
-}
-
-struct Base64Encoder {
-    output: String,
-    buffer: u32,
-    bits: u8,
+    BASE64.decode(s).map_err(|e| format!("Invalid base64 payload: {}", e))
 }
 
-fn base64_encoder() -> Base64Encoder {
-    Base64Encoder {
-        output: String::new(),
-        buffer: 0,
-        bits: 0,
-    }
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-impl std::io::Write for Base64Encoder {
-    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        const TABLE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
-        for &byte in buf {
-            self.buffer = (self.buffer << 8) | (byte as u32);
-            self.bits += 8;
-            while self.bits >= 6 {
-                self.bits -= 6;
-                let idx = ((self.buffer >> self.bits) & 0x3F) as usize;
-                self.output.push(TABLE[idx] as char);
-            }
+    #[test]
+    fn test_base64_roundtrip() {
+        for data in [&b""[..], b"a", b"ab", b"abc", &[0u8, 255, 128, 7, 64]] {
+            assert_eq!(base64_decode(&base64_encode(data)).unwrap(), data);
         }
-        Ok(buf.len())
+        assert_eq!(base64_encode(b"ab"), "YWI=");
     }
 
-    fn flush(&mut self) -> std::io::Result<()> {
-        Ok(())
+    #[test]
+    fn test_base64_decode_rejects_malformed_input() {
+        // URL-safe 字母表、缺少填充、非法字符、多余数据都应报错
+        assert!(base64_decode("-_8=").is_err());
+        assert!(base64_decode("YWI").is_err());
+        assert!(base64_decode("YW!=").is_err());
+        assert!(base64_decode("YWI=YWI=").is_err());
     }
 }
-
-impl Base64Encoder {
-    fn into_inner(mut self) -> String {
-        const TABLE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
-        if self.bits > 0 {
-            self.buffer <<= 6 - self.bits;
-            let idx = (self.buffer & 0x3F) as usize;
-            self.output.push(TABLE[idx] as char);
-            let padding = (4 - (self.output.len() % 4)) % 4;
-            for _ in 0..padding {
-                self.output.push('=');
-            }
-        }
-        self.output
-    }
-}
-
-
-
-
-
-
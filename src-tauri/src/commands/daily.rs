@@ -1,59 +1,286 @@
 //! Daily Note 相关命令
 
-use crate::models::{Card, CardListItem, CardType};
+use crate::models::{Card, CardChange, CardChangeOp, CardListItem, CardType};
 use crate::state::AppState;
+use crate::storage;
+use chrono::NaiveDate;
+use serde::Serialize;
+use serde_json::Value;
 use tauri::State;
 
-/// 获取或创建今日日记
+/// 相邻日记（前一篇/后一篇已存在的日记 ID）
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdjacentDailyNotes {
+    pub prev: Option<String>,
+    pub next: Option<String>,
+}
+
+/// 任务顺延结果
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RolloverResult {
+    /// 来源日记 ID，来源日记不存在时为 None
+    pub from_id: Option<String>,
+    /// 追加到目标日记的任务数
+    pub moved: usize,
+}
+
+/// 从日记 ID (daily-YYYY-MM-DD) 解析日期
+fn parse_daily_id(id: &str) -> Option<NaiveDate> {
+    let date = id.strip_prefix("daily-")?;
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+fn parse_date(date: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|e| format!("Invalid date {}: {}", date, e))
+}
+
+/// 收集所有日记卡片的日期（升序）
+async fn daily_dates(state: &AppState) -> Result<Vec<NaiveDate>, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    let mut dates: Vec<NaiveDate> = services
+        .card
+        .get_ids_with_prefix("daily-")
+        .await
+        .map_err(|e| e.to_string())?
+        .iter()
+        .filter_map(|id| parse_daily_id(id))
+        .collect();
+    dates.sort();
+    Ok(dates)
+}
+
+/// 在升序日期列表中查找给定日期前后最近的日期（可跨越缺失的日期）
+fn find_adjacent(dates: &[NaiveDate], date: NaiveDate) -> (Option<NaiveDate>, Option<NaiveDate>) {
+    let prev = dates.iter().rev().find(|d| **d < date).copied();
+    let next = dates.iter().find(|d| **d > date).copied();
+    (prev, next)
+}
+
+fn daily_id(date: NaiveDate) -> String {
+    format!("daily-{}", date.format("%Y-%m-%d"))
+}
+
+/// 任务项的纯文本（含子任务）
+fn task_text(node: &Value) -> String {
+    let mut text = node.get("text").and_then(Value::as_str).unwrap_or("").to_string();
+    if let Some(children) = node.get("content").and_then(Value::as_array) {
+        for child in children {
+            text.push_str(&task_text(child));
+        }
+    }
+    text
+}
+
+fn is_unfinished_task(item: &Value) -> bool {
+    item.get("type").and_then(Value::as_str) == Some("taskItem")
+        && !item.pointer("/attrs/checked").and_then(Value::as_bool).unwrap_or(false)
+        && !task_text(item).trim().is_empty()
+}
+
+/// 勾选任务并在首段末尾注明顺延到的日期
+fn mark_task_moved(item: &mut Value, moved_to: &str) {
+    item["attrs"]["checked"] = Value::Bool(true);
+    let note = serde_json::json!({ "type": "text", "text": format!(" → {}", moved_to) });
+    let paragraph = item
+        .get_mut("content")
+        .and_then(Value::as_array_mut)
+        .and_then(|children| children.iter_mut().find(|c| c.get("type").and_then(Value::as_str) == Some("paragraph")));
+    if let Some(paragraph) = paragraph {
+        match paragraph.get_mut("content").and_then(Value::as_array_mut) {
+            Some(content) => content.push(note),
+            None => paragraph["content"] = Value::Array(vec![note]),
+        }
+    }
+}
+
+/// 收集文档中未完成的任务项（连同其子任务），moved_to 指定时在原文档中标记为已顺延
+///
+/// 已完成的任务不会被移动，但其下未完成的子任务会被单独收集
+fn take_unfinished_tasks(node: &mut Value, moved_to: Option<&str>, out: &mut Vec<Value>) {
+    let is_task_list = node.get("type").and_then(Value::as_str) == Some("taskList");
+    let Some(children) = node.get_mut("content").and_then(Value::as_array_mut) else {
+        return;
+    };
+    for child in children {
+        if is_task_list && is_unfinished_task(child) {
+            out.push(child.clone());
+            if let Some(date) = moved_to {
+                mark_task_moved(child, date);
+            }
+        } else {
+            take_unfinished_tasks(child, moved_to, out);
+        }
+    }
+}
+
+/// 文档中第一个任务列表的条目
+fn first_task_list(node: &mut Value) -> Option<&mut Vec<Value>> {
+    if node.get("type").and_then(Value::as_str) == Some("taskList") {
+        if !node.get("content").is_some_and(Value::is_array) {
+            node["content"] = Value::Array(Vec::new());
+        }
+        return node["content"].as_array_mut();
+    }
+    node.get_mut("content")?.as_array_mut()?.iter_mut().find_map(first_task_list)
+}
+
+/// 将任务追加到文档第一个任务列表（没有时在末尾新建），跳过文本相同的任务，返回追加数
+///
+/// 新建日记自带的空白任务项会被替换掉
+fn append_tasks(doc: &mut Value, tasks: Vec<Value>) -> usize {
+    let Some(list) = first_task_list(doc) else {
+        let moved = tasks.len();
+        if moved > 0 {
+            let task_list = serde_json::json!({ "type": "taskList", "content": tasks });
+            match doc.get_mut("content").and_then(Value::as_array_mut) {
+                Some(content) => content.push(task_list),
+                None => doc["content"] = Value::Array(vec![task_list]),
+            }
+        }
+        return moved;
+    };
+
+    let mut existing: Vec<String> = list.iter().map(|item| task_text(item).trim().to_string()).collect();
+    let mut new_tasks = Vec::new();
+    for task in tasks {
+        let text = task_text(&task).trim().to_string();
+        if !existing.contains(&text) {
+            existing.push(text);
+            new_tasks.push(task);
+        }
+    }
+    if new_tasks.is_empty() {
+        return 0;
+    }
+    list.retain(|item| !task_text(item).trim().is_empty());
+    let moved = new_tasks.len();
+    list.extend(new_tasks);
+    moved
+}
+
+/// 将 from 日记中未完成的任务追加到 to 日记，mark_moved 时在 from 中勾选并注明去向
+async fn rollover_between(
+    state: &AppState,
+    from: NaiveDate,
+    to: NaiveDate,
+    mark_moved: bool,
+) -> Result<RolloverResult, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    let from_id = daily_id(from);
+    let Some(source) = services.card.get_by_id(&from_id).await.map_err(|e| e.to_string())? else {
+        return Ok(RolloverResult::default());
+    };
+    let target = match services.card.get_by_id(&daily_id(to)).await.map_err(|e| e.to_string())? {
+        Some(card) => card,
+        None => create_daily_note(state, to).await?,
+    };
+
+    let mut source_doc: Value = serde_json::from_str(&source.content).map_err(|e| e.to_string())?;
+    let mut target_doc: Value = serde_json::from_str(&target.content).map_err(|e| e.to_string())?;
+    let to_str = to.format("%Y-%m-%d").to_string();
+    let mut tasks = Vec::new();
+    take_unfinished_tasks(&mut source_doc, mark_moved.then_some(to_str.as_str()), &mut tasks);
+    let moved = append_tasks(&mut target_doc, tasks);
+    if moved == 0 {
+        return Ok(RolloverResult {
+            from_id: Some(from_id),
+            moved,
+        });
+    }
+
+    let notify = |change| state.notify_card_change(change);
+    let target_content = serde_json::to_string(&target_doc).map_err(|e| e.to_string())?;
+    services
+        .card
+        .update(&target.id, None, Some(&target_content), None, None, Some(&state.indexer), Some(&notify))
+        .await
+        .map_err(|e| e.to_string())?;
+    if mark_moved {
+        let source_content = serde_json::to_string(&source_doc).map_err(|e| e.to_string())?;
+        services
+            .card
+            .update(&source.id, None, Some(&source_content), None, None, Some(&state.indexer), Some(&notify))
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(RolloverResult {
+        from_id: Some(from_id),
+        moved,
+    })
+}
+
+/// 获取或创建今日日记；rollover 为 true 且今日日记是新建的，会把最近一篇日记中未完成的任务顺延过来
 #[tauri::command]
-pub async fn get_or_create_daily_note(state: State<'_, AppState>) -> Result<Card, String> {
-    // 生成今日日期格式的 ID
-    let today = chrono::Local::now();
-    let date_str = today.format("%Y-%m-%d").to_string();
-    let daily_id = format!("daily-{}", date_str);
+pub async fn get_or_create_daily_note(
+    state: State<'_, AppState>,
+    rollover: Option<bool>,
+) -> Result<Card, String> {
+    let today = chrono::Local::now().date_naive();
 
     // 检查是否已存在
     let services = state.get_services().ok_or("Vault not initialized")?;
-    if let Some(card) = services.card.get_by_id(&daily_id).await.map_err(|e| e.to_string())? {
+    if let Some(card) = services.card.get_by_id(&daily_id(today)).await.map_err(|e| e.to_string())? {
         return Ok(card);
     }
 
-    // 创建新的日记卡片
-    let title = format!("日记 {}", date_str);
+    let card = create_daily_note(&state, today).await?;
+    if !rollover.unwrap_or(false) {
+        return Ok(card);
+    }
 
-    let content = serde_json::json!({
-        "type": "doc",
-        "content": [
-            {
-                "type": "heading",
-                "attrs": { "level": 1 },
-                "content": [{ "type": "text", "text": today.format("%Y年%m月%d日 %A").to_string() }]
-            },
-            {
-                "type": "heading",
-                "attrs": { "level": 2 },
-                "content": [{ "type": "text", "text": "今日待办" }]
-            },
-            {
-                "type": "taskList",
-                "content": [
-                    {
-                        "type": "taskItem",
-                        "attrs": { "checked": false },
-                        "content": [{ "type": "paragraph" }]
-                    }
-                ]
-            },
-            {
-                "type": "heading",
-                "attrs": { "level": 2 },
-                "content": [{ "type": "text", "text": "笔记" }]
-            },
-            { "type": "paragraph" }
-        ]
-    });
+    // 顺延失败不影响今日日记的创建
+    let (prev, _) = find_adjacent(&daily_dates(&state).await?, today);
+    let Some(prev) = prev else {
+        return Ok(card);
+    };
+    match rollover_between(&state, prev, today, true).await {
+        Ok(result) if result.moved > 0 => services
+            .card
+            .get_by_id(&card.id)
+            .await
+            .map_err(|e| e.to_string())
+            .map(|refreshed| refreshed.unwrap_or(card)),
+        Ok(_) => Ok(card),
+        Err(e) => {
+            eprintln!("Failed to roll over tasks from {}: {}", prev, e);
+            Ok(card)
+        }
+    }
+}
+
+/// 将 from_date 日记中未完成的任务顺延到 to_date 日记（不存在时创建）
+///
+/// mark_moved 为 true 时在原日记中勾选这些任务并注明顺延日期；来源日记不存在时不做任何修改
+#[tauri::command]
+pub async fn rollover_tasks(
+    state: State<'_, AppState>,
+    from_date: String,
+    to_date: String,
+    mark_moved: Option<bool>,
+) -> Result<RolloverResult, String> {
+    let from = parse_date(&from_date)?;
+    let to = parse_date(&to_date)?;
+    if from == to {
+        return Err("Source and target dates must differ".to_string());
+    }
+    rollover_between(&state, from, to, mark_moved.unwrap_or(false)).await
+}
+
+/// 创建指定日期的日记卡片
+async fn create_daily_note(state: &AppState, date: NaiveDate) -> Result<Card, String> {
+    let date_str = date.format("%Y-%m-%d").to_string();
+    let daily_id = daily_id(date);
 
-    let content_str = serde_json::to_string(&content).map_err(|e| e.to_string())?;
+    // 创建新的日记卡片，正文来自日记模板（用户可在 .zentri/templates/daily.json 中覆盖）
+    let title = format!("日记 {}", date_str);
+
+    let vault_path = state.vault_path.lock().unwrap().clone().ok_or("Vault not initialized")?;
+    let template = storage::read_template(&vault_path, storage::DAILY_TEMPLATE_ID)
+        .ok_or("Daily note template not found")?;
+    let content_str = storage::render_template(&template, &title, date).to_string();
 
     // 使用 CardService 创建卡片，需要自定义 ID
     // 我们需要直接调用 CardRepository 来创建带自定义 ID 的卡片
@@ -81,19 +308,14 @@ pub async fn get_or_create_daily_note(state: State<'_, AppState>) -> Result<Card
     
     // 更新搜索索引
     if let Ok(Some(idx)) = state.indexer.lock().as_deref() {
-        let path = card.path.as_ref().map(|p| p.as_str()).unwrap_or("");
-        idx.index_doc_with_type(
-            &card.id,
-            &card.title,
-            &card.plain_text,
-            &card.tags,
-            path,
-            card.modified_at,
-            Some(card.card_type.as_str()),
-        )
-        .ok();
+        idx.index_card(&card).ok();
     }
-    
+
+    state.notify_card_change(CardChange {
+        op: CardChangeOp::Created,
+        id: card.id.clone(),
+        card_type: Some(card.card_type.clone()),
+    });
     Ok(card)
 }
 
@@ -128,3 +350,125 @@ pub async fn get_daily_notes(
 
     Ok(notes)
 }
+
+/// 获取指定日期前后最近的日记
+#[tauri::command]
+pub fn get_adjacent_daily_notes(
+    state: State<AppState>,
+    date: String,
+) -> Result<AdjacentDailyNotes, String> {
+    let date = parse_date(&date)?;
+    let dates = daily_dates(&state).await?;
+    let (prev, next) = find_adjacent(&dates, date);
+    Ok(AdjacentDailyNotes {
+        prev: prev.map(daily_id),
+        next: next.map(daily_id),
+    })
+}
+
+/// 获取日期范围内（含首尾）的日记，按日期升序，用于日历视图
+#[tauri::command]
+pub async fn get_daily_notes_in_range(
+    state: State<'_, AppState>,
+    start_date: String,
+    end_date: String,
+) -> Result<Vec<CardListItem>, String> {
+    let start = parse_date(&start_date)?;
+    let end = parse_date(&end_date)?;
+    let ids: Vec<String> = indexed_daily_dates(&state)?
+        .into_iter()
+        .filter(|d| *d >= start && *d <= end)
+        .map(daily_id)
+        .collect();
+
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    let mut notes = Vec::new();
+    for id in ids {
+        if let Some(card) = services.card.get_by_id(&id).await.map_err(|e| e.to_string())? {
+            notes.push(card.into());
+        }
+    }
+    Ok(notes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_adjacent_skips_gaps() {
+        let d = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        let dates = vec![d("2024-01-01"), d("2024-01-05"), d("2024-01-09")];
+
+        assert_eq!(find_adjacent(&dates, d("2024-01-05")), (Some(d("2024-01-01")), Some(d("2024-01-09"))));
+        // 当天没有日记时仍能找到前后最近的
+        assert_eq!(find_adjacent(&dates, d("2024-01-07")), (Some(d("2024-01-05")), Some(d("2024-01-09"))));
+        assert_eq!(find_adjacent(&dates, d("2024-01-01")), (None, Some(d("2024-01-05"))));
+        assert_eq!(parse_daily_id("daily-2024-02-30"), None);
+    }
+
+    fn task(text: &str, checked: bool) -> Value {
+        serde_json::json!({
+            "type": "taskItem",
+            "attrs": { "checked": checked },
+            "content": [{ "type": "paragraph", "content": [{ "type": "text", "text": text }] }]
+        })
+    }
+
+    #[test]
+    fn test_rollover_moves_only_unfinished_tasks() {
+        let mut done_with_subtask = task("写周报", true);
+        done_with_subtask["content"].as_array_mut().unwrap().push(serde_json::json!({
+            "type": "taskList",
+            "content": [task("补充数据", false)]
+        }));
+        let mut source = serde_json::json!({
+            "type": "doc",
+            "content": [{
+                "type": "taskList",
+                "content": [task("读论文", false), task("买菜", true), done_with_subtask, task("回邮件", false)]
+            }]
+        });
+        let mut target = serde_json::json!({
+            "type": "doc",
+            "content": [
+                { "type": "heading", "content": [{ "type": "text", "text": "今日待办" }] },
+                {
+                    "type": "taskList",
+                    "content": [
+                        { "type": "taskItem", "attrs": { "checked": false }, "content": [{ "type": "paragraph" }] },
+                        task("回邮件", false)
+                    ]
+                }
+            ]
+        });
+
+        let mut tasks = Vec::new();
+        take_unfinished_tasks(&mut source, Some("2024-01-02"), &mut tasks);
+        let texts: Vec<String> = tasks.iter().map(task_text).collect();
+        assert_eq!(texts, vec!["读论文", "补充数据", "回邮件"]);
+
+        // 原日记中的任务被勾选并注明去向，再次顺延不会重复
+        assert_eq!(task_text(&source["content"][0]["content"][0]), "读论文 → 2024-01-02");
+        assert_eq!(source["content"][0]["content"][0]["attrs"]["checked"], true);
+        assert_eq!(source["content"][0]["content"][1]["attrs"]["checked"], true);
+        let mut again = Vec::new();
+        take_unfinished_tasks(&mut source, Some("2024-01-02"), &mut again);
+        assert!(again.is_empty());
+
+        // 空白占位任务被替换，已存在的"回邮件"不重复追加
+        assert_eq!(append_tasks(&mut target, tasks), 2);
+        let list: Vec<String> = target["content"][1]["content"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(task_text)
+            .collect();
+        assert_eq!(list, vec!["回邮件", "读论文", "补充数据"]);
+
+        // 没有任务列表时在末尾新建
+        let mut plain = serde_json::json!({ "type": "doc", "content": [{ "type": "paragraph" }] });
+        assert_eq!(append_tasks(&mut plain, vec![task("散步", false)]), 1);
+        assert_eq!(plain["content"][1]["type"], "taskList");
+    }
+}
@@ -1,31 +1,161 @@
 //! Graph 相关命令
 //! 提供图谱数据、反向链接、重要性排名、知识集群等 API
 
-use crate::graph::{self, BacklinkInfo, CardImportance, GraphData, KnowledgeCluster};
+use crate::graph::{
+    self, BacklinkInfo, CardImportance, Cluster, FocusedGraph, GraphData, GraphDelta,
+    GraphExportFormat, KnowledgeCluster, LocalGraph, PathStep,
+};
+use crate::models::CardListItem;
+use crate::services::card_service::link_contexts;
 use crate::state::AppState;
-use tauri::State;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tauri::{AppHandle, Emitter, State};
+use tauri_plugin_dialog::DialogExt;
 
-/// 获取完整图谱数据 (包含布局)
+/// 布局任务代数：新的流式布局或 stop_layout 都会使旧任务失效
+static LAYOUT_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// 获取完整图谱数据 (包含布局)，默认不含已归档卡片
 #[tauri::command]
-pub async fn get_graph_data(state: State<'_, AppState>) -> Result<GraphData, String> {
+pub async fn get_graph_data(
+    state: State<'_, AppState>,
+    include_archived: Option<bool>,
+) -> Result<GraphData, String> {
     let services = state.get_services().ok_or("Vault not initialized")?;
-    let cards = services.card.get_all().await.map_err(|e| e.to_string())?;
+    let cards = services
+        .card
+        .get_all_filtered(include_archived.unwrap_or(false))
+        .await
+        .map_err(|e| e.to_string())?;
     // 转换为 CardListItem（graph 模块需要的格式）
     let card_list: Vec<_> = cards.into_iter().map(|c| c.into()).collect();
-    Ok(graph::compute_layout(card_list))
+    let data = graph::compute_layout(card_list);
+    if let Some(graph_engine) = state.graph_engine.lock().unwrap().as_ref() {
+        graph_engine.remember_layout(&data);
+    }
+    Ok(data)
+}
+
+/// 获取图谱数据并标注各节点距焦点卡片的跳数，供前端按距离淡化远处节点
+#[tauri::command]
+pub async fn get_graph_with_focus(
+    state: State<'_, AppState>,
+    focus_id: String,
+    include_archived: Option<bool>,
+) -> Result<FocusedGraph, String> {
+    let graph = get_graph_data(state, include_archived).await?;
+    let hop_distances = graph::annotate_hop_distance(&graph, &focus_id);
+    Ok(FocusedGraph { graph, hop_distances })
+}
+
+/// 以某张卡片为中心，取 `depth` 跳内（出链和反向链接）的局部图谱并计算布局。
+/// 超过 `max_nodes`（默认 200）的节点由远到近截掉，在 `truncated` 中返回
+#[tauri::command]
+pub async fn get_local_graph(
+    state: State<'_, AppState>,
+    card_id: String,
+    depth: usize,
+    max_nodes: Option<usize>,
+    include_archived: Option<bool>,
+) -> Result<LocalGraph, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    let cards = services
+        .card
+        .get_all_filtered(include_archived.unwrap_or(false))
+        .await
+        .map_err(|e| e.to_string())?;
+    let card_list: Vec<_> = cards.into_iter().map(|c| c.into()).collect();
+    graph::compute_local_graph(
+        card_list,
+        &card_id,
+        depth,
+        max_nodes.unwrap_or(graph::DEFAULT_LOCAL_GRAPH_MAX_NODES),
+    )
+    .ok_or_else(|| format!("Card not found: {}", card_id))
 }
 
-/// 获取指定卡片的反向链接
+/// 流式计算图谱布局：每 `tick_every` 次迭代发送 `graph-layout-tick` 事件，返回最终布局
 #[tauri::command]
-pub fn get_backlinks(state: State<AppState>, card_id: String) -> Result<Vec<BacklinkInfo>, String> {
+pub async fn compute_layout_streaming(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    iterations: Option<usize>,
+    tick_every: Option<usize>,
+    include_archived: Option<bool>,
+    theta: Option<f32>,
+) -> Result<GraphData, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    let cards = services
+        .card
+        .get_all_filtered(include_archived.unwrap_or(false))
+        .await
+        .map_err(|e| e.to_string())?;
+    let card_list: Vec<_> = cards.into_iter().map(|c| c.into()).collect();
+
+    let generation = LAYOUT_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    let data = tokio::task::spawn_blocking(move || {
+        graph::compute_layout_streaming(
+            card_list,
+            iterations.unwrap_or(300),
+            tick_every.unwrap_or(10),
+            theta.unwrap_or(graph::DEFAULT_BARNES_HUT_THETA),
+            || LAYOUT_GENERATION.load(Ordering::SeqCst) != generation,
+            |tick| {
+                let _ = app.emit("graph-layout-tick", tick);
+            },
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    if let Some(graph_engine) = state.graph_engine.lock().unwrap().as_ref() {
+        graph_engine.remember_layout(&data);
+    }
+    Ok(data)
+}
+
+/// 停止正在进行的流式布局
+#[tauri::command]
+pub fn stop_layout() {
+    LAYOUT_GENERATION.fetch_add(1, Ordering::SeqCst);
+}
+
+/// 获取指定卡片的反向链接，来源卡片每处链接到它的位置各返回一条，并附上所在段落的上下文
+#[tauri::command]
+pub async fn get_backlinks(
+    state: State<'_, AppState>,
+    card_id: String,
+) -> Result<Vec<BacklinkInfo>, String> {
     let graph_engine = state
         .graph_engine
         .lock()
         .unwrap()
         .clone()
         .ok_or("Graph engine not initialized")?;
+    let services = state.get_services().ok_or("Vault not initialized")?;
 
-    Ok(graph_engine.get_backlinks(&card_id))
+    let resolver = graph_engine.link_resolver();
+    let is_target = |link: &str| resolver.resolve(link).as_deref() == Some(card_id.as_str());
+    let mut backlinks = Vec::new();
+    for backlink in graph_engine.get_backlinks(&card_id) {
+        let content = services
+            .card
+            .get_by_id(&backlink.id)
+            .await
+            .map_err(|e| e.to_string())?
+            .map(|card| card.content)
+            .unwrap_or_default();
+        let contexts = link_contexts(&content, &is_target);
+        // 链接不在正文里（如 frontmatter）时仍保留一条无上下文的记录
+        if contexts.is_empty() {
+            backlinks.push(backlink);
+            continue;
+        }
+        backlinks.extend(contexts.into_iter().map(|context| BacklinkInfo {
+            context: Some(context),
+            ..backlink.clone()
+        }));
+    }
+    Ok(backlinks)
 }
 
 /// 获取卡片重要性排名 (PageRank)
@@ -44,6 +174,27 @@ pub fn get_card_importance(
     Ok(graph_engine.get_importance_ranking(limit.unwrap_or(50)))
 }
 
+/// 按当前卡片链接计算 PageRank，返回 `[卡片 ID, 分数]` 列表（从高到低），用于节点大小或“核心笔记”排行
+#[tauri::command]
+pub async fn get_card_pagerank(
+    state: State<'_, AppState>,
+    limit: Option<usize>,
+    include_archived: Option<bool>,
+) -> Result<Vec<(String, f32)>, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    let cards = services
+        .card
+        .get_all_filtered(include_archived.unwrap_or(false))
+        .await
+        .map_err(|e| e.to_string())?;
+    let card_list: Vec<_> = cards.into_iter().map(|c| c.into()).collect();
+    let mut ranked = graph::rank_cards(&card_list);
+    if let Some(limit) = limit {
+        ranked.truncate(limit);
+    }
+    Ok(ranked)
+}
+
 /// 获取知识集群 (连通分量)
 #[tauri::command]
 pub fn get_knowledge_clusters(state: State<AppState>) -> Result<Vec<KnowledgeCluster>, String> {
@@ -57,6 +208,51 @@ pub fn get_knowledge_clusters(state: State<AppState>) -> Result<Vec<KnowledgeClu
     Ok(graph_engine.get_clusters())
 }
 
+/// 在链接图上做社区发现，按主题划分卡片（`resolution` 越大社区越细，默认 1.0）
+#[tauri::command]
+pub async fn get_topic_clusters(
+    state: State<'_, AppState>,
+    resolution: Option<f64>,
+    include_archived: Option<bool>,
+) -> Result<Vec<Cluster>, String> {
+    let resolution = resolution.unwrap_or(graph::DEFAULT_COMMUNITY_RESOLUTION);
+    if !(resolution > 0.0 && resolution.is_finite()) {
+        return Err("Resolution must be a positive number".to_string());
+    }
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    let cards = services
+        .card
+        .get_all_filtered(include_archived.unwrap_or(false))
+        .await
+        .map_err(|e| e.to_string())?;
+    let card_list: Vec<_> = cards.into_iter().map(|c| c.into()).collect();
+    Ok(graph::detect_communities(&card_list, resolution))
+}
+
+/// 求两张卡片之间的最短链接路径，不连通时返回 None（`directed` 默认 false，即出链和反向链接都可走）
+#[tauri::command]
+pub async fn get_card_path(
+    state: State<'_, AppState>,
+    from_id: String,
+    to_id: String,
+    directed: Option<bool>,
+    include_archived: Option<bool>,
+) -> Result<Option<Vec<PathStep>>, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    let cards = services
+        .card
+        .get_all_filtered(include_archived.unwrap_or(false))
+        .await
+        .map_err(|e| e.to_string())?;
+    let card_list: Vec<CardListItem> = cards.into_iter().map(|c| c.into()).collect();
+    for id in [&from_id, &to_id] {
+        if !card_list.iter().any(|c| &c.id == id) {
+            return Err(format!("Card not found: {}", id));
+        }
+    }
+    Ok(graph::shortest_path(&card_list, &from_id, &to_id, directed.unwrap_or(false)))
+}
+
 /// 获取孤立节点 (知识孤岛)
 #[tauri::command]
 pub fn get_orphan_nodes(state: State<AppState>) -> Result<Vec<String>, String> {
@@ -70,9 +266,42 @@ pub fn get_orphan_nodes(state: State<AppState>) -> Result<Vec<String>, String> {
     Ok(graph_engine.get_orphan_nodes())
 }
 
-/// 重建图谱索引
+/// 单张卡片保存、删除或归档后增量更新图谱，其余节点保持上次布局的坐标。
+/// 变化的节点和边既作为返回值，也通过 `graph-delta` 事件推送
 #[tauri::command]
-pub async fn rebuild_graph(state: State<'_, AppState>) -> Result<(), String> {
+pub async fn update_card_in_graph(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    card_id: String,
+    include_archived: Option<bool>,
+) -> Result<GraphDelta, String> {
+    let graph_engine = state
+        .graph_engine
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or("Graph engine not initialized")?;
+
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    let card = services
+        .card
+        .get_by_id(&card_id)
+        .await
+        .map_err(|e| e.to_string())?
+        .filter(|c| include_archived.unwrap_or(false) || !c.archived)
+        .map(CardListItem::from);
+
+    let delta = graph_engine.update_card(&card_id, card);
+    let _ = app.emit("graph-delta", &delta);
+    Ok(delta)
+}
+
+/// 重建图谱索引，默认不含已归档卡片
+#[tauri::command]
+pub async fn rebuild_graph(
+    state: State<'_, AppState>,
+    include_archived: Option<bool>,
+) -> Result<(), String> {
     let graph_engine = state
         .graph_engine
         .lock()
@@ -82,9 +311,65 @@ pub async fn rebuild_graph(state: State<'_, AppState>) -> Result<(), String> {
 
     // 从数据库获取所有卡片
     let services = state.get_services().ok_or("Vault not initialized")?;
-    let cards = services.card.get_all().await.map_err(|e| e.to_string())?;
+    let cards = services
+        .card
+        .get_all_filtered(include_archived.unwrap_or(false))
+        .await
+        .map_err(|e| e.to_string())?;
     let card_list: Vec<_> = cards.into_iter().map(|c| c.into()).collect();
     
     graph_engine.rebuild_with_cards(card_list);
     Ok(())
 }
+
+/// 导出图谱（GraphML / DOT / JSON），未指定路径时弹出保存对话框
+///
+/// 返回写入的文件路径，用户取消时返回 None
+#[tauri::command]
+pub async fn export_graph(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    format: GraphExportFormat,
+    path: Option<String>,
+    include_archived: Option<bool>,
+) -> Result<Option<String>, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    let cards = services
+        .card
+        .get_all_filtered(include_archived.unwrap_or(false))
+        .await
+        .map_err(|e| e.to_string())?;
+    let card_list: Vec<_> = cards.into_iter().map(|c| c.into()).collect();
+
+    let path = match path {
+        Some(path) => std::path::PathBuf::from(path),
+        None => {
+            let extension = format.extension();
+            let picked = tokio::task::spawn_blocking(move || {
+                app.dialog()
+                    .file()
+                    .add_filter(extension, &[extension])
+                    .set_file_name(format!("zentri-graph.{}", extension))
+                    .blocking_save_file()
+            })
+            .await
+            .map_err(|e| e.to_string())?;
+            match picked.and_then(|p| p.into_path().ok()) {
+                Some(path) => path,
+                None => return Ok(None),
+            }
+        }
+    };
+
+    let exported = tokio::task::spawn_blocking(move || {
+        let edges = graph::directed_edges(&card_list);
+        let tags = graph::card_tags(&card_list);
+        let data = graph::compute_layout(card_list);
+        graph::export_graph(&data, &edges, &tags, format)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    std::fs::write(&path, exported).map_err(|e| format!("Failed to write graph: {}", e))?;
+    Ok(Some(path.to_string_lossy().to_string()))
+}
@@ -1,6 +1,11 @@
 //! Highlight 相关命令
 
-use crate::models::{CreateHighlightRequest, Highlight, UpdateHighlightRequest};
+use crate::commands::ai::{ai_chat, ChatMessage};
+use crate::models::{Card, CardType, CreateHighlightRequest, Highlight, UpdateHighlightRequest};
+use crate::services::card_service::link_contexts;
+use crate::services::highlight_service::{
+    build_promoted_card_content, build_summary_prompt, promoted_card_title,
+};
 use crate::state::AppState;
 use serde::{Deserialize, Serialize};
 use tauri::State;
@@ -15,6 +20,18 @@ pub struct SourceBacklink {
     pub highlight_content: String,
     pub page: Option<i32>,
     pub cfi: Option<String>,
+    /// 笔记正文中引用该高亮或文献源处所在段落的上下文
+    #[serde(default)]
+    pub context: Option<String>,
+}
+
+/// 高亮摘要结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HighlightSummary {
+    pub summary: String,
+    /// 若请求创建卡片，返回新建的文献卡片
+    pub card: Option<Card>,
 }
 
 /// 获取文献源的高亮
@@ -35,6 +52,21 @@ pub async fn get_all_highlights(state: State<'_, AppState>) -> Result<Vec<Highli
     services.highlight.get_all().await.map_err(|e| e.to_string())
 }
 
+/// 全文搜索高亮原文和笔记，可限定文献源
+#[tauri::command]
+pub async fn search_highlights(
+    state: State<'_, AppState>,
+    query: String,
+    source_id: Option<String>,
+) -> Result<Vec<Highlight>, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services
+        .highlight
+        .search(&query, source_id.as_deref())
+        .await
+        .map_err(|e| e.to_string())
+}
+
 /// 创建高亮
 #[tauri::command]
 pub async fn create_highlight(state: State<'_, AppState>, req: CreateHighlightRequest) -> Result<Highlight, String> {
@@ -61,7 +93,22 @@ pub async fn update_highlight(
 #[tauri::command]
 pub async fn delete_highlight(state: State<'_, AppState>, id: String) -> Result<(), String> {
     let services = state.get_services().ok_or("Vault not initialized")?;
-    services.highlight.delete(&id).await.map_err(|e| e.to_string())
+    let image_path = services
+        .highlight
+        .get_by_id(&id)
+        .await
+        .map_err(|e| e.to_string())?
+        .and_then(|h| h.position)
+        .filter(|p| p.validate_image_path().is_ok())
+        .and_then(|p| p.image_path);
+
+    services.highlight.delete(&id).await.map_err(|e| e.to_string())?;
+
+    // 同时删除区域高亮的截图
+    if let (Some(image_path), Some(vault_path)) = (image_path, state.vault_path.lock().unwrap().clone()) {
+        let _ = std::fs::remove_file(vault_path.join(image_path));
+    }
+    Ok(())
 }
 
 /// 获取卡片关联的高亮
@@ -76,16 +123,165 @@ pub async fn get_highlights_by_card(state: State<'_, AppState>, card_id: String)
 }
 
 /// 获取引用该文献源的所有笔记（反向链接）
+///
+/// 笔记正文中每处引用该高亮或文献源的位置各返回一条，并附上所在段落的上下文
 #[tauri::command]
 pub async fn get_backlinks_for_source(
     state: State<'_, AppState>,
     source_id: String,
 ) -> Result<Vec<SourceBacklink>, String> {
     let services = state.get_services().ok_or("Vault not initialized")?;
-    services
+    let rows = services
         .highlight
         .get_backlinks(&source_id)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+
+    let mut backlinks = Vec::new();
+    for mut backlink in rows {
+        let Some(card) = services
+            .card
+            .get_by_id(&backlink.card_id)
+            .await
+            .map_err(|e| e.to_string())?
+        else {
+            backlinks.push(backlink);
+            continue;
+        };
+        backlink.card_title = card.title;
+        let is_target = |id: &str| id == source_id || id == backlink.highlight_id;
+        let contexts = link_contexts(&card.content, &is_target);
+        if contexts.is_empty() {
+            backlinks.push(backlink);
+            continue;
+        }
+        backlinks.extend(contexts.into_iter().map(|context| SourceBacklink {
+            context: Some(context),
+            ..backlink.clone()
+        }));
+    }
+    Ok(backlinks)
+}
+
+/// 将高亮提升为卡片：原文作为引用块并附出处，高亮和文献源都关联到新卡片
+#[tauri::command]
+pub async fn promote_highlight_to_card(
+    state: State<'_, AppState>,
+    highlight_id: String,
+    card_type: Option<CardType>,
+) -> Result<Card, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    let highlight = services
+        .highlight
+        .get_by_id(&highlight_id)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Highlight not found: {}", highlight_id))?;
+    if highlight.content.trim().is_empty() {
+        return Err("Highlight has no text to promote".to_string());
+    }
+    let source = services
+        .source
+        .get_by_id(&highlight.source_id)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Source not found: {}", highlight.source_id))?;
+
+    let content = build_promoted_card_content(&highlight, &source);
+    // 带 source_id 创建会同时把卡片加入文献源的 note_ids
+    let notify = |change| state.notify_card_change(change);
+    let card = services
+        .card
+        .create(
+            card_type.unwrap_or(CardType::Permanent),
+            &promoted_card_title(&highlight),
+            Some(&content),
+            Some(&source.id),
+            Some(&state.indexer),
+            Some(&notify),
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+
+    services
+        .highlight
+        .update(
+            &highlight.id,
+            UpdateHighlightRequest {
+                note: None,
+                color: None,
+                annotation_type: None,
+                card_id: Some(card.id.clone()),
+                position: None,
+            },
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(card)
 }
 
+/// 使用 AI 综合文献源的所有高亮，可选生成一张文献卡片
+#[tauri::command]
+pub async fn ai_summarize_highlights(
+    state: State<'_, AppState>,
+    source_id: String,
+    create_card: Option<bool>,
+) -> Result<HighlightSummary, String> {
+    let app_state = state.inner();
+    let services = app_state.get_services().ok_or("Vault not initialized")?;
+    let source = services
+        .source
+        .get_by_id(&source_id)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Source not found: {}", source_id))?;
+    let highlights = services
+        .highlight
+        .get_in_reading_order(&source_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    if highlights.is_empty() {
+        return Err("No highlights to summarize".to_string());
+    }
+
+    let messages = vec![ChatMessage {
+        role: "user".to_string(),
+        content: build_summary_prompt(&source.title, &highlights),
+    }];
+    let summary = ai_chat(state, messages, None)
+        .await
+        .map_err(|e| format!("Failed to summarize highlights: {}", e))?
+        .trim()
+        .to_string();
+
+    let card = if create_card.unwrap_or(false) {
+        let paragraphs: Vec<serde_json::Value> = summary
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                serde_json::json!({
+                    "type": "paragraph",
+                    "content": [{ "type": "text", "text": line.trim() }]
+                })
+            })
+            .collect();
+        let content = serde_json::json!({ "type": "doc", "content": paragraphs }).to_string();
+        let card = services
+            .card
+            .create(
+                CardType::Literature,
+                &format!("{} - 高亮摘要", source.title),
+                Some(&content),
+                Some(&source_id),
+                Some(&app_state.indexer),
+                Some(&|change| app_state.notify_card_change(change)),
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+        Some(card)
+    } else {
+        None
+    };
+
+    Ok(HighlightSummary { summary, card })
+}
@@ -1,136 +1,177 @@
 //! Search 相关命令
 //! 提供全文搜索、模糊搜索、过滤搜索等 API
 
-use crate::models::{CardSearchResult, CardType};
+use crate::config::{ConfigManager, SavedSearch, SavedSearchSort};
+use crate::models::{CardSearchResult, CardType, SearchResultKind};
+use crate::search::{
+    FieldBoosts, FuzzyDistance, FuzzyOptions, IndexStats, Indexer, SearchFilter, SearchResult,
+    TokenizerKind, SOURCE_DOC_TYPE,
+};
 use crate::state::AppState;
+use std::collections::HashSet;
+use std::path::PathBuf;
 use tauri::State;
 
-/// 搜索卡片
-#[tauri::command]
-pub fn search_cards(state: State<AppState>, query: String) -> Result<Vec<CardSearchResult>, String> {
-    let indexer_guard = state.indexer.lock().unwrap();
-    let indexer = indexer_guard.as_ref().ok_or("Indexer not initialized")?;
+/// 保存搜索执行时的最大结果数
+const SAVED_SEARCH_LIMIT: usize = 500;
+
+/// 将索引结果转换为前端结果，文献源文档标记为 Source
+fn to_card_search_result(r: SearchResult) -> CardSearchResult {
+    let kind = if r.card_type.as_deref() == Some(SOURCE_DOC_TYPE) {
+        SearchResultKind::Source
+    } else {
+        SearchResultKind::Card
+    };
+    CardSearchResult {
+        id: r.id,
+        title: r.title,
+        score: r.score,
+        snippet: r.snippet,
+        card_type: r.card_type.map(|s| CardType::from_str(&s)).unwrap_or(CardType::Fleeting),
+        tags: r.tags,
+        kind,
+    }
+}
 
-    let results = indexer.search_with_snippets(&query, 50)?;
+/// 转换为前端结果；`include_archived` 不为 true 时去掉已归档卡片
+async fn finish_results(
+    state: &AppState,
+    results: Vec<SearchResult>,
+    include_archived: Option<bool>,
+) -> Result<Vec<CardSearchResult>, String> {
+    let archived = match state.get_services() {
+        Some(services) if !include_archived.unwrap_or(false) => {
+            services.card.get_archived_ids().await.map_err(|e| e.to_string())?
+        }
+        _ => HashSet::new(),
+    };
 
     Ok(results
         .into_iter()
-        .map(|r| CardSearchResult {
-            id: r.id,
-            title: r.title,
-            score: r.score,
-            snippet: r.snippet,
-            card_type: r.card_type.map(|s| CardType::from_str(&s)).unwrap_or(CardType::Fleeting),
-            tags: r.tags,
-        })
+        .filter(|r| !archived.contains(&r.id))
+        .map(to_card_search_result)
         .collect())
 }
 
+/// 搜索卡片（同时返回匹配的文献源）
+#[tauri::command]
+pub async fn search_cards(
+    state: State<'_, AppState>,
+    query: String,
+    use_synonyms: Option<bool>,
+    include_archived: Option<bool>,
+) -> Result<Vec<CardSearchResult>, String> {
+    let results = {
+        let indexer_guard = state.indexer.lock().unwrap();
+        let indexer = indexer_guard.as_ref().ok_or("Indexer not initialized")?;
+
+        if use_synonyms.unwrap_or(false) {
+            indexer.search_with_synonyms(&query, 50, &SearchFilter::default())?
+        } else {
+            indexer.search_with_snippets(&query, 50)?
+        }
+    };
+
+    finish_results(&state, results, include_archived).await
+}
+
 /// 带过滤条件的搜索
 #[tauri::command]
-pub fn search_cards_filtered(
-    state: State<AppState>,
+pub async fn search_cards_filtered(
+    state: State<'_, AppState>,
     query: String,
     card_type: Option<String>,
     tag: Option<String>,
     limit: Option<usize>,
+    modified_after: Option<i64>,
+    modified_before: Option<i64>,
+    use_synonyms: Option<bool>,
+    include_archived: Option<bool>,
 ) -> Result<Vec<CardSearchResult>, String> {
-    let indexer_guard = state.indexer.lock().unwrap();
-    let indexer = indexer_guard.as_ref().ok_or("Indexer not initialized")?;
+    let filter = SearchFilter {
+        card_type,
+        tag,
+        modified_after,
+        modified_before,
+        ..Default::default()
+    };
+    let results = {
+        let indexer_guard = state.indexer.lock().unwrap();
+        let indexer = indexer_guard.as_ref().ok_or("Indexer not initialized")?;
 
-    let results = indexer.search_with_filter(
-        &query,
-        limit.unwrap_or(50),
-        card_type.as_deref(),
-        tag.as_deref(),
-    )?;
+        if use_synonyms.unwrap_or(false) {
+            indexer.search_with_synonyms(&query, limit.unwrap_or(50), &filter)?
+        } else {
+            indexer.search_with_filter(&query, limit.unwrap_or(50), &filter)?
+        }
+    };
 
-    Ok(results
-        .into_iter()
-        .map(|r| CardSearchResult {
-            id: r.id,
-            title: r.title,
-            score: r.score,
-            snippet: r.snippet,
-            card_type: r.card_type.map(|s| CardType::from_str(&s)).unwrap_or(CardType::Fleeting),
-            tags: r.tags,
-        })
-        .collect())
+    finish_results(&state, results, include_archived).await
 }
 
 /// 模糊搜索 (处理拼写错误)
 #[tauri::command]
-pub fn fuzzy_search_cards(
-    state: State<AppState>,
+pub async fn fuzzy_search_cards(
+    state: State<'_, AppState>,
     query: String,
     limit: Option<usize>,
+    max_distance: Option<FuzzyDistance>,
+    prefix: Option<bool>,
+    include_archived: Option<bool>,
 ) -> Result<Vec<CardSearchResult>, String> {
-    let indexer_guard = state.indexer.lock().unwrap();
-    let indexer = indexer_guard.as_ref().ok_or("Indexer not initialized")?;
-
-    let results = indexer.fuzzy_search(&query, limit.unwrap_or(50))?;
+    let options = FuzzyOptions {
+        distance: max_distance.unwrap_or_default(),
+        prefix: prefix.unwrap_or(false),
+    };
+    let results = {
+        let indexer_guard = state.indexer.lock().unwrap();
+        let indexer = indexer_guard.as_ref().ok_or("Indexer not initialized")?;
+        indexer.fuzzy_search(&query, limit.unwrap_or(50), &options)?
+    };
 
-    Ok(results
-        .into_iter()
-        .map(|r| CardSearchResult {
-            id: r.id,
-            title: r.title,
-            score: r.score,
-            snippet: r.snippet,
-            card_type: r.card_type.map(|s| CardType::from_str(&s)).unwrap_or(CardType::Fleeting),
-            tags: r.tags,
-        })
-        .collect())
+    finish_results(&state, results, include_archived).await
 }
 
 /// 按标签搜索
 #[tauri::command]
-pub fn search_by_tag(
-    state: State<AppState>,
+pub async fn search_by_tag(
+    state: State<'_, AppState>,
     tag: String,
     limit: Option<usize>,
+    include_archived: Option<bool>,
 ) -> Result<Vec<CardSearchResult>, String> {
-    let indexer_guard = state.indexer.lock().unwrap();
-    let indexer = indexer_guard.as_ref().ok_or("Indexer not initialized")?;
-
-    let results = indexer.search_by_tag(&tag, limit.unwrap_or(50))?;
+    let results = {
+        let indexer_guard = state.indexer.lock().unwrap();
+        let indexer = indexer_guard.as_ref().ok_or("Indexer not initialized")?;
+        indexer.search_by_tag(&tag, limit.unwrap_or(50))?
+    };
 
-    Ok(results
-        .into_iter()
-        .map(|r| CardSearchResult {
-            id: r.id,
-            title: r.title,
-            score: r.score,
-            snippet: r.snippet,
-            card_type: r.card_type.map(|s| CardType::from_str(&s)).unwrap_or(CardType::Fleeting),
-            tags: r.tags,
-        })
-        .collect())
+    finish_results(&state, results, include_archived).await
 }
 
 /// 按卡片类型搜索
 #[tauri::command]
-pub fn search_by_type(
-    state: State<AppState>,
+pub async fn search_by_type(
+    state: State<'_, AppState>,
     card_type: String,
     limit: Option<usize>,
+    include_archived: Option<bool>,
 ) -> Result<Vec<CardSearchResult>, String> {
-    let indexer_guard = state.indexer.lock().unwrap();
-    let indexer = indexer_guard.as_ref().ok_or("Indexer not initialized")?;
+    let results = {
+        let indexer_guard = state.indexer.lock().unwrap();
+        let indexer = indexer_guard.as_ref().ok_or("Indexer not initialized")?;
+        indexer.search_by_type(&card_type, limit.unwrap_or(50))?
+    };
 
-    let results = indexer.search_by_type(&card_type, limit.unwrap_or(50))?;
+    finish_results(&state, results, include_archived).await
+}
 
-    Ok(results
-        .into_iter()
-        .map(|r| CardSearchResult {
-            id: r.id,
-            title: r.title,
-            score: r.score,
-            snippet: r.snippet,
-            card_type: r.card_type.map(|s| CardType::from_str(&s)).unwrap_or(CardType::Fleeting),
-            tags: r.tags,
-        })
-        .collect())
+/// 获取搜索索引统计（文档数、段数、磁盘占用、各字段词项数）
+#[tauri::command]
+pub fn index_stats(state: State<AppState>) -> Result<IndexStats, String> {
+    let indexer_guard = state.indexer.lock().unwrap();
+    let indexer = indexer_guard.as_ref().ok_or("Indexer not initialized")?;
+    indexer.stats()
 }
 
 /// 同步索引 (全量重建)
@@ -141,6 +182,9 @@ pub async fn sync_index(state: State<'_, AppState>) -> Result<usize, String> {
         indexer_guard.clone().ok_or("Indexer not initialized")?
     };
 
+    // 先清理索引漂移留下的重复文档
+    indexer.dedup_index()?;
+
     // 获取所有卡片
     let services = state.get_services().ok_or("Vault not initialized")?;
     let cards = services.card.get_all().await.map_err(|e| e.to_string())?;
@@ -157,23 +201,27 @@ pub async fn sync_index(state: State<'_, AppState>) -> Result<usize, String> {
         };
 
         if should_index {
-            let path = card.path.as_ref().map(|p| p.as_str()).unwrap_or("");
-            indexer
-                .index_doc_with_type(
-                    &card.id,
-                    &card.title,
-                    &card.plain_text, // 使用纯文本内容
-                    &card.tags,
-                    path,
-                    card.modified_at,
-                    Some(card.card_type.as_str()),
-                )
-                .map_err(|e| e.to_string())?;
+            // 加密卡片只索引标题
+            indexer.index_card(card)?;
             count += 1;
         }
         
-        // 添加到图谱列表
-        card_list.push(card.clone().into());
+        // 添加到图谱列表（已归档卡片默认不进入图谱）
+        if !card.archived {
+            card_list.push(card.clone().into());
+        }
+    }
+
+    // 文献源与卡片共用索引
+    for source in services.source.get_all().await.map_err(|e| e.to_string())? {
+        let should_index = match indexer.get_doc_mtime(&source.id) {
+            Ok(Some(indexed_mtime)) => source.updated_at > indexed_mtime,
+            _ => true,
+        };
+        if should_index {
+            indexer.index_source(&source)?;
+            count += 1;
+        }
     }
 
     // 同时重建图谱
@@ -183,3 +231,105 @@ pub async fn sync_index(state: State<'_, AppState>) -> Result<usize, String> {
 
     Ok(count)
 }
+
+/// 更换当前 vault 索引的分词器：删除旧索引，以新分词器重建并全量重新索引，返回索引的文档数
+///
+/// 分词器记录在索引中，之后打开该 vault 会沿用
+#[tauri::command]
+pub async fn set_index_tokenizer(
+    state: State<'_, AppState>,
+    tokenizer: TokenizerKind,
+) -> Result<usize, String> {
+    let vault_path = state
+        .vault_path
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or("Vault not initialized")?;
+    {
+        let mut indexer_guard = state.indexer.lock().unwrap();
+        if indexer_guard.as_ref().map(Indexer::tokenizer) == Some(tokenizer) {
+            return Ok(0);
+        }
+        // 先释放旧索引的文件句柄再删除目录
+        indexer_guard.take();
+        let indexer = Indexer::recreate(&vault_path.join(".zentri/index"), tokenizer)?
+            .with_field_boosts(FieldBoosts::from_vault(&vault_path));
+        *indexer_guard = Some(indexer);
+    }
+    sync_index(state).await
+}
+
+/// 应用配置管理器（保存的搜索存放在应用配置中）
+fn config_manager() -> ConfigManager {
+    let app_data_dir = dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("zentri");
+    ConfigManager::new(&app_data_dir)
+}
+
+/// 创建或覆盖保存的搜索
+#[tauri::command]
+pub fn create_saved_search(search: SavedSearch) -> Result<SavedSearch, String> {
+    if search.name.trim().is_empty() {
+        return Err("Saved search name cannot be empty".to_string());
+    }
+    config_manager()
+        .upsert_saved_search(search.clone())
+        .map_err(|e| e.to_string())?;
+    Ok(search)
+}
+
+/// 列出保存的搜索
+#[tauri::command]
+pub fn list_saved_searches() -> Result<Vec<SavedSearch>, String> {
+    config_manager().saved_searches().map_err(|e| e.to_string())
+}
+
+/// 删除保存的搜索
+#[tauri::command]
+pub fn delete_saved_search(name: String) -> Result<bool, String> {
+    config_manager()
+        .remove_saved_search(&name)
+        .map_err(|e| e.to_string())
+}
+
+/// 执行保存的搜索，默认排除已归档卡片
+#[tauri::command]
+pub async fn run_saved_search(
+    state: State<'_, AppState>,
+    name: String,
+    include_archived: Option<bool>,
+) -> Result<Vec<CardSearchResult>, String> {
+    let search = config_manager()
+        .saved_searches()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|s| s.name == name)
+        .ok_or_else(|| format!("Saved search not found: {}", name))?;
+
+    let results = {
+        let indexer_guard = state.indexer.lock().unwrap();
+        let indexer = indexer_guard.as_ref().ok_or("Indexer not initialized")?;
+
+        let filter = SearchFilter {
+            card_type: search.card_type.clone(),
+            tags_include: search.tags_include.clone(),
+            tags_exclude: search.tags_exclude.clone(),
+            ..Default::default()
+        };
+        let mut results = indexer.search_with_filter(&search.query, SAVED_SEARCH_LIMIT, &filter)?;
+
+        match search.sort {
+            SavedSearchSort::Relevance => {}
+            SavedSearchSort::Title => results.sort_by(|a, b| a.title.cmp(&b.title)),
+            SavedSearchSort::Modified => {
+                let mtimes = indexer.all_doc_mtimes()?;
+                results.sort_by_key(|r| std::cmp::Reverse(mtimes.get(&r.id).copied().unwrap_or(0)));
+            }
+        }
+        results
+    };
+
+    finish_results(&state, results, include_archived).await
+}
@@ -1,6 +1,8 @@
 //! Source 相关命令
 
-use crate::models::{CreateSourceRequest, Source, UpdateSourceRequest};
+use crate::models::{
+    CreateSourceRequest, ReadingListFilter, Source, SourceFilter, UpdateSourceRequest,
+};
 use crate::state::AppState;
 use tauri::State;
 
@@ -11,6 +13,44 @@ pub async fn get_sources(state: State<'_, AppState>) -> Result<Vec<Source>, Stri
     services.source.get_all().await.map_err(|e| e.to_string())
 }
 
+/// 按类型、语言和内容格式过滤文献源
+#[tauri::command]
+pub async fn get_sources_filtered(
+    state: State<'_, AppState>,
+    filter: SourceFilter,
+) -> Result<Vec<Source>, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services.source.get_filtered(&filter).await.map_err(|e| e.to_string())
+}
+
+/// 获取"继续阅读"列表（首页 jump back in）
+#[tauri::command]
+pub async fn get_continue_reading(
+    state: State<'_, AppState>,
+    limit: Option<usize>,
+) -> Result<Vec<Source>, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services
+        .source
+        .get_continue_reading(limit.unwrap_or(10))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 生成阅读清单（按标签、类型、进度过滤并排序）
+#[tauri::command]
+pub async fn get_reading_list(
+    state: State<'_, AppState>,
+    filter: ReadingListFilter,
+) -> Result<Vec<Source>, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services
+        .source
+        .get_reading_list(&filter)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 /// 获取单个文献源
 #[tauri::command]
 pub async fn get_source(state: State<'_, AppState>, id: String) -> Result<Option<Source>, String> {
@@ -20,9 +60,11 @@ pub async fn get_source(state: State<'_, AppState>, id: String) -> Result<Option
 
 /// 创建文献源
 #[tauri::command]
-pub async fn create_source(state: State<'_, AppState>, req: CreateSourceRequest) -> Result<Source, String> {
+pub async fn create_source(state: State<'_, AppState>, mut req: CreateSourceRequest) -> Result<Source, String> {
+    // 前端可标明 "web" 等途径，未标明即为手动添加
+    req.source_origin.get_or_insert_with(|| "manual".to_string());
     let services = state.get_services().ok_or("Vault not initialized")?;
-    services.source.create(req).await.map_err(|e| e.to_string())
+    services.source.create(req, Some(&state.indexer)).await.map_err(|e| e.to_string())
 }
 
 /// 更新文献源
@@ -33,13 +75,84 @@ pub async fn update_source(
     req: UpdateSourceRequest,
 ) -> Result<Option<Source>, String> {
     let services = state.get_services().ok_or("Vault not initialized")?;
-    services.source.update(&id, req).await.map_err(|e| e.to_string())
+    services.source.update(&id, req, Some(&state.indexer)).await.map_err(|e| e.to_string())
 }
 
-/// 删除文献源
+/// 删除文献源（移入回收站）
 #[tauri::command]
 pub async fn delete_source(state: State<'_, AppState>, id: String) -> Result<(), String> {
     let services = state.get_services().ok_or("Vault not initialized")?;
-    services.source.delete(&id).await.map_err(|e| e.to_string())
+    services.source.delete(&id, Some(&state.indexer)).await.map_err(|e| e.to_string())
+}
+
+/// 永久删除文献源
+#[tauri::command]
+pub async fn hard_delete_source(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services.source.hard_delete(&id, Some(&state.indexer)).await.map_err(|e| e.to_string())
+}
+
+/// 从回收站恢复文献源
+#[tauri::command]
+pub async fn restore_source(state: State<'_, AppState>, id: String) -> Result<Option<Source>, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services.source.restore(&id, Some(&state.indexer)).await.map_err(|e| e.to_string())
 }
 
+/// 获取回收站中的文献源
+#[tauri::command]
+pub async fn list_trashed_sources(state: State<'_, AppState>) -> Result<Vec<Source>, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services.source.get_trashed().await.map_err(|e| e.to_string())
+}
+
+/// 清空回收站中超过指定天数的文献源（默认 30 天），返回删除数量
+#[tauri::command]
+pub async fn purge_trashed_sources(
+    state: State<'_, AppState>,
+    older_than_days: Option<u32>,
+) -> Result<u64, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services
+        .source
+        .purge_trashed(older_than_days.unwrap_or(crate::db::TRASH_RETENTION_DAYS))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 列出文献源标签及使用次数
+#[tauri::command]
+pub async fn list_source_tags(state: State<'_, AppState>) -> Result<Vec<(String, usize)>, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services.source.list_tags().await.map_err(|e| e.to_string())
+}
+
+/// 重命名文献源标签，返回受影响的文献源数量
+#[tauri::command]
+pub async fn rename_source_tag(
+    state: State<'_, AppState>,
+    old: String,
+    new: String,
+) -> Result<usize, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services
+        .source
+        .rename_tag(&old, &new, Some(&state.indexer))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 将多个文献源标签合并为一个，返回受影响的文献源数量
+#[tauri::command]
+pub async fn merge_source_tags(
+    state: State<'_, AppState>,
+    from: Vec<String>,
+    into: String,
+) -> Result<usize, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services
+        .source
+        .merge_tags(&from, &into, Some(&state.indexer))
+        .await
+        .map_err(|e| e.to_string())
+}
@@ -6,21 +6,391 @@ use crate::search;
 use crate::state::AppState;
 use crate::storage;
 use crate::vault;
-use crate::watcher::VaultWatcher;
-use std::path::PathBuf;
+use crate::watcher::{VaultWatcher, WatcherStatus};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tauri::State;
+use tauri::{AppHandle, Emitter, State};
 
-/// 设置 Vault 路径（支持切换）
+/// 系统状态
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemStatus {
+    pub vault_initialized: bool,
+    pub database_ready: bool,
+    pub indexer_ready: bool,
+    pub watcher: WatcherStatus,
+}
+
+/// 文献源中指向不存在卡片的笔记引用
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DanglingNoteRef {
+    pub source_id: String,
+    pub note_id: String,
+}
+
+/// Vault 完整性检查报告
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityReport {
+    /// 数据库中存在但未进入搜索索引的卡片和文献源
+    pub missing_from_index: Vec<String>,
+    /// 索引版本落后于数据库的卡片和文献源
+    pub outdated_in_index: Vec<String>,
+    /// 索引中存在但数据库中已删除的文档
+    pub stale_index_docs: Vec<String>,
+    /// 文献源 note_ids 中指向不存在卡片的引用
+    pub dangling_note_ids: Vec<DanglingNoteRef>,
+    /// 发现的问题数
+    pub issues_found: usize,
+    /// 已修复的问题数
+    pub issues_fixed: usize,
+}
+
+/// 交叉检查数据库、搜索索引和文献源引用，`repair` 为 true 时顺带修复
+async fn check_vault_integrity(state: &AppState, repair: bool) -> Result<IntegrityReport, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    let indexer = state
+        .indexer
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or("Indexer not initialized")?;
+
+    let cards = services.card.get_all().await.map_err(|e| e.to_string())?;
+    let sources = services.source.get_all().await.map_err(|e| e.to_string())?;
+    let indexed = indexer.all_doc_mtimes()?;
+    let card_ids: HashSet<&str> = cards.iter().map(|c| c.id.as_str()).collect();
+    let source_ids: HashSet<&str> = sources.iter().map(|s| s.id.as_str()).collect();
+
+    let mut report = IntegrityReport::default();
+
+    let doc_mtimes = cards
+        .iter()
+        .map(|c| (&c.id, c.modified_at))
+        .chain(sources.iter().map(|s| (&s.id, s.updated_at)));
+    for (id, modified_at) in doc_mtimes {
+        match indexed.get(id) {
+            None => report.missing_from_index.push(id.clone()),
+            Some(mtime) if *mtime < modified_at => report.outdated_in_index.push(id.clone()),
+            _ => {}
+        }
+    }
+
+    // 文献源也在索引中，不算过期文档
+    report.stale_index_docs = indexed
+        .keys()
+        .filter(|id| !card_ids.contains(id.as_str()) && !source_ids.contains(id.as_str()))
+        .cloned()
+        .collect();
+
+    for source in &sources {
+        for note_id in &source.note_ids {
+            if !card_ids.contains(note_id.as_str()) {
+                report.dangling_note_ids.push(DanglingNoteRef {
+                    source_id: source.id.clone(),
+                    note_id: note_id.clone(),
+                });
+            }
+        }
+    }
+
+    report.issues_found = report.missing_from_index.len()
+        + report.outdated_in_index.len()
+        + report.stale_index_docs.len()
+        + report.dangling_note_ids.len();
+
+    if !repair {
+        return Ok(report);
+    }
+
+    // 重新索引缺失/过期的卡片和文献源
+    let to_reindex: HashSet<&str> = report
+        .missing_from_index
+        .iter()
+        .chain(report.outdated_in_index.iter())
+        .map(|s| s.as_str())
+        .collect();
+    for card in cards.iter().filter(|c| to_reindex.contains(c.id.as_str())) {
+        if indexer.index_card(card).is_ok() {
+            report.issues_fixed += 1;
+        }
+    }
+
+    for source in sources.iter().filter(|s| to_reindex.contains(s.id.as_str())) {
+        if indexer.index_source(source).is_ok() {
+            report.issues_fixed += 1;
+        }
+    }
+
+    // 清理已删除卡片的索引文档
+    for id in &report.stale_index_docs {
+        if indexer.delete_doc(id).is_ok() {
+            report.issues_fixed += 1;
+        }
+    }
+
+    // 清理悬空的 note_ids
+    for dangling in &report.dangling_note_ids {
+        if services
+            .source
+            .remove_note(&dangling.source_id, &dangling.note_id)
+            .await
+            .is_ok()
+        {
+            report.issues_fixed += 1;
+        }
+    }
+
+    Ok(report)
+}
+
+/// 检查 Vault 完整性（只读）
+#[tauri::command]
+pub async fn verify_vault_integrity(state: State<'_, AppState>) -> Result<IntegrityReport, String> {
+    check_vault_integrity(&state, false).await
+}
+
+/// 修复 Vault 完整性问题（重建缺失索引、清理过期文档和悬空引用）
+#[tauri::command]
+pub async fn repair_vault(state: State<'_, AppState>) -> Result<IntegrityReport, String> {
+    check_vault_integrity(&state, true).await
+}
+
+/// 是否有 optimize_vault 正在运行
+static OPTIMIZING: AtomicBool = AtomicBool::new(false);
+
+/// 各存储占用的字节数
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageSizes {
+    pub index: u64,
+    pub database: u64,
+    pub crdt: u64,
+}
+
+/// Vault 压缩报告
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OptimizeReport {
+    pub before: StorageSizes,
+    pub after: StorageSizes,
+    /// 重新编码的 CRDT 文档数
+    pub crdt_docs_compacted: usize,
+}
+
+/// 递归统计目录大小，不存在时为 0
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => dir_size(&entry.path()),
+            Ok(meta) => meta.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+fn storage_sizes(vault_path: &Path) -> StorageSizes {
+    let db_path = vault::get_database_path(vault_path);
+    let database = ["", "-wal", "-shm"]
+        .iter()
+        .filter_map(|suffix| {
+            let mut file = db_path.clone().into_os_string();
+            file.push(suffix);
+            std::fs::metadata(file).ok()
+        })
+        .map(|meta| meta.len())
+        .sum();
+    StorageSizes {
+        index: dir_size(&vault_path.join(".zentri/index")),
+        database,
+        crdt: dir_size(&vault_path.join(".zentri/crdt")),
+    }
+}
+
+/// 运行结束（包括出错）时释放 OPTIMIZING 标记
+struct OptimizeGuard;
+
+impl Drop for OptimizeGuard {
+    fn drop(&mut self) {
+        OPTIMIZING.store(false, Ordering::SeqCst);
+    }
+}
+
+/// 压缩 Vault：合并搜索索引段、VACUUM 数据库、合并 CRDT 更新日志，返回前后大小
 #[tauri::command]
-pub async fn set_initial_vault_path(state: State<'_, AppState>, path: String) -> Result<(), String> {
+pub async fn optimize_vault(state: State<'_, AppState>) -> Result<OptimizeReport, String> {
+    if OPTIMIZING
+        .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+        .is_err()
+    {
+        return Err("Vault optimization already in progress".to_string());
+    }
+    let _guard = OptimizeGuard;
+
+    let vault_path = state
+        .vault_path
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or("Vault not initialized")?;
+    let db = state.get_db().ok_or("Vault not initialized")?;
+    let indexer = state.indexer.lock().unwrap().clone();
+    let crdt = state.crdt.lock().unwrap().clone();
+
+    let before = storage_sizes(&vault_path);
+
+    if let Some(indexer) = indexer {
+        tokio::task::spawn_blocking(move || indexer.optimize())
+            .await
+            .map_err(|e| e.to_string())??;
+    }
+    db.optimize().await.map_err(|e| e.to_string())?;
+    let crdt_docs_compacted = match crdt {
+        Some(crdt) => tokio::task::spawn_blocking(move || crdt.compact())
+            .await
+            .map_err(|e| e.to_string())??,
+        None => 0,
+    };
+
+    Ok(OptimizeReport {
+        before,
+        after: storage_sizes(&vault_path),
+        crdt_docs_compacted,
+    })
+}
+
+/// Obsidian 导入报告
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub created: usize,
+    /// 已存在同 ID 卡片而跳过的笔记
+    pub skipped: usize,
+    pub errored: usize,
+    pub attachments_copied: usize,
+    /// 成功导入的笔记路径
+    pub imported: Vec<String>,
+    /// 出错笔记的路径和原因
+    pub errors: Vec<String>,
+    /// 与其他目录下的笔记同名而未导入的笔记路径（卡片 ID 取文件名，无法同时保留）
+    pub duplicates: Vec<String>,
+}
+
+/// 从 Obsidian vault 导入笔记：Markdown 转为卡片（标题、段落、列表），按目录推断卡片类型，复制附件
+///
+/// 卡片 ID 取文件名，因此 `[[笔记名]]` 链接导入后仍然有效；源目录不会被修改。
+/// 重复导入时已导入的附件和卡片都会跳过
+#[tauri::command]
+pub async fn import_obsidian_vault(
+    state: State<'_, AppState>,
+    src_dir: String,
+) -> Result<ImportReport, String> {
+    let src_dir = PathBuf::from(src_dir);
+    if !src_dir.is_dir() {
+        return Err(format!("Not a directory: {}", src_dir.display()));
+    }
+    let vault_path = state
+        .vault_path
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or("Vault not initialized")?;
+    if src_dir.canonicalize().ok() == vault_path.canonicalize().ok() {
+        return Err("Cannot import a vault into itself".to_string());
+    }
+
+    // 扫描、复制附件和解析 Markdown 都是文件 IO
+    let blocking_src = src_dir.clone();
+    let (attachments_copied, notes) = tokio::task::spawn_blocking(move || {
+        let scan = storage::scan_obsidian_vault(&blocking_src);
+        let attachments = storage::copy_obsidian_attachments(&blocking_src, &vault_path, &scan.attachments)?;
+        let notes: Vec<_> = scan
+            .notes
+            .iter()
+            .map(|relative| storage::read_obsidian_note(&blocking_src, relative, &attachments.paths))
+            .collect();
+        Ok::<_, String>((attachments.copied, notes))
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    let mut report = ImportReport {
+        attachments_copied,
+        ..Default::default()
+    };
+    // 卡片 ID -> 首个使用该 ID 的笔记路径
+    let mut seen: HashMap<String, String> = HashMap::new();
+    let mut cards = Vec::new();
+    for note in notes {
+        let card = match note {
+            Ok(card) if card.id.is_empty() || card.id.contains("..") => {
+                report.errored += 1;
+                report.errors.push(format!("{}: invalid note name", card.path.unwrap_or_default()));
+                continue;
+            }
+            Ok(card) => card,
+            Err(e) => {
+                report.errored += 1;
+                report.errors.push(e);
+                continue;
+            }
+        };
+        let path = card.path.clone().unwrap_or_default();
+        if let Some(first) = seen.get(&card.id) {
+            report.duplicates.push(format!("{} (same name as {})", path, first));
+            continue;
+        }
+        seen.insert(card.id.clone(), path);
+        cards.push(card);
+    }
+
+    let paths: Vec<String> = cards.iter().map(|c| c.path.clone().unwrap_or_default()).collect();
+    let notify = |change| state.notify_card_change(change);
+    let results = services
+        .card
+        .import_cards(cards, Some(&state.indexer), Some(&notify))
+        .await
+        .map_err(|e| e.to_string())?;
+    for (path, result) in paths.into_iter().zip(results) {
+        match result {
+            Ok(true) => {
+                report.created += 1;
+                report.imported.push(path);
+            }
+            Ok(false) => report.skipped += 1,
+            Err(e) => {
+                report.errored += 1;
+                report.errors.push(format!("{}: {}", path, e));
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// 设置 Vault 路径（支持切换），`force` 为 true 时强制接管其他实例持有的锁
+#[tauri::command]
+pub async fn set_initial_vault_path(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    force: Option<bool>,
+) -> Result<(), String> {
     let path = PathBuf::from(&path);
     if !path.exists() {
         std::fs::create_dir_all(&path).map_err(|e| e.to_string())?;
     }
 
     // 尝试获取 vault 锁
-    let _lock = vault::VaultLock::try_lock(&path)
+    let _lock = vault::VaultLock::try_lock(&path, force.unwrap_or(false))
         .map_err(|e| format!("Failed to lock vault: {}", e))?;
 
     // 初始化新的 vault 目录结构
@@ -54,18 +424,18 @@ pub async fn set_initial_vault_path(state: State<'_, AppState>, path: String) ->
     let index_path = path.join(".zentri/index");
     std::fs::create_dir_all(&index_path).map_err(|e| e.to_string())?;
 
-    let indexer = search::Indexer::new(&index_path).map_err(|e| e.to_string())?;
-
-    // 初始化文件监听器
-    let watcher = VaultWatcher::new(&path).ok();
-    if watcher.is_none() {
-        eprintln!("Warning: Failed to initialize file watcher");
-    }
+    let indexer = search::Indexer::new(&index_path)
+        .map_err(|e| e.to_string())?
+        .with_field_boosts(search::FieldBoosts::from_vault(&path));
 
     // 更新状态
     *state.vault_path.lock().unwrap() = Some(path.clone());
     *state.indexer.lock().unwrap() = Some(indexer);
-    *state.watcher.lock().unwrap() = watcher;
+
+    // 初始化文件监听器（失败不阻止打开 vault，但要通知前端）
+    if let Some(error) = state.set_watcher(VaultWatcher::new(&path)) {
+        let _ = app.emit("watcher-error", serde_json::json!({ "error": error }));
+    }
     *state.db.lock().unwrap() = Some(new_db_arc.clone());
     
     // 重新初始化服务层（使用新的数据库和 vault_path）
@@ -96,6 +466,22 @@ pub async fn set_initial_vault_path(state: State<'_, AppState>, path: String) ->
     Ok(())
 }
 
+/// 在空目录中初始化新 vault，然后打开它（获取锁、打开数据库和索引）
+#[tauri::command]
+pub async fn initialize_vault(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<(), String> {
+    let vault_path = PathBuf::from(&path);
+    if !vault_path.exists() {
+        std::fs::create_dir_all(&vault_path).map_err(|e| e.to_string())?;
+    }
+    vault::initialize_vault(&vault_path)?;
+
+    set_initial_vault_path(app, state, path, None).await
+}
+
 /// 获取 Vault 路径
 #[tauri::command]
 pub fn get_vault_path(state: State<AppState>) -> Option<String> {
@@ -106,3 +492,14 @@ pub fn get_vault_path(state: State<AppState>) -> Option<String> {
         .as_ref()
         .map(|p| p.to_string_lossy().to_string())
 }
+
+/// 获取系统状态（包括文件监听是否正常工作）
+#[tauri::command]
+pub fn get_system_status(state: State<AppState>) -> SystemStatus {
+    SystemStatus {
+        vault_initialized: state.is_vault_initialized(),
+        database_ready: state.get_db().is_some(),
+        indexer_ready: state.indexer.lock().unwrap().is_some(),
+        watcher: state.watcher_status.lock().unwrap().clone(),
+    }
+}
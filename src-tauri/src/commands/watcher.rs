@@ -1,114 +1,122 @@
 //! File Watcher 相关命令
 
 use crate::state::AppState;
-use crate::watcher;
-use tauri::State;
+use crate::watcher::{self, VaultWatcher};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// 文件变更事件，载荷为 `FileChangeInfo`
+pub const VAULT_FILE_CHANGED_EVENT: &str = "vault-file-changed";
+
+/// 后台任务检查文件变化的间隔
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
 
 /// 文件变更信息
-#[derive(serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct FileChangeInfo {
     pub changed_ids: Vec<String>,
     pub removed_ids: Vec<String>,
+    /// 本轮重新索引的文档数
+    pub reindexed: usize,
 }
 
 /// 轮询文件变化并更新索引
+///
+/// 已弃用：启动时打开了 vault 的情况下，后台任务会推送 `vault-file-changed` 事件，
+/// 前端无需再定时调用。保留此命令用于兼容
 #[tauri::command]
-pub async fn poll_file_changes(state: State<'_, AppState>) -> Result<FileChangeInfo, String> {
-    let mut changed_ids = Vec::new();
-    let mut removed_ids = Vec::new();
-    
+pub async fn poll_file_changes(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<FileChangeInfo, String> {
+    process_file_changes(&app, &state).await
+}
+
+/// 后台持续取出文件变化、更新索引，有变化时发出 `vault-file-changed` 事件
+pub fn spawn_file_change_task(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(WATCH_INTERVAL).await;
+            let state = app.state::<AppState>();
+            match process_file_changes(&app, &state).await {
+                Ok(info) if !info.changed_ids.is_empty() || !info.removed_ids.is_empty() => {
+                    let _ = app.emit(VAULT_FILE_CHANGED_EVENT, info);
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("Failed to process file changes: {}", e),
+            }
+        }
+    });
+}
+
+/// 取出监听器积累的文件变化并更新索引
+///
+/// 同一轮的变化合并后一次写入索引（如 git pull 带来的大量变更）
+async fn process_file_changes(app: &AppHandle, state: &AppState) -> Result<FileChangeInfo, String> {
     // 获取文件变化（在锁外）
-    let changes = {
+    let (changes, errors) = {
         let watcher_guard = state.watcher.lock().unwrap();
         if let Some(watcher) = watcher_guard.as_ref() {
             watcher.poll_changes()
         } else {
-            return Ok(FileChangeInfo { changed_ids, removed_ids });
+            return Ok(FileChangeInfo {
+                changed_ids: vec![],
+                removed_ids: vec![],
+                reindexed: 0,
+            });
         }
     };
-    
-    for change in changes {
-        match change {
-            watcher::FileChange::Modified(path) => {
-                // 从路径提取 ID
-                if let Some(id) = path.file_stem().and_then(|s| s.to_str()) {
-                    // 在 await 之前释放所有锁
-                    let services = match state.get_services() {
-                        Some(s) => s,
-                        None => continue, // 如果 vault 未初始化，跳过
-                    };
-                    if let Ok(Some(card)) = services.card.get_by_id(id).await {
-                        let path_str = card.path.as_ref().map(|p| p.as_str()).unwrap_or("");
-                        // 重新获取 indexer 锁
-                        {
-                            let indexer_guard = state.indexer.lock().unwrap();
-                            if let Some(idx) = indexer_guard.as_ref() {
-                                idx.index_doc_with_type(
-                                    &card.id,
-                                    &card.title,
-                                    &card.plain_text,
-                                    &card.tags,
-                                    path_str,
-                                    card.modified_at,
-                                    Some(card.card_type.as_str()),
-                                ).ok();
-                            }
-                        }
-                        changed_ids.push(card.id);
-                    }
-                }
-            }
-            watcher::FileChange::Removed(path) => {
-                if let Some(id) = path.file_stem().and_then(|s| s.to_str()) {
-                    {
-                        let indexer_guard = state.indexer.lock().unwrap();
-                        if let Some(idx) = indexer_guard.as_ref() {
-                            idx.delete_doc(id).ok();
-                        }
-                    }
-                    removed_ids.push(id.to_string());
-                }
-            }
-            watcher::FileChange::Renamed(old_path, new_path) => {
-                // 删除旧的
-                if let Some(old_id) = old_path.file_stem().and_then(|s| s.to_str()) {
-                    {
-                        let indexer_guard = state.indexer.lock().unwrap();
-                        if let Some(idx) = indexer_guard.as_ref() {
-                            idx.delete_doc(old_id).ok();
-                        }
-                    }
-                    removed_ids.push(old_id.to_string());
-                }
-                
-                // 添加新的
-                if let Some(new_id) = new_path.file_stem().and_then(|s| s.to_str()) {
-                    let services = match state.get_services() {
-                        Some(s) => s,
-                        None => continue, // 如果 vault 未初始化，跳过
-                    };
-                    if let Ok(Some(card)) = services.card.get_by_id(new_id).await {
-                        let path_str = card.path.as_ref().map(|p| p.as_str()).unwrap_or("");
-                        {
-                            let indexer_guard = state.indexer.lock().unwrap();
-                            if let Some(idx) = indexer_guard.as_ref() {
-                                idx.index_doc_with_type(
-                                    &card.id,
-                                    &card.title,
-                                    &card.plain_text,
-                                    &card.tags,
-                                    path_str,
-                                    card.modified_at,
-                                    Some(card.card_type.as_str()),
-                                ).ok();
-                            }
-                        }
-                        changed_ids.push(card.id);
-                    }
-                }
+
+    // 监听错误记录到状态并通知前端
+    if let Some(error) = errors.last() {
+        state.watcher_status.lock().unwrap().last_error = Some(error.clone());
+        for error in &errors {
+            let _ = app.emit("watcher-error", serde_json::json!({ "error": error }));
+        }
+    }
+
+    let batch = watcher::batch_changes(&changes);
+
+    // 如果 vault 未初始化，只处理删除
+    let mut cards = Vec::new();
+    if let Some(services) = state.get_services() {
+        for id in &batch.upserted {
+            if let Ok(Some(card)) = services.card.get_by_id(id).await {
+                cards.push(card);
             }
         }
     }
-    
-    Ok(FileChangeInfo { changed_ids, removed_ids })
+
+    let reindexed = match state.indexer.lock().unwrap().as_ref() {
+        Some(idx) => idx.index_cards_batch(&cards, &batch.removed)?,
+        None => 0,
+    };
+
+    Ok(FileChangeInfo {
+        changed_ids: cards.into_iter().map(|c| c.id).collect(),
+        removed_ids: batch.removed,
+        reindexed,
+    })
+}
+
+/// 重启文件监听器
+#[tauri::command]
+pub fn restart_watcher(app: AppHandle, state: State<AppState>) -> Result<(), String> {
+    let vault_path = state
+        .vault_path
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or("Vault not initialized")?;
+
+    // 先释放旧的监听器，避免重复占用系统句柄
+    *state.watcher.lock().unwrap() = None;
+
+    match state.set_watcher(VaultWatcher::new(&vault_path)) {
+        Some(error) => {
+            let _ = app.emit("watcher-error", serde_json::json!({ "error": error }));
+            Err(error)
+        }
+        None => Ok(()),
+    }
 }
@@ -1,36 +1,238 @@
 //! 网页阅读器相关命令
 
+use crate::models::{CreateSourceRequest, Source, SourceMetadata, SourceType, UpdateSourceRequest};
 use crate::state::AppState;
-use crate::web_reader::{FetchResult, WebSnapshot, WebpageMetadata};
+use crate::web_reader::{self, FetchResult, FetchedDocument, SimplifyLevel, WebSnapshot, WebpageMetadata};
+use serde::Serialize;
 use tauri::State;
+use uuid::Uuid;
 
-/// 抓取并清洗网页（完整内容）
+/// 链接抓取结果：网页交由前端创建文献源后保存快照，PDF 已直接保存为论文
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum CaptureResult {
+    Html {
+        #[serde(rename = "fetchResult")]
+        fetch_result: FetchResult,
+    },
+    Pdf {
+        source: Source,
+        snapshot: WebSnapshot,
+    },
+}
+
+/// 抓取并清洗网页（完整内容），simplify 指定时移除分享栏、表单等杂项
+#[tauri::command]
+pub async fn fetch_webpage(
+    state: State<'_, AppState>,
+    url: String,
+    simplify: Option<SimplifyLevel>,
+) -> Result<FetchResult, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services.web_reader.fetch_webpage(&url, simplify).await
+}
+
+/// 抓取链接：网页返回清洗结果；PDF 下载到 sources/pdf 并创建论文类型的文献源
+#[tauri::command]
+pub async fn capture_url(state: State<'_, AppState>, url: String) -> Result<CaptureResult, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    let vault_path = state
+        .vault_path
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or("Vault not initialized")?;
+
+    let document = services.web_reader.fetch_document(&url).await?;
+
+    let (bytes, fetch_result) = match document {
+        FetchedDocument::Html(fetch_result) => return Ok(CaptureResult::Html { fetch_result }),
+        FetchedDocument::Pdf { bytes, fetch_result } => (bytes, fetch_result),
+    };
+
+    // 同一 PDF 已保存过时直接返回已有文献源，不再重复下载和创建
+    if let Some(snapshot) = services.web_reader.find_snapshot_by_url(&url).await? {
+        if let Some(source) = services
+            .source
+            .get_by_id(&snapshot.source_id)
+            .await
+            .map_err(|e| e.to_string())?
+        {
+            return Ok(CaptureResult::Pdf { source, snapshot });
+        }
+    }
+
+    let pdf_dir = vault_path.join("sources").join("pdf");
+    std::fs::create_dir_all(&pdf_dir)
+        .map_err(|e| format!("Failed to create pdf directory: {}", e))?;
+    let relative_path = format!("sources/pdf/{}.pdf", Uuid::new_v4());
+    std::fs::write(vault_path.join(&relative_path), &bytes)
+        .map_err(|e| format!("Failed to save PDF: {}", e))?;
+
+    let source = services
+        .source
+        .create(
+            CreateSourceRequest {
+                source_type: SourceType::Paper,
+                title: fetch_result.title.clone(),
+                author: None,
+                url: Some(relative_path),
+                cover: None,
+                description: fetch_result.excerpt.clone(),
+                tags: vec![],
+                source_origin: Some("pdf".to_string()),
+            },
+            Some(&state.indexer),
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let update = UpdateSourceRequest {
+        title: None,
+        author: None,
+        url: None,
+        cover: None,
+        description: None,
+        tags: None,
+        progress: None,
+        last_read_at: None,
+        metadata: Some(SourceMetadata {
+            content_format: Some("pdf".to_string()),
+            ..Default::default()
+        }),
+    };
+    let source = services
+        .source
+        .update(&source.id, update, Some(&state.indexer))
+        .await
+        .map_err(|e| e.to_string())?
+        .unwrap_or(source);
+
+    // 提取的文本存为快照，保留原始链接并供搜索使用
+    let snapshot = services
+        .web_reader
+        .save_snapshot(&source.id, &url, fetch_result)
+        .await?;
+
+    Ok(CaptureResult::Pdf { source, snapshot })
+}
+
+/// 按网页地址查找已保存的快照，用于在创建文献源前检查重复
 #[tauri::command]
-pub fn fetch_webpage(state: State<AppState>, url: String) -> Result<FetchResult, String> {
+pub async fn find_web_snapshot_by_url(
+    state: State<'_, AppState>,
+    url: String,
+) -> Result<Option<WebSnapshot>, String> {
     let services = state.get_services().ok_or("Vault not initialized")?;
-    services.web_reader.fetch_webpage(&url)
+    services.web_reader.find_snapshot_by_url(&url).await
 }
 
 /// 快速获取网页元数据（用于表单自动填充）
 #[tauri::command]
-pub fn fetch_webpage_metadata(state: State<AppState>, url: String) -> Result<WebpageMetadata, String> {
+pub async fn fetch_webpage_metadata(
+    state: State<'_, AppState>,
+    url: String,
+) -> Result<WebpageMetadata, String> {
     let services = state.get_services().ok_or("Vault not initialized")?;
-    services.web_reader.fetch_metadata(&url)
+    services.web_reader.fetch_metadata(&url).await
 }
 
-/// 保存网页快照
+/// 保存网页快照，`download_images` 为 true 时把图片下载到本地供离线阅读
+///
+/// 同一网页（忽略跟踪参数、片段和末尾斜杠）已保存在其他文献源时直接返回已有快照，
+/// 调用方可按返回的 sourceId 打开已有文献源，并删除为本次保存新建、尚无快照的文献源；
+/// `force_new` 为 true 时仍然保存新快照
 #[tauri::command]
 pub async fn save_web_snapshot(
     state: State<'_, AppState>,
     source_id: String,
     url: String,
     fetch_result: FetchResult,
+    download_images: Option<bool>,
+    force_new: Option<bool>,
 ) -> Result<WebSnapshot, String> {
     let services = state.get_services().ok_or("Vault not initialized")?;
-    services
+    if !force_new.unwrap_or(false) {
+        if let Some(existing) = services.web_reader.find_snapshot_by_url(&url).await? {
+            if existing.source_id != source_id {
+                if services.web_reader.get_snapshot(&source_id).await?.is_none() {
+                    services
+                        .source
+                        .hard_delete(&source_id, Some(&state.indexer))
+                        .await
+                        .map_err(|e| e.to_string())?;
+                }
+                return Ok(existing);
+            }
+        }
+    }
+    let language = fetch_result.language.clone();
+    let mut snapshot = services
         .web_reader
         .save_snapshot(&source_id, &url, fetch_result)
-        .await
+        .await?;
+
+    // 图片下载到 attachments/web/<快照 ID>/，失败时保留远程图片地址
+    if download_images.unwrap_or(false) {
+        let vault_path = state
+            .vault_path
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or("Vault not initialized")?;
+        let dir = vault_path.join("attachments").join("web").join(&snapshot.id);
+        let link_prefix = super::assets::vault_relative_path(&vault_path, &dir)?;
+        match web_reader::localize_images(&snapshot.content, &url, &dir, &link_prefix).await {
+            Ok(content) if content != snapshot.content => {
+                snapshot = services.web_reader.update_snapshot_content(snapshot, content).await?;
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("Failed to download images for snapshot {}: {}", snapshot.id, e),
+        }
+    }
+
+    // 记录网页的语言、内容格式和阅读时间，供文献库筛选和展示
+    let metadata = SourceMetadata {
+        language,
+        content_format: Some("html".to_string()),
+        word_count: Some(snapshot.word_count),
+        reading_minutes: Some(snapshot.reading_minutes),
+        ..Default::default()
+    };
+    let update = UpdateSourceRequest {
+        title: None,
+        author: None,
+        url: None,
+        cover: None,
+        description: None,
+        tags: None,
+        progress: None,
+        last_read_at: None,
+        metadata: Some(metadata),
+    };
+    if let Err(e) = services.source.update(&source_id, update, Some(&state.indexer)).await {
+        eprintln!("Failed to update source metadata for {}: {}", source_id, e);
+    }
+    // 文献源由前端先以手动添加的方式创建，有了快照后记为网页
+    if let Err(e) = services.source.set_origin(&source_id, "web").await {
+        eprintln!("Failed to update source origin for {}: {}", source_id, e);
+    }
+
+    Ok(snapshot)
+}
+
+/// 获取估算阅读时间使用的阅读速度（词/分钟）
+#[tauri::command]
+pub async fn get_reading_speed(state: State<'_, AppState>) -> Result<usize, String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services.web_reader.reading_speed().await
+}
+
+/// 设置阅读速度（词/分钟），之后保存的网页按新速度估算阅读时间
+#[tauri::command]
+pub async fn set_reading_speed(state: State<'_, AppState>, words_per_minute: usize) -> Result<(), String> {
+    let services = state.get_services().ok_or("Vault not initialized")?;
+    services.web_reader.set_reading_speed(words_per_minute).await
 }
 
 /// 获取网页快照
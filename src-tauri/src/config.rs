@@ -35,6 +35,40 @@ pub struct AppSettings {
     /// 自动保存间隔（毫秒）
     #[serde(default = "default_auto_save_interval")]
     pub auto_save_interval: u64,
+    /// 保存的搜索（智能文件夹）
+    #[serde(default)]
+    pub saved_searches: Vec<SavedSearch>,
+}
+
+/// 保存的搜索条件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedSearch {
+    pub name: String,
+    /// 查询语句，为空时只按过滤条件匹配
+    #[serde(default)]
+    pub query: String,
+    #[serde(default)]
+    pub card_type: Option<String>,
+    #[serde(default)]
+    pub tags_include: Vec<String>,
+    #[serde(default)]
+    pub tags_exclude: Vec<String>,
+    #[serde(default)]
+    pub sort: SavedSearchSort,
+}
+
+/// 保存搜索的排序方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SavedSearchSort {
+    /// 按相关度
+    #[default]
+    Relevance,
+    /// 按修改时间（新的在前）
+    Modified,
+    /// 按标题
+    Title,
 }
 
 fn default_card_type() -> String {
@@ -122,5 +156,62 @@ impl ConfigManager {
         self.save(&config)?;
         Ok(())
     }
+
+    /// 获取保存的搜索
+    pub fn saved_searches(&self) -> Result<Vec<SavedSearch>, ConfigError> {
+        Ok(self.load()?.settings.saved_searches)
+    }
+
+    /// 保存搜索，同名时覆盖
+    pub fn upsert_saved_search(&self, search: SavedSearch) -> Result<(), ConfigError> {
+        self.update_settings(|settings| {
+            match settings.saved_searches.iter_mut().find(|s| s.name == search.name) {
+                Some(existing) => *existing = search,
+                None => settings.saved_searches.push(search),
+            }
+        })
+    }
+
+    /// 删除保存的搜索，返回是否存在
+    pub fn remove_saved_search(&self, name: &str) -> Result<bool, ConfigError> {
+        let mut removed = false;
+        self.update_settings(|settings| {
+            let before = settings.saved_searches.len();
+            settings.saved_searches.retain(|s| s.name != name);
+            removed = settings.saved_searches.len() != before;
+        })?;
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_saved_search_upsert_and_remove() {
+        let dir = tempdir().unwrap();
+        let manager = ConfigManager::new(dir.path());
+        let search = SavedSearch {
+            name: "physics".to_string(),
+            query: String::new(),
+            card_type: Some("permanent".to_string()),
+            tags_include: vec!["physics".to_string()],
+            tags_exclude: vec![],
+            sort: SavedSearchSort::Modified,
+        };
+
+        manager.upsert_saved_search(search.clone()).unwrap();
+        manager
+            .upsert_saved_search(SavedSearch { query: "energy".to_string(), ..search })
+            .unwrap();
+        let saved = manager.saved_searches().unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].query, "energy");
+
+        assert!(manager.remove_saved_search("physics").unwrap());
+        assert!(!manager.remove_saved_search("physics").unwrap());
+    }
 }
 
@@ -6,15 +6,57 @@
 //! - 增量更新同步
 //! - 历史快照与回滚
 //! - 多窗口/多端协作
+//! - 预写日志: 更新先追加到 pending 日志再写入内存，崩溃后启动时重放
+//! - 增量保存: 日志即持久化，超过阈值才合并进完整状态文件，避免每次保存都重写整个文档
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fs;
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 use yrs::updates::decoder::Decode;
 use yrs::updates::encoder::Encode;
-use yrs::{Doc, GetString, ReadTxn, StateVector, Text, Transact, Update};
+use serde_json::{json, Map, Value as JsonValue};
+use yrs::types::text::{Diff, YChange};
+use yrs::types::Attrs;
+use yrs::{
+    Any, Doc, GetString, ReadTxn, StateVector, Text, Transact, TransactionMut, Update,
+    Xml, XmlElementPrelim, XmlElementRef, XmlFragment, XmlNode, XmlTextPrelim, XmlTextRef,
+};
+
+/// pending 日志写入失败时错误信息的前缀，供调用方区分持久化失败与解码失败
+pub const PENDING_LOG_ERROR: &str = "Failed to write pending log";
+
+/// 富文本内容所在的 XmlFragment 名称，与前端 y-prosemirror 约定一致
+pub const PROSEMIRROR_FRAGMENT: &str = "prosemirror";
+
+/// 默认撤销合并窗口：间隔小于该值的连续编辑合并为一步撤销
+pub const DEFAULT_UNDO_CAPTURE_TIMEOUT_MS: u64 = 500;
+
+/// 每个文档最多保留的撤销步数
+pub const MAX_UNDO_STEPS: usize = 100;
+
+/// pending 日志超过该字节数时合并进 .yrs 状态文件
+pub const DEFAULT_LOG_COMPACT_THRESHOLD: u64 = 256 * 1024;
+
+/// 默认自动快照间隔（分钟）
+pub const DEFAULT_AUTOSNAPSHOT_MINUTES: u64 = 10;
+
+/// vault 配置中保存自动快照间隔的设置项
+pub const AUTOSNAPSHOT_SETTING: &str = "crdtAutosnapshotMinutes";
+
+/// 自动快照的描述
+pub const AUTOSNAPSHOT_DESCRIPTION: &str = "Auto-save";
+
+/// 一步撤销记录的内容快照（纯文本和富文本片段）
+#[derive(Clone, Debug, PartialEq)]
+struct UndoStep {
+    text: String,
+    document: JsonValue,
+}
 
 /// CRDT 文档状态
 #[derive(Clone)]
@@ -26,16 +68,34 @@ pub struct CrdtDocument {
     pub id: String,
     /// 是否有未保存的更改
     pub dirty: bool,
+    /// 自上次快照以来是否有修改，自动快照据此跳过未变化的文档
+    pub changed_since_snapshot: bool,
+    /// 自上次快照以来贡献过更新的来源
+    pub origins: BTreeSet<String>,
+    /// 撤销栈：每组编辑开始前的内容快照，只记录加载之后的编辑。
+    /// 存快照而不是 yrs 的 UndoManager，后者不是 Send，会让 AppState 无法跨线程共享
+    undo_stack: Vec<UndoStep>,
+    /// 重做栈
+    redo_stack: Vec<UndoStep>,
+    /// 最近一次编辑的时间，窗口内的后续编辑并入同一步撤销
+    last_edit_at: Option<Instant>,
+    /// 撤销合并窗口（毫秒）
+    undo_capture_timeout_ms: u64,
 }
 
 impl CrdtDocument {
     /// 创建新文档
     pub fn new(id: &str) -> Self {
-        let doc = Doc::new();
         Self {
-            doc,
+            doc: Doc::new(),
             id: id.to_string(),
             dirty: false,
+            changed_since_snapshot: false,
+            origins: BTreeSet::new(),
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            last_edit_at: None,
+            undo_capture_timeout_ms: DEFAULT_UNDO_CAPTURE_TIMEOUT_MS,
         }
     }
 
@@ -51,9 +111,67 @@ impl CrdtDocument {
             doc,
             id: id.to_string(),
             dirty: false,
+            changed_since_snapshot: false,
+            origins: BTreeSet::new(),
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            last_edit_at: None,
+            undo_capture_timeout_ms: DEFAULT_UNDO_CAPTURE_TIMEOUT_MS,
         })
     }
 
+    /// 使用指定的撤销合并窗口（毫秒），已有的撤销历史会被丢弃
+    pub fn with_undo_capture_timeout(mut self, capture_timeout_ms: u64) -> Self {
+        self.undo_capture_timeout_ms = capture_timeout_ms;
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.last_edit_at = None;
+        self
+    }
+
+    /// 结束当前撤销分组，之后的编辑即使仍在合并窗口内也单独成为一步
+    pub fn stop_capturing(&mut self) {
+        self.last_edit_at = None;
+    }
+
+    fn undo_step(&self) -> UndoStep {
+        UndoStep {
+            text: self.get_text(),
+            document: self.get_tiptap_json(),
+        }
+    }
+
+    /// 把内容恢复为快照，只重写有差异的部分
+    fn restore_step(&mut self, step: &UndoStep) -> Result<(), String> {
+        if self.get_text() != step.text {
+            self.set_text(&step.text);
+        }
+        if self.get_tiptap_json() != step.document {
+            self.set_tiptap_json(&step.document)?;
+        }
+        Ok(())
+    }
+
+    /// 在一组编辑开始时记录编辑前的内容，窗口内的连续编辑共用这一步
+    fn capture_undo_step(&mut self) {
+        let now = Instant::now();
+        let window = Duration::from_millis(self.undo_capture_timeout_ms);
+        let grouped = self
+            .last_edit_at
+            .is_some_and(|last| now.duration_since(last) < window);
+        if !grouped {
+            let step = self.undo_step();
+            if self.undo_stack.last() != Some(&step) {
+                self.undo_stack.push(step);
+                if self.undo_stack.len() > MAX_UNDO_STEPS {
+                    self.undo_stack.remove(0);
+                }
+            }
+        }
+        self.redo_stack.clear();
+        self.last_edit_at = Some(now);
+    }
+
     /// 导出完整状态
     pub fn encode_state(&self) -> Vec<u8> {
         let txn = self.doc.transact();
@@ -73,6 +191,23 @@ impl CrdtDocument {
             Update::decode_v1(update).map_err(|e| format!("Decode update error: {:?}", e))?;
         txn.apply_update(update);
         self.dirty = true;
+        self.changed_since_snapshot = true;
+        Ok(())
+    }
+
+    /// 应用增量更新，并以 origin 标记事务来源
+    pub fn apply_update_with_origin(&mut self, update: &[u8], origin: &str) -> Result<(), String> {
+        // 各窗口的编辑都属于本机用户，纳入撤销范围
+        self.capture_undo_step();
+        {
+            let mut txn = self.doc.transact_mut_with(origin);
+            let update =
+                Update::decode_v1(update).map_err(|e| format!("Decode update error: {:?}", e))?;
+            txn.apply_update(update);
+        }
+        self.origins.insert(origin.to_string());
+        self.dirty = true;
+        self.changed_since_snapshot = true;
         Ok(())
     }
 
@@ -104,9 +239,308 @@ impl CrdtDocument {
         }
         text.insert(&mut txn, 0, content);
         self.dirty = true;
+        self.changed_since_snapshot = true;
+    }
+
+    /// 撤销最近一组编辑，没有可撤销的内容时返回 false
+    pub fn undo(&mut self) -> Result<bool, String> {
+        let current = self.undo_step();
+        while let Some(step) = self.undo_stack.pop() {
+            if step == current {
+                continue;
+            }
+            self.restore_step(&step)?;
+            self.redo_stack.push(current);
+            self.last_edit_at = None;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// 重做最近一次撤销，没有可重做的内容时返回 false
+    pub fn redo(&mut self) -> Result<bool, String> {
+        let current = self.undo_step();
+        while let Some(step) = self.redo_stack.pop() {
+            if step == current {
+                continue;
+            }
+            self.restore_step(&step)?;
+            self.undo_stack.push(current);
+            self.last_edit_at = None;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// 用于比较版本的纯文本：富文本片段非空时每个文本块一行，否则取 "content" 文本
+    pub fn plain_content(&self) -> String {
+        let document = self.get_tiptap_json();
+        let mut lines = Vec::new();
+        if let Some(JsonValue::Array(nodes)) = document.get("content") {
+            for node in nodes {
+                collect_block_lines(node, &mut lines);
+            }
+        }
+        if lines.is_empty() {
+            return self.get_text();
+        }
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+
+    /// 把 "prosemirror" 片段导出为 TipTap JSON（`{"type":"doc","content":[...]}`）
+    #[allow(dead_code)]
+    pub fn get_tiptap_json(&self) -> JsonValue {
+        let fragment = self.doc.get_or_insert_xml_fragment(PROSEMIRROR_FRAGMENT);
+        let txn = self.doc.transact();
+        json!({ "type": "doc", "content": xml_children_to_json(&fragment, &txn) })
+    }
+
+    /// 用 TipTap JSON 整体替换 "prosemirror" 片段。
+    /// 节点映射为 XmlElement（标签为节点类型，attrs 为属性），相邻文本节点合并为一个
+    /// XmlText，marks 作为文本格式属性保存，与 y-prosemirror 的结构相同
+    #[allow(dead_code)]
+    pub fn set_tiptap_json(&mut self, document: &JsonValue) -> Result<(), String> {
+        let nodes = match document.get("content") {
+            Some(JsonValue::Array(nodes)) => nodes.as_slice(),
+            Some(_) => return Err("TipTap document content must be an array".to_string()),
+            None => &[],
+        };
+        let fragment = self.doc.get_or_insert_xml_fragment(PROSEMIRROR_FRAGMENT);
+        let mut txn = self.doc.transact_mut();
+        let len = fragment.len(&txn);
+        if len > 0 {
+            fragment.remove_range(&mut txn, 0, len);
+        }
+        json_to_xml_children(&fragment, &mut txn, nodes)?;
+        self.dirty = true;
+        self.changed_since_snapshot = true;
+        Ok(())
+    }
+}
+
+/// 含文本的节点（段落、标题等）输出为一行，容器节点（列表、引用）继续向下展开
+fn collect_block_lines(node: &JsonValue, lines: &mut Vec<String>) {
+    let Some(JsonValue::Array(children)) = node.get("content") else {
+        return;
+    };
+    let is_textblock = children.iter().any(|c| c.get("type").and_then(JsonValue::as_str) == Some("text"));
+    if is_textblock {
+        lines.push(
+            children
+                .iter()
+                .filter_map(|c| c.get("text").and_then(JsonValue::as_str))
+                .collect(),
+        );
+    } else {
+        for child in children {
+            collect_block_lines(child, lines);
+        }
     }
 }
 
+/// 写入子节点，相邻的文本节点写进同一个 XmlText
+fn json_to_xml_children<F: XmlFragment>(
+    parent: &F,
+    txn: &mut TransactionMut,
+    nodes: &[JsonValue],
+) -> Result<(), String> {
+    let mut current_text: Option<XmlTextRef> = None;
+    for node in nodes {
+        let node_type = node
+            .get("type")
+            .and_then(JsonValue::as_str)
+            .ok_or("TipTap node is missing its type")?;
+
+        if node_type == "text" {
+            let chunk = node.get("text").and_then(JsonValue::as_str).unwrap_or_default();
+            let text = current_text.get_or_insert_with(|| parent.push_back(txn, XmlTextPrelim::new("")));
+            let index = text.len(txn);
+            text.insert_with_attributes(txn, index, chunk, marks_to_attrs(node.get("marks")));
+            continue;
+        }
+
+        current_text = None;
+        let element = parent.push_back(txn, XmlElementPrelim::empty(node_type));
+        if let Some(JsonValue::Object(attrs)) = node.get("attrs") {
+            for (key, value) in attrs {
+                element.insert_attribute(txn, key.as_str(), encode_attribute(value));
+            }
+        }
+        if let Some(JsonValue::Array(children)) = node.get("content") {
+            json_to_xml_children(&element, txn, children)?;
+        }
+    }
+    Ok(())
+}
+
+fn xml_children_to_json<F: XmlFragment, T: ReadTxn>(parent: &F, txn: &T) -> Vec<JsonValue> {
+    let mut nodes = Vec::new();
+    for index in 0..parent.len(txn) {
+        match parent.get(txn, index) {
+            Some(XmlNode::Element(element)) => nodes.push(xml_element_to_json(&element, txn)),
+            Some(XmlNode::Text(text)) => nodes.extend(xml_text_to_json(&text, txn)),
+            _ => {}
+        }
+    }
+    nodes
+}
+
+fn xml_element_to_json<T: ReadTxn>(element: &XmlElementRef, txn: &T) -> JsonValue {
+    let mut node = Map::new();
+    node.insert("type".to_string(), JsonValue::String(element.tag().to_string()));
+
+    let attrs: Map<String, JsonValue> = element
+        .attributes(txn)
+        .map(|(key, value)| (key.to_string(), decode_attribute(&value)))
+        .collect();
+    if !attrs.is_empty() {
+        node.insert("attrs".to_string(), JsonValue::Object(attrs));
+    }
+
+    let content = xml_children_to_json(element, txn);
+    if !content.is_empty() {
+        node.insert("content".to_string(), JsonValue::Array(content));
+    }
+    JsonValue::Object(node)
+}
+
+/// 按格式分段导出文本，每段成为一个带 marks 的 text 节点
+fn xml_text_to_json<T: ReadTxn>(text: &XmlTextRef, txn: &T) -> Vec<JsonValue> {
+    let chunks: Vec<Diff<YChange>> = text.diff(txn, YChange::identity);
+    chunks
+        .into_iter()
+        .filter_map(|chunk| {
+            let yrs::types::Value::Any(Any::String(content)) = chunk.insert else {
+                return None;
+            };
+            let mut node = json!({ "type": "text", "text": content.to_string() });
+            let mut marks: Vec<(String, JsonValue)> = chunk
+                .attributes
+                .iter()
+                .flat_map(|attrs| attrs.iter())
+                .map(|(name, value)| (name.to_string(), any_to_json(value)))
+                .collect();
+            if !marks.is_empty() {
+                marks.sort_by(|a, b| a.0.cmp(&b.0));
+                node["marks"] = marks
+                    .into_iter()
+                    .map(|(name, attrs)| match attrs {
+                        JsonValue::Object(map) if !map.is_empty() => json!({ "type": name, "attrs": map }),
+                        _ => json!({ "type": name }),
+                    })
+                    .collect();
+            }
+            Some(node)
+        })
+        .collect()
+}
+
+/// marks 转为文本格式属性：mark 类型为键，mark 的 attrs（没有则为空对象）为值
+fn marks_to_attrs(marks: Option<&JsonValue>) -> Attrs {
+    let mut attrs = Attrs::new();
+    for mark in marks.and_then(JsonValue::as_array).into_iter().flatten() {
+        if let Some(name) = mark.get("type").and_then(JsonValue::as_str) {
+            let value = mark.get("attrs").cloned().unwrap_or_else(|| json!({}));
+            attrs.insert(name.into(), json_to_any(&value));
+        }
+    }
+    attrs
+}
+
+/// XML 属性只能是字符串：字符串原样保存，其余值存为 JSON 文本
+fn encode_attribute(value: &JsonValue) -> String {
+    match value {
+        JsonValue::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn decode_attribute(value: &str) -> JsonValue {
+    match serde_json::from_str::<JsonValue>(value) {
+        Ok(parsed) if !parsed.is_string() => parsed,
+        _ => JsonValue::String(value.to_string()),
+    }
+}
+
+fn json_to_any(value: &JsonValue) -> Any {
+    match value {
+        JsonValue::Null => Any::Null,
+        JsonValue::Bool(b) => Any::Bool(*b),
+        JsonValue::Number(n) => Any::Number(n.as_f64().unwrap_or_default()),
+        JsonValue::String(s) => Any::String(s.as_str().into()),
+        JsonValue::Array(items) => Any::Array(items.iter().map(json_to_any).collect::<Vec<_>>().into()),
+        JsonValue::Object(map) => Any::Map(Arc::new(
+            map.iter().map(|(k, v)| (k.clone(), json_to_any(v))).collect(),
+        )),
+    }
+}
+
+fn any_to_json(value: &Any) -> JsonValue {
+    match value {
+        Any::Null | Any::Undefined => JsonValue::Null,
+        Any::Bool(b) => JsonValue::Bool(*b),
+        // 整数值还原为 JSON 整数，避免 level: 2 变成 2.0
+        Any::Number(n) if n.fract() == 0.0 && n.abs() < i64::MAX as f64 => json!(*n as i64),
+        Any::Number(n) => json!(n),
+        Any::BigInt(n) => json!(n),
+        Any::String(s) => JsonValue::String(s.to_string()),
+        Any::Buffer(bytes) => json!(bytes.as_ref()),
+        Any::Array(items) => JsonValue::Array(items.iter().map(any_to_json).collect()),
+        Any::Map(map) => JsonValue::Object(map.iter().map(|(k, v)| (k.clone(), any_to_json(v))).collect()),
+    }
+}
+
+/// 差异片段类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffKind {
+    Equal,
+    Insert,
+    Delete,
+}
+
+/// 快照差异的粒度
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffGranularity {
+    #[default]
+    Line,
+    Word,
+}
+
+/// 两个版本之间的一段差异，相邻的同类片段已合并
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffOp {
+    pub kind: DiffKind,
+    pub text: String,
+}
+
+/// 计算两段文本的差异
+pub fn diff_text(old: &str, new: &str, granularity: DiffGranularity) -> Vec<DiffOp> {
+    use similar::{ChangeTag, TextDiff};
+
+    let diff = match granularity {
+        DiffGranularity::Line => TextDiff::from_lines(old, new),
+        DiffGranularity::Word => TextDiff::from_words(old, new),
+    };
+    let mut ops: Vec<DiffOp> = Vec::new();
+    for change in diff.iter_all_changes() {
+        let kind = match change.tag() {
+            ChangeTag::Equal => DiffKind::Equal,
+            ChangeTag::Insert => DiffKind::Insert,
+            ChangeTag::Delete => DiffKind::Delete,
+        };
+        match ops.last_mut() {
+            Some(last) if last.kind == kind => last.text.push_str(change.value()),
+            _ => ops.push(DiffOp {
+                kind,
+                text: change.value().to_string(),
+            }),
+        }
+    }
+    ops
+}
+
 /// 历史快照
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -117,11 +551,26 @@ pub struct HistorySnapshot {
     pub timestamp: i64,
     /// 快照描述 (可选)
     pub description: Option<String>,
+    /// 自上一个快照以来参与编辑的来源
+    #[serde(default)]
+    pub origins: Vec<String>,
     /// 状态数据 (base64 编码)
     #[serde(skip)]
     pub state: Vec<u8>,
 }
 
+/// 快照清理结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PruneResult {
+    /// 保留的快照数
+    pub kept: usize,
+    /// 删除的快照数
+    pub removed: usize,
+    /// 释放的磁盘空间（字节）
+    pub freed_bytes: u64,
+}
+
 /// CRDT 管理器
 /// 负责管理所有打开文档的 CRDT 状态
 pub struct CrdtManager {
@@ -129,6 +578,18 @@ pub struct CrdtManager {
     documents: RwLock<HashMap<String, Arc<RwLock<CrdtDocument>>>>,
     /// 存储路径
     storage_path: PathBuf,
+    /// vault 根目录，自动快照间隔保存在其配置中
+    vault_path: PathBuf,
+    /// 本机客户端 ID（未指定来源时的默认 origin）
+    client_id: String,
+    /// 启动恢复 pending 日志时遇到的错误
+    recovery_errors: Vec<String>,
+    /// 撤销合并窗口（毫秒）
+    undo_capture_timeout_ms: u64,
+    /// pending 日志合并阈值（字节）
+    log_compact_threshold: u64,
+    /// 自动快照间隔（分钟），0 表示关闭
+    autosnapshot_minutes: AtomicU64,
 }
 
 impl CrdtManager {
@@ -137,11 +598,195 @@ impl CrdtManager {
         let storage_path = vault_path.join(".zentri/crdt");
         // 确保目录存在
         fs::create_dir_all(&storage_path).ok();
+        let client_id = Self::load_client_id(&storage_path);
+        let autosnapshot_minutes = crate::vault::read_setting(vault_path, AUTOSNAPSHOT_SETTING)
+            .and_then(|value| value.as_u64())
+            .unwrap_or(DEFAULT_AUTOSNAPSHOT_MINUTES);
 
-        Self {
+        let mut manager = Self {
             documents: RwLock::new(HashMap::new()),
             storage_path,
+            vault_path: vault_path.to_path_buf(),
+            client_id,
+            recovery_errors: Vec::new(),
+            undo_capture_timeout_ms: DEFAULT_UNDO_CAPTURE_TIMEOUT_MS,
+            log_compact_threshold: DEFAULT_LOG_COMPACT_THRESHOLD,
+            autosnapshot_minutes: AtomicU64::new(autosnapshot_minutes),
+        };
+        // 重放上次会话崩溃前未落盘的更新
+        manager.recovery_errors = manager.recover_pending();
+        manager
+    }
+
+    /// 读取或生成本机客户端 ID（持久化，保证跨会话稳定）
+    fn load_client_id(storage_path: &Path) -> String {
+        let id_path = storage_path.join("client_id");
+        if let Ok(id) = fs::read_to_string(&id_path) {
+            let id = id.trim();
+            if !id.is_empty() {
+                return id.to_string();
+            }
+        }
+        let id = format!("local-{}", uuid::Uuid::new_v4());
+        fs::write(&id_path, &id).ok();
+        id
+    }
+
+    /// 设置之后加载的文档使用的撤销合并窗口（毫秒）
+    #[allow(dead_code)]
+    pub fn with_undo_capture_timeout(mut self, capture_timeout_ms: u64) -> Self {
+        self.undo_capture_timeout_ms = capture_timeout_ms;
+        self
+    }
+
+    /// 设置 pending 日志合并进状态文件的阈值（字节）
+    #[allow(dead_code)]
+    pub fn with_log_compact_threshold(mut self, threshold: u64) -> Self {
+        self.log_compact_threshold = threshold;
+        self
+    }
+
+    /// 设置自动快照间隔（分钟），0 表示关闭；写入 vault 配置，下次打开时沿用
+    pub fn set_autosnapshot_interval(&self, minutes: u64) -> Result<(), String> {
+        crate::vault::write_setting(&self.vault_path, AUTOSNAPSHOT_SETTING, json!(minutes))?;
+        self.autosnapshot_minutes.store(minutes, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// 自动快照间隔，已关闭时返回 None
+    pub fn autosnapshot_interval(&self) -> Option<Duration> {
+        match self.autosnapshot_minutes.load(Ordering::Relaxed) {
+            0 => None,
+            minutes => Some(Duration::from_secs(minutes * 60)),
+        }
+    }
+
+    /// 本机客户端 ID
+    #[cfg(test)]
+    fn client_id(&self) -> &str {
+        &self.client_id
+    }
+
+    /// 启动时恢复 pending 日志失败的文档及原因
+    pub fn recovery_errors(&self) -> &[String] {
+        &self.recovery_errors
+    }
+
+    fn doc_path(&self, doc_id: &str) -> PathBuf {
+        self.storage_path.join(format!("{}.yrs", doc_id))
+    }
+
+    fn pending_dir(&self) -> PathBuf {
+        self.storage_path.join("pending")
+    }
+
+    fn pending_path(&self, doc_id: &str) -> PathBuf {
+        self.pending_dir().join(format!("{}.log", doc_id))
+    }
+
+    /// 将更新追加到文档的 pending 日志并 fsync
+    /// 记录格式: [origin 长度 u32][origin][update 长度 u32][update]
+    fn append_pending(&self, doc_id: &str, origin: &str, update: &[u8]) -> Result<(), String> {
+        fs::create_dir_all(self.pending_dir()).map_err(|e| e.to_string())?;
+        let mut record = Vec::with_capacity(8 + origin.len() + update.len());
+        record.extend_from_slice(&(origin.len() as u32).to_le_bytes());
+        record.extend_from_slice(origin.as_bytes());
+        record.extend_from_slice(&(update.len() as u32).to_le_bytes());
+        record.extend_from_slice(update);
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.pending_path(doc_id))
+            .map_err(|e| e.to_string())?;
+        file.write_all(&record).map_err(|e| e.to_string())?;
+        file.sync_data().map_err(|e| e.to_string())
+    }
+
+    /// 读取 pending 日志中的全部记录，末尾写了一半的记录会被忽略
+    fn read_pending(path: &Path) -> Result<Vec<(String, Vec<u8>)>, String> {
+        let mut bytes = Vec::new();
+        fs::File::open(path)
+            .and_then(|mut f| f.read_to_end(&mut bytes))
+            .map_err(|e| e.to_string())?;
+
+        fn take<'a>(bytes: &'a [u8], pos: &mut usize) -> Option<&'a [u8]> {
+            let len_bytes = bytes.get(*pos..*pos + 4)?;
+            let len = u32::from_le_bytes(len_bytes.try_into().ok()?) as usize;
+            let data = bytes.get(*pos + 4..*pos + 4 + len)?;
+            *pos += 4 + len;
+            Some(data)
+        }
+
+        let mut records = Vec::new();
+        let mut pos = 0;
+        while pos < bytes.len() {
+            let Some(origin) = take(&bytes, &mut pos) else { break };
+            let Some(update) = take(&bytes, &mut pos) else { break };
+            records.push((String::from_utf8_lossy(origin).into_owned(), update.to_vec()));
         }
+        Ok(records)
+    }
+
+    /// 重放所有 pending 日志并落盘，返回失败的文档及原因
+    fn recover_pending(&self) -> Vec<String> {
+        let Ok(entries) = fs::read_dir(self.pending_dir()) else {
+            return vec![];
+        };
+
+        let mut errors = vec![];
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().map(|e| e != "log").unwrap_or(true) {
+                continue;
+            }
+            let Some(doc_id) = path.file_stem().and_then(|s| s.to_str()).map(String::from) else {
+                continue;
+            };
+
+            if let Err(e) = self.replay_pending(&doc_id) {
+                errors.push(format!("{}: {}", doc_id, e));
+            }
+        }
+        errors
+    }
+
+    /// 将磁盘状态与 pending 日志合并后重新落盘
+    fn replay_pending(&self, doc_id: &str) -> Result<(), String> {
+        let doc = self
+            .read_from_disk(doc_id)?
+            .unwrap_or_else(|| CrdtDocument::new(doc_id));
+        self.persist(doc_id, &doc)
+    }
+
+    fn pending_len(&self, doc_id: &str) -> u64 {
+        fs::metadata(self.pending_path(doc_id)).map(|m| m.len()).unwrap_or(0)
+    }
+
+    /// 落盘尚未写入日志的本地修改，或在日志超过阈值时将其合并进状态文件，
+    /// 返回是否重写了状态文件
+    fn checkpoint(&self, doc_id: &str, doc: &mut CrdtDocument) -> Result<bool, String> {
+        if !doc.dirty && self.pending_len(doc_id) <= self.log_compact_threshold {
+            return Ok(false);
+        }
+        self.persist(doc_id, doc)?;
+        doc.dirty = false;
+        Ok(true)
+    }
+
+    /// 原子写入文档状态，成功后清空该文档的 pending 日志
+    fn persist(&self, doc_id: &str, doc: &CrdtDocument) -> Result<(), String> {
+        let state = doc.encode_state();
+        let file_path = self.doc_path(doc_id);
+        let tmp_path = file_path.with_extension("yrs.tmp");
+        fs::write(&tmp_path, &state).map_err(|e| e.to_string())?;
+        fs::rename(&tmp_path, &file_path).map_err(|e| e.to_string())?;
+
+        let pending = self.pending_path(doc_id);
+        if pending.exists() {
+            fs::remove_file(&pending).map_err(|e| e.to_string())?;
+        }
+        Ok(())
     }
 
     /// 获取或创建文档
@@ -154,11 +799,14 @@ impl CrdtManager {
             }
         }
 
-        // 尝试从磁盘加载
-        let doc = self.load_from_disk(doc_id).unwrap_or_else(|| {
-            // 创建新文档
-            CrdtDocument::new(doc_id)
-        });
+        // 尝试从磁盘加载（重建撤销管理器，重放日志不计入撤销历史）
+        let doc = self
+            .load_from_disk(doc_id)
+            .unwrap_or_else(|| {
+                // 创建新文档
+                CrdtDocument::new(doc_id)
+            })
+            .with_undo_capture_timeout(self.undo_capture_timeout_ms);
 
         let arc_doc = Arc::new(RwLock::new(doc));
 
@@ -171,37 +819,115 @@ impl CrdtManager {
         arc_doc
     }
 
-    /// 从磁盘加载文档
+    /// 从磁盘加载文档，读取失败时视为不存在
     fn load_from_disk(&self, doc_id: &str) -> Option<CrdtDocument> {
-        let file_path = self.storage_path.join(format!("{}.yrs", doc_id));
-        if file_path.exists() {
-            let state = fs::read(&file_path).ok()?;
-            CrdtDocument::from_state(doc_id, &state).ok()
+        self.read_from_disk(doc_id).ok().flatten()
+    }
+
+    /// 先加载 .yrs 状态文件，再重放 pending 日志中尚未合并的更新；两者都不存在时返回 None
+    fn read_from_disk(&self, doc_id: &str) -> Result<Option<CrdtDocument>, String> {
+        let file_path = self.doc_path(doc_id);
+        let pending = self.pending_path(doc_id);
+        let mut doc = if file_path.exists() {
+            let state = fs::read(&file_path).map_err(|e| e.to_string())?;
+            CrdtDocument::from_state(doc_id, &state)?
+        } else if pending.exists() {
+            CrdtDocument::new(doc_id)
         } else {
-            None
+            return Ok(None);
+        };
+
+        if pending.exists() {
+            for (origin, update) in Self::read_pending(&pending)? {
+                doc.apply_update_with_origin(&update, &origin)?;
+            }
+            // 日志中的更新已在磁盘上
+            doc.dirty = false;
         }
+        Ok(Some(doc))
     }
 
     /// 保存文档到磁盘
+    ///
+    /// 经 `apply_update` 进入的更新已写入 pending 日志，这里只在有未记入日志的本地修改、
+    /// 或日志超过阈值时才重写状态文件
     pub fn save_to_disk(&self, doc_id: &str) -> Result<(), String> {
         let docs = self.documents.read().unwrap();
         if let Some(doc_arc) = docs.get(doc_id) {
-            let doc = doc_arc.read().unwrap();
-            let state = doc.encode_state();
-            let file_path = self.storage_path.join(format!("{}.yrs", doc_id));
-            fs::write(&file_path, &state).map_err(|e| e.to_string())?;
+            let mut doc = doc_arc.write().unwrap();
+            self.checkpoint(doc_id, &mut doc)?;
         }
         Ok(())
     }
 
-    /// 应用来自前端的更新
+    /// 应用来自前端的更新（来源为本机客户端）
     pub fn apply_update(&self, doc_id: &str, update: &[u8]) -> Result<(), String> {
+        self.apply_update_with_origin(doc_id, update, None)
+    }
+
+    /// 应用带来源标记的更新，origin 为空时使用本机客户端 ID
+    ///
+    /// 更新先写入 pending 日志再修改内存文档；日志写入失败时不应用更新并返回错误，
+    /// 由调用方保留更新稍后重试
+    pub fn apply_update_with_origin(
+        &self,
+        doc_id: &str,
+        update: &[u8],
+        origin: Option<&str>,
+    ) -> Result<(), String> {
+        let origin = origin.unwrap_or(&self.client_id);
+        // 先校验，避免无法解码的更新进入日志
+        Update::decode_v1(update).map_err(|e| format!("Decode update error: {:?}", e))?;
+
         let doc_arc = self.get_or_create(doc_id);
         let mut doc = doc_arc.write().unwrap();
-        doc.apply_update(update)?;
+        self.append_pending(doc_id, origin, update)
+            .map_err(|e| format!("{}: {}", PENDING_LOG_ERROR, e))?;
+        let had_unlogged = doc.dirty;
+        doc.apply_update_with_origin(update, origin)?;
+        // 更新已在日志中，不算未保存的修改
+        doc.dirty = had_unlogged;
+
+        if self.pending_len(doc_id) > self.log_compact_threshold {
+            // 合并失败不影响本次更新，日志仍完整，下次保存时重试
+            if let Err(e) = self.checkpoint(doc_id, &mut doc) {
+                eprintln!("Failed to compact CRDT log for {}: {}", doc_id, e);
+            }
+        }
         Ok(())
     }
 
+    /// 撤销文档最近一组编辑，产生的变更同样写入 pending 日志
+    pub fn undo(&self, doc_id: &str) -> Result<bool, String> {
+        self.undo_redo(doc_id, CrdtDocument::undo)
+    }
+
+    /// 重做文档最近一次撤销
+    pub fn redo(&self, doc_id: &str) -> Result<bool, String> {
+        self.undo_redo(doc_id, CrdtDocument::redo)
+    }
+
+    fn undo_redo(
+        &self,
+        doc_id: &str,
+        op: fn(&mut CrdtDocument) -> Result<bool, String>,
+    ) -> Result<bool, String> {
+        let doc_arc = self.get_or_create(doc_id);
+        let mut doc = doc_arc.write().unwrap();
+        let had_unlogged = doc.dirty;
+        let before = doc.state_vector();
+        if !op(&mut doc)? {
+            return Ok(false);
+        }
+        let update = doc.encode_diff(&before)?;
+        match self.append_pending(doc_id, &self.client_id, &update) {
+            Ok(()) => doc.dirty = had_unlogged,
+            // 写日志失败时保留 dirty，下次保存会写入完整状态
+            Err(e) => return Err(format!("{}: {}", PENDING_LOG_ERROR, e)),
+        }
+        Ok(true)
+    }
+
     /// 获取增量更新
     pub fn get_diff(&self, doc_id: &str, state_vector: &[u8]) -> Result<Vec<u8>, String> {
         let doc_arc = self.get_or_create(doc_id);
@@ -226,18 +952,30 @@ impl CrdtManager {
     /// 创建历史快照
     pub fn create_snapshot(&self, doc_id: &str, description: Option<&str>) -> Result<HistorySnapshot, String> {
         let doc_arc = self.get_or_create(doc_id);
-        let doc = doc_arc.read().unwrap();
+        let mut doc = doc_arc.write().unwrap();
         let state = doc.encode_state();
+        // 取出自上次快照以来的编辑来源
+        let origins: Vec<String> = std::mem::take(&mut doc.origins).into_iter().collect();
+        doc.changed_since_snapshot = false;
         
         let snapshot = HistorySnapshot {
             id: format!("{}-{}", doc_id, chrono::Utc::now().timestamp_millis()),
             timestamp: chrono::Utc::now().timestamp_millis(),
             description: description.map(String::from),
+            origins,
             state,
         };
-        
-        // 保存快照到磁盘
-        let snapshots_dir = self.storage_path.join("snapshots").join(doc_id);
+        self.write_snapshot(doc_id, &snapshot)?;
+        Ok(snapshot)
+    }
+
+    fn snapshots_dir(&self, doc_id: &str) -> PathBuf {
+        self.storage_path.join("snapshots").join(doc_id)
+    }
+
+    /// 将快照状态和元数据写入 snapshots/<doc_id>/
+    fn write_snapshot(&self, doc_id: &str, snapshot: &HistorySnapshot) -> Result<(), String> {
+        let snapshots_dir = self.snapshots_dir(doc_id);
         fs::create_dir_all(&snapshots_dir).map_err(|e| e.to_string())?;
         
         let snapshot_path = snapshots_dir.join(format!("{}.yrs", snapshot.timestamp));
@@ -249,11 +987,82 @@ impl CrdtManager {
             "id": snapshot.id,
             "timestamp": snapshot.timestamp,
             "description": snapshot.description,
+            "origins": snapshot.origins,
         });
         fs::write(&meta_path, serde_json::to_string_pretty(&meta).unwrap())
-            .map_err(|e| e.to_string())?;
-        
-        Ok(snapshot)
+            .map_err(|e| e.to_string())
+    }
+
+    /// 清理历史快照：保留最近 keep_last 个；更早的快照按天（UTC）各保留当天最新的一个，
+    /// 最多保留 keep_daily 天，其余删除。
+    ///
+    /// 待删除的文件先全部移入临时目录，任一移动失败即全部移回，保证不会只删掉一部分
+    pub fn prune_snapshots(&self, doc_id: &str, keep_last: usize, keep_daily: usize) -> Result<PruneResult, String> {
+        // list_snapshots 按时间从新到旧排列
+        let snapshots = self.list_snapshots(doc_id);
+        let mut kept_days = HashSet::new();
+        let mut doomed = vec![];
+        for snapshot in snapshots.iter().skip(keep_last) {
+            let day = chrono::DateTime::from_timestamp_millis(snapshot.timestamp).map(|t| t.date_naive());
+            if kept_days.len() < keep_daily && kept_days.insert(day) {
+                continue;
+            }
+            doomed.push(snapshot.timestamp);
+        }
+
+        let mut result = PruneResult {
+            kept: snapshots.len() - doomed.len(),
+            ..PruneResult::default()
+        };
+        if doomed.is_empty() {
+            return Ok(result);
+        }
+
+        let snapshots_dir = self.snapshots_dir(doc_id);
+        let trash = snapshots_dir.join(format!(".prune-{}", chrono::Utc::now().timestamp_millis()));
+        fs::create_dir_all(&trash).map_err(|e| e.to_string())?;
+
+        let mut moved: Vec<(PathBuf, PathBuf)> = vec![];
+        for timestamp in &doomed {
+            // 先移走元数据，列表中不会出现缺少状态文件的快照
+            for ext in ["json", "yrs"] {
+                let from = snapshots_dir.join(format!("{}.{}", timestamp, ext));
+                if !from.exists() {
+                    continue;
+                }
+                let to = trash.join(format!("{}.{}", timestamp, ext));
+                let size = fs::metadata(&from).map(|m| m.len()).unwrap_or(0);
+                if let Err(e) = fs::rename(&from, &to) {
+                    for (from, to) in moved.iter().rev() {
+                        fs::rename(to, from).ok();
+                    }
+                    fs::remove_dir_all(&trash).ok();
+                    return Err(format!("Failed to prune snapshot {}: {}", timestamp, e));
+                }
+                result.freed_bytes += size;
+                moved.push((from, to));
+            }
+        }
+
+        fs::remove_dir_all(&trash).map_err(|e| e.to_string())?;
+        result.removed = doomed.len();
+        Ok(result)
+    }
+
+    /// 为所有自上次快照以来有修改的已加载文档创建自动快照，返回创建数量。
+    /// 不改变 dirty 状态，与 flush_all 互不影响
+    pub fn auto_snapshot(&self) -> Result<usize, String> {
+        let changed: Vec<String> = {
+            let docs = self.documents.read().unwrap();
+            docs.iter()
+                .filter(|(_, doc)| doc.read().unwrap().changed_since_snapshot)
+                .map(|(doc_id, _)| doc_id.clone())
+                .collect()
+        };
+        for doc_id in &changed {
+            self.create_snapshot(doc_id, Some(AUTOSNAPSHOT_DESCRIPTION))?;
+        }
+        Ok(changed.len())
     }
 
     /// 获取快照列表
@@ -274,6 +1083,10 @@ impl CrdtManager {
                                 id: meta["id"].as_str().unwrap_or("").to_string(),
                                 timestamp: meta["timestamp"].as_i64().unwrap_or(0),
                                 description: meta["description"].as_str().map(String::from),
+                                origins: meta["origins"]
+                                    .as_array()
+                                    .map(|a| a.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+                                    .unwrap_or_default(),
                                 state: vec![], // 不加载完整状态
                             });
                         }
@@ -286,26 +1099,47 @@ impl CrdtManager {
         snapshots
     }
 
-    /// 恢复到指定快照
-    pub fn restore_snapshot(&self, doc_id: &str, snapshot_timestamp: i64) -> Result<(), String> {
-        let snapshots_dir = self.storage_path.join("snapshots").join(doc_id);
-        let snapshot_path = snapshots_dir.join(format!("{}.yrs", snapshot_timestamp));
-        
+    /// 读取快照文件中的文档状态
+    fn load_snapshot(&self, doc_id: &str, snapshot_timestamp: i64) -> Result<CrdtDocument, String> {
+        let snapshot_path = self
+            .storage_path
+            .join("snapshots")
+            .join(doc_id)
+            .join(format!("{}.yrs", snapshot_timestamp));
+
         if !snapshot_path.exists() {
-            return Err("Snapshot not found".to_string());
+            return Err(format!("Snapshot not found: {}", snapshot_timestamp));
         }
-        
+
         let state = fs::read(&snapshot_path).map_err(|e| e.to_string())?;
-        
+        CrdtDocument::from_state(doc_id, &state)
+    }
+
+    /// 比较两个快照的内容（富文本按文本块分行），返回从 ts_a 到 ts_b 的差异；
+    /// 任一快照文件缺失时返回错误
+    pub fn diff_snapshots(
+        &self,
+        doc_id: &str,
+        ts_a: i64,
+        ts_b: i64,
+        granularity: DiffGranularity,
+    ) -> Result<Vec<DiffOp>, String> {
+        let old = self.load_snapshot(doc_id, ts_a)?.plain_content();
+        let new = self.load_snapshot(doc_id, ts_b)?.plain_content();
+        Ok(diff_text(&old, &new, granularity))
+    }
+
+    /// 恢复到指定快照
+    pub fn restore_snapshot(&self, doc_id: &str, snapshot_timestamp: i64) -> Result<(), String> {
         // 创建新文档并替换
-        let new_doc = CrdtDocument::from_state(doc_id, &state)?;
+        let new_doc = self.load_snapshot(doc_id, snapshot_timestamp)?;
         
+        // 同时保存到主存储（恢复的状态取代 pending 日志中的更新）
+        self.persist(doc_id, &new_doc)?;
+
         let mut docs = self.documents.write().unwrap();
         docs.insert(doc_id.to_string(), Arc::new(RwLock::new(new_doc)));
-        
-        // 同时保存到主存储
-        self.save_to_disk(doc_id)?;
-        
+
         Ok(())
     }
 
@@ -315,11 +1149,8 @@ impl CrdtManager {
         let mut count = 0;
         
         for (doc_id, doc_arc) in docs.iter() {
-            let doc = doc_arc.read().unwrap();
-            if doc.dirty {
-                let state = doc.encode_state();
-                let file_path = self.storage_path.join(format!("{}.yrs", doc_id));
-                fs::write(&file_path, &state).map_err(|e| e.to_string())?;
+            let mut doc = doc_arc.write().unwrap();
+            if self.checkpoint(doc_id, &mut doc)? {
                 count += 1;
             }
         }
@@ -327,6 +1158,51 @@ impl CrdtManager {
         Ok(count)
     }
 
+    /// 压缩存储：所有文档重新编码为单个状态文件并清空 pending 日志，返回处理的文档数
+    pub fn compact(&self) -> Result<usize, String> {
+        // 持有缓存写锁，避免压缩期间有文档被加载
+        let docs = self.documents.write().unwrap();
+        let mut count = 0;
+
+        for (doc_id, doc_arc) in docs.iter() {
+            let mut doc = doc_arc.write().unwrap();
+            self.persist(doc_id, &doc)?;
+            doc.dirty = false;
+            count += 1;
+        }
+
+        let mut unloaded = BTreeSet::new();
+        for dir in [self.storage_path.clone(), self.pending_dir()] {
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+            for path in entries.flatten().map(|e| e.path()) {
+                let is_doc_file = path
+                    .extension()
+                    .map(|e| e == "yrs" || e == "log")
+                    .unwrap_or(false);
+                if let Some(doc_id) = path.file_stem().and_then(|s| s.to_str()) {
+                    if is_doc_file && !docs.contains_key(doc_id) {
+                        unloaded.insert(doc_id.to_string());
+                    }
+                }
+            }
+        }
+
+        for doc_id in unloaded {
+            if self.pending_path(&doc_id).exists() {
+                self.replay_pending(&doc_id)?;
+            } else if let Some(doc) = self.load_from_disk(&doc_id) {
+                self.persist(&doc_id, &doc)?;
+            } else {
+                continue;
+            }
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
     /// 从缓存移除文档
     pub fn unload(&self, doc_id: &str) {
         let mut docs = self.documents.write().unwrap();
@@ -346,6 +1222,76 @@ mod tests {
         assert_eq!(doc.get_text(), "Hello, World!");
     }
 
+    #[test]
+    fn test_tiptap_json_roundtrip_through_xml_fragment() {
+        let document = serde_json::json!({
+            "type": "doc",
+            "content": [
+                {"type": "heading", "attrs": {"level": 2}, "content": [{"type": "text", "text": "标题"}]},
+                {"type": "paragraph", "content": [
+                    {"type": "text", "text": "plain "},
+                    {"type": "text", "text": "bold", "marks": [{"type": "bold"}]},
+                    {"type": "text", "text": " link", "marks": [{"type": "link", "attrs": {"href": "https://example.com"}}]}
+                ]},
+                {"type": "bulletList", "content": [
+                    {"type": "listItem", "content": [
+                        {"type": "paragraph", "content": [{"type": "text", "text": "one"}]}
+                    ]},
+                    {"type": "listItem", "content": [
+                        {"type": "paragraph", "content": [{"type": "text", "text": "two", "marks": [{"type": "bold"}]}]}
+                    ]}
+                ]}
+            ]
+        });
+
+        let mut doc = CrdtDocument::new("rich");
+        doc.set_tiptap_json(&document).unwrap();
+        assert_eq!(doc.get_tiptap_json(), document);
+
+        // 同步到另一端后结构不变，纯文本字段不受影响
+        let mut peer = CrdtDocument::new("rich");
+        peer.apply_update(&doc.encode_state()).unwrap();
+        assert_eq!(peer.get_tiptap_json(), document);
+        assert_eq!(peer.get_text(), "");
+    }
+
+    /// 模拟前端窗口输入：在 peer 上追加文本，再把增量作为带来源的更新应用到 doc
+    fn type_into(peer: &CrdtDocument, doc: &mut CrdtDocument, chunk: &str) {
+        let text = peer.doc.get_or_insert_text("content");
+        let before = doc.state_vector();
+        {
+            let mut txn = peer.doc.transact_mut();
+            let len = text.len(&txn);
+            text.insert(&mut txn, len, chunk);
+        }
+        let update = peer.encode_diff(&before).unwrap();
+        doc.apply_update_with_origin(&update, "window-1").unwrap();
+    }
+
+    #[test]
+    fn test_undo_reverts_last_grouped_change() {
+        let peer = CrdtDocument::new("doc");
+        let mut doc = CrdtDocument::new("doc");
+
+        type_into(&peer, &mut doc, "Hel");
+        type_into(&peer, &mut doc, "lo");
+        doc.stop_capturing();
+        type_into(&peer, &mut doc, " wor");
+        type_into(&peer, &mut doc, "ld");
+        assert_eq!(doc.get_text(), "Hello world");
+
+        // 窗口内的连续输入作为一步撤销
+        assert!(doc.undo().unwrap());
+        assert_eq!(doc.get_text(), "Hello");
+        assert!(doc.redo().unwrap());
+        assert_eq!(doc.get_text(), "Hello world");
+
+        assert!(doc.undo().unwrap());
+        assert!(doc.undo().unwrap());
+        assert_eq!(doc.get_text(), "");
+        assert!(!doc.undo().unwrap());
+    }
+
     #[test]
     fn test_crdt_sync() {
         let mut doc1 = CrdtDocument::new("test");
@@ -377,5 +1323,303 @@ mod tests {
         let doc_guard = doc2.read().unwrap();
         assert_eq!(doc_guard.get_text(), "Test content");
     }
-}
 
+    #[test]
+    fn test_diff_snapshots() {
+        let dir = tempdir().unwrap();
+        let manager = CrdtManager::new(dir.path());
+
+        let doc = manager.get_or_create("doc");
+        doc.write().unwrap().set_text("line one\nline two\n");
+        let a = manager.create_snapshot("doc", None).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        doc.write().unwrap().set_text("line one\nline 2\nline three\n");
+        let b = manager.create_snapshot("doc", None).unwrap();
+
+        let ops = manager
+            .diff_snapshots("doc", a.timestamp, b.timestamp, DiffGranularity::Line)
+            .unwrap();
+        let kinds: Vec<(DiffKind, &str)> = ops.iter().map(|op| (op.kind, op.text.as_str())).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (DiffKind::Equal, "line one\n"),
+                (DiffKind::Delete, "line two\n"),
+                (DiffKind::Insert, "line 2\nline three\n"),
+            ]
+        );
+
+        let words = diff_text("the quick fox", "the slow fox", DiffGranularity::Word);
+        assert!(words.contains(&DiffOp { kind: DiffKind::Delete, text: "quick".to_string() }));
+        assert!(words.contains(&DiffOp { kind: DiffKind::Insert, text: "slow".to_string() }));
+
+        let missing = manager.diff_snapshots("doc", a.timestamp, 42, DiffGranularity::Line);
+        assert!(missing.unwrap_err().contains("Snapshot not found"));
+    }
+
+    #[test]
+    fn test_diff_snapshots_of_rich_text() {
+        let dir = tempdir().unwrap();
+        let manager = CrdtManager::new(dir.path());
+        let paragraph = |text: &str| serde_json::json!({"type": "paragraph", "content": [{"type": "text", "text": text}]});
+
+        let doc = manager.get_or_create("doc");
+        doc.write()
+            .unwrap()
+            .set_tiptap_json(&serde_json::json!({"type": "doc", "content": [paragraph("intro")]}))
+            .unwrap();
+        let a = manager.create_snapshot("doc", None).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        doc.write()
+            .unwrap()
+            .set_tiptap_json(&serde_json::json!({"type": "doc", "content": [
+                paragraph("intro"),
+                {"type": "bulletList", "content": [{"type": "listItem", "content": [paragraph("new item")]}]}
+            ]}))
+            .unwrap();
+        let b = manager.create_snapshot("doc", None).unwrap();
+
+        let ops = manager
+            .diff_snapshots("doc", a.timestamp, b.timestamp, DiffGranularity::Line)
+            .unwrap();
+        assert_eq!(
+            ops,
+            vec![
+                DiffOp { kind: DiffKind::Equal, text: "intro\n".to_string() },
+                DiffOp { kind: DiffKind::Insert, text: "new item\n".to_string() },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_snapshot_records_origins() {
+        let dir = tempdir().unwrap();
+        let manager = CrdtManager::new(dir.path());
+
+        let mut remote = CrdtDocument::new("remote");
+        remote.set_text("from peer");
+        manager
+            .apply_update_with_origin("doc", &remote.encode_state(), Some("peer-a"))
+            .unwrap();
+        let mut local = CrdtDocument::new("local");
+        local.set_text("!");
+        manager.apply_update("doc", &local.encode_state()).unwrap();
+
+        let snapshot = manager.create_snapshot("doc", None).unwrap();
+        assert_eq!(snapshot.origins.len(), 2);
+        assert!(snapshot.origins.contains(&"peer-a".to_string()));
+        assert!(snapshot.origins.contains(&manager.client_id().to_string()));
+
+        let listed = manager.list_snapshots("doc");
+        assert_eq!(listed[0].origins, snapshot.origins);
+
+        // 快照后来源集合被清空
+        let doc = manager.get_or_create("doc");
+        assert!(doc.read().unwrap().origins.is_empty());
+    }
+
+    /// 以指定时间戳写入一个内容为 text 的快照
+    fn write_test_snapshot(manager: &CrdtManager, timestamp: i64, text: &str) {
+        let mut doc = CrdtDocument::new("doc");
+        doc.set_text(text);
+        let snapshot = HistorySnapshot {
+            id: format!("doc-{}", timestamp),
+            timestamp,
+            description: None,
+            origins: vec![],
+            state: doc.encode_state(),
+        };
+        manager.write_snapshot("doc", &snapshot).unwrap();
+    }
+
+    fn snapshot_timestamps(manager: &CrdtManager) -> Vec<i64> {
+        manager.list_snapshots("doc").iter().map(|s| s.timestamp).collect()
+    }
+
+    #[test]
+    fn test_prune_snapshots_keeps_last_n() {
+        let dir = tempdir().unwrap();
+        let manager = CrdtManager::new(dir.path());
+        let base = 1_700_000_000_000;
+        for i in 0..5 {
+            write_test_snapshot(&manager, base + i * 1000, &format!("v{}", i));
+        }
+
+        let result = manager.prune_snapshots("doc", 3, 0).unwrap();
+        assert_eq!((result.kept, result.removed), (3, 2));
+        assert!(result.freed_bytes > 0);
+        assert_eq!(snapshot_timestamps(&manager), vec![base + 4000, base + 3000, base + 2000]);
+        assert!(!dir.path().join(".zentri/crdt/snapshots/doc").join(format!("{}.yrs", base)).exists());
+
+        // 保留的快照仍可恢复，已删除的报错
+        manager.restore_snapshot("doc", base + 2000).unwrap();
+        assert_eq!(manager.get_or_create("doc").read().unwrap().get_text(), "v2");
+        assert!(manager.restore_snapshot("doc", base + 1000).is_err());
+
+        // 数量不超过 keep_last 时不删除
+        assert_eq!(manager.prune_snapshots("doc", 3, 0).unwrap().removed, 0);
+    }
+
+    #[test]
+    fn test_prune_snapshots_keeps_one_per_day() {
+        let dir = tempdir().unwrap();
+        let manager = CrdtManager::new(dir.path());
+        const DAY: i64 = 24 * 60 * 60 * 1000;
+        const HOUR: i64 = 60 * 60 * 1000;
+        // 2023-11-14 00:00 UTC，四天各两个快照（12 点和 13 点）
+        let midnight = 1_699_920_000_000;
+        for day in 0..4 {
+            for hour in [12, 13] {
+                write_test_snapshot(&manager, midnight + day * DAY + hour * HOUR, &format!("d{}h{}", day, hour));
+            }
+        }
+
+        let result = manager.prune_snapshots("doc", 1, 2).unwrap();
+        assert_eq!((result.kept, result.removed), (3, 5));
+        // 最新的一个，加上更早快照中最近两天各自的最后一个
+        assert_eq!(
+            snapshot_timestamps(&manager),
+            vec![
+                midnight + 3 * DAY + 13 * HOUR,
+                midnight + 3 * DAY + 12 * HOUR,
+                midnight + 2 * DAY + 13 * HOUR,
+            ]
+        );
+
+        manager.restore_snapshot("doc", midnight + 2 * DAY + 13 * HOUR).unwrap();
+        assert_eq!(manager.get_or_create("doc").read().unwrap().get_text(), "d2h13");
+        // 临时目录已清理
+        let leftovers = fs::read_dir(dir.path().join(".zentri/crdt/snapshots/doc"))
+            .unwrap()
+            .flatten()
+            .filter(|e| e.path().is_dir())
+            .count();
+        assert_eq!(leftovers, 0);
+    }
+
+    #[test]
+    fn test_auto_snapshot_skips_unchanged_documents() {
+        let dir = tempdir().unwrap();
+        let manager = CrdtManager::new(dir.path());
+        manager.get_or_create("idle");
+        manager.get_or_create("doc").write().unwrap().set_text("draft");
+
+        assert_eq!(manager.auto_snapshot().unwrap(), 1);
+        let snapshots = manager.list_snapshots("doc");
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].description.as_deref(), Some(AUTOSNAPSHOT_DESCRIPTION));
+        assert!(manager.list_snapshots("idle").is_empty());
+
+        // 没有新修改时不再创建；自动快照不影响保存
+        assert_eq!(manager.auto_snapshot().unwrap(), 0);
+        assert_eq!(manager.flush_all().unwrap(), 1);
+
+        let mut edit = CrdtDocument::new("edit");
+        edit.set_text("!");
+        manager.apply_update("doc", &edit.encode_state()).unwrap();
+        assert_eq!(manager.auto_snapshot().unwrap(), 1);
+
+        assert_eq!(manager.autosnapshot_interval(), Some(Duration::from_secs(600)));
+        manager.set_autosnapshot_interval(0).unwrap();
+        assert_eq!(manager.autosnapshot_interval(), None);
+
+        // 间隔保存在 vault 配置中，重新打开后沿用
+        drop(manager);
+        assert_eq!(CrdtManager::new(dir.path()).autosnapshot_interval(), None);
+    }
+
+    #[test]
+    fn test_pending_log_replayed_after_crash() {
+        let dir = tempdir().unwrap();
+        {
+            let manager = CrdtManager::new(dir.path());
+            let mut edit = CrdtDocument::new("edit");
+            edit.set_text("unsaved edit");
+            manager
+                .apply_update_with_origin("doc", &edit.encode_state(), Some("peer-a"))
+                .unwrap();
+            // 模拟在 save_to_disk 之前崩溃：直接丢弃管理器
+        }
+        assert!(dir.path().join(".zentri/crdt/pending/doc.log").exists());
+
+        let manager = CrdtManager::new(dir.path());
+        assert!(manager.recovery_errors().is_empty());
+        assert!(!dir.path().join(".zentri/crdt/pending/doc.log").exists());
+        let doc = manager.get_or_create("doc");
+        assert_eq!(doc.read().unwrap().get_text(), "unsaved edit");
+    }
+
+    #[test]
+    fn test_save_keeps_small_log_and_ignores_torn_record() {
+        let dir = tempdir().unwrap();
+        let manager = CrdtManager::new(dir.path());
+        let mut edit = CrdtDocument::new("edit");
+        edit.set_text("saved");
+        manager.apply_update("doc", &edit.encode_state()).unwrap();
+        // 日志未超过阈值，保存时不重写状态文件
+        assert_eq!(manager.flush_all().unwrap(), 0);
+        manager.save_to_disk("doc").unwrap();
+        let pending = dir.path().join(".zentri/crdt/pending/doc.log");
+        assert!(pending.exists());
+        assert!(!dir.path().join(".zentri/crdt/doc.yrs").exists());
+
+        // 写了一半的记录（崩溃于追加过程中）在恢复时被忽略
+        let mut more = CrdtDocument::new("more");
+        more.set_text("!");
+        manager.apply_update("doc", &more.encode_state()).unwrap();
+        let mut file = fs::OpenOptions::new().append(true).open(&pending).unwrap();
+        file.write_all(&[9, 0, 0, 0, b'x']).unwrap();
+        drop(file);
+
+        let manager2 = CrdtManager::new(dir.path());
+        assert!(manager2.recovery_errors().is_empty());
+        let text = manager2.get_or_create("doc").read().unwrap().get_text();
+        assert!(text.contains("saved") && text.contains('!'));
+    }
+
+    #[test]
+    fn test_compact_merges_pending_logs_of_unloaded_docs() {
+        let dir = tempdir().unwrap();
+        let manager = CrdtManager::new(dir.path());
+        let mut edit = CrdtDocument::new("edit");
+        edit.set_text("kept");
+        manager.apply_update("doc", &edit.encode_state()).unwrap();
+        manager.unload("doc");
+
+        assert_eq!(manager.compact().unwrap(), 1);
+        assert!(!dir.path().join(".zentri/crdt/pending/doc.log").exists());
+        assert_eq!(manager.get_or_create("doc").read().unwrap().get_text(), "kept");
+    }
+
+    #[test]
+    fn test_log_compacts_into_snapshot_and_tail_replays_on_load() {
+        let dir = tempdir().unwrap();
+        let manager = CrdtManager::new(dir.path()).with_log_compact_threshold(1024);
+        let peer = CrdtDocument::new("peer");
+        let text = peer.doc.get_or_insert_text("content");
+
+        let mut expected = String::new();
+        for i in 0..100 {
+            let chunk = format!("{} ", i);
+            let before = manager.get_state_vector("doc");
+            {
+                let mut txn = peer.doc.transact_mut();
+                let len = text.len(&txn);
+                text.insert(&mut txn, len, &chunk);
+            }
+            manager
+                .apply_update("doc", &peer.encode_diff(&before).unwrap())
+                .unwrap();
+            expected.push_str(&chunk);
+        }
+
+        // 日志至少合并过一次，剩余部分不超过阈值
+        let log = dir.path().join(".zentri/crdt/pending/doc.log");
+        assert!(dir.path().join(".zentri/crdt/doc.yrs").exists());
+        assert!(fs::metadata(&log).map(|m| m.len()).unwrap_or(0) <= 1024);
+
+        // 不保存直接卸载，重新加载时由状态文件加日志尾部还原
+        manager.unload("doc");
+        assert_eq!(manager.get_or_create("doc").read().unwrap().get_text(), expected);
+    }
+}
@@ -0,0 +1,140 @@
+//! 卡片加密模块
+//! 口令经 Argon2id 派生出 256 位密钥，卡片正文用 XChaCha20-Poly1305 加密后以 base64 保存。
+//! 密钥只保存在内存中，数据库里只有盐和用于校验口令的密文
+
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use zeroize::Zeroize;
+
+/// 加密正文的前缀，用于识别已加密内容
+pub const ENVELOPE_PREFIX: &str = "zentri-enc:v1:";
+
+/// 校验口令用的固定明文
+const VERIFIER_PLAINTEXT: &str = "zentri-vault-key";
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+#[derive(Debug, Error)]
+pub enum CryptoError {
+    #[error("口令错误")]
+    WrongPassphrase,
+    #[error("密钥派生失败: {0}")]
+    KeyDerivation(String),
+    #[error("密文已损坏")]
+    Corrupt,
+}
+
+/// 保存在配置表中的密钥参数（不含密钥本身）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyParams {
+    /// base64 编码的 Argon2 盐
+    pub salt: String,
+    /// 用密钥加密的固定明文，解锁时据此判断口令是否正确
+    pub verifier: String,
+}
+
+/// 由口令派生的对称密钥，释放时清零内存
+pub struct VaultKey([u8; 32]);
+
+impl Drop for VaultKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl VaultKey {
+    fn derive(passphrase: &str, salt: &[u8]) -> Result<Self, CryptoError> {
+        let mut key = Self([0u8; 32]);
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key.0)
+            .map_err(|e| CryptoError::KeyDerivation(e.to_string()))?;
+        Ok(key)
+    }
+
+    /// 首次设置口令：生成随机盐和校验密文
+    pub fn create(passphrase: &str) -> Result<(Self, KeyParams), CryptoError> {
+        let mut salt = [0u8; SALT_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let key = Self::derive(passphrase, &salt)?;
+        let params = KeyParams {
+            salt: BASE64.encode(salt),
+            verifier: key.encrypt(VERIFIER_PLAINTEXT)?,
+        };
+        Ok((key, params))
+    }
+
+    /// 用已保存的参数解锁，口令不对时返回 `WrongPassphrase`
+    pub fn unlock(passphrase: &str, params: &KeyParams) -> Result<Self, CryptoError> {
+        let salt = BASE64.decode(&params.salt).map_err(|_| CryptoError::Corrupt)?;
+        let key = Self::derive(passphrase, &salt)?;
+        match key.decrypt(&params.verifier) {
+            Ok(text) if text == VERIFIER_PLAINTEXT => Ok(key),
+            _ => Err(CryptoError::WrongPassphrase),
+        }
+    }
+
+    fn cipher(&self) -> XChaCha20Poly1305 {
+        XChaCha20Poly1305::new(Key::from_slice(&self.0))
+    }
+
+    /// 加密文本，返回带前缀的 base64（随机 nonce 在前，密文在后）
+    pub fn encrypt(&self, plaintext: &str) -> Result<String, CryptoError> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher()
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| CryptoError::Corrupt)?;
+        let mut payload = nonce.to_vec();
+        payload.extend_from_slice(&ciphertext);
+        Ok(format!("{}{}", ENVELOPE_PREFIX, BASE64.encode(payload)))
+    }
+
+    /// 解密 `encrypt` 的输出，密钥不对或内容被篡改时返回错误
+    pub fn decrypt(&self, envelope: &str) -> Result<String, CryptoError> {
+        let payload = envelope
+            .strip_prefix(ENVELOPE_PREFIX)
+            .and_then(|b64| BASE64.decode(b64).ok())
+            .filter(|p| p.len() > NONCE_LEN)
+            .ok_or(CryptoError::Corrupt)?;
+        let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher()
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| CryptoError::WrongPassphrase)?;
+        String::from_utf8(plaintext).map_err(|_| CryptoError::Corrupt)
+    }
+}
+
+/// 内容是否为加密后的正文
+pub fn is_encrypted(content: &str) -> bool {
+    content.starts_with(ENVELOPE_PREFIX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_roundtrip_and_wrong_passphrase() {
+        let (key, params) = VaultKey::create("correct horse").unwrap();
+        let sealed = key.encrypt(r#"{"type":"doc"}"#).unwrap();
+        assert!(is_encrypted(&sealed));
+        assert!(!sealed.contains("doc"));
+
+        let key = VaultKey::unlock("correct horse", &params).unwrap();
+        assert_eq!(key.decrypt(&sealed).unwrap(), r#"{"type":"doc"}"#);
+
+        assert!(matches!(
+            VaultKey::unlock("wrong", &params),
+            Err(CryptoError::WrongPassphrase)
+        ));
+        assert!(key.decrypt("zentri-enc:v1:AAAA").is_err());
+    }
+}
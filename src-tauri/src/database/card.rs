@@ -2,7 +2,10 @@
 
 use crate::db::Database;
 use crate::error::AppResult;
-use crate::models::{Card, CardType, CreateCardRequest, UpdateCardRequest};
+use crate::models::{
+    Card, CardListItem, CardListSort, CardType, CreateCardRequest, ReviewState, TrashedCard,
+    UpdateCardRequest,
+};
 use std::sync::Arc;
 
 /// Card 数据访问层
@@ -25,9 +28,9 @@ impl CardRepository {
         self.db.get_card(id).await
     }
 
-    /// 获取所有卡片
+    /// 获取所有卡片（含已归档）
     pub async fn get_all(&self) -> AppResult<Vec<Card>> {
-        self.db.get_all_cards().await
+        self.db.get_all_cards(true).await
     }
 
     /// 按类型获取卡片
@@ -50,11 +53,95 @@ impl CardRepository {
         self.db.update_card(id, req).await
     }
 
-    /// 删除卡片
+    /// 删除卡片（软删除）
     pub async fn delete(&self, id: &str) -> AppResult<()> {
         self.db.delete_card(id).await
     }
 
+    /// 从回收站恢复卡片
+    pub async fn restore(&self, id: &str) -> AppResult<Option<Card>> {
+        self.db.restore_card(id).await
+    }
+
+    /// 获取回收站中的卡片
+    pub async fn get_trashed(&self) -> AppResult<Vec<TrashedCard>> {
+        self.db.get_trashed_cards().await
+    }
+
+    /// 获取回收站中卡片的 ID
+    pub async fn get_trashed_ids(&self) -> AppResult<Vec<String>> {
+        self.db.get_trashed_card_ids().await
+    }
+
+    /// 获取 ID 以指定前缀开头的卡片 ID
+    pub async fn get_ids_with_prefix(&self, prefix: &str) -> AppResult<Vec<String>> {
+        self.db.get_card_ids_with_prefix(prefix).await
+    }
+
+    /// 清理回收站中过期的卡片，返回被删除卡片的 ID
+    pub async fn purge_trashed(&self, older_than_days: u32) -> AppResult<Vec<String>> {
+        self.db.purge_trashed_cards(older_than_days).await
+    }
+
+    /// 设置置顶状态
+    pub async fn set_pinned(&self, id: &str, pinned: bool) -> AppResult<Option<Card>> {
+        self.db.set_card_pinned(id, pinned).await
+    }
+
+    /// 设置归档状态
+    pub async fn set_archived(&self, id: &str, archived: bool) -> AppResult<Option<Card>> {
+        self.db.set_card_archived(id, archived).await
+    }
+
+    /// 写入正文并设置加密标记
+    pub async fn set_encrypted(
+        &self,
+        id: &str,
+        content: &str,
+        encrypted: bool,
+    ) -> AppResult<Option<Card>> {
+        self.db.set_card_encrypted(id, content, encrypted).await
+    }
+
+    /// 设置复习状态
+    pub async fn set_review(&self, id: &str, review: Option<&ReviewState>) -> AppResult<Option<Card>> {
+        self.db.set_card_review(id, review).await
+    }
+
+    /// 获取到期需要复习的卡片
+    pub async fn get_due(&self, now: i64) -> AppResult<Vec<CardListItem>> {
+        self.db.get_due_cards(now).await
+    }
+
+    /// 批量修改卡片类型（单个事务），返回实际更新的 ID
+    pub async fn set_type_batch(&self, ids: &[String], card_type: &CardType) -> AppResult<Vec<String>> {
+        self.db.set_cards_type(ids, card_type).await
+    }
+
+    /// 将 `from` 标签替换为 `into`（None 表示移除），返回被修改的卡片 ID
+    pub async fn merge_tags(&self, from: &[String], into: Option<&str>) -> AppResult<Vec<String>> {
+        self.db.merge_card_tags(from, into).await
+    }
+
+    /// 获取已归档卡片的 ID
+    pub async fn get_archived_ids(&self) -> AppResult<Vec<String>> {
+        self.db.get_archived_card_ids().await
+    }
+
+    /// 获取置顶卡片
+    pub async fn get_pinned(&self) -> AppResult<Vec<Card>> {
+        self.db.get_pinned_cards().await
+    }
+
+    /// 获取卡片列表（不含正文）
+    pub async fn get_list(
+        &self,
+        sort: CardListSort,
+        include_archived: bool,
+    ) -> AppResult<Vec<CardListItem>> {
+        self.db.get_card_list(sort, include_archived).await
+    }
+
     /// 获取卡片的所有链接
     pub async fn get_links(&self, card_id: &str) -> AppResult<Vec<String>> {
         self.db.get_card_links(card_id).await
@@ -31,6 +31,11 @@ impl HighlightRepository {
         self.db.get_all_highlights().await
     }
 
+    /// 全文搜索高亮
+    pub async fn search(&self, query: &str, source_id: Option<&str>) -> AppResult<Vec<Highlight>> {
+        self.db.search_highlights(query, source_id).await
+    }
+
     /// 获取单个高亮
     pub async fn get_by_id(&self, id: &str) -> AppResult<Option<Highlight>> {
         self.db.get_highlight(id).await
@@ -2,7 +2,7 @@
 
 use crate::db::Database;
 use crate::error::AppResult;
-use crate::models::{CreateSourceRequest, Source, UpdateSourceRequest};
+use crate::models::{CreateSourceRequest, Source, SourceFilter, UpdateSourceRequest};
 use std::sync::Arc;
 
 /// Source 数据访问层
@@ -30,11 +30,21 @@ impl SourceRepository {
         self.db.get_sources_paginated(offset, limit).await
     }
 
+    /// 按条件过滤文献源
+    pub async fn get_filtered(&self, filter: &SourceFilter) -> AppResult<Vec<Source>> {
+        self.db.get_sources_filtered(filter).await
+    }
+
     /// 获取文献源总数
     pub async fn get_count(&self) -> AppResult<usize> {
         self.db.get_sources_count().await
     }
 
+    /// 获取"继续阅读"列表
+    pub async fn get_continue_reading(&self, limit: usize) -> AppResult<Vec<Source>> {
+        self.db.get_continue_reading(limit).await
+    }
+
     /// 获取单个文献源
     pub async fn get_by_id(&self, id: &str) -> AppResult<Option<Source>> {
         self.db.get_source(id).await
@@ -45,15 +55,55 @@ impl SourceRepository {
         self.db.update_source(id, req).await
     }
 
-    /// 删除文献源
+    /// 删除文献源（软删除）
     pub async fn delete(&self, id: &str) -> AppResult<()> {
         self.db.delete_source(id).await
     }
 
+    /// 修改文献源加入资料库的途径
+    pub async fn set_origin(&self, id: &str, origin: &str) -> AppResult<()> {
+        self.db.set_source_origin(id, origin).await
+    }
+
+    /// 永久删除文献源
+    pub async fn hard_delete(&self, id: &str) -> AppResult<()> {
+        self.db.hard_delete_source(id).await
+    }
+
+    /// 从回收站恢复文献源
+    pub async fn restore(&self, id: &str) -> AppResult<Option<Source>> {
+        self.db.restore_source(id).await
+    }
+
+    /// 获取回收站中的文献源
+    pub async fn get_trashed(&self) -> AppResult<Vec<Source>> {
+        self.db.get_trashed_sources().await
+    }
+
+    /// 清理回收站中过期的文献源
+    pub async fn purge_trashed(&self, older_than_days: u32) -> AppResult<u64> {
+        self.db.purge_trashed_sources(older_than_days).await
+    }
+
     /// 添加笔记 ID 到文献源
     pub async fn add_note(&self, source_id: &str, note_id: &str) -> AppResult<()> {
         self.db.add_note_to_source(source_id, note_id).await
     }
+
+    /// 从文献源移除笔记 ID
+    pub async fn remove_note(&self, source_id: &str, note_id: &str) -> AppResult<()> {
+        self.db.remove_note_from_source(source_id, note_id).await
+    }
+
+    /// 统计文献源标签使用次数
+    pub async fn get_tag_counts(&self) -> AppResult<Vec<(String, usize)>> {
+        self.db.get_source_tag_counts().await
+    }
+
+    /// 合并文献源标签，返回被修改的文献源 ID
+    pub async fn merge_tags(&self, from: &[String], into: &str) -> AppResult<Vec<String>> {
+        self.db.merge_source_tags(from, into).await
+    }
 }
 
 impl crate::database::Repository for SourceRepository {
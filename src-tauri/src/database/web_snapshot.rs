@@ -54,6 +54,14 @@ impl WebSnapshotRepository {
         Ok(snapshot)
     }
 
+    /// 按网页地址查找已有快照（地址先规范化，忽略跟踪参数、片段和末尾斜杠）
+    pub async fn find_by_url(&self, url: &str) -> AppResult<Option<WebSnapshot>> {
+        match self.db.find_snapshot_by_url(url).await? {
+            Some(snapshot) => self.get_by_source(&snapshot.source_id).await,
+            None => Ok(None),
+        }
+    }
+
     /// 删除网页快照
     #[allow(dead_code)]
     pub async fn delete(&self, source_id: &str) -> AppResult<()> {
@@ -4,20 +4,65 @@
 use crate::commands::highlights::SourceBacklink;
 use crate::error::AppResult;
 use crate::models::{
-    Bookmark, Card, CardType, CreateBookmarkRequest, CreateCardRequest, CreateHighlightRequest,
-    CreateSourceRequest, Highlight, HighlightPosition, Source, SourceMetadata, SourceType,
+    count_words, Bookmark, Card, CardListItem, CardListSort, CardType, CreateBookmarkRequest,
+    CreateCardRequest, CreateHighlightRequest, CreateSourceRequest, Highlight, HighlightPosition, ReviewState, Source, SourceFilter, SourceMetadata, SourceType, TrashedCard,
     UpdateBookmarkRequest, UpdateCardRequest, UpdateHighlightRequest, UpdateSourceRequest,
 };
 use crate::web_reader::WebSnapshot;
 use chrono::Utc;
-use sqlx::{sqlite::{SqlitePool, SqliteConnectOptions}, Row};
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions, SqliteSynchronous},
+    Row,
+};
+use std::collections::HashMap;
 use std::path::Path;
+use std::time::Duration;
 use uuid::Uuid;
 
+/// 回收站保留天数，超过后永久删除
+pub const TRASH_RETENTION_DAYS: u32 = 30;
+
+/// 连接池大小：WAL 模式下读连接可以并发，写入由 SQLite 自身的锁串行化
+const POOL_MAX_CONNECTIONS: u32 = 4;
+
+/// 每个连接缓存的预编译语句数
+const STATEMENT_CACHE_CAPACITY: usize = 200;
+
+/// 写锁被占用时的等待时间，避免 watcher 重建索引与界面写入冲突时直接报 SQLITE_BUSY
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 记录卡片纯文本和字数已按文本块重新计算的配置项及版本
+const WORD_COUNT_BACKFILL_KEY: &str = "word_count_backfill";
+const WORD_COUNT_BACKFILL_VERSION: &str = "blocks-v1";
+
+/// 旧数据库需要补齐的列：(表, 列, DDL)
+const SCHEMA_UPGRADES: &[(&str, &str, &str)] = &[
+    ("sources", "deleted_at", "ALTER TABLE sources ADD COLUMN deleted_at INTEGER"),
+    ("cards", "pinned", "ALTER TABLE cards ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0"),
+    ("cards", "word_count", "ALTER TABLE cards ADD COLUMN word_count INTEGER NOT NULL DEFAULT 0"),
+    ("cards", "archived", "ALTER TABLE cards ADD COLUMN archived INTEGER NOT NULL DEFAULT 0"),
+    ("sources", "source_origin", "ALTER TABLE sources ADD COLUMN source_origin TEXT DEFAULT 'manual'"),
+    ("cards", "review", "ALTER TABLE cards ADD COLUMN review TEXT"),
+    ("cards", "encrypted", "ALTER TABLE cards ADD COLUMN encrypted INTEGER NOT NULL DEFAULT 0"),
+    ("cards", "deleted_at", "ALTER TABLE cards ADD COLUMN deleted_at INTEGER"),
+    ("web_snapshots", "published_at", "ALTER TABLE web_snapshots ADD COLUMN published_at INTEGER"),
+    ("web_snapshots", "word_count", "ALTER TABLE web_snapshots ADD COLUMN word_count INTEGER NOT NULL DEFAULT 0"),
+    ("web_snapshots", "reading_minutes", "ALTER TABLE web_snapshots ADD COLUMN reading_minutes INTEGER NOT NULL DEFAULT 0"),
+];
+
+/// 旧数据库需要补齐的表（幂等 DDL）
+const SCHEMA_TABLES: &[&str] = &[
+    include_str!("../migrations/007_add_source_index_meta.sql"),
+    include_str!("../migrations/009_add_chat_sessions.sql"),
+    include_str!("../migrations/014_add_highlights_fts.sql"),
+];
+
 /// 数据库管理器
 /// 使用 SQLx 提供类型安全的异步数据库操作
 pub struct Database {
     pool: SqlitePool,
+    /// 串行化 JSON 数组列（如 sources.note_ids）的读-改-写，避免并发写入互相覆盖
+    json_write_lock: tokio::sync::Mutex<()>,
 }
 
 impl Database {
@@ -45,19 +90,26 @@ impl Database {
         
         // 使用 SqliteConnectOptions 直接设置路径，这样可以更好地处理包含非 ASCII 字符的路径
         // 这是 SQLx 推荐的方式，可以避免连接字符串解析的问题
+        // PRAGMA 按连接生效，放在连接选项里才能作用于池中的每个连接
         let connect_options = SqliteConnectOptions::new()
             .filename(&absolute_path)
-            .create_if_missing(true);
+            .create_if_missing(true)
+            .foreign_keys(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .synchronous(SqliteSynchronous::Normal)
+            .busy_timeout(BUSY_TIMEOUT)
+            .statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
         
         // 创建连接池
-        let pool = SqlitePool::connect_with(connect_options).await?;
-
-        // 启用外键约束
-        sqlx::query("PRAGMA foreign_keys = ON")
-            .execute(&pool)
+        let pool = SqlitePoolOptions::new()
+            .max_connections(POOL_MAX_CONNECTIONS)
+            .connect_with(connect_options)
             .await?;
 
-        let db = Database { pool };
+        let db = Database {
+            pool,
+            json_write_lock: tokio::sync::Mutex::new(()),
+        };
         
         // 直接尝试检查一个关键表，如果失败就初始化所有表
         let schema_complete = sqlx::query_scalar::<_, i64>(
@@ -72,10 +124,84 @@ impl Database {
             eprintln!("Database schema incomplete (found {} tables), initializing...", schema_complete);
             db.initialize_schema().await?;
         }
+
+        // 为旧 vault 补齐后续新增的列
+        db.upgrade_schema().await?;
+
+        // 清理回收站中过期的文献源和卡片
+        if let Err(e) = db.purge_trashed_sources(TRASH_RETENTION_DAYS).await {
+            eprintln!("Failed to purge trashed sources: {}", e);
+        }
+        match db.purge_trashed_cards(TRASH_RETENTION_DAYS).await {
+            // 数据库位于 <vault>/.zentri/zentri.db，一并删除被清理卡片的历史版本和旧版 Markdown 源文件
+            Ok(ids) => {
+                if let Some(vault_path) = db_path.parent().and_then(Path::parent) {
+                    for id in &ids {
+                        if let Err(e) = crate::storage::delete_card_revisions(vault_path, id) {
+                            eprintln!("Failed to delete revisions of card {}: {}", id, e);
+                        }
+                    }
+                    if let Err(e) = crate::storage::delete_markdown_cards(vault_path, &ids) {
+                        eprintln!("Failed to delete markdown files of purged cards: {}", e);
+                    }
+                }
+            }
+            Err(e) => eprintln!("Failed to purge trashed cards: {}", e),
+        }
         
         Ok(db)
     }
 
+    /// 为已有数据库补齐新增列（迁移文件只在全新创建时执行）
+    async fn upgrade_schema(&self) -> AppResult<()> {
+        for (table, column, ddl) in SCHEMA_UPGRADES {
+            let exists = sqlx::query_scalar::<_, i64>(&format!(
+                "SELECT COUNT(*) FROM pragma_table_info('{}') WHERE name = ?",
+                table
+            ))
+            .bind(column)
+            .fetch_one(&self.pool)
+            .await?;
+
+            if exists == 0 {
+                eprintln!("Upgrading schema: {}.{}", table, column);
+                sqlx::query(ddl).execute(&self.pool).await?;
+            }
+        }
+
+        for ddl in SCHEMA_TABLES {
+            let sql: String = ddl
+                .lines()
+                .filter(|line| !line.trim_start().starts_with("--"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            for statement in sql.split(';').map(str::trim).filter(|s| !s.is_empty()) {
+                sqlx::query(statement).execute(&self.pool).await?;
+            }
+        }
+
+        // 按文本块重新计算纯文本和字数（旧版本的纯文本块之间没有分隔），完成后记录标记，之后不再扫描
+        if self.get_config(WORD_COUNT_BACKFILL_KEY).await?.as_deref() != Some(WORD_COUNT_BACKFILL_VERSION) {
+            let rows = sqlx::query("SELECT id, content FROM cards WHERE encrypted = 0")
+                .fetch_all(&self.pool)
+                .await?;
+            for row in rows {
+                let content: String = row.get(1);
+                let Ok(plain_text) = extract_plain_text_from_json(&content) else {
+                    continue;
+                };
+                sqlx::query("UPDATE cards SET plain_text = ?, word_count = ? WHERE id = ?")
+                    .bind(&plain_text)
+                    .bind(count_words_in_json(&content) as i64)
+                    .bind(row.get::<String, _>(0))
+                    .execute(&self.pool)
+                    .await?;
+            }
+            self.set_config(WORD_COUNT_BACKFILL_KEY, WORD_COUNT_BACKFILL_VERSION).await?;
+        }
+        Ok(())
+    }
+
     /// 初始化数据库架构（全新创建所有表）
     async fn initialize_schema(&self) -> AppResult<()> {
         // 运行所有迁移文件
@@ -85,28 +211,46 @@ impl Database {
             ("002_add_highlight_type.sql", include_str!("../migrations/002_add_highlight_type.sql")),
             ("003_add_vectors.sql", include_str!("../migrations/003_add_vectors.sql")),
             ("004_add_cards.sql", include_str!("../migrations/004_add_cards.sql")),
+            ("005_add_source_deleted_at.sql", include_str!("../migrations/005_add_source_deleted_at.sql")),
+            ("006_add_card_pinned.sql", include_str!("../migrations/006_add_card_pinned.sql")),
+            ("007_add_source_index_meta.sql", include_str!("../migrations/007_add_source_index_meta.sql")),
+            ("008_add_card_word_count.sql", include_str!("../migrations/008_add_card_word_count.sql")),
+            ("009_add_chat_sessions.sql", include_str!("../migrations/009_add_chat_sessions.sql")),
+            ("010_add_card_archived.sql", include_str!("../migrations/010_add_card_archived.sql")),
+            ("011_add_source_origin.sql", include_str!("../migrations/011_add_source_origin.sql")),
+            ("012_add_card_review.sql", include_str!("../migrations/012_add_card_review.sql")),
+            ("013_add_card_encrypted.sql", include_str!("../migrations/013_add_card_encrypted.sql")),
+            ("014_add_highlights_fts.sql", include_str!("../migrations/014_add_highlights_fts.sql")),
+            ("015_add_card_deleted_at.sql", include_str!("../migrations/015_add_card_deleted_at.sql")),
+            ("016_add_web_snapshot_published_at.sql", include_str!("../migrations/016_add_web_snapshot_published_at.sql")),
+            ("017_add_web_snapshot_reading_time.sql", include_str!("../migrations/017_add_web_snapshot_reading_time.sql")),
         ];
         
         for (filename, migration_sql) in migration_files {
             eprintln!("Running migration: {}", filename);
             
-            let statements: Vec<&str> = migration_sql
+            // 先去掉整行注释再拆分，避免注释开头的语句被整段跳过
+            let sql: String = migration_sql
+                .lines()
+                .filter(|line| !line.trim_start().starts_with("--"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let statements: Vec<&str> = sql
                 .split(';')
                 .map(|s| s.trim())
-                .filter(|s| !s.is_empty() && !s.starts_with("--"))
+                .filter(|s| !s.is_empty())
                 .collect();
             
             // 执行所有语句
             for statement in statements {
-                if statement.is_empty() {
-                    continue;
+                if let Err(e) = sqlx::query(statement).execute(&self.pool).await {
+                    // 初始架构已包含后续迁移新增的列，重复添加可忽略
+                    if e.to_string().contains("duplicate column name") {
+                        continue;
+                    }
+                    eprintln!("Failed to execute SQL statement from {}: {}\nError: {}", filename, statement, e);
+                    return Err(e.into());
                 }
-                
-                sqlx::query(statement).execute(&self.pool).await
-                    .map_err(|e| {
-                        eprintln!("Failed to execute SQL statement from {}: {}\nError: {}", filename, statement, e);
-                        e
-                    })?;
             }
         }
         
@@ -142,6 +286,14 @@ impl Database {
         Ok(())
     }
 
+    /// 回收空闲页、更新查询规划统计，并把 WAL 合并回主库文件
+    pub async fn optimize(&self) -> AppResult<()> {
+        sqlx::query("VACUUM").execute(&self.pool).await?;
+        sqlx::query("PRAGMA optimize").execute(&self.pool).await?;
+        sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)").execute(&self.pool).await?;
+        Ok(())
+    }
+
     // ==================== Source 操作 ====================
 
     /// 创建文献源
@@ -150,8 +302,8 @@ impl Database {
         let id = Uuid::new_v4().to_string();
 
         sqlx::query(
-            "INSERT INTO sources (id, type, title, author, url, cover, description, tags, progress, last_read_at, metadata, note_ids, created_at, updated_at)
-             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            "INSERT INTO sources (id, type, title, author, url, cover, description, tags, progress, last_read_at, metadata, note_ids, created_at, updated_at, source_origin)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
         )
         .bind(&id)
         .bind(req.source_type.as_str())
@@ -167,6 +319,7 @@ impl Database {
         .bind(serde_json::to_string(&Vec::<String>::new())?)
         .bind(now)
         .bind(now)
+        .bind(req.source_origin.as_ref())
         .execute(&self.pool)
         .await?;
 
@@ -185,14 +338,16 @@ impl Database {
             note_ids: vec![],
             created_at: now,
             updated_at: now,
+            deleted_at: None,
+            source_origin: req.source_origin,
         })
     }
 
     /// 获取所有文献源
     pub async fn get_all_sources(&self) -> AppResult<Vec<Source>> {
         let rows = sqlx::query(
-            "SELECT id, type, title, author, url, cover, description, tags, progress, last_read_at, metadata, note_ids, created_at, updated_at 
-             FROM sources ORDER BY updated_at DESC",
+            "SELECT id, type, title, author, url, cover, description, tags, progress, last_read_at, metadata, note_ids, created_at, updated_at, deleted_at, source_origin 
+             FROM sources WHERE deleted_at IS NULL ORDER BY updated_at DESC",
         )
         .fetch_all(&self.pool)
         .await?;
@@ -208,8 +363,8 @@ impl Database {
     /// 分页获取文献源
     pub async fn get_sources_paginated(&self, offset: usize, limit: usize) -> AppResult<Vec<Source>> {
         let rows = sqlx::query(
-            "SELECT id, type, title, author, url, cover, description, tags, progress, last_read_at, metadata, note_ids, created_at, updated_at 
-             FROM sources ORDER BY updated_at DESC LIMIT ? OFFSET ?",
+            "SELECT id, type, title, author, url, cover, description, tags, progress, last_read_at, metadata, note_ids, created_at, updated_at, deleted_at, source_origin 
+             FROM sources WHERE deleted_at IS NULL ORDER BY updated_at DESC LIMIT ? OFFSET ?",
         )
         .bind(limit as i64)
         .bind(offset as i64)
@@ -224,19 +379,76 @@ impl Database {
         Ok(sources)
     }
 
+    /// 按类型、语言、内容格式和来源途径过滤文献源
+    pub async fn get_sources_filtered(&self, filter: &SourceFilter) -> AppResult<Vec<Source>> {
+        let language = filter
+            .language
+            .as_deref()
+            .and_then(SourceMetadata::normalize_language)
+            .map(|l| l.to_lowercase());
+        let content_format = filter.content_format.as_ref().map(|f| f.to_lowercase());
+        let source_origin = filter.source_origin.as_ref().map(|o| o.to_lowercase());
+
+        let rows = sqlx::query(
+            "SELECT id, type, title, author, url, cover, description, tags, progress, last_read_at, metadata, note_ids, created_at, updated_at, deleted_at, source_origin 
+             FROM sources 
+             WHERE deleted_at IS NULL 
+               AND (?1 IS NULL OR type = ?1) 
+               AND (?2 IS NULL OR lower(json_extract(metadata, '$.language')) = ?2 
+                    OR lower(json_extract(metadata, '$.language')) LIKE ?2 || '-%') 
+               AND (?3 IS NULL OR lower(json_extract(metadata, '$.contentFormat')) = ?3) 
+               AND (?4 IS NULL OR source_origin = ?4) 
+             ORDER BY updated_at DESC",
+        )
+        .bind(filter.source_type.as_ref().map(|t| t.as_str()))
+        .bind(language)
+        .bind(content_format)
+        .bind(source_origin)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut sources = Vec::new();
+        for row in rows {
+            sources.push(self.row_to_source(row)?);
+        }
+
+        Ok(sources)
+    }
+
     /// 获取文献源总数
     pub async fn get_sources_count(&self) -> AppResult<usize> {
-        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sources")
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sources WHERE deleted_at IS NULL")
             .fetch_one(&self.pool)
             .await?;
         Ok(count as usize)
     }
 
+    /// 获取"继续阅读"列表：进度在 0-100 之间，按最近阅读时间倒序
+    pub async fn get_continue_reading(&self, limit: usize) -> AppResult<Vec<Source>> {
+        let rows = sqlx::query(
+            "SELECT id, type, title, author, url, cover, description, tags, progress, last_read_at, metadata, note_ids, created_at, updated_at, deleted_at, source_origin 
+             FROM sources 
+             WHERE progress > 0 AND progress < 100 AND deleted_at IS NULL 
+             ORDER BY last_read_at IS NULL, last_read_at DESC, updated_at DESC 
+             LIMIT ?",
+        )
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut sources = Vec::new();
+        for row in rows {
+            sources.push(self.row_to_source(row)?);
+        }
+
+        Ok(sources)
+    }
+
     /// 获取单个文献源
     pub async fn get_source(&self, id: &str) -> AppResult<Option<Source>> {
         let row = sqlx::query(
-            "SELECT id, type, title, author, url, cover, description, tags, progress, last_read_at, metadata, note_ids, created_at, updated_at 
-             FROM sources WHERE id = ?",
+            "SELECT id, type, title, author, url, cover, description, tags, progress, last_read_at, metadata, note_ids, created_at, updated_at, deleted_at, source_origin 
+             FROM sources WHERE id = ? AND deleted_at IS NULL",
         )
         .bind(id)
         .fetch_optional(&self.pool)
@@ -255,6 +467,8 @@ impl Database {
 
         // 使用 COALESCE 实现可选更新
         let tags_json = req.tags.as_ref().map(|t| serde_json::to_string(t).unwrap_or_default());
+        // 更新阅读进度时同步刷新最近阅读时间
+        let last_read_at = req.last_read_at.or(req.progress.map(|_| now));
         
         sqlx::query(
             "UPDATE sources SET 
@@ -275,7 +489,7 @@ impl Database {
         .bind(req.cover.as_ref())
         .bind(req.description.as_ref())
         .bind(req.progress)
-        .bind(req.last_read_at)
+        .bind(last_read_at)
         .bind(tags_json.as_ref())
         .bind(now)
         .bind(id)
@@ -321,6 +535,12 @@ impl Database {
             if new_metadata.last_cfi.is_some() {
                 existing_metadata.last_cfi = new_metadata.last_cfi;
             }
+            if new_metadata.language.is_some() {
+                existing_metadata.language = new_metadata.language;
+            }
+            if new_metadata.content_format.is_some() {
+                existing_metadata.content_format = new_metadata.content_format;
+            }
             
             sqlx::query("UPDATE sources SET metadata = ? WHERE id = ?")
                 .bind(serde_json::to_string(&existing_metadata).ok())
@@ -332,18 +552,86 @@ impl Database {
         self.get_source(id).await
     }
 
-    /// 删除文献源
+    /// 删除文献源（软删除，移入回收站，高亮等关联数据保持不变）
     pub async fn delete_source(&self, id: &str) -> AppResult<()> {
+        sqlx::query("UPDATE sources SET deleted_at = ? WHERE id = ? AND deleted_at IS NULL")
+            .bind(Utc::now().timestamp_millis())
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// 修改文献源加入资料库的途径
+    pub async fn set_source_origin(&self, id: &str, origin: &str) -> AppResult<()> {
+        sqlx::query("UPDATE sources SET source_origin = ? WHERE id = ?")
+            .bind(origin)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// 永久删除文献源（级联删除高亮、书签和快照）
+    pub async fn hard_delete_source(&self, id: &str) -> AppResult<()> {
         sqlx::query("DELETE FROM sources WHERE id = ?")
             .bind(id)
             .execute(&self.pool)
             .await?;
+        self.prune_highlights_fts().await
+    }
+
+    /// 从回收站恢复文献源
+    pub async fn restore_source(&self, id: &str) -> AppResult<Option<Source>> {
+        sqlx::query("UPDATE sources SET deleted_at = NULL WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        self.get_source(id).await
+    }
+
+    /// 获取回收站中的文献源
+    pub async fn get_trashed_sources(&self) -> AppResult<Vec<Source>> {
+        let rows = sqlx::query(
+            "SELECT id, type, title, author, url, cover, description, tags, progress, last_read_at, metadata, note_ids, created_at, updated_at, deleted_at, source_origin 
+             FROM sources WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut sources = Vec::new();
+        for row in rows {
+            sources.push(self.row_to_source(row)?);
+        }
+
+        Ok(sources)
+    }
+
+    /// 永久删除回收站中超过指定天数的文献源，返回删除数量
+    pub async fn purge_trashed_sources(&self, older_than_days: u32) -> AppResult<u64> {
+        let cutoff = Utc::now().timestamp_millis() - i64::from(older_than_days) * 24 * 60 * 60 * 1000;
+        let result = sqlx::query("DELETE FROM sources WHERE deleted_at IS NOT NULL AND deleted_at <= ?")
+            .bind(cutoff)
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() > 0 {
+            self.prune_highlights_fts().await?;
+        }
+        Ok(result.rows_affected())
+    }
+
+    /// 清理全文索引中已随文献源级联删除的高亮
+    async fn prune_highlights_fts(&self) -> AppResult<()> {
+        sqlx::query("DELETE FROM highlights_fts WHERE highlight_id NOT IN (SELECT id FROM highlights)")
+            .execute(&self.pool)
+            .await?;
         Ok(())
     }
 
     /// 添加笔记 ID 到文献源
     pub async fn add_note_to_source(&self, source_id: &str, note_id: &str) -> AppResult<()> {
         let now = Utc::now().timestamp_millis();
+        let _guard = self.json_write_lock.lock().await;
 
         // 获取当前 note_ids
         let row = sqlx::query("SELECT note_ids FROM sources WHERE id = ?")
@@ -369,6 +657,95 @@ impl Database {
         Ok(())
     }
 
+    /// 从文献源移除笔记 ID
+    pub async fn remove_note_from_source(&self, source_id: &str, note_id: &str) -> AppResult<()> {
+        let now = Utc::now().timestamp_millis();
+        let _guard = self.json_write_lock.lock().await;
+
+        let row = sqlx::query("SELECT note_ids FROM sources WHERE id = ?")
+            .bind(source_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        if let Some(row) = row {
+            let note_ids_str: String = row.get(0);
+            let mut note_ids: Vec<String> = serde_json::from_str(&note_ids_str).unwrap_or_default();
+            let before = note_ids.len();
+            note_ids.retain(|id| id != note_id);
+
+            if note_ids.len() != before {
+                sqlx::query("UPDATE sources SET note_ids = ?, updated_at = ? WHERE id = ?")
+                    .bind(serde_json::to_string(&note_ids)?)
+                    .bind(now)
+                    .bind(source_id)
+                    .execute(&self.pool)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 统计未删除文献源的标签使用次数，按次数降序、名称升序排列
+    pub async fn get_source_tag_counts(&self) -> AppResult<Vec<(String, usize)>> {
+        let rows = sqlx::query("SELECT tags FROM sources WHERE deleted_at IS NULL")
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for row in rows {
+            let tags_str: String = row.get(0);
+            let tags: Vec<String> = serde_json::from_str(&tags_str).unwrap_or_default();
+            for tag in tags {
+                *counts.entry(tag).or_default() += 1;
+            }
+        }
+
+        let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(counts)
+    }
+
+    /// 在一个事务中把所有文献源（含回收站）的 `from` 标签替换为 `into` 并去重，
+    /// 返回被修改的文献源 ID
+    pub async fn merge_source_tags(&self, from: &[String], into: &str) -> AppResult<Vec<String>> {
+        let now = Utc::now().timestamp_millis();
+        let mut tx = self.pool.begin().await?;
+
+        let rows = sqlx::query("SELECT id, tags FROM sources")
+            .fetch_all(&mut *tx)
+            .await?;
+
+        let mut changed = Vec::new();
+        for row in rows {
+            let id: String = row.get(0);
+            let tags_str: String = row.get(1);
+            let tags: Vec<String> = serde_json::from_str(&tags_str).unwrap_or_default();
+            if !tags.iter().any(|t| from.contains(t)) {
+                continue;
+            }
+
+            let mut merged: Vec<String> = Vec::with_capacity(tags.len());
+            for tag in tags {
+                let tag = if from.contains(&tag) { into.to_string() } else { tag };
+                if !merged.contains(&tag) {
+                    merged.push(tag);
+                }
+            }
+
+            sqlx::query("UPDATE sources SET tags = ?, updated_at = ? WHERE id = ?")
+                .bind(serde_json::to_string(&merged)?)
+                .bind(now)
+                .bind(&id)
+                .execute(&mut *tx)
+                .await?;
+            changed.push(id);
+        }
+
+        tx.commit().await?;
+        Ok(changed)
+    }
+
     /// 将数据库行转换为 Source
     fn row_to_source(&self, row: sqlx::sqlite::SqliteRow) -> AppResult<Source> {
         let tags_str: String = row.get(7);
@@ -390,6 +767,8 @@ impl Database {
             note_ids: serde_json::from_str(&note_ids_str).unwrap_or_default(),
             created_at: row.get(12),
             updated_at: row.get(13),
+            deleted_at: row.get(14),
+            source_origin: row.get(15),
         })
     }
 
@@ -406,6 +785,7 @@ impl Database {
             crate::models::AnnotationType::Strikethrough => "strikethrough",
         });
 
+        let mut tx = self.pool.begin().await?;
         sqlx::query(
             "INSERT INTO highlights (id, source_id, card_id, content, note, position, color, type, created_at)
              VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
@@ -419,9 +799,17 @@ impl Database {
         .bind(req.color.as_ref())
         .bind(type_str)
         .bind(now)
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?;
 
+        sqlx::query("INSERT INTO highlights_fts (highlight_id, content, note) VALUES (?, ?, ?)")
+            .bind(&id)
+            .bind(&req.content)
+            .bind(req.note.as_deref().unwrap_or(""))
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
         Ok(Highlight {
             id,
             source_id: req.source_id,
@@ -478,22 +866,35 @@ impl Database {
             crate::models::AnnotationType::Strikethrough => "strikethrough",
         });
         
+        let mut tx = self.pool.begin().await?;
         sqlx::query(
             "UPDATE highlights SET 
                 note = COALESCE(?, note),
                 color = COALESCE(?, color),
                 type = COALESCE(?, type),
-                card_id = COALESCE(?, card_id)
+                card_id = COALESCE(?, card_id),
+                position = COALESCE(?, position)
              WHERE id = ?",
         )
         .bind(req.note.as_ref())
         .bind(req.color.as_ref())
         .bind(type_str.as_ref())
         .bind(req.card_id.as_ref())
+        .bind(req.position.as_ref().map(|p| serde_json::to_string(p).unwrap_or_default()))
         .bind(id)
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?;
 
+        // 高亮原文不可修改，索引只需跟随笔记
+        if let Some(note) = &req.note {
+            sqlx::query("UPDATE highlights_fts SET note = ? WHERE highlight_id = ?")
+                .bind(note)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
         self.get_highlight(id).await
     }
 
@@ -516,13 +917,79 @@ impl Database {
 
     /// 删除高亮
     pub async fn delete_highlight(&self, id: &str) -> AppResult<()> {
+        let mut tx = self.pool.begin().await?;
         sqlx::query("DELETE FROM highlights WHERE id = ?")
             .bind(id)
-            .execute(&self.pool)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM highlights_fts WHERE highlight_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
             .await?;
+        tx.commit().await?;
         Ok(())
     }
 
+    /// 全文搜索高亮原文和笔记，按 bm25 相关度排序，可限定文献源。
+    /// trigram 索引只能匹配不少于 3 个字符的词，更短的词退回 LIKE 子串匹配
+    pub async fn search_highlights(&self, query: &str, source_id: Option<&str>) -> AppResult<Vec<Highlight>> {
+        let (long_terms, short_terms): (Vec<&str>, Vec<&str>) =
+            query.split_whitespace().partition(|t| t.chars().count() >= 3);
+        if long_terms.is_empty() && short_terms.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut sql = String::from(
+            "SELECT h.id, h.source_id, h.card_id, h.content, h.note, h.position, h.color, h.type, h.created_at
+             FROM highlights h",
+        );
+        if !long_terms.is_empty() {
+            sql.push_str(" JOIN highlights_fts f ON f.highlight_id = h.id WHERE highlights_fts MATCH ?");
+        } else {
+            sql.push_str(" WHERE 1 = 1");
+        }
+        for _ in &short_terms {
+            sql.push_str(" AND (h.content LIKE ? ESCAPE '\\' OR h.note LIKE ? ESCAPE '\\')");
+        }
+        if source_id.is_some() {
+            sql.push_str(" AND h.source_id = ?");
+        }
+        sql.push_str(if long_terms.is_empty() {
+            " ORDER BY h.created_at DESC"
+        } else {
+            " ORDER BY bm25(highlights_fts), h.created_at DESC"
+        });
+
+        let mut q = sqlx::query(&sql);
+        if !long_terms.is_empty() {
+            // 每个词都加引号作为字面量，避免用户输入被当成 FTS5 语法
+            let fts_query = long_terms
+                .iter()
+                .map(|t| format!("\"{}\"", t.replace('"', "\"\"")))
+                .collect::<Vec<_>>()
+                .join(" ");
+            q = q.bind(fts_query);
+        }
+        for term in &short_terms {
+            let pattern = format!(
+                "%{}%",
+                term.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+            );
+            q = q.bind(pattern.clone()).bind(pattern);
+        }
+        if let Some(source_id) = source_id {
+            q = q.bind(source_id);
+        }
+
+        let rows = q.fetch_all(&self.pool).await?;
+        let mut highlights = Vec::new();
+        for row in rows {
+            highlights.push(self.row_to_highlight(row)?);
+        }
+
+        Ok(highlights)
+    }
+
     /// 获取卡片关联的高亮
     pub async fn get_highlights_by_card(&self, card_id: &str) -> AppResult<Vec<Highlight>> {
         let rows = sqlx::query(
@@ -567,6 +1034,7 @@ impl Database {
                 highlight_content: row.get(2),
                 page: position.as_ref().and_then(|p| p.page),
                 cfi: position.as_ref().and_then(|p| p.cfi.clone()),
+                context: None,
             });
         }
 
@@ -601,8 +1069,8 @@ impl Database {
     pub async fn save_web_snapshot(&self, snapshot: &WebSnapshot) -> AppResult<()> {
         sqlx::query(
             "INSERT OR REPLACE INTO web_snapshots 
-             (id, source_id, original_url, title, author, site_name, content, text_content, excerpt, created_at)
-             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+             (id, source_id, original_url, title, author, site_name, content, text_content, excerpt, published_at, created_at, word_count, reading_minutes)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
         )
         .bind(&snapshot.id)
         .bind(&snapshot.source_id)
@@ -613,7 +1081,10 @@ impl Database {
         .bind(&snapshot.content)
         .bind(&snapshot.text_content)
         .bind(snapshot.excerpt.as_ref())
+        .bind(snapshot.published_at)
         .bind(snapshot.created_at)
+        .bind(snapshot.word_count as i64)
+        .bind(snapshot.reading_minutes as i64)
         .execute(&self.pool)
         .await?;
 
@@ -623,7 +1094,7 @@ impl Database {
     /// 获取网页快照
     pub async fn get_web_snapshot(&self, source_id: &str) -> AppResult<Option<WebSnapshot>> {
         let row = sqlx::query(
-            "SELECT id, source_id, original_url, title, author, site_name, content, text_content, excerpt, created_at 
+            "SELECT id, source_id, original_url, title, author, site_name, content, text_content, excerpt, created_at, published_at, word_count, reading_minutes
              FROM web_snapshots WHERE source_id = ?",
         )
         .bind(source_id)
@@ -641,6 +1112,9 @@ impl Database {
                 content: row.get(6),
                 text_content: row.get(7),
                 excerpt: row.get(8),
+                published_at: row.get(10),
+                word_count: row.get::<i64, _>(11) as usize,
+                reading_minutes: row.get::<i64, _>(12) as usize,
                 created_at: row.get(9),
             }))
         } else {
@@ -648,6 +1122,28 @@ impl Database {
         }
     }
 
+    /// 按规范化后的网页地址查找未删除文献源的快照（最早保存的优先，content 可能为空）
+    pub async fn find_snapshot_by_url(&self, url: &str) -> AppResult<Option<WebSnapshot>> {
+        let target = crate::web_reader::normalize_url(url);
+        let rows = sqlx::query(
+            "SELECT w.source_id, w.original_url FROM web_snapshots w
+             JOIN sources s ON s.id = w.source_id
+             WHERE s.deleted_at IS NULL
+             ORDER BY w.created_at",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        for row in rows {
+            let original_url: String = row.get(1);
+            if crate::web_reader::normalize_url(&original_url) == target {
+                let source_id: String = row.get(0);
+                return self.get_web_snapshot(&source_id).await;
+            }
+        }
+        Ok(None)
+    }
+
     /// 删除网页快照
     pub async fn delete_web_snapshot(&self, source_id: &str) -> AppResult<()> {
         sqlx::query("DELETE FROM web_snapshots WHERE source_id = ?")
@@ -663,8 +1159,8 @@ impl Database {
         // text_content 仍然保存在数据库中用于搜索
         sqlx::query(
             "INSERT OR REPLACE INTO web_snapshots 
-             (id, source_id, original_url, title, author, site_name, content, text_content, excerpt, created_at)
-             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+             (id, source_id, original_url, title, author, site_name, content, text_content, excerpt, published_at, created_at, word_count, reading_minutes)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
         )
         .bind(&snapshot.id)
         .bind(&snapshot.source_id)
@@ -675,7 +1171,10 @@ impl Database {
         .bind("") // content 存储在文件系统中，这里留空或存储路径引用
         .bind(&snapshot.text_content)
         .bind(snapshot.excerpt.as_ref())
+        .bind(snapshot.published_at)
         .bind(snapshot.created_at)
+        .bind(snapshot.word_count as i64)
+        .bind(snapshot.reading_minutes as i64)
         .execute(&self.pool)
         .await?;
 
@@ -685,7 +1184,7 @@ impl Database {
     /// 获取网页快照元数据（不包含 content）
     pub async fn get_web_snapshot_metadata(&self, source_id: &str) -> AppResult<Option<WebSnapshot>> {
         let row = sqlx::query(
-            "SELECT id, source_id, original_url, title, author, site_name, content, text_content, excerpt, created_at 
+            "SELECT id, source_id, original_url, title, author, site_name, content, text_content, excerpt, created_at, published_at, word_count, reading_minutes
              FROM web_snapshots WHERE source_id = ?",
         )
         .bind(source_id)
@@ -703,6 +1202,9 @@ impl Database {
                 content: String::new(), // 从文件系统读取
                 text_content: row.get(7),
                 excerpt: row.get(8),
+                published_at: row.get(10),
+                word_count: row.get::<i64, _>(11) as usize,
+                reading_minutes: row.get::<i64, _>(12) as usize,
                 created_at: row.get(9),
             }))
         } else {
@@ -914,10 +1416,11 @@ impl Database {
         // 从 content 中提取 plain_text 和 preview（简化版，实际应该在 Service 层处理）
         let plain_text = extract_plain_text_from_json(&req.content).unwrap_or_default();
         let preview = generate_preview_from_json(&req.content, 200);
+        let word_count = count_words_in_json(&req.content);
 
         sqlx::query(
-            "INSERT INTO cards (id, title, type, content, plain_text, preview, tags, aliases, links, source_id, created_at, updated_at)
-             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            "INSERT INTO cards (id, title, type, content, plain_text, preview, tags, aliases, links, source_id, created_at, updated_at, word_count)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
         )
         .bind(&id)
         .bind(&req.title)
@@ -931,6 +1434,7 @@ impl Database {
         .bind(req.source_id.as_ref())
         .bind(now)
         .bind(now)
+        .bind(word_count as i64)
         .execute(&self.pool)
         .await?;
 
@@ -951,14 +1455,19 @@ impl Database {
             aliases: req.aliases,
             links,
             source_id: req.source_id,
+            pinned: false,
+            word_count,
+            archived: false,
+            review: None,
+            encrypted: false,
         })
     }
 
     /// 获取单个卡片
     pub async fn get_card(&self, id: &str) -> AppResult<Option<Card>> {
         let row = sqlx::query(
-            "SELECT id, title, type, content, plain_text, preview, tags, aliases, links, source_id, created_at, updated_at 
-             FROM cards WHERE id = ?",
+            "SELECT id, title, type, content, plain_text, preview, tags, aliases, links, source_id, created_at, updated_at, pinned, word_count, archived, review, encrypted
+             FROM cards WHERE id = ? AND deleted_at IS NULL",
         )
         .bind(id)
         .fetch_optional(&self.pool)
@@ -971,12 +1480,13 @@ impl Database {
         }
     }
 
-    /// 获取所有卡片
-    pub async fn get_all_cards(&self) -> AppResult<Vec<Card>> {
+    /// 获取所有卡片，`include_archived` 为 false 时跳过已归档卡片
+    pub async fn get_all_cards(&self, include_archived: bool) -> AppResult<Vec<Card>> {
         let rows = sqlx::query(
-            "SELECT id, title, type, content, plain_text, preview, tags, aliases, links, source_id, created_at, updated_at 
-             FROM cards ORDER BY updated_at DESC",
+            "SELECT id, title, type, content, plain_text, preview, tags, aliases, links, source_id, created_at, updated_at, pinned, word_count, archived, review, encrypted
+             FROM cards WHERE deleted_at IS NULL AND (archived = 0 OR ?) ORDER BY pinned DESC, updated_at DESC",
         )
+        .bind(include_archived)
         .fetch_all(&self.pool)
         .await?;
 
@@ -991,8 +1501,8 @@ impl Database {
     /// 按类型获取卡片
     pub async fn get_cards_by_type(&self, card_type: CardType) -> AppResult<Vec<Card>> {
         let rows = sqlx::query(
-            "SELECT id, title, type, content, plain_text, preview, tags, aliases, links, source_id, created_at, updated_at 
-             FROM cards WHERE type = ? ORDER BY updated_at DESC",
+            "SELECT id, title, type, content, plain_text, preview, tags, aliases, links, source_id, created_at, updated_at, pinned, word_count, archived, review, encrypted
+             FROM cards WHERE type = ? AND deleted_at IS NULL ORDER BY pinned DESC, updated_at DESC",
         )
         .bind(card_type.as_str())
         .fetch_all(&self.pool)
@@ -1009,8 +1519,8 @@ impl Database {
     /// 按文献源获取卡片
     pub async fn get_cards_by_source(&self, source_id: &str) -> AppResult<Vec<Card>> {
         let rows = sqlx::query(
-            "SELECT id, title, type, content, plain_text, preview, tags, aliases, links, source_id, created_at, updated_at 
-             FROM cards WHERE source_id = ? ORDER BY updated_at DESC",
+            "SELECT id, title, type, content, plain_text, preview, tags, aliases, links, source_id, created_at, updated_at, pinned, word_count, archived, review, encrypted
+             FROM cards WHERE source_id = ? AND deleted_at IS NULL ORDER BY updated_at DESC",
         )
         .bind(source_id)
         .fetch_all(&self.pool)
@@ -1027,8 +1537,8 @@ impl Database {
     /// 分页获取卡片
     pub async fn get_cards_paginated(&self, offset: usize, limit: usize) -> AppResult<Vec<Card>> {
         let rows = sqlx::query(
-            "SELECT id, title, type, content, plain_text, preview, tags, aliases, links, source_id, created_at, updated_at 
-             FROM cards ORDER BY updated_at DESC LIMIT ? OFFSET ?",
+            "SELECT id, title, type, content, plain_text, preview, tags, aliases, links, source_id, created_at, updated_at, pinned, word_count, archived, review, encrypted
+             FROM cards WHERE deleted_at IS NULL ORDER BY pinned DESC, updated_at DESC LIMIT ? OFFSET ?",
         )
         .bind(limit as i64)
         .bind(offset as i64)
@@ -1071,6 +1581,7 @@ impl Database {
             None
         };
         let links_json = links.as_ref().map(|l| serde_json::to_string(l).unwrap_or_default());
+        let word_count = req.content.as_deref().map(|c| count_words_in_json(c) as i64);
 
         sqlx::query(
             "UPDATE cards SET 
@@ -1082,6 +1593,7 @@ impl Database {
                 tags = COALESCE(?, tags),
                 aliases = COALESCE(?, aliases),
                 links = COALESCE(?, links),
+                word_count = COALESCE(?, word_count),
                 updated_at = ?
              WHERE id = ?",
         )
@@ -1093,6 +1605,7 @@ impl Database {
         .bind(tags_json.as_ref())
         .bind(aliases_json.as_ref())
         .bind(links_json.as_ref())
+        .bind(word_count)
         .bind(now)
         .bind(id)
         .execute(&self.pool)
@@ -1101,18 +1614,306 @@ impl Database {
         self.get_card(id).await
     }
 
-    /// 删除卡片
+    /// 删除卡片（软删除，移入回收站，正文和链接保持不变）
     pub async fn delete_card(&self, id: &str) -> AppResult<()> {
-        sqlx::query("DELETE FROM cards WHERE id = ?")
+        sqlx::query("UPDATE cards SET deleted_at = ? WHERE id = ? AND deleted_at IS NULL")
+            .bind(Utc::now().timestamp_millis())
             .bind(id)
             .execute(&self.pool)
             .await?;
         Ok(())
     }
 
+    /// 从回收站恢复卡片
+    pub async fn restore_card(&self, id: &str) -> AppResult<Option<Card>> {
+        sqlx::query("UPDATE cards SET deleted_at = NULL WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        self.get_card(id).await
+    }
+
+    /// 获取回收站中的卡片（不含正文），最近删除的在前
+    pub async fn get_trashed_cards(&self) -> AppResult<Vec<TrashedCard>> {
+        let rows = sqlx::query(
+            "SELECT id, title, type, preview, tags, aliases, links, source_id, created_at, updated_at, pinned, word_count, archived, review, encrypted, deleted_at
+             FROM cards WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let deleted_at = row.get(15);
+                TrashedCard {
+                    card: Self::row_to_list_item(row),
+                    deleted_at,
+                }
+            })
+            .collect())
+    }
+
+    /// 回收站中所有卡片的 ID
+    pub async fn get_trashed_card_ids(&self) -> AppResult<Vec<String>> {
+        let ids = sqlx::query_scalar("SELECT id FROM cards WHERE deleted_at IS NOT NULL")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(ids)
+    }
+
+    /// 获取 ID 以指定前缀开头的卡片 ID（不含回收站）
+    pub async fn get_card_ids_with_prefix(&self, prefix: &str) -> AppResult<Vec<String>> {
+        let ids = sqlx::query_scalar(
+            "SELECT id FROM cards WHERE deleted_at IS NULL AND substr(id, 1, length(?1)) = ?1",
+        )
+        .bind(prefix)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(ids)
+    }
+
+    /// 永久删除回收站中超过指定天数的卡片，返回被删除卡片的 ID
+    pub async fn purge_trashed_cards(&self, older_than_days: u32) -> AppResult<Vec<String>> {
+        let cutoff = Utc::now().timestamp_millis() - i64::from(older_than_days) * 24 * 60 * 60 * 1000;
+        let ids = sqlx::query_scalar(
+            "DELETE FROM cards WHERE deleted_at IS NOT NULL AND deleted_at <= ? RETURNING id",
+        )
+        .bind(cutoff)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(ids)
+    }
+
+    /// 设置卡片置顶状态（不修改 updated_at，避免打乱最近编辑排序）
+    pub async fn set_card_pinned(&self, id: &str, pinned: bool) -> AppResult<Option<Card>> {
+        sqlx::query("UPDATE cards SET pinned = ? WHERE id = ?")
+            .bind(pinned as i64)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        self.get_card(id).await
+    }
+
+    /// 设置卡片归档状态（不修改 updated_at）
+    pub async fn set_card_archived(&self, id: &str, archived: bool) -> AppResult<Option<Card>> {
+        sqlx::query("UPDATE cards SET archived = ? WHERE id = ?")
+            .bind(archived as i64)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        self.get_card(id).await
+    }
+
+    /// 写入卡片正文并设置加密标记；加密卡片不保存纯文本、预览和链接，避免明文落盘
+    pub async fn set_card_encrypted(
+        &self,
+        id: &str,
+        content: &str,
+        encrypted: bool,
+    ) -> AppResult<Option<Card>> {
+        let now = Utc::now().timestamp_millis();
+        let (plain_text, preview, links) = if encrypted {
+            (String::new(), None, Vec::new())
+        } else {
+            (
+                extract_plain_text_from_json(content).unwrap_or_default(),
+                generate_preview_from_json(content, 200),
+                extract_links_from_json(content),
+            )
+        };
+
+        sqlx::query(
+            "UPDATE cards SET content = ?, plain_text = ?, preview = ?, links = ?, word_count = ?, encrypted = ?, updated_at = ?
+             WHERE id = ?",
+        )
+        .bind(content)
+        .bind(&plain_text)
+        .bind(preview.as_ref())
+        .bind(serde_json::to_string(&links)?)
+        .bind(if encrypted { 0 } else { count_words_in_json(content) as i64 })
+        .bind(encrypted as i64)
+        .bind(now)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        self.get_card(id).await
+    }
+
+    /// 在一个事务中修改多张卡片的类型，返回实际更新的 ID；出错时整体回滚
+    pub async fn set_cards_type(&self, ids: &[String], card_type: &CardType) -> AppResult<Vec<String>> {
+        let now = Utc::now().timestamp_millis();
+        let mut tx = self.pool.begin().await?;
+        let mut updated = Vec::new();
+        for id in ids {
+            let result = sqlx::query("UPDATE cards SET type = ?, updated_at = ? WHERE id = ?")
+                .bind(card_type.as_str())
+                .bind(now)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            if result.rows_affected() > 0 {
+                updated.push(id.clone());
+            }
+        }
+        tx.commit().await?;
+        Ok(updated)
+    }
+
+    /// 在一个事务中把所有卡片（含回收站）的 `from` 标签替换为 `into` 并去重，`into` 为 None 时直接移除，
+    /// 返回被修改的卡片 ID（不修改 updated_at）
+    pub async fn merge_card_tags(&self, from: &[String], into: Option<&str>) -> AppResult<Vec<String>> {
+        let mut tx = self.pool.begin().await?;
+
+        let rows = sqlx::query("SELECT id, tags FROM cards")
+            .fetch_all(&mut *tx)
+            .await?;
+
+        let mut changed = Vec::new();
+        for row in rows {
+            let id: String = row.get(0);
+            let tags_str: String = row.get(1);
+            let tags: Vec<String> = serde_json::from_str(&tags_str).unwrap_or_default();
+            if !tags.iter().any(|t| from.contains(t)) {
+                continue;
+            }
+
+            let mut merged: Vec<String> = Vec::with_capacity(tags.len());
+            for tag in tags {
+                let tag = if from.contains(&tag) {
+                    match into {
+                        Some(into) => into.to_string(),
+                        None => continue,
+                    }
+                } else {
+                    tag
+                };
+                if !merged.contains(&tag) {
+                    merged.push(tag);
+                }
+            }
+
+            sqlx::query("UPDATE cards SET tags = ? WHERE id = ?")
+                .bind(serde_json::to_string(&merged)?)
+                .bind(&id)
+                .execute(&mut *tx)
+                .await?;
+            changed.push(id);
+        }
+
+        tx.commit().await?;
+        Ok(changed)
+    }
+
+    /// 获取所有已归档卡片的 ID
+    pub async fn get_archived_card_ids(&self) -> AppResult<Vec<String>> {
+        let ids = sqlx::query_scalar("SELECT id FROM cards WHERE archived = 1 AND deleted_at IS NULL")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(ids)
+    }
+
+    /// 获取所有置顶卡片
+    pub async fn get_pinned_cards(&self) -> AppResult<Vec<Card>> {
+        let rows = sqlx::query(
+            "SELECT id, title, type, content, plain_text, preview, tags, aliases, links, source_id, created_at, updated_at, pinned, word_count, archived, review, encrypted
+             FROM cards WHERE pinned = 1 AND archived = 0 AND deleted_at IS NULL ORDER BY updated_at DESC",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut cards = Vec::new();
+        for row in rows {
+            cards.push(self.row_to_card(row)?);
+        }
+
+        Ok(cards)
+    }
+
+    /// 获取卡片列表（不加载正文），`include_archived` 为 false 时跳过已归档卡片
+    pub async fn get_card_list(
+        &self,
+        sort: CardListSort,
+        include_archived: bool,
+    ) -> AppResult<Vec<CardListItem>> {
+        let order_by = match sort {
+            CardListSort::Modified => "pinned DESC, updated_at DESC",
+            CardListSort::WordCount => "word_count DESC, updated_at DESC",
+        };
+        let rows = sqlx::query(&format!(
+            "SELECT id, title, type, preview, tags, aliases, links, source_id, created_at, updated_at, pinned, word_count, archived, review, encrypted
+             FROM cards WHERE deleted_at IS NULL AND (archived = 0 OR ?) ORDER BY {}",
+            order_by
+        ))
+        .bind(include_archived)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(Self::row_to_list_item).collect())
+    }
+
+    /// 设置卡片复习状态，None 表示移出复习（不修改 updated_at）
+    pub async fn set_card_review(&self, id: &str, review: Option<&ReviewState>) -> AppResult<Option<Card>> {
+        let review_json = review.map(serde_json::to_string).transpose()?;
+        sqlx::query("UPDATE cards SET review = ? WHERE id = ?")
+            .bind(review_json)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        self.get_card(id).await
+    }
+
+    /// 获取到期需要复习的卡片（不含已归档），最早到期的在前
+    pub async fn get_due_cards(&self, now: i64) -> AppResult<Vec<CardListItem>> {
+        let rows = sqlx::query(
+            "SELECT id, title, type, preview, tags, aliases, links, source_id, created_at, updated_at, pinned, word_count, archived, review, encrypted
+             FROM cards
+             WHERE archived = 0 AND deleted_at IS NULL AND review IS NOT NULL AND json_extract(review, '$.dueAt') <= ?
+             ORDER BY json_extract(review, '$.dueAt') ASC",
+        )
+        .bind(now)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(Self::row_to_list_item).collect())
+    }
+
+    /// 将不含正文的查询行转换为列表项
+    fn row_to_list_item(row: sqlx::sqlite::SqliteRow) -> CardListItem {
+        let tags_str: String = row.get(4);
+        let aliases_str: String = row.get(5);
+        let links_str: String = row.get(6);
+        let mut card = Card {
+            id: row.get(0),
+            path: None,
+            title: row.get(1),
+            card_type: CardType::from_str(&row.get::<String, _>(2)),
+            content: String::new(),
+            plain_text: String::new(),
+            preview: row.get(3),
+            tags: serde_json::from_str(&tags_str).unwrap_or_default(),
+            aliases: serde_json::from_str(&aliases_str).unwrap_or_default(),
+            links: serde_json::from_str(&links_str).unwrap_or_default(),
+            source_id: row.get(7),
+            created_at: row.get(8),
+            modified_at: row.get(9),
+            pinned: row.get::<i64, _>(10) != 0,
+            word_count: row.get::<i64, _>(11) as usize,
+            archived: row.get::<i64, _>(12) != 0,
+            review: parse_review(row.get(13)),
+            encrypted: row.get::<i64, _>(14) != 0,
+        };
+        card.path = Some(card.generate_path());
+        card.into()
+    }
+
     /// 获取卡片的所有链接
     pub async fn get_card_links(&self, card_id: &str) -> AppResult<Vec<String>> {
-        let row = sqlx::query("SELECT links FROM cards WHERE id = ?")
+        let row = sqlx::query("SELECT links FROM cards WHERE id = ? AND deleted_at IS NULL")
             .bind(card_id)
             .fetch_optional(&self.pool)
             .await?;
@@ -1129,8 +1930,8 @@ impl Database {
     pub async fn get_backlinks(&self, card_id: &str) -> AppResult<Vec<Card>> {
         // 查找所有 links 字段包含 card_id 的卡片
         let rows = sqlx::query(
-            "SELECT id, title, type, content, plain_text, preview, tags, aliases, links, source_id, created_at, updated_at 
-             FROM cards WHERE links LIKE ?",
+            "SELECT id, title, type, content, plain_text, preview, tags, aliases, links, source_id, created_at, updated_at, pinned, word_count, archived, review, encrypted
+             FROM cards WHERE links LIKE ? AND deleted_at IS NULL",
         )
         .bind(format!("%\"{}\"%", card_id))
         .fetch_all(&self.pool)
@@ -1164,19 +1965,58 @@ impl Database {
             source_id: row.get(9),
             created_at: row.get(10),
             modified_at: row.get(11),
+            pinned: row.get::<i64, _>(12) != 0,
+            word_count: row.get::<i64, _>(13) as usize,
+            archived: row.get::<i64, _>(14) != 0,
+            review: parse_review(row.get(15)),
+            encrypted: row.get::<i64, _>(16) != 0,
         })
     }
 }
 
-// 辅助函数：从 TipTap JSON 中提取纯文本
-fn extract_plain_text_from_json(content: &str) -> Result<String, serde_json::Error> {
+// 辅助函数：解析 review 列，内容损坏时视为未加入复习
+fn parse_review(review: Option<String>) -> Option<ReviewState> {
+    review.and_then(|json| serde_json::from_str(&json).ok())
+}
+
+// 辅助函数：从 TipTap JSON 中提取纯文本，每个文本块一行
+pub(crate) fn extract_plain_text_from_json(content: &str) -> Result<String, serde_json::Error> {
     let json: serde_json::Value = serde_json::from_str(content)?;
+    let mut blocks = Vec::new();
+    extract_text_blocks(&json, &mut blocks);
+    Ok(blocks.join("\n"))
+}
+
+/// 逐个文本块统计字数，避免相邻段落首尾的词被连在一起
+pub(crate) fn count_words_in_json(content: &str) -> usize {
+    let Ok(json) = serde_json::from_str::<serde_json::Value>(content) else {
+        return 0;
+    };
+    let mut blocks = Vec::new();
+    extract_text_blocks(&json, &mut blocks);
+    blocks.iter().map(|block| count_words(block)).sum()
+}
+
+/// 按文本块提取文本：含行内内容的节点（段落、标题等）各为一块，列表、引用等容器继续向下展开
+pub(crate) fn extract_text_blocks(node: &serde_json::Value, blocks: &mut Vec<String>) {
+    let Some(children) = node.get("content").and_then(|c| c.as_array()) else {
+        return;
+    };
+    if children.iter().any(|child| child.get("content").is_some()) {
+        for child in children {
+            extract_text_blocks(child, blocks);
+        }
+        return;
+    }
     let mut text = String::new();
-    extract_text_recursive(&json, &mut text);
-    Ok(text.trim().to_string())
+    extract_text_recursive(node, &mut text);
+    let text = text.trim();
+    if !text.is_empty() {
+        blocks.push(text.to_string());
+    }
 }
 
-fn extract_text_recursive(node: &serde_json::Value, text: &mut String) {
+pub(crate) fn extract_text_recursive(node: &serde_json::Value, text: &mut String) {
     if let Some(text_node) = node.get("text") {
         if let Some(s) = text_node.as_str() {
             text.push_str(s);
@@ -1210,6 +2050,24 @@ fn extract_links_from_json(content: &str) -> Vec<String> {
 
 fn extract_links_recursive(node: &serde_json::Value, links: &mut Vec<String>) {
     if let Some(node_type) = node.get("type").and_then(|t| t.as_str()) {
+        // 指向卡片的 reference 节点与 wiki link 一样计入出链
+        if node_type == "reference" {
+            let attrs = node.get("attrs");
+            if attrs.and_then(|a| a.get("kind")).and_then(|k| k.as_str()) == Some("card") {
+                if let Some(id) = attrs.and_then(|a| a.get("id")).and_then(|i| i.as_str()) {
+                    if !id.is_empty() && !links.contains(&id.to_string()) {
+                        links.push(id.to_string());
+                    }
+                }
+            }
+        }
+        if node_type == "wikiLink" {
+            if let Some(href) = node.get("attrs").and_then(|a| a.get("href")).and_then(|h| h.as_str()) {
+                if !href.is_empty() && !links.contains(&href.to_string()) {
+                    links.push(href.to_string());
+                }
+            }
+        }
         if node_type == "link" {
             if let Some(attrs) = node.get("attrs") {
                 if let Some(href) = attrs.get("href").and_then(|h| h.as_str()) {
@@ -1234,3 +2092,602 @@ fn extract_links_recursive(node: &serde_json::Value, links: &mut Vec<String>) {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_source_soft_delete_and_restore() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(&dir.path().join("zentri.db")).await.unwrap();
+        let source = db
+            .create_source(CreateSourceRequest {
+                source_type: SourceType::Book,
+                title: "Book".to_string(),
+                author: None,
+                url: None,
+                cover: None,
+                description: None,
+                tags: vec![],
+                source_origin: None,
+            })
+            .await
+            .unwrap();
+        db.create_highlight(CreateHighlightRequest {
+            source_id: source.id.clone(),
+            card_id: None,
+            content: "quote".to_string(),
+            note: None,
+            annotation_type: None,
+            position: None,
+            color: None,
+        })
+        .await
+        .unwrap();
+
+        db.delete_source(&source.id).await.unwrap();
+        assert!(db.get_source(&source.id).await.unwrap().is_none());
+        assert_eq!(db.get_sources_count().await.unwrap(), 0);
+        assert_eq!(db.get_trashed_sources().await.unwrap().len(), 1);
+
+        // 未过期的不会被清理
+        assert_eq!(db.purge_trashed_sources(30).await.unwrap(), 0);
+
+        let restored = db.restore_source(&source.id).await.unwrap();
+        assert!(restored.is_some_and(|s| s.deleted_at.is_none()));
+        assert_eq!(db.get_highlights_by_source(&source.id).await.unwrap().len(), 1);
+
+        db.delete_source(&source.id).await.unwrap();
+        assert_eq!(db.purge_trashed_sources(0).await.unwrap(), 1);
+        assert!(db.get_trashed_sources().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_highlights_fts() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(&dir.path().join("zentri.db")).await.unwrap();
+        let source = db
+            .create_source(CreateSourceRequest {
+                source_type: SourceType::Book,
+                title: "Book".to_string(),
+                author: None,
+                url: None,
+                cover: None,
+                description: None,
+                tags: vec![],
+                source_origin: None,
+            })
+            .await
+            .unwrap();
+        let mut ids = Vec::new();
+        for (content, note) in [
+            ("Spaced repetition beats cramming", None),
+            ("卡片盒笔记法的核心是链接", Some("zettelkasten")),
+            ("Unrelated \"quoted\" text", None),
+        ] {
+            let highlight = db
+                .create_highlight(CreateHighlightRequest {
+                    source_id: source.id.clone(),
+                    card_id: None,
+                    content: content.to_string(),
+                    note: note.map(str::to_string),
+                    annotation_type: None,
+                    position: None,
+                    color: None,
+                })
+                .await
+                .unwrap();
+            ids.push(highlight.id);
+        }
+
+        let found = db.search_highlights("repetition", None).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, ids[0]);
+        assert_eq!(db.search_highlights("笔记法", Some(&source.id)).await.unwrap().len(), 1);
+        assert_eq!(db.search_highlights("链接", None).await.unwrap().len(), 1);
+        assert_eq!(db.search_highlights("ZETTEL", None).await.unwrap().len(), 1);
+        assert!(db.search_highlights("repetition", Some("other")).await.unwrap().is_empty());
+        // FTS5 语法字符按字面处理，不会报错
+        assert_eq!(db.search_highlights("\"quoted\"", None).await.unwrap().len(), 1);
+        assert!(db.search_highlights("AND OR", None).await.unwrap().is_empty());
+
+        db.update_highlight(
+            &ids[0],
+            UpdateHighlightRequest {
+                note: Some("memory research".to_string()),
+                color: None,
+                annotation_type: None,
+                card_id: None,
+                position: None,
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(db.search_highlights("memory", None).await.unwrap().len(), 1);
+
+        db.delete_highlight(&ids[0]).await.unwrap();
+        assert!(db.search_highlights("repetition", None).await.unwrap().is_empty());
+
+        db.hard_delete_source(&source.id).await.unwrap();
+        let indexed = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM highlights_fts")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(indexed, 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_source_note_updates_are_not_lost() {
+        let dir = tempdir().unwrap();
+        let db = std::sync::Arc::new(Database::open(&dir.path().join("zentri.db")).await.unwrap());
+        let source = db
+            .create_source(CreateSourceRequest {
+                source_type: SourceType::Book,
+                title: "Book".to_string(),
+                author: None,
+                url: None,
+                cover: None,
+                description: None,
+                tags: vec![],
+                source_origin: None,
+            })
+            .await
+            .unwrap();
+
+        let tasks: Vec<_> = (0..16)
+            .map(|i| {
+                let db = db.clone();
+                let source_id = source.id.clone();
+                tokio::spawn(async move {
+                    let card = db
+                        .create_card(CreateCardRequest {
+                            id: None,
+                            title: format!("Note {}", i),
+                            card_type: CardType::Literature,
+                            content: r#"{"type":"doc","content":[]}"#.to_string(),
+                            tags: vec![],
+                            aliases: vec![],
+                            source_id: Some(source_id.clone()),
+                        })
+                        .await
+                        .unwrap();
+                    db.add_note_to_source(&source_id, &card.id).await.unwrap();
+                    card.id
+                })
+            })
+            .collect();
+        let mut created = Vec::new();
+        for task in tasks {
+            created.push(task.await.unwrap());
+        }
+
+        let mut note_ids = db.get_source(&source.id).await.unwrap().unwrap().note_ids;
+        note_ids.sort();
+        created.sort();
+        assert_eq!(note_ids, created);
+
+        let removals: Vec<_> = created
+            .iter()
+            .take(8)
+            .map(|id| {
+                let db = db.clone();
+                let (source_id, id) = (source.id.clone(), id.clone());
+                tokio::spawn(async move { db.remove_note_from_source(&source_id, &id).await.unwrap() })
+            })
+            .collect();
+        for task in removals {
+            task.await.unwrap();
+        }
+        assert_eq!(db.get_source(&source.id).await.unwrap().unwrap().note_ids.len(), 8);
+    }
+
+    #[tokio::test]
+    async fn test_archived_cards_hidden_by_default() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(&dir.path().join("zentri.db")).await.unwrap();
+        let card = db
+            .create_card(CreateCardRequest {
+                id: None,
+                title: "Old idea".to_string(),
+                card_type: CardType::Fleeting,
+                content: r#"{"type":"doc","content":[]}"#.to_string(),
+                tags: vec![],
+                aliases: vec![],
+                source_id: None,
+            })
+            .await
+            .unwrap();
+
+        let archived = db.set_card_archived(&card.id, true).await.unwrap().unwrap();
+        assert!(archived.archived);
+        assert_eq!(archived.modified_at, card.modified_at);
+        assert!(db.get_all_cards(false).await.unwrap().is_empty());
+        assert_eq!(db.get_all_cards(true).await.unwrap().len(), 1);
+        assert!(db.get_card_list(CardListSort::Modified, false).await.unwrap().is_empty());
+        assert_eq!(db.get_archived_card_ids().await.unwrap(), vec![card.id.clone()]);
+
+        db.set_card_archived(&card.id, false).await.unwrap();
+        assert_eq!(db.get_all_cards(false).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_card_soft_delete_and_restore() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(&dir.path().join("zentri.db")).await.unwrap();
+        let card = db
+            .create_card(CreateCardRequest {
+                id: None,
+                title: "Draft".to_string(),
+                card_type: CardType::Permanent,
+                content: r#"{"type":"doc","content":[]}"#.to_string(),
+                tags: vec![],
+                aliases: vec![],
+                source_id: None,
+            })
+            .await
+            .unwrap();
+
+        db.delete_card(&card.id).await.unwrap();
+        assert!(db.get_card(&card.id).await.unwrap().is_none());
+        assert!(db.get_all_cards(true).await.unwrap().is_empty());
+        assert!(db.get_card_list(CardListSort::Modified, true).await.unwrap().is_empty());
+        let trashed = db.get_trashed_cards().await.unwrap();
+        assert_eq!(trashed.len(), 1);
+        assert_eq!(trashed[0].card.title, "Draft");
+        assert_eq!(db.get_trashed_card_ids().await.unwrap(), vec![card.id.clone()]);
+
+        // 未过期的不会被清理
+        assert!(db.purge_trashed_cards(30).await.unwrap().is_empty());
+
+        let restored = db.restore_card(&card.id).await.unwrap().unwrap();
+        assert_eq!(restored.content, card.content);
+        assert!(db.get_trashed_cards().await.unwrap().is_empty());
+
+        db.delete_card(&card.id).await.unwrap();
+        assert_eq!(db.purge_trashed_cards(0).await.unwrap(), vec![card.id.clone()]);
+        assert!(db.restore_card(&card.id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_merge_card_tags_dedups() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(&dir.path().join("zentri.db")).await.unwrap();
+        let mut ids = Vec::new();
+        for tags in [vec!["ml", "ai"], vec!["ml"], vec!["cooking"]] {
+            let card = db
+                .create_card(CreateCardRequest {
+                    id: None,
+                    title: "Card".to_string(),
+                    card_type: CardType::Permanent,
+                    content: r#"{"type":"doc","content":[]}"#.to_string(),
+                    tags: tags.into_iter().map(String::from).collect(),
+                    aliases: vec![],
+                    source_id: None,
+                })
+                .await
+                .unwrap();
+            ids.push(card.id);
+        }
+
+        let changed = db.merge_card_tags(&["ml".to_string()], Some("ai")).await.unwrap();
+        assert_eq!(changed.len(), 2);
+        assert_eq!(db.get_card(&ids[0]).await.unwrap().unwrap().tags, vec!["ai"]);
+        assert_eq!(db.get_card(&ids[1]).await.unwrap().unwrap().tags, vec!["ai"]);
+
+        assert_eq!(db.merge_card_tags(&["ai".to_string()], None).await.unwrap().len(), 2);
+        assert!(db.get_card(&ids[0]).await.unwrap().unwrap().tags.is_empty());
+        assert_eq!(db.get_card(&ids[2]).await.unwrap().unwrap().tags, vec!["cooking"]);
+    }
+
+    #[tokio::test]
+    async fn test_filter_sources_by_origin() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(&dir.path().join("zentri.db")).await.unwrap();
+        for origin in ["epub", "web", "manual"] {
+            db.create_source(CreateSourceRequest {
+                source_type: SourceType::Book,
+                title: origin.to_string(),
+                author: None,
+                url: None,
+                cover: None,
+                description: None,
+                tags: vec![],
+                source_origin: Some(origin.to_string()),
+            })
+            .await
+            .unwrap();
+        }
+
+        let filter = SourceFilter {
+            source_origin: Some("EPUB".to_string()),
+            ..Default::default()
+        };
+        let sources = db.get_sources_filtered(&filter).await.unwrap();
+        assert_eq!(sources.len(), 1);
+        assert_eq!(sources[0].source_origin.as_deref(), Some("epub"));
+        assert_eq!(db.get_sources_filtered(&SourceFilter::default()).await.unwrap().len(), 3);
+
+        // 保存网页快照时手动添加的文献源改记为网页
+        let manual = db.get_sources_filtered(&SourceFilter::default()).await.unwrap();
+        let manual = manual.iter().find(|s| s.title == "manual").unwrap();
+        db.set_source_origin(&manual.id, "web").await.unwrap();
+        let filter = SourceFilter {
+            source_origin: Some("web".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(db.get_sources_filtered(&filter).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_set_cards_type_in_one_transaction() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(&dir.path().join("zentri.db")).await.unwrap();
+        let mut ids = Vec::new();
+        for title in ["One", "Two"] {
+            let card = db
+                .create_card(CreateCardRequest {
+                    id: None,
+                    title: title.to_string(),
+                    card_type: CardType::Fleeting,
+                    content: r#"{"type":"doc","content":[]}"#.to_string(),
+                    tags: vec![],
+                    aliases: vec![],
+                    source_id: None,
+                })
+                .await
+                .unwrap();
+            ids.push(card.id);
+        }
+        ids.push("missing".to_string());
+
+        let updated = db.set_cards_type(&ids, &CardType::Permanent).await.unwrap();
+        assert_eq!(updated, ids[..2].to_vec());
+        for id in &ids[..2] {
+            assert_eq!(db.get_card(id).await.unwrap().unwrap().card_type, CardType::Permanent);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_due_cards() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(&dir.path().join("zentri.db")).await.unwrap();
+        let mut ids = Vec::new();
+        for title in ["Due", "Later", "Not in review"] {
+            let card = db
+                .create_card(CreateCardRequest {
+                    id: None,
+                    title: title.to_string(),
+                    card_type: CardType::Permanent,
+                    content: r#"{"type":"doc","content":[]}"#.to_string(),
+                    tags: vec![],
+                    aliases: vec![],
+                    source_id: None,
+                })
+                .await
+                .unwrap();
+            ids.push(card.id);
+        }
+
+        let now = 1_000_000;
+        let due = db.set_card_review(&ids[0], Some(&ReviewState::new(now - 1))).await.unwrap().unwrap();
+        assert_eq!(due.review, Some(ReviewState::new(now - 1)));
+        db.set_card_review(&ids[1], Some(&ReviewState::new(now + 1))).await.unwrap();
+
+        let due_cards = db.get_due_cards(now).await.unwrap();
+        assert_eq!(due_cards.len(), 1);
+        assert_eq!(due_cards[0].id, ids[0]);
+        assert!(db.get_card(&ids[2]).await.unwrap().unwrap().review.is_none());
+
+        let removed = db.set_card_review(&ids[0], None).await.unwrap().unwrap();
+        assert!(removed.review.is_none());
+        assert!(db.get_due_cards(now).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_merge_source_tags() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(&dir.path().join("zentri.db")).await.unwrap();
+        let mut ids = Vec::new();
+        for tags in [vec!["ml", "AI"], vec!["machine-learning"], vec!["history"]] {
+            let source = db
+                .create_source(CreateSourceRequest {
+                    source_type: SourceType::Article,
+                    title: "Source".to_string(),
+                    author: None,
+                    url: None,
+                    cover: None,
+                    description: None,
+                    tags: tags.into_iter().map(String::from).collect(),
+                    source_origin: None,
+                })
+                .await
+                .unwrap();
+            ids.push(source.id);
+        }
+
+        let changed = db
+            .merge_source_tags(&["ml".to_string(), "machine-learning".to_string()], "AI")
+            .await
+            .unwrap();
+        assert_eq!(changed.len(), 2);
+
+        // 合并后去重
+        let first = db.get_source(&ids[0]).await.unwrap().unwrap();
+        assert_eq!(first.tags, vec!["AI".to_string()]);
+        let counts = db.get_source_tag_counts().await.unwrap();
+        assert_eq!(counts, vec![("AI".to_string(), 2), ("history".to_string(), 1)]);
+    }
+
+    #[tokio::test]
+    async fn test_word_count_per_block_and_backfill_once() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(&dir.path().join("zentri.db")).await.unwrap();
+        let card = db
+            .create_card(CreateCardRequest {
+                id: None,
+                title: "Blocks".to_string(),
+                card_type: CardType::Permanent,
+                content: r#"{"type":"doc","content":[{"type":"paragraph","content":[{"type":"text","text":"alpha beta"}]},{"type":"paragraph","content":[{"type":"text","text":"gamma"}]}]}"#.to_string(),
+                tags: vec![],
+                aliases: vec![],
+                source_id: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(card.plain_text, "alpha beta\ngamma");
+        assert_eq!(card.word_count, 3);
+
+        // 旧版本写入的纯文本块之间没有分隔，字数少算一个
+        let legacy = || async {
+            sqlx::query("UPDATE cards SET plain_text = 'alpha betagamma', word_count = 2")
+                .execute(&db.pool)
+                .await
+                .unwrap();
+        };
+        legacy().await;
+        sqlx::query("DELETE FROM config WHERE key = ?")
+            .bind(WORD_COUNT_BACKFILL_KEY)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        db.upgrade_schema().await.unwrap();
+        let card = db.get_card(&card.id).await.unwrap().unwrap();
+        assert_eq!((card.plain_text.as_str(), card.word_count), ("alpha beta\ngamma", 3));
+
+        // 补算只执行一次
+        legacy().await;
+        db.upgrade_schema().await.unwrap();
+        assert_eq!(db.get_card(&card.id).await.unwrap().unwrap().word_count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_pinned_cards_sort_first() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(&dir.path().join("zentri.db")).await.unwrap();
+        let mut ids = Vec::new();
+        for title in ["A", "B"] {
+            let card = db
+                .create_card(CreateCardRequest {
+                    id: None,
+                    title: title.to_string(),
+                    card_type: CardType::Permanent,
+                    content: r#"{"type":"doc","content":[]}"#.to_string(),
+                    tags: vec![],
+                    aliases: vec![],
+                    source_id: None,
+                })
+                .await
+                .unwrap();
+            assert!(!card.pinned);
+            ids.push(card.id);
+        }
+
+        let pinned = db.set_card_pinned(&ids[0], true).await.unwrap().unwrap();
+        assert!(pinned.pinned);
+
+        let all = db.get_all_cards(false).await.unwrap();
+        assert_eq!(all[0].id, ids[0]);
+        let by_type = db.get_cards_by_type(CardType::Permanent).await.unwrap();
+        assert_eq!(by_type[0].id, ids[0]);
+        let page = db.get_cards_paginated(0, 1).await.unwrap();
+        assert_eq!(page[0].id, ids[0]);
+        let pinned_only = db.get_pinned_cards().await.unwrap();
+        assert_eq!(pinned_only.len(), 1);
+        assert_eq!(pinned_only[0].id, ids[0]);
+    }
+
+    /// 连接池并发读取：1000 次并发 get_source 全部成功，且不会因连接池排队而明显变慢
+    #[tokio::test]
+    async fn test_get_source_concurrent_reads() {
+        let dir = tempdir().unwrap();
+        let db = std::sync::Arc::new(Database::open(&dir.path().join("zentri.db")).await.unwrap());
+        let source = db
+            .create_source(CreateSourceRequest {
+                source_type: SourceType::Article,
+                title: "Bench".to_string(),
+                author: None,
+                url: None,
+                cover: None,
+                description: None,
+                tags: vec![],
+                source_origin: None,
+            })
+            .await
+            .unwrap();
+
+        let started = std::time::Instant::now();
+        let tasks: Vec<_> = (0..1000)
+            .map(|_| {
+                let db = db.clone();
+                let id = source.id.clone();
+                tokio::spawn(async move { db.get_source(&id).await.unwrap() })
+            })
+            .collect();
+        for task in tasks {
+            assert_eq!(task.await.unwrap().unwrap().title, "Bench");
+        }
+        assert!(started.elapsed() < std::time::Duration::from_secs(10));
+    }
+
+    #[tokio::test]
+    async fn test_card_word_count_and_list_sort() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(&dir.path().join("zentri.db")).await.unwrap();
+        let doc = |text: &str| {
+            serde_json::json!({
+                "type": "doc",
+                "content": [{"type": "paragraph", "content": [{"type": "text", "text": text}]}]
+            })
+            .to_string()
+        };
+        let short = db
+            .create_card(CreateCardRequest {
+                id: None,
+                title: "Short".to_string(),
+                card_type: CardType::Fleeting,
+                content: doc("just a stub"),
+                tags: vec![],
+                aliases: vec![],
+                source_id: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(short.word_count, 3);
+        let long = db
+            .create_card(CreateCardRequest {
+                id: None,
+                title: "Long".to_string(),
+                card_type: CardType::Permanent,
+                content: doc("卡片盒笔记法"),
+                tags: vec![],
+                aliases: vec![],
+                source_id: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(long.word_count, 6);
+
+        let updated = db
+            .update_card(
+                &short.id,
+                UpdateCardRequest {
+                    title: None,
+                    content: Some(doc("one")),
+                    tags: None,
+                    card_type: None,
+                    aliases: None,
+                },
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.word_count, 1);
+
+        let list = db.get_card_list(CardListSort::WordCount, false).await.unwrap();
+        let ids: Vec<_> = list.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, vec![long.id.as_str(), short.id.as_str()]);
+        assert_eq!(list[0].word_count, 6);
+    }
+}
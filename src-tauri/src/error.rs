@@ -57,6 +57,14 @@ pub enum AppError {
     /// 网页读取错误
     #[error("网页读取错误: {0}")]
     WebReader(String),
+
+    /// 加密错误
+    #[error("加密错误: {0}")]
+    Crypto(#[from] crate::crypto::CryptoError),
+
+    /// 加密卡片需要先解锁
+    #[error("Vault 已锁定，请先解锁")]
+    Locked,
 }
 
 /// 结果类型别名
@@ -2,14 +2,14 @@
 //! 提供图谱计算、反向链接、PageRank 排序、连通分量分析等功能
 
 use crate::models::CardListItem;
-use petgraph::algo::{connected_components, kosaraju_scc};
+use petgraph::algo::{astar, connected_components, kosaraju_scc};
 use petgraph::graph::{DiGraph, Graph, NodeIndex};
 use petgraph::visit::EdgeRef;
 use petgraph::Direction;
 use petgraph::Undirected;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
 use std::path::{Path, PathBuf};
 use std::sync::RwLock;
 
@@ -33,11 +33,21 @@ pub struct GraphNode {
     pub cluster_id: usize,
 }
 
+/// 布局图中的无向边，端点顺序不保证
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphLink {
+    pub source: String,
+    pub target: String,
+    /// 两张卡片之间解析到的链接总数（双向合计），权重越大引力越强
+    pub weight: f32,
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GraphData {
     pub nodes: Vec<GraphNode>,
-    pub links: Vec<(String, String)>,
+    pub links: Vec<GraphLink>,
     /// 连通分量数量
     #[serde(default)]
     pub cluster_count: usize,
@@ -79,6 +89,105 @@ pub struct KnowledgeCluster {
     pub center_node: Option<String>,
 }
 
+/// 单张卡片变化后的图谱增量（`update_card_in_graph` 返回值，同时以 `graph-delta` 事件推送）
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphDelta {
+    /// 新增、移动或邻居发生变化的节点
+    pub nodes: Vec<GraphNode>,
+    pub removed_nodes: Vec<String>,
+    /// 新增的无向边，端点按 ID 排序（增量更新不跟踪权重）
+    pub added_links: Vec<(String, String)>,
+    pub removed_links: Vec<(String, String)>,
+}
+
+/// 出链解析结果
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkTarget {
+    /// 原始链接文本
+    pub link: String,
+    pub resolved_id: Option<String>,
+    /// 解析成功时为目标标题，否则为原始链接
+    pub display_text: String,
+    pub exists: bool,
+}
+
+// ============ 链接解析 ============
+
+/// 链接解析器，优先级：ID → 别名 → 标题
+pub struct LinkResolver {
+    titles_by_id: HashMap<String, String>,
+    alias_to_id: HashMap<String, String>,
+    title_to_id: HashMap<String, String>,
+}
+
+impl LinkResolver {
+    pub fn new(cards: &[CardListItem]) -> Self {
+        let mut titles_by_id = HashMap::new();
+        let mut alias_to_id = HashMap::new();
+        let mut title_to_id = HashMap::new();
+        for card in cards {
+            titles_by_id.insert(card.id.clone(), card.title.clone());
+            title_to_id.insert(card.title.clone(), card.id.clone());
+            for alias in &card.aliases {
+                alias_to_id.insert(alias.clone(), card.id.clone());
+            }
+        }
+        Self {
+            titles_by_id,
+            alias_to_id,
+            title_to_id,
+        }
+    }
+
+    /// 由图谱引擎缓存的卡片元数据构建，优先级与 `new` 相同
+    fn from_meta(meta: &HashMap<String, CardMeta>) -> Self {
+        let mut titles_by_id = HashMap::new();
+        let mut alias_to_id = HashMap::new();
+        let mut title_to_id = HashMap::new();
+        for (id, m) in meta {
+            titles_by_id.insert(id.clone(), m.title.clone());
+            title_to_id.insert(m.title.clone(), id.clone());
+            for alias in &m.aliases {
+                alias_to_id.insert(alias.clone(), id.clone());
+            }
+        }
+        Self {
+            titles_by_id,
+            alias_to_id,
+            title_to_id,
+        }
+    }
+
+    /// 将链接文本解析为卡片 ID
+    pub fn resolve(&self, link: &str) -> Option<String> {
+        if self.titles_by_id.contains_key(link) {
+            return Some(link.to_string());
+        }
+        self.alias_to_id
+            .get(link)
+            .or_else(|| self.title_to_id.get(link))
+            .cloned()
+    }
+
+    /// 解析链接并附带展示文本
+    pub fn resolve_target(&self, link: &str) -> LinkTarget {
+        let resolved_id = self.resolve(link);
+        let display_text = resolved_id
+            .as_ref()
+            .and_then(|id| self.titles_by_id.get(id))
+            .cloned()
+            .unwrap_or_else(|| link.to_string());
+        LinkTarget {
+            link: link.to_string(),
+            exists: resolved_id.is_some(),
+            resolved_id,
+            display_text,
+        }
+    }
+}
+
 // ============ 图谱引擎 ============
 
 /// 图谱引擎 - 维护内存中的图结构
@@ -93,6 +202,8 @@ pub struct GraphEngine {
     title_to_id: RwLock<HashMap<String, String>>,
     /// 卡片元数据缓存
     card_meta: RwLock<HashMap<String, CardMeta>>,
+    /// 最近一次全量布局的节点，增量更新时其余节点保持原坐标
+    layout: RwLock<HashMap<String, GraphNode>>,
     /// 是否已初始化
     initialized: RwLock<bool>,
 }
@@ -101,7 +212,6 @@ pub struct GraphEngine {
 struct CardMeta {
     title: String,
     card_type: String,
-    #[allow(dead_code)]
     links: Vec<String>,
     aliases: Vec<String>,
 }
@@ -115,6 +225,7 @@ impl GraphEngine {
             node_indices: RwLock::new(HashMap::new()),
             title_to_id: RwLock::new(HashMap::new()),
             card_meta: RwLock::new(HashMap::new()),
+            layout: RwLock::new(HashMap::new()),
             initialized: RwLock::new(false),
         }
     }
@@ -161,15 +272,12 @@ impl GraphEngine {
         }
 
         // 第二遍：添加边
+        let resolver = LinkResolver::new(&cards);
         for card in &cards {
             if let Some(&source_idx) = indices.get(&card.id) {
                 for link_text in &card.links {
                     // 解析链接目标
-                    let target_id = if indices.contains_key(link_text) {
-                        Some(link_text.clone())
-                    } else {
-                        title_map.get(link_text).cloned()
-                    };
+                    let target_id = resolver.resolve(link_text);
 
                     if let Some(tid) = target_id {
                         if let Some(&target_idx) = indices.get(&tid) {
@@ -228,15 +336,12 @@ impl GraphEngine {
             for edge in graph.edges_directed(target_idx, Direction::Incoming) {
                 let source_id = &graph[edge.source()];
                 if let Some(source_meta) = meta.get(source_id) {
-                    // 上下文提取已移除（需要从数据库获取，性能影响较大）
-                    // 如果需要上下文，可以在调用 get_backlinks 时传入卡片数据
-                    let context = None;
-
+                    // 图谱不缓存正文，上下文由命令层读取来源卡片后填充
                     backlinks.push(BacklinkInfo {
                         id: source_id.clone(),
                         title: source_meta.title.clone(),
                         card_type: source_meta.card_type.clone(),
-                        context,
+                        context: None,
                     });
                 }
             }
@@ -245,8 +350,14 @@ impl GraphEngine {
         backlinks
     }
 
+    /// 按当前图谱中的卡片构建链接解析器，与建图时的解析规则一致
+    pub fn link_resolver(&self) -> LinkResolver {
+        self.ensure_initialized();
+        LinkResolver::from_meta(&self.card_meta.read().unwrap_or_else(|e| e.into_inner()))
+    }
+
     /// 计算 PageRank
-    pub fn compute_pagerank(&self, damping: f32, iterations: usize) -> HashMap<String, f32> {
+    pub fn compute_pagerank(&self, damping: f32, max_iterations: usize) -> HashMap<String, f32> {
         self.ensure_initialized();
 
         let graph = self
@@ -255,42 +366,7 @@ impl GraphEngine {
             .unwrap_or_else(|e| e.into_inner());
         let indices = self.node_indices.read().unwrap_or_else(|e| e.into_inner());
 
-        let n = graph.node_count();
-        if n == 0 {
-            return HashMap::new();
-        }
-
-        let mut ranks: HashMap<NodeIndex, f32> = HashMap::new();
-        let initial_rank = 1.0 / n as f32;
-
-        // 初始化
-        for idx in graph.node_indices() {
-            ranks.insert(idx, initial_rank);
-        }
-
-        // 迭代计算
-        for _ in 0..iterations {
-            let mut new_ranks: HashMap<NodeIndex, f32> = HashMap::new();
-
-            for idx in graph.node_indices() {
-                let mut rank_sum = 0.0;
-
-                // 累加所有入边的贡献
-                for edge in graph.edges_directed(idx, Direction::Incoming) {
-                    let source_idx = edge.source();
-                    let source_out_degree = graph
-                        .edges_directed(source_idx, Direction::Outgoing)
-                        .count();
-                    if source_out_degree > 0 {
-                        rank_sum += ranks[&source_idx] / source_out_degree as f32;
-                    }
-                }
-
-                new_ranks.insert(idx, (1.0 - damping) / n as f32 + damping * rank_sum);
-            }
-
-            ranks = new_ranks;
-        }
+        let ranks = pagerank(&graph, damping, max_iterations);
 
         // 转换为 card_id -> score
         let mut result = HashMap::new();
@@ -314,7 +390,7 @@ impl GraphEngine {
         let indices = self.node_indices.read().unwrap_or_else(|e| e.into_inner());
         let meta = self.card_meta.read().unwrap_or_else(|e| e.into_inner());
 
-        let pagerank = self.compute_pagerank(0.85, 20);
+        let pagerank = self.compute_pagerank(PAGERANK_DAMPING, PAGERANK_MAX_ITERATIONS);
 
         let mut rankings: Vec<CardImportance> = indices
             .iter()
@@ -361,7 +437,7 @@ impl GraphEngine {
         // 使用 Kosaraju 算法获取强连通分量
         let sccs = kosaraju_scc(&*graph);
 
-        let pagerank = self.compute_pagerank(0.85, 20);
+        let pagerank = self.compute_pagerank(PAGERANK_DAMPING, PAGERANK_MAX_ITERATIONS);
 
         let mut clusters: Vec<KnowledgeCluster> = sccs
             .into_iter()
@@ -414,11 +490,17 @@ impl GraphEngine {
             .collect()
     }
 
-    /// 更新单个卡片的图关系
-    #[allow(dead_code)]
-    pub fn update_card(&self, card_id: &str, links: Vec<String>, title: &str, aliases: &[String]) {
-        self.ensure_initialized();
+    /// 记住全量布局的结果，作为之后增量更新的坐标基准
+    pub fn remember_layout(&self, data: &GraphData) {
+        *self.layout.write().unwrap_or_else(|e| e.into_inner()) =
+            data.nodes.iter().map(|n| (n.id.clone(), n.clone())).collect();
+    }
 
+    /// 单张卡片新增、修改或移除（`card` 为 None）后增量更新图谱
+    ///
+    /// 只重新解析该卡片以及链接文本提到其 ID/标题/别名的卡片的出链，
+    /// 再让该卡片和直接邻居做局部松弛，其余节点保持原坐标。返回变化的节点和边
+    pub fn update_card(&self, card_id: &str, card: Option<CardListItem>) -> GraphDelta {
         let mut graph = self
             .directed_graph
             .write()
@@ -426,79 +508,257 @@ impl GraphEngine {
         let mut indices = self.node_indices.write().unwrap_or_else(|e| e.into_inner());
         let mut title_map = self.title_to_id.write().unwrap_or_else(|e| e.into_inner());
         let mut meta = self.card_meta.write().unwrap_or_else(|e| e.into_inner());
+        let mut layout = self.layout.write().unwrap_or_else(|e| e.into_inner());
 
-        // 获取或创建节点
-        let source_idx = if let Some(&idx) = indices.get(card_id) {
-            // 删除旧的出边
-            let edges_to_remove: Vec<_> = graph
-                .edges_directed(idx, Direction::Outgoing)
-                .map(|e| e.id())
-                .collect();
-            for edge_id in edges_to_remove {
-                graph.remove_edge(edge_id);
+        // 新旧标题和别名都要看：改名后原来指向它的链接可能失效，也可能有链接新解析到它
+        let mut names: HashSet<&str> = HashSet::from([card_id]);
+        for (title, aliases) in meta
+            .get(card_id)
+            .map(|m| (&m.title, &m.aliases))
+            .into_iter()
+            .chain(card.as_ref().map(|c| (&c.title, &c.aliases)))
+        {
+            names.insert(title.as_str());
+            names.extend(aliases.iter().map(String::as_str));
+        }
+        let mut sources: Vec<String> = meta
+            .iter()
+            .filter(|(id, m)| id.as_str() != card_id && m.links.iter().any(|l| names.contains(l.as_str())))
+            .map(|(id, _)| id.clone())
+            .collect();
+        sources.push(card_id.to_string());
+
+        let before = incident_links(&graph, &indices, &sources);
+
+        let mut removed_nodes = Vec::new();
+        match card {
+            Some(card) => {
+                if !indices.contains_key(card_id) {
+                    indices.insert(card_id.to_string(), graph.add_node(card_id.to_string()));
+                }
+                meta.insert(
+                    card_id.to_string(),
+                    CardMeta {
+                        title: card.title,
+                        card_type: card.card_type.as_str().to_string(),
+                        links: card.links,
+                        aliases: card.aliases,
+                    },
+                );
             }
-            idx
-        } else {
-            let idx = graph.add_node(card_id.to_string());
-            indices.insert(card_id.to_string(), idx);
-            idx
-        };
+            None => {
+                if let Some(idx) = indices.remove(card_id) {
+                    graph.remove_node(idx);
+                    // remove_node 会把最后一个节点挪到被删除的位置
+                    if let Some(moved) = graph.node_weight(idx) {
+                        indices.insert(moved.clone(), idx);
+                    }
+                    removed_nodes.push(card_id.to_string());
+                }
+                meta.remove(card_id);
+                layout.remove(card_id);
+            }
+        }
 
-        // 更新标题映射
-        title_map.insert(title.to_string(), card_id.to_string());
-        for alias in aliases {
-            title_map.insert(alias.clone(), card_id.to_string());
+        title_map.clear();
+        for (id, m) in meta.iter() {
+            title_map.insert(m.title.clone(), id.clone());
+            for alias in &m.aliases {
+                title_map.insert(alias.clone(), id.clone());
+            }
         }
 
-        // 添加新的出边
-        for link_text in &links {
-            let target_id = if indices.contains_key(link_text) {
-                Some(link_text.clone())
-            } else {
-                title_map.get(link_text).cloned()
+        // 重新解析受影响卡片的出链
+        let resolver = LinkResolver::from_meta(&meta);
+        for source in &sources {
+            let (Some(&source_idx), Some(source_meta)) = (indices.get(source), meta.get(source)) else {
+                continue;
             };
-
-            if let Some(tid) = target_id {
-                if let Some(&target_idx) = indices.get(&tid) {
-                    if source_idx != target_idx {
-                        graph.add_edge(source_idx, target_idx, ());
-                    }
+            while let Some(edge) = graph
+                .edges_directed(source_idx, Direction::Outgoing)
+                .next()
+                .map(|e| e.id())
+            {
+                graph.remove_edge(edge);
+            }
+            for link in &source_meta.links {
+                let Some(target_idx) = resolver.resolve(link).and_then(|id| indices.get(&id).copied()) else {
+                    continue;
+                };
+                if source_idx != target_idx && graph.find_edge(source_idx, target_idx).is_none() {
+                    graph.add_edge(source_idx, target_idx, ());
                 }
             }
         }
 
-        // 更新元数据
-        if let Some(m) = meta.get_mut(card_id) {
-            m.title = title.to_string();
-            m.links = links;
-            m.aliases = aliases.to_vec();
-        } else {
-            meta.insert(
-                card_id.to_string(),
-                CardMeta {
-                    title: title.to_string(),
-                    card_type: "fleeting".to_string(),
-                    links,
-                    aliases: aliases.to_vec(),
-                },
-            );
+        let after = incident_links(&graph, &indices, &sources);
+        let added_links: Vec<(String, String)> = after.difference(&before).cloned().collect();
+        let removed_links: Vec<(String, String)> = before.difference(&after).cloned().collect();
+
+        let undirected_neighbors = |id: &str| -> BTreeSet<String> {
+            indices
+                .get(id)
+                .map(|&idx| graph.neighbors_undirected(idx).map(|n| graph[n].clone()).collect())
+                .unwrap_or_default()
+        };
+
+        // 需要下发的节点：自身、边有变化的端点；参与松弛的：自身、直接邻居以及还没有坐标的节点
+        let mut changed: BTreeSet<String> = added_links
+            .iter()
+            .chain(&removed_links)
+            .flat_map(|(a, b)| [a.clone(), b.clone()])
+            .filter(|id| indices.contains_key(id))
+            .collect();
+        let mut moving: BTreeSet<String> = BTreeSet::new();
+        if indices.contains_key(card_id) {
+            changed.insert(card_id.to_string());
+            moving.insert(card_id.to_string());
+            moving.extend(undirected_neighbors(card_id));
+        }
+        moving.extend(changed.iter().filter(|id| !layout.contains_key(*id)).cloned());
+        changed.extend(moving.iter().cloned());
+
+        let mut positions: HashMap<String, (f32, f32)> =
+            layout.iter().map(|(id, n)| (id.clone(), (n.x, n.y))).collect();
+        let mut rng = rand::thread_rng();
+        for id in &moving {
+            if positions.contains_key(id) {
+                continue;
+            }
+            // 新节点放在已有邻居的质心附近，没有邻居时随机放置
+            let placed: Vec<(f32, f32)> = undirected_neighbors(id)
+                .iter()
+                .filter_map(|n| positions.get(n).copied())
+                .collect();
+            let position = if placed.is_empty() {
+                (rng.gen_range(-100.0..100.0), rng.gen_range(-100.0..100.0))
+            } else {
+                let count = placed.len() as f32;
+                let (sx, sy) = placed.iter().fold((0.0f32, 0.0f32), |(sx, sy), (x, y)| (sx + x, sy + y));
+                (sx / count + rng.gen_range(-10.0..10.0), sy / count + rng.gen_range(-10.0..10.0))
+            };
+            positions.insert(id.clone(), position);
+        }
+        let moving: Vec<String> = moving.into_iter().collect();
+        let relax_links: Vec<(String, String)> = incident_links(&graph, &indices, &moving).into_iter().collect();
+        relax_local(&mut positions, &moving, &relax_links);
+
+        // 簇编号沿用上次全量布局的结果，新节点跟随邻居，完整重算要等下次全量布局
+        let ranks = pagerank(&graph, PAGERANK_DAMPING, PAGERANK_MAX_ITERATIONS);
+        let next_cluster = layout.values().map(|n| n.cluster_id + 1).max().unwrap_or(0);
+        let mut nodes = Vec::new();
+        for id in &changed {
+            let (Some(&idx), Some(m)) = (indices.get(id), meta.get(id)) else {
+                continue;
+            };
+            let neighbors: Vec<String> = undirected_neighbors(id).into_iter().collect();
+            let cluster_id = layout.get(id).map(|n| n.cluster_id).unwrap_or_else(|| {
+                neighbors
+                    .iter()
+                    .find_map(|n| layout.get(n).map(|n| n.cluster_id))
+                    .unwrap_or(next_cluster)
+            });
+            let (x, y) = positions[id];
+            let node = GraphNode {
+                id: id.clone(),
+                title: m.title.clone(),
+                card_type: m.card_type.clone(),
+                x,
+                y,
+                link_count: neighbors.len(),
+                neighbors,
+                importance: ranks.get(&idx).copied().unwrap_or(0.0),
+                cluster_id,
+            };
+            layout.insert(id.clone(), node.clone());
+            nodes.push(node);
+        }
+
+        GraphDelta {
+            nodes,
+            removed_nodes,
+            added_links,
+            removed_links,
         }
     }
 
     /// 删除卡片
     #[allow(dead_code)]
-    pub fn remove_card(&self, card_id: &str) {
-        let mut graph = self
-            .directed_graph
-            .write()
-            .unwrap_or_else(|e| e.into_inner());
-        let mut indices = self.node_indices.write().unwrap_or_else(|e| e.into_inner());
-        let mut meta = self.card_meta.write().unwrap_or_else(|e| e.into_inner());
+    pub fn remove_card(&self, card_id: &str) -> GraphDelta {
+        self.update_card(card_id, None)
+    }
+}
+
+/// 与给定卡片相连（出边或入边）的所有无向边，端点按 ID 排序
+fn incident_links(
+    graph: &DiGraph<String, ()>,
+    indices: &HashMap<String, NodeIndex>,
+    ids: &[String],
+) -> BTreeSet<(String, String)> {
+    let mut links = BTreeSet::new();
+    for id in ids {
+        let Some(&idx) = indices.get(id) else {
+            continue;
+        };
+        for neighbor in graph.neighbors_undirected(idx) {
+            let (a, b) = (graph[idx].clone(), graph[neighbor].clone());
+            links.insert(if a <= b { (a, b) } else { (b, a) });
+        }
+    }
+    links
+}
+
+/// 局部力导向松弛：只移动 `moving` 中的节点，其余节点作为固定锚点参与斥力和引力
+fn relax_local(positions: &mut HashMap<String, (f32, f32)>, moving: &[String], links: &[(String, String)]) {
+    let slot: HashMap<&str, usize> = moving.iter().enumerate().map(|(i, id)| (id.as_str(), i)).collect();
+    let mut velocity = vec![(0.0f32, 0.0f32); moving.len()];
+
+    for _ in 0..LOCAL_RELAX_ITERATIONS {
+        let mut forces = vec![(0.0f32, 0.0f32); moving.len()];
+        for (i, id) in moving.iter().enumerate() {
+            let (x, y) = positions[id];
+            for (other, &(ox, oy)) in positions.iter() {
+                if other == id {
+                    continue;
+                }
+                let dx = x - ox;
+                let dy = y - oy;
+                let dist_sq = dx * dx + dy * dy;
+                let dist = dist_sq.sqrt().max(0.1);
+                let f = LAYOUT_REPULSION / dist_sq.max(0.01);
+                forces[i].0 += (dx / dist) * f;
+                forces[i].1 += (dy / dist) * f;
+            }
+        }
+
+        for (a, b) in links {
+            let (Some(&(x1, y1)), Some(&(x2, y2))) = (positions.get(a), positions.get(b)) else {
+                continue;
+            };
+            let dx = x1 - x2;
+            let dy = y1 - y2;
+            let dist = (dx * dx + dy * dy).sqrt().max(0.1);
+            let f = (dist * dist) / LAYOUT_SPRING_LENGTH;
+            let (fx, fy) = ((dx / dist) * f, (dy / dist) * f);
+            if let Some(&i) = slot.get(a.as_str()) {
+                forces[i].0 -= fx;
+                forces[i].1 -= fy;
+            }
+            if let Some(&i) = slot.get(b.as_str()) {
+                forces[i].0 += fx;
+                forces[i].1 += fy;
+            }
+        }
 
-        if let Some(idx) = indices.remove(card_id) {
-            graph.remove_node(idx);
+        for (i, id) in moving.iter().enumerate() {
+            let v = &mut velocity[i];
+            v.0 = (v.0 + forces[i].0) * LAYOUT_VELOCITY_DAMPING;
+            v.1 = (v.1 + forces[i].1) * LAYOUT_VELOCITY_DAMPING;
+            if let Some(p) = positions.get_mut(id) {
+                p.0 += v.0 * LAYOUT_TIME_STEP;
+                p.1 += v.1 * LAYOUT_TIME_STEP;
+            }
         }
-        meta.remove(card_id);
     }
 }
 
@@ -515,9 +775,311 @@ struct NodeState {
     vy: f32,
 }
 
+/// 默认布局迭代次数
+const LAYOUT_ITERATIONS: usize = 100;
+/// 流式布局的迭代上限
+const MAX_LAYOUT_ITERATIONS: usize = 1000;
+/// 流式布局的节点对计算预算（迭代次数 × 节点数²），超大图会自动减少迭代
+const LAYOUT_PAIR_BUDGET: usize = 2_000_000_000;
+/// Barnes-Hut 近似的默认 theta：区域宽度与距离之比小于它时整体按质心计算斥力，0 为精确计算
+pub const DEFAULT_BARNES_HUT_THETA: f32 = 0.8;
+/// 四叉树最大深度，重合的点在此深度合并，避免无限细分
+const QUADTREE_MAX_DEPTH: usize = 32;
+/// 弹簧理想长度
+const LAYOUT_SPRING_LENGTH: f32 = 50.0;
+/// 斥力强度
+const LAYOUT_REPULSION: f32 = 5000.0;
+/// 每次迭代的时间步长
+const LAYOUT_TIME_STEP: f32 = 0.1;
+/// 速度衰减系数
+const LAYOUT_VELOCITY_DAMPING: f32 = 0.85;
+/// 增量更新时局部松弛的迭代次数
+const LOCAL_RELAX_ITERATIONS: usize = 50;
+
+/// Barnes-Hut 四叉树节点
+struct QuadNode {
+    /// 区域中心和半边长
+    cx: f32,
+    cy: f32,
+    half: f32,
+    /// 区域内的点数和质心
+    mass: f32,
+    mx: f32,
+    my: f32,
+    children: Option<[usize; 4]>,
+    /// 只含一个点的叶子记录该点（序号和坐标）
+    point: Option<(usize, f32, f32)>,
+}
+
+impl QuadNode {
+    fn empty(cx: f32, cy: f32, half: f32) -> Self {
+        Self {
+            cx,
+            cy,
+            half,
+            mass: 0.0,
+            mx: 0.0,
+            my: 0.0,
+            children: None,
+            point: None,
+        }
+    }
+}
+
+/// 用于近似计算斥力的四叉树，节点存放在数组中
+struct QuadTree {
+    nodes: Vec<QuadNode>,
+}
+
+impl QuadTree {
+    fn build(points: &[(f32, f32)]) -> Self {
+        let (mut min_x, mut min_y) = (f32::MAX, f32::MAX);
+        let (mut max_x, mut max_y) = (f32::MIN, f32::MIN);
+        for &(x, y) in points {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+        let half = (max_x - min_x).max(max_y - min_y) / 2.0 + 1.0;
+        let mut tree = Self {
+            nodes: vec![QuadNode::empty((min_x + max_x) / 2.0, (min_y + max_y) / 2.0, half)],
+        };
+        for (i, &(x, y)) in points.iter().enumerate() {
+            tree.insert(0, i, x, y, 0);
+        }
+        tree
+    }
+
+    fn insert(&mut self, node: usize, i: usize, x: f32, y: f32, depth: usize) {
+        let n = &mut self.nodes[node];
+        n.mass += 1.0;
+        n.mx += (x - n.mx) / n.mass;
+        n.my += (y - n.my) / n.mass;
+        if n.mass == 1.0 {
+            n.point = Some((i, x, y));
+            return;
+        }
+        if depth >= QUADTREE_MAX_DEPTH {
+            // 重合的点合并为一个质点
+            n.point = None;
+            return;
+        }
+
+        let existing = n.point.take();
+        if n.children.is_none() {
+            self.subdivide(node);
+        }
+        if let Some((j, px, py)) = existing {
+            let child = self.child_for(node, px, py);
+            self.insert(child, j, px, py, depth + 1);
+        }
+        let child = self.child_for(node, x, y);
+        self.insert(child, i, x, y, depth + 1);
+    }
+
+    fn subdivide(&mut self, node: usize) {
+        let (cx, cy, half) = {
+            let n = &self.nodes[node];
+            (n.cx, n.cy, n.half / 2.0)
+        };
+        let first = self.nodes.len();
+        for (dx, dy) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+            self.nodes.push(QuadNode::empty(cx + dx * half, cy + dy * half, half));
+        }
+        self.nodes[node].children = Some([first, first + 1, first + 2, first + 3]);
+    }
+
+    fn child_for(&self, node: usize, x: f32, y: f32) -> usize {
+        let n = &self.nodes[node];
+        let quadrant = usize::from(x >= n.cx) + 2 * usize::from(y >= n.cy);
+        n.children.map_or(node, |children| children[quadrant])
+    }
+
+    /// 其余所有点对点 i 的斥力；足够远的区域按质心整体计算
+    fn repulsion(&self, i: usize, (x, y): (f32, f32), theta: f32, strength: f32) -> (f32, f32) {
+        let (mut fx, mut fy) = (0.0, 0.0);
+        let mut stack = vec![0];
+        while let Some(idx) = stack.pop() {
+            let n = &self.nodes[idx];
+            if n.mass == 0.0 || matches!(n.point, Some((j, _, _)) if j == i) {
+                continue;
+            }
+            let dx = x - n.mx;
+            let dy = y - n.my;
+            let dist_sq = dx * dx + dy * dy;
+            match n.children {
+                // 区域宽度与距离之比超过 theta 时展开子区域
+                Some(children) if 4.0 * n.half * n.half > theta * theta * dist_sq => stack.extend(children),
+                _ => {
+                    let dist = dist_sq.sqrt().max(0.1);
+                    let f = strength * n.mass / dist_sq.max(0.01);
+                    fx += (dx / dist) * f;
+                    fy += (dy / dist) * f;
+                }
+            }
+        }
+        (fx, fy)
+    }
+}
+
+/// 布局中间帧的节点坐标
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodePosition {
+    pub id: String,
+    pub x: f32,
+    pub y: f32,
+}
+
+/// 布局中间帧（`graph-layout-tick` 事件载荷）
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LayoutTick {
+    pub iteration: usize,
+    pub total_iterations: usize,
+    pub positions: Vec<NodePosition>,
+}
+
+/// 带焦点跳数的图谱（`get_graph_with_focus` 返回值）
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FocusedGraph {
+    #[serde(flatten)]
+    pub graph: GraphData,
+    /// 节点 ID -> 距焦点的跳数，不可达的节点不出现
+    pub hop_distances: HashMap<String, u32>,
+}
+
+/// 在无向图上从焦点节点 BFS，返回各可达节点的跳数（焦点自身为 0）
+///
+/// 焦点不在图中时返回空表
+pub fn annotate_hop_distance(graph_data: &GraphData, focus_id: &str) -> HashMap<String, u32> {
+    let mut adjacency: HashMap<&str, Vec<&str>> = HashMap::new();
+    for link in &graph_data.links {
+        adjacency.entry(link.source.as_str()).or_default().push(link.target.as_str());
+        adjacency.entry(link.target.as_str()).or_default().push(link.source.as_str());
+    }
+
+    let mut distances = HashMap::new();
+    if !graph_data.nodes.iter().any(|n| n.id == focus_id) {
+        return distances;
+    }
+
+    distances.insert(focus_id.to_string(), 0);
+    let mut queue = VecDeque::from([(focus_id, 0u32)]);
+    while let Some((id, hops)) = queue.pop_front() {
+        for &neighbor in adjacency.get(id).into_iter().flatten() {
+            if !distances.contains_key(neighbor) {
+                distances.insert(neighbor.to_string(), hops + 1);
+                queue.push_back((neighbor, hops + 1));
+            }
+        }
+    }
+    distances
+}
+
+/// 局部图谱默认的节点数上限
+pub const DEFAULT_LOCAL_GRAPH_MAX_NODES: usize = 200;
+
+/// 以某张卡片为中心的局部图谱（`get_local_graph` 返回值）
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalGraph {
+    #[serde(flatten)]
+    pub graph: GraphData,
+    /// 在跳数范围内、但因节点数上限被截掉的卡片 ID（离中心由近到远）
+    pub truncated: Vec<String>,
+}
+
+/// 沿出链和反向链接从中心卡片 BFS 到 `depth` 跳，只对这些卡片的导出子图计算布局。
+/// 按距离由近到远保留至多 `max_nodes` 个节点（中心总会保留），中心卡片不存在时返回 None
+pub fn compute_local_graph(
+    cards: Vec<CardListItem>,
+    center_id: &str,
+    depth: usize,
+    max_nodes: usize,
+) -> Option<LocalGraph> {
+    let center = cards.iter().position(|c| c.id == center_id)?;
+    let resolver = LinkResolver::new(&cards);
+    let indices: HashMap<&str, usize> = cards.iter().enumerate().map(|(i, c)| (c.id.as_str(), i)).collect();
+    let mut adjacency: Vec<Vec<usize>> = vec![Vec::new(); cards.len()];
+    for (source, card) in cards.iter().enumerate() {
+        for link in &card.links {
+            if let Some(&target) = resolver.resolve(link).and_then(|id| indices.get(id.as_str())) {
+                if source != target {
+                    adjacency[source].push(target);
+                    adjacency[target].push(source);
+                }
+            }
+        }
+    }
+
+    let mut visited = vec![false; cards.len()];
+    visited[center] = true;
+    let mut order = vec![center];
+    let mut queue = VecDeque::from([(center, 0usize)]);
+    while let Some((node, hops)) = queue.pop_front() {
+        if hops >= depth {
+            continue;
+        }
+        let mut neighbors = adjacency[node].clone();
+        neighbors.sort_unstable();
+        neighbors.dedup();
+        for neighbor in neighbors {
+            if !visited[neighbor] {
+                visited[neighbor] = true;
+                order.push(neighbor);
+                queue.push_back((neighbor, hops + 1));
+            }
+        }
+    }
+
+    let keep = max_nodes.max(1).min(order.len());
+    let truncated = order[keep..].iter().map(|&i| cards[i].id.clone()).collect();
+    let mut kept = vec![false; cards.len()];
+    for &i in &order[..keep] {
+        kept[i] = true;
+    }
+    let subgraph: Vec<CardListItem> = cards
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| kept[*i])
+        .map(|(_, c)| c)
+        .collect();
+
+    Some(LocalGraph {
+        graph: compute_layout(subgraph),
+        truncated,
+    })
+}
+
+/// 按节点数限制迭代次数，避免超大图失控
+pub fn cap_layout_iterations(requested: usize, node_count: usize) -> usize {
+    let pairs = node_count.saturating_mul(node_count).max(1);
+    let budget = (LAYOUT_PAIR_BUDGET / pairs).max(1);
+    requested.min(MAX_LAYOUT_ITERATIONS).min(budget)
+}
+
 /// 计算图谱布局 (原有函数，保持兼容)
 pub fn compute_layout(cards: Vec<CardListItem>) -> GraphData {
-    let mut graph: Graph<String, (), Undirected> = Graph::new_undirected();
+    compute_layout_streaming(cards, LAYOUT_ITERATIONS, 0, DEFAULT_BARNES_HUT_THETA, || false, |_| {})
+}
+
+/// 计算图谱布局，每 `tick_every` 次迭代回调一次中间坐标（0 表示不回调）
+///
+/// 斥力用 Barnes-Hut 四叉树近似（`theta` 越大越快、越粗略），引力仍逐条边计算并乘以边权重，
+/// 同一对卡片之间的链接越多靠得越近。
+/// `should_stop` 返回 true 时提前结束，返回当前坐标
+pub fn compute_layout_streaming(
+    cards: Vec<CardListItem>,
+    iterations: usize,
+    tick_every: usize,
+    theta: f32,
+    should_stop: impl Fn() -> bool,
+    mut on_tick: impl FnMut(LayoutTick),
+) -> GraphData {
+    let mut graph: Graph<String, f32, Undirected> = Graph::new_undirected();
     let mut node_indices: HashMap<String, NodeIndex> = HashMap::new();
     let mut node_states: HashMap<String, NodeState> = HashMap::new();
     let mut title_to_id: HashMap<String, String> = HashMap::new();
@@ -559,10 +1121,14 @@ pub fn compute_layout(cards: Vec<CardListItem>) -> GraphData {
 
                 if let Some(tid) = target_id {
                     if let Some(&target_idx) = node_indices.get(&tid) {
-                        if graph.find_edge(source_idx, target_idx).is_none()
-                            && source_idx != target_idx
-                        {
-                            graph.add_edge(source_idx, target_idx, ());
+                        if source_idx != target_idx {
+                            // 重复链接累加到同一条边的权重上
+                            match graph.find_edge(source_idx, target_idx) {
+                                Some(edge) => graph[edge] += 1.0,
+                                None => {
+                                    graph.add_edge(source_idx, target_idx, 1.0);
+                                }
+                            }
                         }
                     }
                 }
@@ -571,7 +1137,7 @@ pub fn compute_layout(cards: Vec<CardListItem>) -> GraphData {
     }
 
     // 2. 计算 PageRank (使用临时有向图)
-    let pagerank = compute_pagerank_for_cards(&cards, &node_indices, &title_to_id);
+    let pagerank: HashMap<String, f32> = rank_cards(&cards).into_iter().collect();
 
     // 3. 计算连通分量
     let num_clusters = connected_components(&graph);
@@ -601,45 +1167,31 @@ pub fn compute_layout(cards: Vec<CardListItem>) -> GraphData {
     }
 
     // 4. Run Force-Directed Simulation
-    let iterations = 100;
-    let k = 50.0;
-    let repulsion = 5000.0;
-    let dt = 0.1;
-    let damping = 0.85;
-
-    for _ in 0..iterations {
-        let ids: Vec<String> = node_states.keys().cloned().collect();
-        for i in 0..ids.len() {
-            for j in (i + 1)..ids.len() {
-                let id1 = &ids[i];
-                let id2 = &ids[j];
-
-                let (x1, y1) = {
-                    let n = &node_states[id1];
-                    (n.x, n.y)
-                };
-                let (x2, y2) = {
-                    let n = &node_states[id2];
-                    (n.x, n.y)
-                };
-
-                let dx = x1 - x2;
-                let dy = y1 - y2;
-                let dist_sq = dx * dx + dy * dy;
-                let dist = dist_sq.sqrt().max(0.1);
-
-                let f = repulsion / dist_sq;
-                let fx = (dx / dist) * f;
-                let fy = (dy / dist) * f;
+    let iterations = cap_layout_iterations(iterations, node_states.len());
+    let k = LAYOUT_SPRING_LENGTH;
+    let repulsion = LAYOUT_REPULSION;
+    let dt = LAYOUT_TIME_STEP;
+    let damping = LAYOUT_VELOCITY_DAMPING;
+    let ids: Vec<String> = node_states.keys().cloned().collect();
+
+    for iteration in 0..iterations {
+        if should_stop() {
+            break;
+        }
 
-                if let Some(n1) = node_states.get_mut(id1) {
-                    n1.vx += fx;
-                    n1.vy += fy;
-                }
-                if let Some(n2) = node_states.get_mut(id2) {
-                    n2.vx -= fx;
-                    n2.vy -= fy;
-                }
+        let points: Vec<(f32, f32)> = ids
+            .iter()
+            .map(|id| {
+                let n = &node_states[id];
+                (n.x, n.y)
+            })
+            .collect();
+        let tree = QuadTree::build(&points);
+        for (i, id) in ids.iter().enumerate() {
+            let (fx, fy) = tree.repulsion(i, points[i], theta, repulsion);
+            if let Some(n) = node_states.get_mut(id) {
+                n.vx += fx;
+                n.vy += fy;
             }
         }
 
@@ -661,7 +1213,7 @@ pub fn compute_layout(cards: Vec<CardListItem>) -> GraphData {
                 let dy = y1 - y2;
                 let dist = (dx * dx + dy * dy).sqrt().max(0.1);
 
-                let f = (dist * dist) / k;
+                let f = graph[edge] * (dist * dist) / k;
                 let fx = (dx / dist) * f;
                 let fy = (dy / dist) * f;
 
@@ -682,6 +1234,21 @@ pub fn compute_layout(cards: Vec<CardListItem>) -> GraphData {
             node.x += node.vx * dt;
             node.y += node.vy * dt;
         }
+
+        if tick_every > 0 && (iteration + 1) % tick_every == 0 && iteration + 1 < iterations {
+            on_tick(LayoutTick {
+                iteration: iteration + 1,
+                total_iterations: iterations,
+                positions: node_states
+                    .iter()
+                    .map(|(id, n)| NodePosition {
+                        id: id.clone(),
+                        x: n.x,
+                        y: n.y,
+                    })
+                    .collect(),
+            });
+        }
     }
 
     // 5. Export Data
@@ -719,7 +1286,11 @@ pub fn compute_layout(cards: Vec<CardListItem>) -> GraphData {
 
     for edge in graph.edge_indices() {
         if let Some((s, t)) = graph.edge_endpoints(edge) {
-            final_links.push((graph[s].clone(), graph[t].clone()));
+            final_links.push(GraphLink {
+                source: graph[s].clone(),
+                target: graph[t].clone(),
+                weight: graph[edge],
+            });
         }
     }
 
@@ -731,85 +1302,870 @@ pub fn compute_layout(cards: Vec<CardListItem>) -> GraphData {
     }
 }
 
-/// 为卡片计算 PageRank (辅助函数)
-fn compute_pagerank_for_cards(
-    cards: &[CardListItem],
-    _node_indices: &HashMap<String, NodeIndex>,
-    title_to_id: &HashMap<String, String>,
-) -> HashMap<String, f32> {
-    // 构建有向图
+// ============ PageRank ============
+
+/// PageRank 阻尼系数
+pub const PAGERANK_DAMPING: f32 = 0.85;
+/// 相邻两轮分数变化总和（L1）小于该值即视为收敛
+const PAGERANK_TOLERANCE: f32 = 1e-6;
+/// 未收敛时的迭代上限
+const PAGERANK_MAX_ITERATIONS: usize = 100;
+
+/// 在有向图上迭代计算 PageRank，直到收敛或达到迭代上限。
+/// 没有出链的节点把分数均分给所有节点，分数总和保持为 1
+fn pagerank<N>(graph: &DiGraph<N, ()>, damping: f32, max_iterations: usize) -> HashMap<NodeIndex, f32> {
+    let n = graph.node_count();
+    if n == 0 {
+        return HashMap::new();
+    }
+
+    let out_degree: Vec<usize> = graph
+        .node_indices()
+        .map(|idx| graph.edges_directed(idx, Direction::Outgoing).count())
+        .collect();
+    let mut ranks = vec![1.0 / n as f32; n];
+
+    for _ in 0..max_iterations {
+        let dangling: f32 = (0..n).filter(|&i| out_degree[i] == 0).map(|i| ranks[i]).sum();
+        let base = (1.0 - damping + damping * dangling) / n as f32;
+        let mut next = vec![base; n];
+        for edge in graph.edge_references() {
+            let source = edge.source().index();
+            next[edge.target().index()] += damping * ranks[source] / out_degree[source] as f32;
+        }
+
+        let delta: f32 = next.iter().zip(&ranks).map(|(a, b)| (a - b).abs()).sum();
+        ranks = next;
+        if delta < PAGERANK_TOLERANCE {
+            break;
+        }
+    }
+
+    graph.node_indices().map(|idx| (idx, ranks[idx.index()])).collect()
+}
+
+/// 按卡片出链（ID、别名、标题均可解析）构建有向图计算 PageRank，按分数从高到低返回
+pub fn rank_cards(cards: &[CardListItem]) -> Vec<(String, f32)> {
+    let resolver = LinkResolver::new(cards);
     let mut digraph: DiGraph<String, ()> = DiGraph::new();
-    let mut di_indices: HashMap<String, NodeIndex> = HashMap::new();
+    let mut indices: HashMap<String, NodeIndex> = HashMap::new();
+    for card in cards {
+        indices.insert(card.id.clone(), digraph.add_node(card.id.clone()));
+    }
 
     for card in cards {
-        let idx = digraph.add_node(card.id.clone());
-        di_indices.insert(card.id.clone(), idx);
+        let source = indices[&card.id];
+        for link in &card.links {
+            let Some(target) = resolver.resolve(link).and_then(|id| indices.get(&id).copied()) else {
+                continue;
+            };
+            if source != target && digraph.find_edge(source, target).is_none() {
+                digraph.add_edge(source, target, ());
+            }
+        }
     }
 
+    let ranks = pagerank(&digraph, PAGERANK_DAMPING, PAGERANK_MAX_ITERATIONS);
+    let mut ranked: Vec<(String, f32)> = ranks
+        .into_iter()
+        .map(|(idx, score)| (digraph[idx].clone(), score))
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked
+}
+
+// ============ 最短路径 ============
+
+/// 路径上的一张卡片
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PathStep {
+    pub id: String,
+    pub title: String,
+}
+
+/// 求两张卡片之间跳数最少的链接路径（含两端），不连通或卡片不存在时返回 None。
+/// `directed` 为 false 时出链和反向链接都可以走
+pub fn shortest_path(cards: &[CardListItem], from_id: &str, to_id: &str, directed: bool) -> Option<Vec<PathStep>> {
+    let resolver = LinkResolver::new(cards);
+    let mut digraph: DiGraph<usize, ()> = DiGraph::new();
+    let mut indices: HashMap<&str, NodeIndex> = HashMap::new();
+    for (i, card) in cards.iter().enumerate() {
+        indices.insert(card.id.as_str(), digraph.add_node(i));
+    }
+    let start = *indices.get(from_id)?;
+    let goal = *indices.get(to_id)?;
+
     for card in cards {
-        if let Some(&source_idx) = di_indices.get(&card.id) {
-            for link_text in &card.links {
-                let target_id = if di_indices.contains_key(link_text) {
-                    Some(link_text.clone())
-                } else {
-                    title_to_id.get(link_text).cloned()
-                };
+        let source = indices[card.id.as_str()];
+        for link in &card.links {
+            let Some(target) = resolver.resolve(link).and_then(|id| indices.get(id.as_str()).copied()) else {
+                continue;
+            };
+            if source != target && digraph.find_edge(source, target).is_none() {
+                digraph.add_edge(source, target, ());
+            }
+        }
+    }
 
-                if let Some(tid) = target_id {
-                    if let Some(&target_idx) = di_indices.get(&tid) {
-                        if source_idx != target_idx {
-                            digraph.add_edge(source_idx, target_idx, ());
-                        }
+    // 边权均为 1，零启发的 A* 即 Dijkstra，并直接给出路径
+    let (_, path) = if directed {
+        astar(&digraph, start, |n| n == goal, |_| 1u32, |_| 0)?
+    } else {
+        let undirected: Graph<usize, (), Undirected> = digraph.clone().into_edge_type();
+        astar(&undirected, start, |n| n == goal, |_| 1u32, |_| 0)?
+    };
+
+    Some(
+        path.into_iter()
+            .map(|idx| {
+                let card = &cards[digraph[idx]];
+                PathStep {
+                    id: card.id.clone(),
+                    title: card.title.clone(),
+                }
+            })
+            .collect(),
+    )
+}
+
+// ============ 社区发现 ============
+
+/// 默认分辨率，越大社区越小越多
+pub const DEFAULT_COMMUNITY_RESOLUTION: f64 = 1.0;
+/// Louvain 的层数上限（每层都会合并社区，正常几层内就会停止）
+const LOUVAIN_MAX_LEVELS: usize = 32;
+
+/// 按链接紧密程度划分的主题社区
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Cluster {
+    pub id: usize,
+    pub card_ids: Vec<String>,
+    /// 社区内出现最多的标签，没有卡片带标签时为 None
+    pub label: Option<String>,
+}
+
+/// Louvain 社区发现：先逐点移动到模块度增益最大的相邻社区，再把社区合并成超级节点重复，
+/// 直到没有节点移动。`adjacency[i][j]` 为无向边权重（对称），返回每个节点所属社区的编号
+fn louvain(adjacency: &[HashMap<usize, f64>], resolution: f64) -> Vec<usize> {
+    let n = adjacency.len();
+    let mut membership: Vec<usize> = (0..n).collect();
+    let total: f64 = adjacency.iter().flat_map(|row| row.values()).sum();
+    if total == 0.0 {
+        return membership;
+    }
+
+    let mut graph: Vec<HashMap<usize, f64>> = adjacency.to_vec();
+    for _ in 0..LOUVAIN_MAX_LEVELS {
+        let size = graph.len();
+        let degree: Vec<f64> = graph.iter().map(|row| row.values().sum()).collect();
+        let mut community: Vec<usize> = (0..size).collect();
+        let mut community_degree = degree.clone();
+
+        let mut moved_any = false;
+        loop {
+            let mut moved = false;
+            for node in 0..size {
+                let current = community[node];
+                community_degree[current] -= degree[node];
+
+                // 到各相邻社区的边权重之和（不含自环）
+                let mut links: HashMap<usize, f64> = HashMap::new();
+                for (&neighbor, &weight) in &graph[node] {
+                    if neighbor != node {
+                        *links.entry(community[neighbor]).or_insert(0.0) += weight;
+                    }
+                }
+
+                let gain = |c: usize, w: f64| w - resolution * community_degree[c] * degree[node] / total;
+                let mut best = current;
+                let mut best_gain = gain(current, links.get(&current).copied().unwrap_or(0.0));
+                let mut candidates: Vec<(usize, f64)> = links.into_iter().collect();
+                candidates.sort_by_key(|(c, _)| *c);
+                for (c, w) in candidates {
+                    let g = gain(c, w);
+                    if g > best_gain + 1e-12 {
+                        best = c;
+                        best_gain = g;
                     }
                 }
+
+                community_degree[best] += degree[node];
+                if best != current {
+                    community[node] = best;
+                    moved = true;
+                    moved_any = true;
+                }
+            }
+            if !moved {
+                break;
             }
         }
+
+        if !moved_any {
+            break;
+        }
+
+        // 社区重新编号，合并为下一层的超级节点
+        let mut renumber: HashMap<usize, usize> = HashMap::new();
+        for c in &community {
+            let next = renumber.len();
+            renumber.entry(*c).or_insert(next);
+        }
+        let mut aggregated: Vec<HashMap<usize, f64>> = vec![HashMap::new(); renumber.len()];
+        for (node, row) in graph.iter().enumerate() {
+            let from = renumber[&community[node]];
+            for (&neighbor, &weight) in row {
+                *aggregated[from].entry(renumber[&community[neighbor]]).or_insert(0.0) += weight;
+            }
+        }
+        for m in membership.iter_mut() {
+            *m = renumber[&community[*m]];
+        }
+        graph = aggregated;
     }
 
-    // 计算 PageRank
-    let n = digraph.node_count();
-    if n == 0 {
-        return HashMap::new();
+    membership
+}
+
+/// 在无向链接图上做 Louvain 社区发现，用于按主题给图谱着色。
+/// 孤立卡片各自成为单独的社区；结果按社区大小从大到小排列，`id` 即排列序号
+pub fn detect_communities(cards: &[CardListItem], resolution: f64) -> Vec<Cluster> {
+    let resolver = LinkResolver::new(cards);
+    let indices: HashMap<&str, usize> = cards.iter().enumerate().map(|(i, c)| (c.id.as_str(), i)).collect();
+    let mut adjacency: Vec<HashMap<usize, f64>> = vec![HashMap::new(); cards.len()];
+    for (source, card) in cards.iter().enumerate() {
+        for link in &card.links {
+            let Some(target) = resolver.resolve(link).and_then(|id| indices.get(id.as_str()).copied()) else {
+                continue;
+            };
+            // 互相引用或多次引用只算一条边
+            if source != target {
+                adjacency[source].insert(target, 1.0);
+                adjacency[target].insert(source, 1.0);
+            }
+        }
     }
 
-    let mut ranks: HashMap<NodeIndex, f32> = HashMap::new();
-    let initial_rank = 1.0 / n as f32;
-    let damping = 0.85;
-    let iterations = 20;
+    let membership = louvain(&adjacency, resolution);
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (node, community) in membership.into_iter().enumerate() {
+        groups.entry(community).or_default().push(node);
+    }
 
-    for idx in digraph.node_indices() {
-        ranks.insert(idx, initial_rank);
+    let mut clusters: Vec<Cluster> = groups
+        .into_values()
+        .map(|members| {
+            let mut tag_counts: HashMap<&str, usize> = HashMap::new();
+            for &i in &members {
+                for tag in &cards[i].tags {
+                    *tag_counts.entry(tag.as_str()).or_insert(0) += 1;
+                }
+            }
+            let label = tag_counts
+                .into_iter()
+                .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(a.0)))
+                .map(|(tag, _)| tag.to_string());
+            let mut card_ids: Vec<String> = members.iter().map(|&i| cards[i].id.clone()).collect();
+            card_ids.sort();
+            Cluster { id: 0, card_ids, label }
+        })
+        .collect();
+
+    clusters.sort_by(|a, b| b.card_ids.len().cmp(&a.card_ids.len()).then_with(|| a.card_ids.cmp(&b.card_ids)));
+    for (id, cluster) in clusters.iter_mut().enumerate() {
+        cluster.id = id;
     }
+    clusters
+}
+
+// ============ 图谱导出 ============
+
+/// 图谱导出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphExportFormat {
+    /// GraphML（Gephi、Cytoscape）
+    GraphMl,
+    /// Graphviz DOT
+    Dot,
+    /// node-link JSON（networkx、d3）
+    Json,
+}
+
+impl GraphExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            GraphExportFormat::GraphMl => "graphml",
+            GraphExportFormat::Dot => "dot",
+            GraphExportFormat::Json => "json",
+        }
+    }
+}
 
-    for _ in 0..iterations {
-        let mut new_ranks: HashMap<NodeIndex, f32> = HashMap::new();
+/// 导出用的有向边
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExportEdge {
+    pub source: String,
+    pub target: String,
+    /// 源卡片中解析到同一目标的链接数（如同时用 ID 和标题引用）
+    pub weight: f32,
+}
 
-        for idx in digraph.node_indices() {
-            let mut rank_sum = 0.0;
+/// 按卡片出链构建有向边（布局用的无向图会合并互相引用的两条边）
+pub fn directed_edges(cards: &[CardListItem]) -> Vec<ExportEdge> {
+    let resolver = LinkResolver::new(cards);
+    let mut edges: Vec<ExportEdge> = Vec::new();
+    let mut positions: HashMap<(String, String), usize> = HashMap::new();
 
-            for edge in digraph.edges_directed(idx, Direction::Incoming) {
-                let source_idx = edge.source();
-                let out_degree = digraph
-                    .edges_directed(source_idx, Direction::Outgoing)
-                    .count();
-                if out_degree > 0 {
-                    rank_sum += ranks[&source_idx] / out_degree as f32;
+    for card in cards {
+        for link in &card.links {
+            let Some(target) = resolver.resolve(link) else {
+                continue;
+            };
+            if target == card.id {
+                continue;
+            }
+            let key = (card.id.clone(), target.clone());
+            match positions.get(&key) {
+                Some(&pos) => edges[pos].weight += 1.0,
+                None => {
+                    positions.insert(key, edges.len());
+                    edges.push(ExportEdge {
+                        source: card.id.clone(),
+                        target,
+                        weight: 1.0,
+                    });
                 }
             }
+        }
+    }
+    edges
+}
+
+/// 按卡片 ID 收集标签，供导出时附在节点上
+pub fn card_tags(cards: &[CardListItem]) -> HashMap<String, Vec<String>> {
+    cards.iter().map(|c| (c.id.clone(), c.tags.clone())).collect()
+}
+
+/// 将图谱序列化为指定格式，节点包含标题、类型、标签、连接数和 PageRank
+///
+/// GraphML 和 DOT 中标签以逗号连接为一个字符串，JSON 中保留为数组
+pub fn export_graph(
+    data: &GraphData,
+    edges: &[ExportEdge],
+    tags: &HashMap<String, Vec<String>>,
+    format: GraphExportFormat,
+) -> String {
+    let mut nodes: Vec<&GraphNode> = data.nodes.iter().collect();
+    nodes.sort_by(|a, b| a.id.cmp(&b.id));
+    let node_tags = |id: &str| tags.get(id).map(Vec::as_slice).unwrap_or_default();
+
+    match format {
+        GraphExportFormat::GraphMl => {
+            let mut out = String::from(concat!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+                "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+                "  <key id=\"title\" for=\"node\" attr.name=\"title\" attr.type=\"string\"/>\n",
+                "  <key id=\"type\" for=\"node\" attr.name=\"type\" attr.type=\"string\"/>\n",
+                "  <key id=\"tags\" for=\"node\" attr.name=\"tags\" attr.type=\"string\"/>\n",
+                "  <key id=\"linkCount\" for=\"node\" attr.name=\"linkCount\" attr.type=\"int\"/>\n",
+                "  <key id=\"pagerank\" for=\"node\" attr.name=\"pagerank\" attr.type=\"double\"/>\n",
+                "  <key id=\"weight\" for=\"edge\" attr.name=\"weight\" attr.type=\"double\"/>\n",
+                "  <graph id=\"zentri\" edgedefault=\"directed\">\n",
+            ));
+            for node in nodes {
+                out.push_str(&format!(
+                    "    <node id=\"{}\">\n      <data key=\"title\">{}</data>\n      <data key=\"type\">{}</data>\n      <data key=\"tags\">{}</data>\n      <data key=\"linkCount\">{}</data>\n      <data key=\"pagerank\">{}</data>\n    </node>\n",
+                    xml_escape(&node.id),
+                    xml_escape(&node.title),
+                    xml_escape(&node.card_type),
+                    xml_escape(&node_tags(&node.id).join(",")),
+                    node.link_count,
+                    node.importance
+                ));
+            }
+            for (i, edge) in edges.iter().enumerate() {
+                out.push_str(&format!(
+                    "    <edge id=\"e{}\" source=\"{}\" target=\"{}\">\n      <data key=\"weight\">{}</data>\n    </edge>\n",
+                    i,
+                    xml_escape(&edge.source),
+                    xml_escape(&edge.target),
+                    edge.weight
+                ));
+            }
+            out.push_str("  </graph>\n</graphml>\n");
+            out
+        }
+        GraphExportFormat::Dot => {
+            let mut out = String::from("digraph zentri {\n");
+            for node in nodes {
+                out.push_str(&format!(
+                    "  \"{}\" [label=\"{}\", type=\"{}\", tags=\"{}\", link_count={}, pagerank={}];\n",
+                    dot_escape(&node.id),
+                    dot_escape(&node.title),
+                    dot_escape(&node.card_type),
+                    dot_escape(&node_tags(&node.id).join(",")),
+                    node.link_count,
+                    node.importance
+                ));
+            }
+            for edge in edges {
+                out.push_str(&format!(
+                    "  \"{}\" -> \"{}\" [weight={}];\n",
+                    dot_escape(&edge.source),
+                    dot_escape(&edge.target),
+                    edge.weight
+                ));
+            }
+            out.push_str("}\n");
+            out
+        }
+        GraphExportFormat::Json => serde_json::json!({
+            "directed": true,
+            "multigraph": false,
+            "nodes": nodes
+                .iter()
+                .map(|n| serde_json::json!({
+                    "id": n.id,
+                    "title": n.title,
+                    "type": n.card_type,
+                    "tags": node_tags(&n.id),
+                    "linkCount": n.link_count,
+                    "pagerank": n.importance,
+                }))
+                .collect::<Vec<_>>(),
+            "links": edges,
+        })
+        .to_string(),
+    }
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn dot_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CardType;
+
+    fn card(id: &str, title: &str, aliases: &[&str]) -> CardListItem {
+        CardListItem {
+            id: id.to_string(),
+            path: String::new(),
+            title: title.to_string(),
+            tags: vec![],
+            card_type: CardType::Permanent,
+            preview: None,
+            created_at: 0,
+            modified_at: 0,
+            aliases: aliases.iter().map(|a| a.to_string()).collect(),
+            links: vec![],
+            source_id: None,
+            pinned: false,
+            word_count: 0,
+            reading_minutes: 0,
+            archived: false,
+            review: None,
+            encrypted: false,
+        }
+    }
+
+    #[test]
+    fn test_link_resolver_precedence() {
+        let resolver = LinkResolver::new(&[
+            card("a", "Entropy", &[]),
+            card("b", "Thermodynamics", &["Entropy"]),
+        ]);
+        assert_eq!(resolver.resolve("a").as_deref(), Some("a"));
+        // 别名优先于标题
+        assert_eq!(resolver.resolve("Entropy").as_deref(), Some("b"));
+        assert_eq!(resolver.resolve("Thermodynamics").as_deref(), Some("b"));
+
+        let broken = resolver.resolve_target("Missing");
+        assert!(!broken.exists);
+        assert_eq!(broken.display_text, "Missing");
+        assert_eq!(resolver.resolve_target("a").display_text, "Entropy");
+    }
+
+    #[test]
+    fn test_streaming_layout_ticks_and_stops() {
+        let cards = vec![card("a", "A", &[]), card("b", "B", &[]), card("c", "C", &[])];
+
+        let mut ticks = Vec::new();
+        let data = compute_layout_streaming(cards.clone(), 20, 5, DEFAULT_BARNES_HUT_THETA, || false, |t| ticks.push(t.iteration));
+        assert_eq!(ticks, vec![5, 10, 15]);
+        assert_eq!(data.nodes.len(), 3);
+
+        let calls = std::cell::Cell::new(0);
+        let mut stopped_ticks = 0;
+        compute_layout_streaming(
+            cards,
+            20,
+            1,
+            DEFAULT_BARNES_HUT_THETA,
+            || {
+                calls.set(calls.get() + 1);
+                calls.get() > 3
+            },
+            |_| stopped_ticks += 1,
+        );
+        assert_eq!(stopped_ticks, 3);
+    }
 
-            new_ranks.insert(idx, (1.0 - damping) / n as f32 + damping * rank_sum);
+    #[test]
+    fn test_detect_communities_splits_two_groups() {
+        let mut cards: Vec<CardListItem> = ["a1", "a2", "a3", "b1", "b2", "b3", "solo"]
+            .iter()
+            .map(|id| card(id, &id.to_uppercase(), &[]))
+            .collect();
+        // 两个三角形，仅靠 a3 -> b1 一条边相连；solo 没有任何链接
+        let links: [&[&str]; 6] = [&["a2", "a3"], &["a3"], &["b1"], &["b2", "b3"], &["b3"], &[]];
+        for (card, links) in cards.iter_mut().zip(links) {
+            card.links = links.iter().map(|l| l.to_string()).collect();
+        }
+        for card in &mut cards[..3] {
+            card.tags = vec!["rust".to_string()];
         }
+        cards[3].tags = vec!["cooking".to_string(), "rust".to_string()];
+        cards[4].tags = vec!["cooking".to_string()];
+        cards[5].tags = vec!["cooking".to_string()];
+
+        let clusters = detect_communities(&cards, DEFAULT_COMMUNITY_RESOLUTION);
+        assert_eq!(clusters.len(), 3);
+        assert_eq!(clusters[0].card_ids, vec!["a1", "a2", "a3"]);
+        assert_eq!(clusters[0].label.as_deref(), Some("rust"));
+        assert_eq!(clusters[1].card_ids, vec!["b1", "b2", "b3"]);
+        assert_eq!(clusters[1].label.as_deref(), Some("cooking"));
+        assert_eq!(clusters[2].card_ids, vec!["solo"]);
+        assert_eq!(clusters[2].label, None);
+        assert_eq!(clusters.iter().map(|c| c.id).collect::<Vec<_>>(), vec![0, 1, 2]);
+
+        // 分辨率足够低时两个三角形合并为一个社区
+        assert_eq!(detect_communities(&cards, 0.1).len(), 2);
+    }
 
-        ranks = new_ranks;
+    #[test]
+    fn test_rank_cards_finds_hub() {
+        let mut cards = vec![
+            card("hub", "Hub", &["Center"]),
+            card("a", "A", &[]),
+            card("b", "B", &[]),
+            card("c", "C", &[]),
+            card("lonely", "Lonely", &[]),
+        ];
+        // a、b、c 都指向 hub（分别通过 ID、标题和别名），hub 只指向 a
+        cards[0].links = vec!["a".to_string()];
+        cards[1].links = vec!["hub".to_string(), "Hub".to_string()];
+        cards[2].links = vec!["Hub".to_string()];
+        cards[3].links = vec!["Center".to_string(), "b".to_string()];
+
+        let ranked = rank_cards(&cards);
+        assert_eq!(ranked.len(), 5);
+        assert_eq!(ranked[0].0, "hub");
+        // hub 唯一的出链让 a 排第二，无人引用的 lonely 排在最后
+        assert_eq!(ranked[1].0, "a");
+        assert_eq!(ranked[4].0, "lonely");
+        assert!(ranked.windows(2).all(|w| w[0].1 >= w[1].1));
+        let total: f32 = ranked.iter().map(|(_, score)| score).sum();
+        assert!((total - 1.0).abs() < 1e-4);
     }
 
-    // 转换结果
-    let mut result = HashMap::new();
-    for (card_id, &idx) in di_indices.iter() {
-        if let Some(&rank) = ranks.get(&idx) {
-            result.insert(card_id.clone(), rank);
+    /// 确定性的伪随机点，避免测试依赖随机数
+    fn scattered_points(n: usize) -> Vec<(f32, f32)> {
+        (0..n)
+            .map(|i| {
+                let t = i as f32;
+                ((t * 37.17).sin() * 500.0, (t * 91.43).cos() * 500.0)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_barnes_hut_repulsion_close_to_exact() {
+        let points = scattered_points(500);
+        let tree = QuadTree::build(&points);
+        let mut total_error = 0.0;
+        let mut total_magnitude = 0.0;
+        for (i, &p) in points.iter().enumerate() {
+            let exact = tree.repulsion(i, p, 0.0, 5000.0);
+            let approx = tree.repulsion(i, p, DEFAULT_BARNES_HUT_THETA, 5000.0);
+            total_error += ((exact.0 - approx.0).powi(2) + (exact.1 - approx.1).powi(2)).sqrt();
+            total_magnitude += (exact.0.powi(2) + exact.1.powi(2)).sqrt();
         }
+        assert!(total_error / total_magnitude < 0.05);
+
+        // 重合的点不会导致无限细分或 NaN
+        let tree = QuadTree::build(&[(1.0, 1.0), (1.0, 1.0), (1.0, 1.0)]);
+        let (fx, fy) = tree.repulsion(0, (1.0, 1.0), DEFAULT_BARNES_HUT_THETA, 5000.0);
+        assert!(fx.is_finite() && fy.is_finite());
+    }
+
+    /// 5000 个节点的布局耗时对比：cargo test --release -- --ignored bench_layout --nocapture
+    #[test]
+    #[ignore]
+    fn bench_layout_5000_nodes() {
+        let cards: Vec<CardListItem> = (0..5000)
+            .map(|i| {
+                let mut c = card(&format!("n{}", i), &format!("Node {}", i), &[]);
+                c.links = vec![format!("n{}", (i * 7 + 1) % 5000), format!("n{}", (i * 13 + 5) % 5000)];
+                c
+            })
+            .collect();
+
+        let spacing = |data: &GraphData| {
+            let n = data.nodes.len() as f32;
+            let (cx, cy) = data.nodes.iter().fold((0.0, 0.0), |(x, y), node| (x + node.x / n, y + node.y / n));
+            data.nodes.iter().map(|node| ((node.x - cx).powi(2) + (node.y - cy).powi(2)).sqrt()).sum::<f32>() / n
+        };
+
+        let start = std::time::Instant::now();
+        let exact = compute_layout_streaming(cards.clone(), 20, 0, 0.0, || false, |_| {});
+        let exact_time = start.elapsed();
+        let start = std::time::Instant::now();
+        let approx = compute_layout_streaming(cards, 20, 0, DEFAULT_BARNES_HUT_THETA, || false, |_| {});
+        let approx_time = start.elapsed();
+
+        println!(
+            "exact: {:?} (mean radius {:.1}), barnes-hut: {:?} (mean radius {:.1})",
+            exact_time,
+            spacing(&exact),
+            approx_time,
+            spacing(&approx)
+        );
+        assert!(approx_time < exact_time);
+    }
+
+    #[test]
+    fn test_cap_layout_iterations() {
+        assert_eq!(cap_layout_iterations(100, 10), 100);
+        assert_eq!(cap_layout_iterations(5000, 10), MAX_LAYOUT_ITERATIONS);
+        assert!(cap_layout_iterations(1000, 100_000) < 1000);
+    }
+
+    #[test]
+    fn test_repeated_links_add_edge_weight() {
+        // a 用 ID 和标题各链接一次 b，b 反向链接 a，c 只链接一次 a
+        let mut a = card("a", "A", &[]);
+        a.links = vec!["b".to_string(), "B".to_string()];
+        let mut b = card("b", "B", &[]);
+        b.links = vec!["a".to_string()];
+        let mut c = card("c", "C", &[]);
+        c.links = vec!["a".to_string()];
+        let data = compute_layout(vec![a, b, c]);
+
+        assert_eq!(data.links.len(), 2);
+        let weight = |x: &str, y: &str| {
+            data.links
+                .iter()
+                .find(|l| (l.source == x && l.target == y) || (l.source == y && l.target == x))
+                .map(|l| l.weight)
+        };
+        assert_eq!(weight("a", "b"), Some(3.0));
+        assert_eq!(weight("a", "c"), Some(1.0));
+        let node_a = data.nodes.iter().find(|n| n.id == "a").unwrap();
+        assert_eq!(node_a.link_count, 2);
     }
 
-    result
+    fn export_fixture() -> (GraphData, Vec<ExportEdge>, HashMap<String, Vec<String>>) {
+        let mut a = card("a", "A & \"B\"", &[]);
+        a.links = vec!["b".to_string(), "Bee".to_string()];
+        a.tags = vec!["rust".to_string(), "<graph>".to_string()];
+        let mut b = card("b", "Bee", &[]);
+        b.links = vec!["a".to_string()];
+        let cards = vec![a, b];
+        let edges = directed_edges(&cards);
+        let tags = card_tags(&cards);
+        (compute_layout(cards), edges, tags)
+    }
+
+    #[test]
+    fn test_annotate_hop_distance() {
+        let mut a = card("a", "A", &[]);
+        a.links = vec!["b".to_string()];
+        let mut c = card("c", "C", &[]);
+        c.links = vec!["b".to_string()];
+        let data = compute_layout(vec![a, card("b", "B", &[]), c, card("d", "D", &[])]);
+
+        let hops = annotate_hop_distance(&data, "a");
+        assert_eq!(hops.get("a"), Some(&0));
+        assert_eq!(hops.get("b"), Some(&1));
+        assert_eq!(hops.get("c"), Some(&2));
+        assert!(!hops.contains_key("d"));
+        assert!(annotate_hop_distance(&data, "missing").is_empty());
+    }
+
+    #[test]
+    fn test_local_graph_depth_and_truncation() {
+        // e -> a -> b -> c -> d
+        let mut cards: Vec<CardListItem> =
+            ["a", "b", "c", "d", "e"].iter().map(|id| card(id, &id.to_uppercase(), &[])).collect();
+        cards[0].links = vec!["B".to_string()];
+        cards[1].links = vec!["c".to_string()];
+        cards[2].links = vec!["d".to_string()];
+        cards[4].links = vec!["a".to_string()];
+
+        let ids = |g: &LocalGraph| {
+            let mut ids: Vec<String> = g.graph.nodes.iter().map(|n| n.id.clone()).collect();
+            ids.sort();
+            ids
+        };
+
+        // 深度 1：中心加上出链 b 和反向链接 e
+        let local = compute_local_graph(cards.clone(), "a", 1, 10).unwrap();
+        assert_eq!(ids(&local), vec!["a", "b", "e"]);
+        assert_eq!(local.graph.links.len(), 2);
+        assert!(local.truncated.is_empty());
+
+        let local = compute_local_graph(cards.clone(), "a", 2, 10).unwrap();
+        assert_eq!(ids(&local), vec!["a", "b", "c", "e"]);
+
+        let local = compute_local_graph(cards.clone(), "a", 2, 2).unwrap();
+        assert_eq!(ids(&local), vec!["a", "b"]);
+        assert_eq!(local.truncated, vec!["e", "c"]);
+
+        assert!(compute_local_graph(cards, "missing", 1, 10).is_none());
+    }
+
+    #[test]
+    fn test_shortest_path() {
+        // a -> b -> c，d -> c，e 孤立
+        let mut cards: Vec<CardListItem> =
+            ["a", "b", "c", "d", "e"].iter().map(|id| card(id, &id.to_uppercase(), &[])).collect();
+        cards[0].links = vec!["b".to_string()];
+        cards[1].links = vec!["C".to_string()];
+        cards[3].links = vec!["c".to_string()];
+        let ids = |path: Option<Vec<PathStep>>| path.map(|p| p.into_iter().map(|s| s.id).collect::<Vec<_>>());
+
+        // 直接链接
+        let direct = shortest_path(&cards, "a", "b", true).unwrap();
+        assert_eq!(direct, vec![
+            PathStep { id: "a".to_string(), title: "A".to_string() },
+            PathStep { id: "b".to_string(), title: "B".to_string() },
+        ]);
+
+        // 两跳（经标题解析的链接）
+        assert_eq!(ids(shortest_path(&cards, "a", "c", true)), Some(vec!["a".into(), "b".into(), "c".into()]));
+
+        // 有向时逆着链接走不通，无向时可以
+        assert_eq!(ids(shortest_path(&cards, "c", "a", true)), None);
+        assert_eq!(ids(shortest_path(&cards, "a", "d", false)), Some(vec!["a".into(), "b".into(), "c".into(), "d".into()]));
+
+        // 不可达或不存在
+        assert!(shortest_path(&cards, "a", "e", false).is_none());
+        assert!(shortest_path(&cards, "a", "missing", false).is_none());
+        assert_eq!(ids(shortest_path(&cards, "e", "e", true)), Some(vec!["e".into()]));
+    }
+
+    #[test]
+    fn test_update_card_is_incremental() {
+        // a -> b（按标题链接），c、d 互不相连
+        let mut cards: Vec<CardListItem> =
+            ["a", "b", "c", "d"].iter().map(|id| card(id, &id.to_uppercase(), &[])).collect();
+        cards[0].links = vec!["B".to_string()];
+        let engine = GraphEngine::new(Path::new("."));
+        engine.rebuild_with_cards(cards.clone());
+        let full = compute_layout(cards.clone());
+        engine.remember_layout(&full);
+        let position = |id: &str| full.nodes.iter().find(|n| n.id == id).map(|n| (n.x, n.y)).unwrap();
+
+        // c 改为链接 a：只新增 a-c 一条边，只有 c 和它的邻居会下发
+        cards[2].links = vec!["a".to_string()];
+        let delta = engine.update_card("c", Some(cards[2].clone()));
+        assert_eq!(delta.added_links, vec![("a".to_string(), "c".to_string())]);
+        assert!(delta.removed_links.is_empty());
+        let ids: Vec<&str> = delta.nodes.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "c"]);
+        let c = &delta.nodes[1];
+        assert_eq!(c.neighbors, vec!["a"]);
+        assert_ne!((c.x, c.y), position("c"));
+        assert_eq!(engine.get_backlinks("a").len(), 1);
+
+        // 新卡片 e 的别名 "B" 优先于 b 的标题：a -> b 变为 a -> e
+        let delta = engine.update_card("e", Some(card("e", "E", &["B"])));
+        assert_eq!(delta.added_links, vec![("a".to_string(), "e".to_string())]);
+        assert_eq!(delta.removed_links, vec![("a".to_string(), "b".to_string())]);
+        assert!(delta.nodes.iter().all(|n| n.id != "d"));
+
+        // 删除 a：与它相连的边一并移除
+        let delta = engine.update_card("a", None);
+        assert_eq!(delta.removed_nodes, vec!["a"]);
+        assert_eq!(delta.removed_links.len(), 2);
+        assert!(engine.get_backlinks("e").is_empty());
+        assert_eq!(engine.get_orphan_nodes().len(), 4);
+    }
+
+    #[test]
+    fn test_directed_edges_keep_both_directions() {
+        let (_, edges, _) = export_fixture();
+        assert_eq!(edges.len(), 2);
+        assert_eq!(edges[0].source, "a");
+        assert_eq!(edges[0].weight, 2.0);
+        assert_eq!(edges[1].source, "b");
+        assert_eq!(edges[1].target, "a");
+    }
+
+    #[test]
+    fn test_export_graphml_parses() {
+        let (data, edges, tags) = export_fixture();
+        let xml = export_graph(&data, &edges, &tags, GraphExportFormat::GraphMl);
+        let doc = roxmltree::Document::parse(&xml).unwrap();
+        let nodes = doc.descendants().filter(|n| n.has_tag_name("node")).count();
+        let edge_count = doc.descendants().filter(|n| n.has_tag_name("edge")).count();
+        assert_eq!((nodes, edge_count), (2, 2));
+        assert!(doc
+            .descendants()
+            .any(|n| n.has_tag_name("data") && n.text() == Some("A & \"B\"")));
+        let data_of = |key: &str| {
+            doc.descendants()
+                .filter(|n| n.has_tag_name("data") && n.attribute("key") == Some(key))
+                .map(|n| n.text().unwrap_or("").to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(data_of("tags"), vec!["rust,<graph>", ""]);
+        assert_eq!(data_of("linkCount"), vec!["1", "1"]);
+    }
+
+    #[test]
+    fn test_export_json_parses() {
+        let (data, edges, tags) = export_fixture();
+        let json: serde_json::Value =
+            serde_json::from_str(&export_graph(&data, &edges, &tags, GraphExportFormat::Json)).unwrap();
+        assert_eq!(json["directed"], true);
+        assert_eq!(json["nodes"].as_array().unwrap().len(), 2);
+        assert_eq!(json["links"][0]["weight"], 2.0);
+        assert_eq!(json["nodes"][0]["tags"], serde_json::json!(["rust", "<graph>"]));
+        assert_eq!(json["nodes"][1]["linkCount"], 1);
+    }
+
+    #[test]
+    fn test_export_dot_is_well_formed() {
+        let (data, edges, tags) = export_fixture();
+        let dot = export_graph(&data, &edges, &tags, GraphExportFormat::Dot);
+        let lines: Vec<&str> = dot.lines().collect();
+        assert_eq!(lines.first(), Some(&"digraph zentri {"));
+        assert_eq!(lines.last(), Some(&"}"));
+        // 每条语句以分号结束，引号成对（忽略转义的引号）
+        for line in &lines[1..lines.len() - 1] {
+            assert!(line.ends_with(';'));
+            assert_eq!(line.replace("\\\"", "").matches('"').count() % 2, 0);
+        }
+        assert_eq!(lines.iter().filter(|l| l.contains(" -> ")).count(), 2);
+        assert!(dot.contains(r#"label="A & \"B\"""#));
+        assert!(dot.contains(r#"tags="rust,<graph>", link_count=1"#));
+    }
 }
@@ -17,6 +17,7 @@ mod book_processor;
 mod commands;
 mod config;
 mod crdt;
+mod crypto;
 mod database;
 mod db;
 mod error;
@@ -74,12 +75,16 @@ pub fn run() {
 
         // 初始化索引器
         let index_path = vp.join(".zentri/index");
-        let indexer = search::Indexer::new(&index_path).ok();
+        let indexer = search::Indexer::new(&index_path)
+            .ok()
+            .map(|indexer| indexer.with_field_boosts(search::FieldBoosts::from_vault(&vp)));
 
-        // 初始化文件监听器
-        let watcher = VaultWatcher::new(&vp).ok();
+        // 初始化文件监听器（失败时记录到 watcher_status，启动后通知前端）
+        let watcher = VaultWatcher::new(&vp);
 
-        AppState::new_with_vault(db, vp, indexer, watcher)
+        let state = AppState::new_with_vault(db, vp, indexer, None);
+        state.set_watcher(watcher);
+        state
     } else {
         // 没有 vault_path，创建空状态（等待用户选择 vault）
         AppState::new_empty()
@@ -101,7 +106,40 @@ pub fn run() {
     // app.set_activation_policy(tauri::ActivationPolicy::Accessory);
 
     tauri::Builder::default()
-        .setup(|_| {
+        .setup(|app| {
+            // 文件监听器初始化失败时通知前端
+            let watcher_error = app.state::<AppState>().watcher_status.lock().unwrap().last_error.clone();
+            if let Some(error) = watcher_error {
+                let _ = app.emit("watcher-error", serde_json::json!({ "error": error }));
+            }
+
+            // 将卡片变更转发为前端事件
+            if let Some(mut changes) = app.state::<AppState>().take_card_change_receiver() {
+                let handle = app.handle().clone();
+                tauri::async_runtime::spawn(async move {
+                    while let Some(change) = changes.recv().await {
+                        let _ = handle.emit(state::CARD_CHANGED_EVENT, change);
+                    }
+                });
+            }
+
+            // 有 vault 时由后台任务推送文件变化，前端不必轮询
+            if app.state::<AppState>().vault_path.lock().unwrap().is_some() {
+                commands::spawn_file_change_task(app.handle().clone());
+            }
+
+            // 定期为有修改的 CRDT 文档创建自动快照
+            commands::spawn_autosnapshot_task(app.handle().clone());
+
+            // 上次会话未落盘的 CRDT 更新重放失败时通知前端
+            let crdt = app.state::<AppState>().crdt.lock().unwrap().clone();
+            for error in crdt.iter().flat_map(|c| c.recovery_errors().to_vec()) {
+                let _ = app.emit(
+                    commands::CRDT_PERSIST_ERROR_EVENT,
+                    commands::CrdtPersistError { doc_id: None, error },
+                );
+            }
+
             // 在 macOS 上，使用系统原生窗口控制按钮
             // 窗口装饰在 tauri.conf.json 中设置为 true，这样 macOS 会显示系统原生按钮
             // 在 Windows/Linux 上也会显示系统标题栏，但我们的自定义标题栏会覆盖它
@@ -266,45 +304,108 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             // Vault
             commands::set_initial_vault_path,
+            commands::initialize_vault,
             commands::get_vault_path,
+            commands::get_system_status,
+            commands::verify_vault_integrity,
+            commands::repair_vault,
+            commands::optimize_vault,
+            commands::import_obsidian_vault,
             commands::migrate_vault_structure,
             // Cards
             commands::get_cards,
             commands::get_card,
             commands::get_card_by_path,
             commands::create_card,
+            commands::list_templates,
             commands::update_card,
+            commands::rename_card,
             commands::delete_card,
+            commands::restore_card,
+            commands::get_card_revisions,
+            commands::restore_card_revision,
+            commands::set_card_revision_limit,
+            commands::export_card_to_markdown,
+            commands::export_vault_to_markdown,
+            commands::list_trashed_cards,
+            commands::purge_trashed_cards,
+            commands::rename_card_tag,
+            commands::delete_card_tag,
+            commands::find_in_cards,
+            commands::find_unlinked_mentions,
+            commands::get_backlinks_with_context,
+            commands::resolve_references,
+            commands::replace_in_cards,
+            commands::get_outgoing_links,
+            commands::set_card_pinned,
+            commands::set_card_encrypted,
+            commands::has_vault_passphrase,
+            commands::set_vault_passphrase,
+            commands::unlock_vault,
+            commands::lock_vault,
+            commands::is_vault_unlocked,
+            commands::get_pinned_cards,
+            commands::set_card_review,
+            commands::review_card,
+            commands::get_due_cards,
+            commands::batch_change_card_type,
+            commands::archive_card,
+            commands::unarchive_card,
+            commands::get_card_list,
             // Daily Notes
             commands::get_or_create_daily_note,
             commands::get_daily_note,
             commands::get_daily_notes,
+            commands::get_adjacent_daily_notes,
+            commands::get_daily_notes_in_range,
+            commands::rollover_tasks,
             // Search (P1 增强)
             commands::search_cards,
             commands::search_cards_filtered,
             commands::fuzzy_search_cards,
+            commands::create_saved_search,
+            commands::list_saved_searches,
+            commands::run_saved_search,
+            commands::delete_saved_search,
             commands::search_by_tag,
             commands::search_by_type,
             commands::sync_index,
-            commands::poll_file_changes,
+            commands::set_index_tokenizer,
+            commands::index_stats,
+            commands::poll_file_changes, // 已弃用：改用 vault-file-changed 事件
+            commands::restart_watcher,
             // Graph (P2 增强)
             commands::get_graph_data,
+            commands::get_graph_with_focus,
+            commands::get_local_graph,
+            commands::compute_layout_streaming,
+            commands::stop_layout,
             commands::get_backlinks,
             commands::get_card_importance,
+            commands::get_card_pagerank,
+            commands::get_card_path,
             commands::get_knowledge_clusters,
+            commands::get_topic_clusters,
             commands::get_orphan_nodes,
+            commands::update_card_in_graph,
             commands::rebuild_graph,
+            commands::export_graph,
             // CRDT (P0 新增)
             commands::crdt_get_state,
             commands::crdt_get_state_vector,
             commands::crdt_apply_update,
+            commands::crdt_undo,
+            commands::crdt_redo,
             commands::crdt_get_diff,
             commands::crdt_sync,
             commands::crdt_save,
             commands::crdt_flush_all,
             commands::crdt_create_snapshot,
             commands::crdt_list_snapshots,
+            commands::crdt_set_autosnapshot_interval,
+            commands::crdt_prune_snapshots,
             commands::crdt_restore_snapshot,
+            commands::crdt_diff_snapshots,
             commands::crdt_unload,
             // Sources
             commands::get_sources,
@@ -312,14 +413,27 @@ pub fn run() {
             commands::create_source,
             commands::update_source,
             commands::delete_source,
+            commands::hard_delete_source,
+            commands::restore_source,
+            commands::list_trashed_sources,
+            commands::purge_trashed_sources,
+            commands::get_continue_reading,
+            commands::get_reading_list,
+            commands::get_sources_filtered,
+            commands::list_source_tags,
+            commands::rename_source_tag,
+            commands::merge_source_tags,
             // Highlights
             commands::get_highlights_by_source,
             commands::get_all_highlights,
+            commands::search_highlights,
             commands::create_highlight,
             commands::delete_highlight,
             commands::update_highlight,
             commands::get_highlights_by_card,
             commands::get_backlinks_for_source,
+            commands::ai_summarize_highlights,
+            commands::promote_highlight_to_card,
             // Bookmarks
             commands::get_bookmarks_by_source,
             commands::get_all_bookmarks,
@@ -329,8 +443,12 @@ pub fn run() {
             commands::get_bookmark,
             // Web Reader
             commands::fetch_webpage,
+            commands::capture_url,
             commands::fetch_webpage_metadata,
+            commands::find_web_snapshot_by_url,
             commands::save_web_snapshot,
+            commands::get_reading_speed,
+            commands::set_reading_speed,
             commands::get_web_snapshot,
             commands::convert_to_markdown,
             // Canvas
@@ -341,6 +459,7 @@ pub fn run() {
             commands::delete_canvas,
             // Assets
             commands::save_image,
+            commands::save_highlight_image,
             commands::read_image,
             commands::delete_image,
             commands::read_local_file,
@@ -353,15 +472,31 @@ pub fn run() {
             // AI
             commands::ai_start_server,
             commands::ai_stop_server,
+            commands::ai_get_server_logs,
             commands::ai_check_status,
+            commands::ai_hardware_info,
             commands::ai_list_models,
+            commands::ai_refresh_model_catalog,
             commands::ai_list_downloaded_models,
             commands::ai_download_model,
+            commands::ai_cancel_download,
+            commands::ai_verify_model,
             commands::ai_set_active_model,
             commands::ai_chat,
+            commands::ai_estimate_context,
+            commands::create_chat_session,
+            commands::get_chat_session,
+            commands::list_chat_sessions,
+            commands::delete_chat_session,
             commands::ai_explain_text,
             commands::ai_rag_query,
+            commands::ai_ask_card,
             commands::ai_index_source,
+            commands::ai_reindex_source,
+            commands::get_rag_coverage,
+            commands::ai_rebuild_vector_index,
+            commands::ai_export_embeddings,
+            commands::ai_import_embeddings,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -14,6 +14,69 @@ pub struct Canvas {
     pub updated_at: i64,
 }
 
+/// Canvas 节点类型（对应 React Flow 节点的 `type` 字段）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeKind {
+    Card,
+    Highlight,
+    Snapshot,
+    Text,
+    Image,
+}
+
+impl NodeKind {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "card" => Some(NodeKind::Card),
+            "highlight" => Some(NodeKind::Highlight),
+            "snapshot" => Some(NodeKind::Snapshot),
+            "text" => Some(NodeKind::Text),
+            "image" => Some(NodeKind::Image),
+            _ => None,
+        }
+    }
+
+    /// 引用型节点在 `data` 中保存目标 ID 的字段
+    pub fn ref_field(&self) -> Option<&'static str> {
+        match self {
+            NodeKind::Card => Some("cardId"),
+            NodeKind::Highlight => Some("highlightId"),
+            // 网页快照按 source_id 一对一存储
+            NodeKind::Snapshot => Some("sourceId"),
+            NodeKind::Text | NodeKind::Image => None,
+        }
+    }
+}
+
+/// Canvas 节点对其他实体的引用
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeRef {
+    pub node_id: String,
+    pub kind: NodeKind,
+    pub target_id: String,
+}
+
+/// 从 React Flow 节点数组中提取所有引用型节点
+pub fn node_refs(nodes: &Value) -> Vec<NodeRef> {
+    let Some(nodes) = nodes.as_array() else {
+        return Vec::new();
+    };
+
+    nodes
+        .iter()
+        .filter_map(|node| {
+            let kind = NodeKind::from_str(node.get("type")?.as_str()?)?;
+            let target_id = node.get("data")?.get(kind.ref_field()?)?.as_str()?;
+            Some(NodeRef {
+                node_id: node.get("id").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+                kind,
+                target_id: target_id.to_string(),
+            })
+        })
+        .collect()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CanvasListItem {
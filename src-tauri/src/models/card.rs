@@ -42,22 +42,22 @@ impl CardType {
     }
 }
 
-/// Markdown 文件的 Frontmatter
+/// Markdown 文件的 Frontmatter（导出时省略空字段）
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Frontmatter {
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub title: Option<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub tags: Vec<String>,
-    #[serde(default, rename = "type")]
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
     pub card_type: Option<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub aliases: Vec<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub created: Option<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub modified: Option<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub source_id: Option<String>,
 }
 
@@ -109,6 +109,21 @@ pub struct Card {
     pub links: Vec<String>,
     #[serde(default)]
     pub source_id: Option<String>,
+    /// 是否置顶
+    #[serde(default)]
+    pub pinned: bool,
+    /// 字数（中日韩文字按字计，其他按词计）
+    #[serde(default)]
+    pub word_count: usize,
+    /// 是否已归档
+    #[serde(default)]
+    pub archived: bool,
+    /// 间隔重复复习状态，未加入复习时为 None
+    #[serde(default)]
+    pub review: Option<ReviewState>,
+    /// 正文是否加密；Vault 锁定时 content 为空
+    #[serde(default)]
+    pub encrypted: bool,
 }
 
 impl Card {
@@ -144,6 +159,19 @@ pub struct CardListItem {
     pub links: Vec<String>,
     #[serde(default)]
     pub source_id: Option<String>,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub word_count: usize,
+    /// 预计阅读分钟数
+    #[serde(default)]
+    pub reading_minutes: usize,
+    #[serde(default)]
+    pub archived: bool,
+    #[serde(default)]
+    pub review: Option<ReviewState>,
+    #[serde(default)]
+    pub encrypted: bool,
 }
 
 impl From<Card> for CardListItem {
@@ -161,6 +189,252 @@ impl From<Card> for CardListItem {
             aliases: card.aliases,
             links: card.links,
             source_id: card.source_id,
+            pinned: card.pinned,
+            word_count: card.word_count,
+            reading_minutes: reading_minutes(card.word_count),
+            archived: card.archived,
+            review: card.review,
+            encrypted: card.encrypted,
         }
     }
 }
+
+/// 回收站中的卡片
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashedCard {
+    #[serde(flatten)]
+    pub card: CardListItem,
+    /// 移入回收站的时间（毫秒）
+    pub deleted_at: i64,
+}
+
+/// 卡片的历史版本
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CardRevision {
+    /// 该版本的修改时间（毫秒），用于恢复
+    pub timestamp: i64,
+    pub title: String,
+    pub word_count: usize,
+}
+
+/// 新建卡片时可选的模板
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CardTemplate {
+    pub id: String,
+    pub name: String,
+    /// 是否为未被用户模板覆盖的内置模板
+    pub builtin: bool,
+}
+
+/// 卡片列表排序方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CardListSort {
+    /// 置顶优先，再按修改时间（新的在前）
+    #[default]
+    Modified,
+    /// 按字数（长的在前）
+    WordCount,
+}
+
+/// 卡片变更操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CardChangeOp {
+    Created,
+    Updated,
+    Deleted,
+}
+
+/// 卡片变更通知，以 `card-changed` 事件发送给前端
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CardChange {
+    pub op: CardChangeOp,
+    pub id: String,
+    /// 卡片类型，删除不存在的卡片时为空
+    #[serde(rename = "type")]
+    pub card_type: Option<CardType>,
+}
+
+/// 批量修改卡片类型时单张卡片的结果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypeChangeResult {
+    pub id: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+/// 引用目标类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReferenceKind {
+    Source,
+    Highlight,
+    Card,
+}
+
+/// 卡片正文中的 reference 节点：`{ type: "reference", attrs: { kind, id } }`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reference {
+    pub kind: ReferenceKind,
+    pub id: String,
+}
+
+/// 解析后的引用，附带目标的展示信息
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedReference {
+    pub kind: ReferenceKind,
+    pub id: String,
+    /// 目标是否存在（已删除的文献源、高亮或卡片为 false）
+    pub exists: bool,
+    /// 卡片/文献源标题，高亮为摘录原文
+    pub title: Option<String>,
+    /// 补充信息：卡片类型、文献源作者、高亮所属文献源标题
+    pub detail: Option<String>,
+}
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// 间隔重复复习状态（SM-2 算法）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewState {
+    /// 下次复习时间（毫秒时间戳）
+    pub due_at: i64,
+    /// 当前复习间隔（天）
+    pub interval: u32,
+    /// 难度系数，不低于 1.3
+    pub ease: f64,
+    /// 连续记住的次数
+    pub reps: u32,
+}
+
+impl ReviewState {
+    /// 新卡片的难度系数
+    pub const INITIAL_EASE: f64 = 2.5;
+    /// 难度系数下限
+    pub const MIN_EASE: f64 = 1.3;
+
+    /// 刚加入复习的卡片，立即到期
+    pub fn new(now: i64) -> Self {
+        Self {
+            due_at: now,
+            interval: 0,
+            ease: Self::INITIAL_EASE,
+            reps: 0,
+        }
+    }
+
+    /// 按评分（0-5，超出按 5 计）计算下一次复习；低于 3 分视为遗忘，间隔从 1 天重新开始
+    pub fn next(&self, grade: u8, now: i64) -> Self {
+        let grade = grade.min(5);
+        let (interval, reps) = if grade < 3 {
+            (1, 0)
+        } else {
+            let interval = match self.reps {
+                0 => 1,
+                1 => 6,
+                _ => (self.interval as f64 * self.ease).round() as u32,
+            };
+            (interval, self.reps + 1)
+        };
+        let miss = (5 - grade) as f64;
+        let ease = (self.ease + 0.1 - miss * (0.08 + miss * 0.02)).max(Self::MIN_EASE);
+
+        Self {
+            due_at: now + interval as i64 * DAY_MS,
+            interval,
+            ease,
+            reps,
+        }
+    }
+}
+
+/// 每分钟阅读字数
+pub const WORDS_PER_MINUTE: usize = 250;
+
+/// 统计字数：中日韩文字每个字计一次，其余按连续的字母数字计为一个词
+pub fn count_words(text: &str) -> usize {
+    let mut count = 0;
+    let mut in_word = false;
+    for c in text.chars() {
+        if is_cjk(c) {
+            count += 1;
+            in_word = false;
+        } else if c.is_alphanumeric() {
+            if !in_word {
+                count += 1;
+                in_word = true;
+            }
+        } else if !(in_word && (c == '\'' || c == '’')) {
+            // 撇号不打断单词（如 don't）
+            in_word = false;
+        }
+    }
+    count
+}
+
+/// 预计阅读分钟数，有内容时至少 1 分钟
+pub fn reading_minutes(word_count: usize) -> usize {
+    reading_minutes_at(word_count, WORDS_PER_MINUTE)
+}
+
+/// 按指定阅读速度（词/分钟）估算阅读时间，速度为 0 时按每分钟 1 词计
+pub fn reading_minutes_at(word_count: usize, words_per_minute: usize) -> usize {
+    word_count.div_ceil(words_per_minute.max(1))
+}
+
+pub(crate) fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x4E00..=0x9FFF     // CJK 统一表意文字
+        | 0x3400..=0x4DBF   // 扩展 A
+        | 0x20000..=0x2A6DF // 扩展 B
+        | 0xF900..=0xFAFF   // 兼容表意文字
+        | 0x3040..=0x30FF   // 平假名、片假名
+        | 0xAC00..=0xD7AF   // 谚文音节
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_words_mixed_scripts() {
+        assert_eq!(count_words("Hello, world! don't"), 3);
+        assert_eq!(count_words("卡片盒笔记"), 5);
+        assert_eq!(count_words("用 Rust 写笔记"), 5);
+        assert_eq!(count_words(""), 0);
+        assert_eq!(reading_minutes(0), 0);
+        assert_eq!(reading_minutes(1), 1);
+        assert_eq!(reading_minutes(251), 2);
+        assert_eq!(reading_minutes_at(2000, 200), 10);
+        assert_eq!(reading_minutes_at(1, 0), 1);
+    }
+
+    #[test]
+    fn test_review_state_sm2() {
+        let start = ReviewState::new(0);
+        let first = start.next(5, 0);
+        assert_eq!((first.interval, first.reps, first.due_at), (1, 1, DAY_MS));
+        assert!((first.ease - 2.6).abs() < 1e-9);
+
+        let second = first.next(4, 0);
+        assert_eq!((second.interval, second.reps), (6, 2));
+        assert!((second.ease - 2.6).abs() < 1e-9);
+
+        let third = second.next(3, 0);
+        assert_eq!(third.interval, 16); // round(6 * 2.6)
+        assert!((third.ease - 2.46).abs() < 1e-9);
+
+        // 遗忘后重新开始，难度系数不低于下限
+        let lapsed = ReviewState { ease: 1.4, ..third }.next(0, 10);
+        assert_eq!((lapsed.interval, lapsed.reps, lapsed.due_at), (1, 0, 10 + DAY_MS));
+        assert_eq!(lapsed.ease, ReviewState::MIN_EASE);
+    }
+}
@@ -27,12 +27,34 @@ pub struct HighlightPosition {
     
     // PDF 专用 - 精确坐标
     pub rects: Option<Vec<PdfRect>>,
+    /// PDF 区域高亮的截图（相对 vault 路径，位于 attachments/highlights/ 下）
+    pub image_path: Option<String>,
     
     // 网页专用 - XPath/CSS 选择器
     pub selector: Option<String>,
     pub text_offset: Option<i32>,
 }
 
+/// 区域高亮截图的存放目录（相对 vault）
+pub const HIGHLIGHT_IMAGE_DIR: &str = "attachments/highlights";
+
+impl HighlightPosition {
+    /// 校验截图路径必须位于高亮截图目录内
+    pub fn validate_image_path(&self) -> Result<(), String> {
+        match self.image_path.as_deref() {
+            Some(path)
+                if path.contains("..")
+                    || !path
+                        .replace('\\', "/")
+                        .starts_with(&format!("{}/", HIGHLIGHT_IMAGE_DIR)) =>
+            {
+                Err(format!("Invalid highlight image path: {}", path))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
 /// 标注类型
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
@@ -87,5 +109,8 @@ pub struct UpdateHighlightRequest {
     #[serde(rename = "type")]
     pub annotation_type: Option<AnnotationType>,
     pub card_id: Option<String>,
+    /// 调整区域高亮的坐标或截图
+    #[serde(default)]
+    pub position: Option<HighlightPosition>,
 }
 
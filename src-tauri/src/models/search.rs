@@ -14,6 +14,18 @@ pub struct CardSearchResult {
     #[serde(rename = "type")]
     pub card_type: CardType,
     pub tags: Vec<String>,
+    /// 命中的是卡片还是文献源
+    #[serde(default)]
+    pub kind: SearchResultKind,
+}
+
+/// 搜索结果类别
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchResultKind {
+    #[default]
+    Card,
+    Source,
 }
 
 /// 应用配置
@@ -25,3 +37,67 @@ pub struct AppConfig {
     pub font_size: i32,
 }
 
+
+/// 全库查找选项
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FindOptions {
+    /// 按正则表达式匹配
+    pub regex: bool,
+    /// 区分大小写
+    pub case_sensitive: bool,
+    /// 全词匹配
+    pub whole_word: bool,
+}
+
+/// 单张卡片的查找结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CardMatch {
+    pub card_id: String,
+    pub title: String,
+    pub match_count: usize,
+    /// 命中位置的上下文片段
+    pub snippets: Vec<String>,
+}
+
+/// 单张卡片的替换结果（dry_run 时为预览）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplaceResult {
+    pub card_id: String,
+    pub title: String,
+    pub replacements: usize,
+    /// 替换前后的文本节点对照
+    pub changes: Vec<TextChange>,
+    pub applied: bool,
+}
+
+/// 文本节点的替换前后对照
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextChange {
+    pub before: String,
+    pub after: String,
+}
+
+/// 反向链接：其他卡片中指向本卡片的 wikiLink 及其所在的上下文
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Backlink {
+    pub card_id: String,
+    pub title: String,
+    /// 链接所在文本块的片段，链接本身渲染为 `[[标题]]`
+    pub snippet: String,
+}
+
+/// 未链接提及：其他卡片正文中以纯文本出现的标题或别名
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnlinkedMention {
+    pub card_id: String,
+    pub title: String,
+    /// 命中的原文（保留原大小写）
+    pub matched_text: String,
+    pub snippet: String,
+}
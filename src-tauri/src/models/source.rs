@@ -56,6 +56,91 @@ pub struct SourceMetadata {
     pub duration: Option<i32>,
     pub last_page: Option<i32>, // 向后兼容，新数据优先使用 last_cfi
     pub last_cfi: Option<String>, // 精确位置标识（CFI 或等效），用于精确恢复阅读位置
+    /// 内容语言（BCP-47，如 "en"、"zh-CN"）
+    pub language: Option<String>,
+    /// 内容格式（如 "pdf"、"epub"、"html"）
+    pub content_format: Option<String>,
+    /// 正文词数（中文按分词计），目前仅网页有
+    pub word_count: Option<usize>,
+    /// 预计阅读时间（分钟）
+    pub reading_minutes: Option<usize>,
+}
+
+impl SourceMetadata {
+    /// 规范化语言标签：下划线转连字符，主语言小写，地区大写
+    pub fn normalize_language(tag: &str) -> Option<String> {
+        let tag = tag.trim().replace('_', "-");
+        let mut parts = tag.split('-').filter(|p| !p.is_empty());
+        let primary = parts.next()?.to_lowercase();
+        if !primary.chars().all(|c| c.is_ascii_alphabetic()) {
+            return None;
+        }
+        let rest = parts.map(|p| {
+            if p.len() == 2 {
+                p.to_uppercase()
+            } else {
+                p.to_string()
+            }
+        });
+        Some(std::iter::once(primary).chain(rest).collect::<Vec<_>>().join("-"))
+    }
+
+    /// 根据文件扩展名推断内容格式
+    pub fn content_format_from_path(path: &str) -> Option<String> {
+        std::path::Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| match e.to_lowercase().as_str() {
+                "htm" | "xhtml" => "html".to_string(),
+                other => other.to_string(),
+            })
+    }
+}
+
+/// 文献源过滤条件
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceFilter {
+    #[serde(rename = "type")]
+    pub source_type: Option<SourceType>,
+    /// 语言前缀匹配："en" 可匹配 "en-US"
+    pub language: Option<String>,
+    pub content_format: Option<String>,
+    /// 来源途径（"manual"、"epub"、"web"、"pdf"、"bibtex"）
+    pub source_origin: Option<String>,
+}
+
+/// 阅读清单排序方式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum ReadingListSort {
+    /// 篇幅最短优先（页数，其次时长；篇幅未知的排在最后）
+    Shortest,
+    /// 最近加入优先
+    #[default]
+    RecentlyAdded,
+    /// 最近阅读优先
+    RecentlyRead,
+    /// 进度最高优先（快读完的排在前面）
+    Progress,
+    /// 按标题字母顺序
+    Title,
+}
+
+/// 阅读清单过滤条件
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ReadingListFilter {
+    /// 需同时包含的标签（忽略大小写和前导 #）
+    pub tags: Vec<String>,
+    #[serde(rename = "type")]
+    pub source_type: Option<SourceType>,
+    /// 进度下限（含）
+    pub min_progress: Option<i32>,
+    /// 进度上限（含），设为 0 即只看未开始的
+    pub max_progress: Option<i32>,
+    pub sort: ReadingListSort,
+    pub limit: Option<usize>,
 }
 
 /// 文献源
@@ -77,6 +162,12 @@ pub struct Source {
     pub note_ids: Vec<String>,
     pub created_at: i64,
     pub updated_at: i64,
+    /// 移入回收站的时间，未删除为 None
+    #[serde(default)]
+    pub deleted_at: Option<i64>,
+    /// 加入资料库的途径：manual / epub / web / pdf / bibtex
+    #[serde(default)]
+    pub source_origin: Option<String>,
 }
 
 /// 创建文献源的请求
@@ -91,6 +182,9 @@ pub struct CreateSourceRequest {
     pub cover: Option<String>,
     pub description: Option<String>,
     pub tags: Vec<String>,
+    /// 由创建途径填写，未指定时视为手动添加
+    #[serde(default)]
+    pub source_origin: Option<String>,
 }
 
 /// 更新文献源的请求
@@ -1,16 +1,51 @@
 //! 全文搜索模块
 //! 基于 tantivy 实现高性能搜索，支持中文分词、模糊搜索、结构化过滤
 
+use crate::models::{is_cjk, Card, Source};
 use jieba_rs::Jieba;
-use std::path::Path;
-use std::sync::Arc;
-use tantivy::collector::TopDocs;
+use serde::{Deserialize, Serialize};
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, RwLock};
+use std::collections::{BTreeMap, HashMap};
+use std::time::SystemTime;
+use tantivy::collector::{DocSetCollector, TopDocs};
 use tantivy::directory::MmapDirectory;
-use tantivy::query::{BooleanQuery, FuzzyTermQuery, Occur, Query, QueryParser, TermQuery};
+use tantivy::query::{
+    AllQuery, BooleanQuery, BoostQuery, FuzzyTermQuery, Occur, PhraseQuery, Query, QueryParser,
+    RangeQuery, TermQuery,
+};
 use tantivy::schema::*;
-use tantivy::tokenizer::{LowerCaser, TextAnalyzer, Token, TokenStream, Tokenizer};
+use tantivy::tokenizer::{
+    LowerCaser, RemoveLongFilter, SimpleTokenizer, TextAnalyzer, Token, TokenStream, Tokenizer, WhitespaceTokenizer,
+};
 use tantivy::{Index, IndexReader, IndexWriter, ReloadPolicy, TantivyDocument, Term};
 
+/// 文献源在索引中的类型值（与卡片类型共用 card_type 字段）
+pub const SOURCE_DOC_TYPE: &str = "source";
+
+/// 按 ID 去重，同一 ID 只保留分数最高的结果，其余结果保持原有顺序
+///
+/// 索引漂移（旧文档未删除就以新路径重建）时同一卡片可能出现多次
+fn dedup_results(results: Vec<SearchResult>) -> Vec<SearchResult> {
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    let mut deduped: Vec<SearchResult> = Vec::with_capacity(results.len());
+    for result in results {
+        match positions.get(&result.id) {
+            Some(&i) => {
+                if result.score > deduped[i].score {
+                    deduped[i] = result;
+                }
+            }
+            None => {
+                positions.insert(result.id.clone(), deduped.len());
+                deduped.push(result);
+            }
+        }
+    }
+    deduped
+}
+
 /// 搜索结果结构
 pub struct SearchResult {
     pub id: String,
@@ -21,6 +56,110 @@ pub struct SearchResult {
     pub card_type: Option<String>,
 }
 
+/// 索引统计信息（诊断用）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexStats {
+    /// 有效文档数（不含已删除）
+    pub num_docs: u64,
+    /// 已删除但尚未被合并清除的文档数
+    pub num_deleted_docs: u64,
+    pub num_segments: usize,
+    /// 索引目录占用的字节数
+    pub size_bytes: u64,
+    /// 各索引字段的词项数（按段累加，同一词出现在多个段中会重复计数）
+    pub field_terms: BTreeMap<String, u64>,
+    /// 标题和正文使用的分词器
+    pub tokenizer: TokenizerKind,
+}
+
+/// 标题和正文使用的分词器
+///
+/// 分词器名称写入索引 schema，重新打开索引时沿用建索引时的分词器；
+/// 更换分词器必须删除旧索引后全量重建（见 [`Indexer::recreate`]）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenizerKind {
+    /// jieba 中文分词（加载较大的词典）
+    #[default]
+    Jieba,
+    /// 按非字母数字字符切分，适合英文等拉丁字母文本
+    Default,
+    /// 只按空白切分
+    Whitespace,
+}
+
+impl TokenizerKind {
+    /// 注册到 tantivy 的分词器名称
+    fn name(self) -> &'static str {
+        match self {
+            TokenizerKind::Jieba => "jieba",
+            TokenizerKind::Default => "default",
+            TokenizerKind::Whitespace => "whitespace",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        [TokenizerKind::Jieba, TokenizerKind::Default, TokenizerKind::Whitespace]
+            .into_iter()
+            .find(|kind| kind.name() == name)
+    }
+
+    /// 只有选用 jieba 时才加载词典
+    fn analyzer(self) -> TextAnalyzer {
+        match self {
+            TokenizerKind::Jieba => TextAnalyzer::builder(JiebaTokenizer::default())
+                .filter(LowerCaser)
+                .build(),
+            TokenizerKind::Default => TextAnalyzer::builder(SimpleTokenizer::default())
+                .filter(RemoveLongFilter::limit(40))
+                .filter(LowerCaser)
+                .build(),
+            TokenizerKind::Whitespace => TextAnalyzer::builder(WhitespaceTokenizer::default())
+                .filter(RemoveLongFilter::limit(40))
+                .filter(LowerCaser)
+                .build(),
+        }
+    }
+
+    /// 已有索引的 title 字段记录的分词器
+    fn of_schema(schema: &Schema) -> Option<Self> {
+        let field = schema.get_field("title").ok()?;
+        match schema.get_field_entry(field).field_type() {
+            FieldType::Str(options) => Self::from_name(options.get_indexing_options()?.tokenizer()),
+            _ => None,
+        }
+    }
+}
+
+/// 搜索过滤条件
+#[derive(Debug, Clone, Default)]
+pub struct SearchFilter {
+    /// 卡片类型
+    pub card_type: Option<String>,
+    /// 标签
+    pub tag: Option<String>,
+    /// 修改时间下限（含，毫秒时间戳）
+    pub modified_after: Option<i64>,
+    /// 修改时间上限（含，毫秒时间戳）
+    pub modified_before: Option<i64>,
+    /// 必须同时包含的标签
+    pub tags_include: Vec<String>,
+    /// 必须不包含的标签
+    pub tags_exclude: Vec<String>,
+}
+
+impl SearchFilter {
+    fn is_empty(&self) -> bool {
+        self.card_type.is_none()
+            && self.tag.is_none()
+            && self.modified_after.is_none()
+            && self.modified_before.is_none()
+            && self.tags_include.is_empty()
+            && self.tags_exclude.is_empty()
+    }
+}
+
 /// Jieba 中文分词器
 #[derive(Clone)]
 struct JiebaTokenizer {
@@ -29,12 +168,39 @@ struct JiebaTokenizer {
 
 impl Default for JiebaTokenizer {
     fn default() -> Self {
-        Self {
-            jieba: Arc::new(Jieba::new()),
-        }
+        Self { jieba: shared_jieba() }
     }
 }
 
+/// 进程内共享的 jieba 实例，词典只加载一次
+pub(crate) fn shared_jieba() -> Arc<Jieba> {
+    static JIEBA: OnceLock<Arc<Jieba>> = OnceLock::new();
+    JIEBA.get_or_init(|| Arc::new(Jieba::new())).clone()
+}
+
+/// 统计词数：中日韩文字按 jieba 分词计词，其余文字按空白分隔计词，纯标点不计
+pub fn count_segmented_words(text: &str) -> usize {
+    let jieba = shared_jieba();
+    let is_word = |w: &str| w.chars().any(char::is_alphanumeric);
+    let mut count = 0;
+    let mut rest = text;
+    while let Some(first) = rest.chars().next() {
+        let cjk = is_cjk(first);
+        let end = rest
+            .char_indices()
+            .find(|&(_, c)| is_cjk(c) != cjk)
+            .map_or(rest.len(), |(i, _)| i);
+        let (run, tail) = rest.split_at(end);
+        count += if cjk {
+            jieba.cut(run, false).into_iter().filter(|w| is_word(w)).count()
+        } else {
+            run.split_whitespace().filter(|w| is_word(w)).count()
+        };
+        rest = tail;
+    }
+    count
+}
+
 struct JiebaTokenStream {
     tokens: Vec<Token>,
     index: usize,
@@ -89,10 +255,202 @@ impl Tokenizer for JiebaTokenizer {
     }
 }
 
+/// 同义词表，从 `<vault>/.zentri/synonyms.json` 加载，文件变化后自动重新加载
+///
+/// 文件格式：`{ "ML": ["machine learning", "机器学习"] }`，同组词互为同义词
+struct SynonymStore {
+    path: PathBuf,
+    /// (加载时文件的修改时间, 小写词 -> 同组的其他词)
+    cache: RwLock<(Option<SystemTime>, Arc<HashMap<String, Vec<String>>>)>,
+}
+
+impl SynonymStore {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            cache: RwLock::new((None, Arc::new(HashMap::new()))),
+        }
+    }
+
+    /// 获取当前同义词表，文件修改时间变化时重新加载
+    fn current(&self) -> Arc<HashMap<String, Vec<String>>> {
+        let mtime = std::fs::metadata(&self.path).and_then(|m| m.modified()).ok();
+        {
+            let cache = self.cache.read().unwrap_or_else(|e| e.into_inner());
+            if cache.0 == mtime {
+                return cache.1.clone();
+            }
+        }
+
+        let groups = mtime
+            .and_then(|_| std::fs::read_to_string(&self.path).ok())
+            .and_then(|s| serde_json::from_str::<HashMap<String, Vec<String>>>(&s).ok())
+            .unwrap_or_default();
+        let map = Arc::new(Self::build_map(groups));
+        *self.cache.write().unwrap_or_else(|e| e.into_inner()) = (mtime, map.clone());
+        map
+    }
+
+    fn build_map(groups: HashMap<String, Vec<String>>) -> HashMap<String, Vec<String>> {
+        let mut map: HashMap<String, Vec<String>> = HashMap::new();
+        for (key, values) in groups {
+            let group: Vec<String> = std::iter::once(key).chain(values).collect();
+            for word in &group {
+                let entry = map.entry(word.to_lowercase()).or_default();
+                for other in &group {
+                    if other.to_lowercase() != word.to_lowercase() && !entry.contains(other) {
+                        entry.push(other.clone());
+                    }
+                }
+            }
+        }
+        map
+    }
+}
+
+/// 小写化文本，并记录小写文本每个字节对应的原文字节位置（末尾追加原文长度）
+fn lowercase_with_offsets(text: &str) -> (String, Vec<usize>) {
+    let mut lower = String::with_capacity(text.len());
+    let mut offsets = Vec::with_capacity(text.len() + 1);
+    for (idx, c) in text.char_indices() {
+        for lc in c.to_lowercase() {
+            offsets.extend(std::iter::repeat(idx).take(lc.len_utf8()));
+            lower.push(lc);
+        }
+    }
+    offsets.push(text.len());
+    (lower, offsets)
+}
+
+/// 拆出查询中用双引号括起的短语，返回 (短语, 其余查询词)；未闭合的引号按普通字符忽略
+fn split_phrases(query_str: &str) -> (Vec<String>, String) {
+    let mut phrases = Vec::new();
+    let mut free = String::new();
+    let mut rest = query_str;
+
+    while let Some(open) = rest.find('"') {
+        let Some(close) = rest[open + 1..].find('"') else {
+            break;
+        };
+        free.push_str(&rest[..open]);
+        free.push(' ');
+        let phrase = rest[open + 1..open + 1 + close].trim();
+        if !phrase.is_empty() {
+            phrases.push(phrase.to_string());
+        }
+        rest = &rest[open + 1 + close + 1..];
+    }
+    free.push_str(&rest.replace('"', " "));
+
+    (phrases, free.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// 将查询词展开为同义词的 OR 组合
+fn expand_query(query_str: &str, synonyms: &HashMap<String, Vec<String>>) -> String {
+    query_str
+        .split_whitespace()
+        .map(|term| match synonyms.get(&term.to_lowercase()) {
+            Some(others) if !others.is_empty() => {
+                let alternatives: Vec<String> = std::iter::once(term.to_string())
+                    .chain(others.iter().map(|s| format!("\"{}\"", s.replace('"', ""))))
+                    .collect();
+                format!("({})", alternatives.join(" OR "))
+            }
+            _ => term.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 查询时各字段的权重
+#[derive(Debug, Clone, Copy)]
+pub struct FieldBoosts {
+    pub title: f32,
+    pub content: f32,
+}
+
+impl Default for FieldBoosts {
+    /// 标题命中的相关性明显高于正文提及
+    fn default() -> Self {
+        Self {
+            title: 3.0,
+            content: 1.0,
+        }
+    }
+}
+
+/// vault 配置中保存字段权重的键，值形如 `{"title": 3.0, "content": 1.0}`
+pub const FIELD_BOOSTS_SETTING: &str = "searchFieldBoosts";
+
+impl FieldBoosts {
+    /// 从 vault 配置读取字段权重，缺失或无效（非正数）的项使用默认值
+    pub fn from_vault(vault_path: &Path) -> Self {
+        let defaults = Self::default();
+        let setting = crate::vault::read_setting(vault_path, FIELD_BOOSTS_SETTING);
+        let boost = |key: &str, default: f32| {
+            setting
+                .as_ref()
+                .and_then(|s| s.get(key))
+                .and_then(serde_json::Value::as_f64)
+                .map(|b| b as f32)
+                .filter(|b| b.is_finite() && *b > 0.0)
+                .unwrap_or(default)
+        };
+        Self {
+            title: boost("title", defaults.title),
+            content: boost("content", defaults.content),
+        }
+    }
+}
+
+/// 模糊搜索允许的最大编辑距离
+///
+/// 前端传 `"auto"` 或 0-2 的数字
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FuzzyDistance {
+    /// 按词长调整：≤3 个字符不容错，4-7 个允许 1 处，更长允许 2 处
+    Auto,
+    #[serde(untagged)]
+    Fixed(u8),
+}
+
+impl Default for FuzzyDistance {
+    fn default() -> Self {
+        FuzzyDistance::Fixed(1)
+    }
+}
+
+impl FuzzyDistance {
+    /// tantivy 的 Levenshtein 自动机最多支持到 2
+    pub const MAX: u8 = 2;
+
+    /// 某个查询词实际使用的编辑距离
+    pub fn for_term(&self, term: &str) -> u8 {
+        match *self {
+            FuzzyDistance::Fixed(d) => d.min(Self::MAX),
+            FuzzyDistance::Auto => match term.chars().count() {
+                0..=3 => 0,
+                4..=7 => 1,
+                _ => 2,
+            },
+        }
+    }
+}
+
+/// 模糊搜索选项
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FuzzyOptions {
+    pub distance: FuzzyDistance,
+    /// 允许查询词作为前缀匹配（如输入中途的词）
+    pub prefix: bool,
+}
+
 #[derive(Clone)]
 pub struct Indexer {
     index: Index,
     reader: IndexReader,
+    index_path: PathBuf,
     #[allow(dead_code)]
     schema: Schema,
     // Fields
@@ -103,17 +461,38 @@ pub struct Indexer {
     pub path: Field,
     pub modified_at: Field,
     pub card_type: Field,
+    synonyms: Arc<SynonymStore>,
+    boosts: FieldBoosts,
+    tokenizer: TokenizerKind,
 }
 
 impl Indexer {
+    /// 打开或创建索引，新索引使用 jieba 分词
     pub fn new(index_path: &Path) -> Result<Self, String> {
+        Self::with_tokenizer(index_path, TokenizerKind::default())
+    }
+
+    /// 打开或创建索引；`tokenizer` 只对新建的索引生效，已有索引沿用建索引时的分词器
+    pub fn with_tokenizer(index_path: &Path, tokenizer: TokenizerKind) -> Result<Self, String> {
+        // 确保索引目录存在
+        if !index_path.exists() {
+            std::fs::create_dir_all(index_path).map_err(|e| e.to_string())?;
+        }
+        let dir = MmapDirectory::open(index_path).map_err(|e| e.to_string())?;
+        let tokenizer = if Index::exists(&dir).map_err(|e| e.to_string())? {
+            let existing = Index::open(dir.clone()).map_err(|e| e.to_string())?;
+            TokenizerKind::of_schema(&existing.schema()).unwrap_or_default()
+        } else {
+            tokenizer
+        };
+
         let mut schema_builder = Schema::builder();
 
         // 定义 Schema
         let id = schema_builder.add_text_field("id", STRING | STORED);
 
         let text_indexing = TextFieldIndexing::default()
-            .set_tokenizer("jieba")
+            .set_tokenizer(tokenizer.name())
             .set_index_option(IndexRecordOption::WithFreqsAndPositions);
         let text_options = TextOptions::default()
             .set_indexing_options(text_indexing)
@@ -131,20 +510,11 @@ impl Indexer {
 
         let schema = schema_builder.build();
 
-        // 确保索引目录存在
-        if !index_path.exists() {
-            std::fs::create_dir_all(index_path).map_err(|e| e.to_string())?;
-        }
-
         // 打开或创建索引
-        let dir = MmapDirectory::open(index_path).map_err(|e| e.to_string())?;
         let index = Index::open_or_create(dir, schema.clone()).map_err(|e| e.to_string())?;
 
-        // 注册 Jieba 中文分词器
-        let jieba_tokenizer = TextAnalyzer::builder(JiebaTokenizer::default())
-            .filter(LowerCaser)
-            .build();
-        index.tokenizers().register("jieba", jieba_tokenizer);
+        // 注册标题和正文使用的分词器
+        index.tokenizers().register(tokenizer.name(), tokenizer.analyzer());
 
         // 创建 reader
         let reader = index
@@ -153,9 +523,16 @@ impl Indexer {
             .try_into()
             .map_err(|e| e.to_string())?;
 
+        // 同义词表与索引目录同级 (.zentri/synonyms.json)
+        let synonyms_path = index_path
+            .parent()
+            .unwrap_or(index_path)
+            .join("synonyms.json");
+
         Ok(Self {
             index,
             reader,
+            index_path: index_path.to_path_buf(),
             schema,
             id,
             title,
@@ -164,9 +541,39 @@ impl Indexer {
             path,
             modified_at,
             card_type,
+            synonyms: Arc::new(SynonymStore::new(synonyms_path)),
+            boosts: FieldBoosts::default(),
+            tokenizer,
         })
     }
 
+    /// 删除已有索引并以指定分词器新建空索引，调用方需要随后全量重建
+    pub fn recreate(index_path: &Path, tokenizer: TokenizerKind) -> Result<Self, String> {
+        if index_path.exists() {
+            std::fs::remove_dir_all(index_path).map_err(|e| e.to_string())?;
+        }
+        Self::with_tokenizer(index_path, tokenizer)
+    }
+
+    /// 当前索引使用的分词器
+    pub fn tokenizer(&self) -> TokenizerKind {
+        self.tokenizer
+    }
+
+    /// 使用自定义字段权重
+    pub fn with_field_boosts(mut self, boosts: FieldBoosts) -> Self {
+        self.boosts = boosts;
+        self
+    }
+
+    /// 构建 title + content 的查询解析器，并应用字段权重
+    fn query_parser(&self) -> QueryParser {
+        let mut query_parser = QueryParser::for_index(&self.index, vec![self.title, self.content]);
+        query_parser.set_field_boost(self.title, self.boosts.title);
+        query_parser.set_field_boost(self.content, self.boosts.content);
+        query_parser
+    }
+
     /// 添加或更新文档
     #[allow(dead_code)]
     pub fn index_doc(
@@ -207,7 +614,61 @@ impl Indexer {
         let term = Term::from_field_text(self.id, id_val);
         index_writer.delete_term(term);
 
-        // 构建新文档
+        let doc = self.build_doc(
+            id_val,
+            title_val,
+            content_val,
+            tags_val,
+            path_val,
+            modified_at_val,
+            card_type_val,
+        );
+        index_writer.add_document(doc).map_err(|e| e.to_string())?;
+        index_writer.commit().map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+
+    /// 批量更新：删除 `removed` 中的文档并重建 `cards`，共用一个 writer 只提交一次，返回写入的卡片数
+    pub fn index_cards_batch(&self, cards: &[Card], removed: &[String]) -> Result<usize, String> {
+        if cards.is_empty() && removed.is_empty() {
+            return Ok(0);
+        }
+
+        let mut index_writer: IndexWriter<TantivyDocument> =
+            self.index.writer(50_000_000).map_err(|e| e.to_string())?;
+        for id_val in removed {
+            index_writer.delete_term(Term::from_field_text(self.id, id_val));
+        }
+        for card in cards {
+            index_writer.delete_term(Term::from_field_text(self.id, &card.id));
+            let doc = self.build_doc(
+                &card.id,
+                &card.title,
+                card_body(card),
+                &card.tags,
+                card.path.as_deref().unwrap_or(""),
+                card.modified_at,
+                Some(card.card_type.as_str()),
+            );
+            index_writer.add_document(doc).map_err(|e| e.to_string())?;
+        }
+        index_writer.commit().map_err(|e| e.to_string())?;
+
+        Ok(cards.len())
+    }
+
+    /// 构建索引文档
+    fn build_doc(
+        &self,
+        id_val: &str,
+        title_val: &str,
+        content_val: &str,
+        tags_val: &[String],
+        path_val: &str,
+        modified_at_val: i64,
+        card_type_val: Option<&str>,
+    ) -> TantivyDocument {
         let mut doc = TantivyDocument::default();
         doc.add_text(self.id, id_val);
         doc.add_text(self.title, title_val);
@@ -222,11 +683,7 @@ impl Indexer {
         if let Some(ct) = card_type_val {
             doc.add_text(self.card_type, ct);
         }
-
-        index_writer.add_document(doc).map_err(|e| e.to_string())?;
-        index_writer.commit().map_err(|e| e.to_string())?;
-
-        Ok(())
+        doc
     }
 
     /// 搜索
@@ -239,7 +696,7 @@ impl Indexer {
         let searcher = self.reader.searcher();
 
         // 搜索 title 和 content
-        let query_parser = QueryParser::for_index(&self.index, vec![self.title, self.content]);
+        let query_parser = self.query_parser();
         let query = query_parser
             .parse_query(query_str)
             .map_err(|e| e.to_string())?;
@@ -277,30 +734,117 @@ impl Indexer {
         query_str: &str,
         limit: usize,
     ) -> Result<Vec<SearchResult>, String> {
-        self.search_with_filter(query_str, limit, None, None)
+        self.search_with_filter(query_str, limit, &SearchFilter::default())
     }
 
-    /// 带过滤条件的搜索
+    /// 同义词扩展搜索：查询词会被展开为其同义词的 OR 组合
+    pub fn search_with_synonyms(
+        &self,
+        query_str: &str,
+        limit: usize,
+        filter: &SearchFilter,
+    ) -> Result<Vec<SearchResult>, String> {
+        self.search_filtered(query_str, limit, filter, true)
+    }
+
+    /// 带过滤条件的搜索；双引号括起的部分按短语匹配（分词后的词必须相邻）
     pub fn search_with_filter(
         &self,
         query_str: &str,
         limit: usize,
-        card_type_filter: Option<&str>,
-        tag_filter: Option<&str>,
+        filter: &SearchFilter,
     ) -> Result<Vec<SearchResult>, String> {
-        let searcher = self.reader.searcher();
+        self.search_filtered(query_str, limit, filter, false)
+    }
 
-        // 构建主查询
-        let query_parser = QueryParser::for_index(&self.index, vec![self.title, self.content]);
-        let text_query = query_parser
-            .parse_query(query_str)
+    /// 构建查询文本部分：每个短语都必须命中，其余查询词交给查询解析器
+    fn text_query(&self, phrases: &[String], free: &str) -> Result<Box<dyn Query>, String> {
+        let query_parser = self.query_parser();
+        if phrases.is_empty() {
+            return query_parser.parse_query(free).map_err(|e| e.to_string());
+        }
+
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+        for phrase in phrases {
+            if let Some(query) = self.phrase_query(phrase)? {
+                clauses.push((Occur::Must, query));
+            }
+        }
+        if !free.is_empty() {
+            let query = query_parser.parse_query(free).map_err(|e| e.to_string())?;
+            clauses.push((Occur::Must, query));
+        }
+        Ok(Box::new(BooleanQuery::new(clauses)))
+    }
+
+    /// 用索引的分词器切分短语，在 title 和 content 上构建短语查询；短语没有可检索的词时返回 None
+    fn phrase_query(&self, phrase: &str) -> Result<Option<Box<dyn Query>>, String> {
+        let mut analyzer = self
+            .index
+            .tokenizer_for_field(self.title)
             .map_err(|e| e.to_string())?;
+        let mut stream = analyzer.token_stream(phrase);
+        let mut terms: Vec<(usize, String)> = Vec::new();
+        // jieba 会把空白也作为词输出，跳过它们但保留位置，相邻关系不变
+        while let Some(token) = stream.next() {
+            if !token.text.trim().is_empty() {
+                terms.push((token.position, token.text.clone()));
+            }
+        }
+        if terms.is_empty() {
+            return Ok(None);
+        }
+
+        let clauses = [(self.title, self.boosts.title), (self.content, self.boosts.content)]
+            .into_iter()
+            .map(|(field, boost)| {
+                let query: Box<dyn Query> = if terms.len() == 1 {
+                    Box::new(TermQuery::new(
+                        Term::from_field_text(field, &terms[0].1),
+                        IndexRecordOption::WithFreqs,
+                    ))
+                } else {
+                    Box::new(PhraseQuery::new_with_offset(
+                        terms
+                            .iter()
+                            .map(|(position, text)| (*position, Term::from_field_text(field, text)))
+                            .collect(),
+                    ))
+                };
+                (Occur::Should, Box::new(BoostQuery::new(query, boost)) as Box<dyn Query>)
+            })
+            .collect();
+        Ok(Some(Box::new(BooleanQuery::new(clauses))))
+    }
+
+    fn search_filtered(
+        &self,
+        query_str: &str,
+        limit: usize,
+        filter: &SearchFilter,
+        expand_synonyms: bool,
+    ) -> Result<Vec<SearchResult>, String> {
+        let searcher = self.reader.searcher();
+
+        // 构建主查询：短语单独处理，同义词只展开短语以外的查询词
+        let (phrases, free) = split_phrases(query_str);
+        let parsed_free = if expand_synonyms {
+            expand_query(&free, &self.synonyms.current())
+        } else {
+            free.clone()
+        };
+        // 查询为空但有过滤条件时，匹配全部文档再过滤
+        let text_query: Box<dyn Query> = if query_str.trim().is_empty() && !filter.is_empty() {
+            Box::new(AllQuery)
+        } else {
+            self.text_query(&phrases, &parsed_free)?
+        };
 
         // 构建复合查询 (可选过滤)
-        let final_query: Box<dyn Query> = if card_type_filter.is_some() || tag_filter.is_some() {
+        let final_query: Box<dyn Query> = if !filter.is_empty() {
             let mut clauses: Vec<(Occur, Box<dyn Query>)> = vec![(Occur::Must, text_query)];
 
-            if let Some(ct) = card_type_filter {
+            if let Some(ct) = filter.card_type.as_deref() {
                 let term = Term::from_field_text(self.card_type, ct);
                 clauses.push((
                     Occur::Must,
@@ -308,7 +852,7 @@ impl Indexer {
                 ));
             }
 
-            if let Some(tag) = tag_filter {
+            if let Some(tag) = filter.tag.as_deref() {
                 let term = Term::from_field_text(self.tags, tag);
                 clauses.push((
                     Occur::Must,
@@ -316,6 +860,32 @@ impl Indexer {
                 ));
             }
 
+            for tag in &filter.tags_include {
+                let term = Term::from_field_text(self.tags, tag);
+                clauses.push((
+                    Occur::Must,
+                    Box::new(TermQuery::new(term, IndexRecordOption::Basic)),
+                ));
+            }
+
+            for tag in &filter.tags_exclude {
+                let term = Term::from_field_text(self.tags, tag);
+                clauses.push((
+                    Occur::MustNot,
+                    Box::new(TermQuery::new(term, IndexRecordOption::Basic)),
+                ));
+            }
+
+            // 修改时间范围 (modified_at 为 FAST 字段)
+            if filter.modified_after.is_some() || filter.modified_before.is_some() {
+                let lower = filter.modified_after.map_or(Bound::Unbounded, Bound::Included);
+                let upper = filter.modified_before.map_or(Bound::Unbounded, Bound::Included);
+                clauses.push((
+                    Occur::Must,
+                    Box::new(RangeQuery::new_i64_bounds("modified_at".to_string(), lower, upper)),
+                ));
+            }
+
             Box::new(BooleanQuery::new(clauses))
         } else {
             text_query
@@ -325,7 +895,8 @@ impl Indexer {
             .search(&*final_query, &TopDocs::with_limit(limit))
             .map_err(|e| e.to_string())?;
 
-        let query_lower = query_str.to_lowercase();
+        // 高亮优先使用第一个短语
+        let query_lower = phrases.first().unwrap_or(&free).to_lowercase();
         let mut results = Vec::new();
 
         for (score, doc_address) in top_docs {
@@ -374,27 +945,48 @@ impl Indexer {
             });
         }
 
-        Ok(results)
+        Ok(dedup_results(results))
     }
 
     /// 模糊搜索 (处理拼写错误)
-    pub fn fuzzy_search(&self, query_str: &str, limit: usize) -> Result<Vec<SearchResult>, String> {
+    pub fn fuzzy_search(
+        &self,
+        query_str: &str,
+        limit: usize,
+        options: &FuzzyOptions,
+    ) -> Result<Vec<SearchResult>, String> {
         let searcher = self.reader.searcher();
 
         // 对每个词进行模糊匹配
         let words: Vec<&str> = query_str.split_whitespace().collect();
         let mut clauses: Vec<(Occur, Box<dyn Query>)> = Vec::new();
 
+        let fuzzy = |term: Term, distance: u8| {
+            if options.prefix {
+                FuzzyTermQuery::new_prefix(term, distance, true)
+            } else {
+                FuzzyTermQuery::new(term, distance, true)
+            }
+        };
+
         for word in words {
+            let distance = options.distance.for_term(word);
+
             // 标题模糊匹配
             let title_term = Term::from_field_text(self.title, word);
-            let title_fuzzy = FuzzyTermQuery::new(title_term, 1, true);
-            clauses.push((Occur::Should, Box::new(title_fuzzy)));
+            let title_fuzzy = fuzzy(title_term, distance);
+            clauses.push((
+                Occur::Should,
+                Box::new(BoostQuery::new(Box::new(title_fuzzy), self.boosts.title)),
+            ));
 
             // 内容模糊匹配
             let content_term = Term::from_field_text(self.content, word);
-            let content_fuzzy = FuzzyTermQuery::new(content_term, 1, true);
-            clauses.push((Occur::Should, Box::new(content_fuzzy)));
+            let content_fuzzy = fuzzy(content_term, distance);
+            clauses.push((
+                Occur::Should,
+                Box::new(BoostQuery::new(Box::new(content_fuzzy), self.boosts.content)),
+            ));
         }
 
         let query = BooleanQuery::new(clauses);
@@ -450,113 +1042,65 @@ impl Indexer {
             });
         }
 
-        Ok(results)
+        Ok(dedup_results(results))
     }
 
-    /// 生成高亮片段 (UTF-8 safe)
+    /// 生成高亮片段：大小写无关匹配，所有切片位置都落在原文的字符边界上
     fn generate_snippet(&self, content: &str, query: &str) -> Option<String> {
-        let content_lower = content.to_lowercase();
-        let query_lower = query.to_lowercase();
+        // 匹配前后保留的字符数
+        const CHARS_BEFORE: usize = 20;
+        const CHARS_AFTER: usize = 100;
 
+        let query_lower = query.to_lowercase();
         if query_lower.is_empty() {
             return self.generate_preview(content);
         }
 
-        // 找到查询词的位置 (byte index in lower string)
-        if let Some(pos) = content_lower.find(&query_lower) {
-            // Note: Indices from to_lowercase might not strictly map to content,
-            // but for simple search snippet it's often close enough or we accept a slight drift.
-            // A perfect solution requires mapping indices or case-insensitive search on original string.
-            // Here we prioritize safety over pixel-perfect alignment for now.
-
-            // Safe start calculation
-            let context_chars = 20; // Reduce context to avoid huge drift
-
-            // Find a safe char boundary backwards approx 50 bytes
-            // Iterate chars backwards from pos
-            let mut char_count = 0;
-            let mut found_start = 0;
-
-            for (curr_idx, _) in content.char_indices().rev() {
-                if curr_idx <= pos {
-                    if char_count < context_chars {
-                        char_count += 1;
-                        found_start = curr_idx;
-                    } else {
-                        break;
-                    }
-                }
-            }
-            let start = found_start;
-
-            // Safe end calculation
-            let target_end = pos + query_lower.len() + 100; // ample buffer
-            let end = if target_end >= content.len() {
-                content.len()
-            } else {
-                // Align to next char boundary
-                let mut safe_e = target_end;
-                while !content.is_char_boundary(safe_e) && safe_e < content.len() {
-                    safe_e += 1;
-                }
-                safe_e
-            };
-
-            let safe_slice = &content[start..end];
-
-            let mut snippet = String::new();
-            if start > 0 {
-                snippet.push_str("...");
-            }
-
-            // Highlight inside the safe slice
-            // Simple approach: case-insensitive replace? No, need to keep original case.
-            // We use the same naive find logic on the slice.
-            let slice_lower = safe_slice.to_lowercase();
-            // We need to re-locate the query inside this slice because lowercasing might change lengths slightly
-            // or if we drifted.
-            // Better approach for display: just markup the text.
-
-            let mut last_end = 0;
-            // Note: matching inside slice_lower and mapping back to safe_slice is still risky for length mapping.
-            // But usually 1:1 for most chars.
-
-            for (match_start, part_str) in slice_lower.match_indices(&query_lower) {
-                // Add text before match
-                if match_start > last_end {
-                    // Check boundaries again just in case length differs (rare but possible with weird unicode)
-                    if last_end < safe_slice.len() && match_start <= safe_slice.len() {
-                        snippet.push_str(&safe_slice[last_end..match_start]);
-                    }
-                }
-
-                // Add highlighted match
-                snippet.push_str("<mark>");
-                // Use original text length if possible, or query length
-                let match_end = match_start + part_str.len();
-                if match_end <= safe_slice.len() {
-                    snippet.push_str(&safe_slice[match_start..match_end]);
-                } else {
-                    snippet.push_str(&query); // Fallback
-                }
-                snippet.push_str("</mark>");
+        // 小写化可能改变字节长度，匹配位置要经过映射才能用于原文
+        let (content_lower, offsets) = lowercase_with_offsets(content);
+        let Some(pos) = content_lower.find(&query_lower) else {
+            return self.generate_preview(content);
+        };
+        let match_start = offsets[pos];
+        let match_end = offsets[pos + query_lower.len()];
+
+        let start = content[..match_start]
+            .char_indices()
+            .rev()
+            .nth(CHARS_BEFORE - 1)
+            .map_or(0, |(i, _)| i);
+        let end = content[match_end..]
+            .char_indices()
+            .nth(CHARS_AFTER)
+            .map_or(content.len(), |(i, _)| match_end + i);
+
+        let mut snippet = String::new();
+        if start > 0 {
+            snippet.push_str("...");
+        }
 
-                last_end = match_end;
+        let mut last_end = start;
+        for (lower_pos, matched) in content_lower.match_indices(&query_lower) {
+            let (from, to) = (offsets[lower_pos], offsets[lower_pos + matched.len()]);
+            if from < last_end || from == to {
+                continue;
             }
-
-            // Add remainder
-            if last_end < safe_slice.len() {
-                snippet.push_str(&safe_slice[last_end..]);
-            }
-
-            if end < content.len() {
-                snippet.push_str("...");
+            if to > end {
+                break;
             }
+            snippet.push_str(&content[last_end..from]);
+            snippet.push_str("<mark>");
+            snippet.push_str(&content[from..to]);
+            snippet.push_str("</mark>");
+            last_end = to;
+        }
+        snippet.push_str(&content[last_end..end]);
 
-            Some(snippet)
-        } else {
-            self.generate_preview(content)
+        if end < content.len() {
+            snippet.push_str("...");
         }
+
+        Some(snippet)
     }
 
     fn generate_preview(&self, content: &str) -> Option<String> {
@@ -569,6 +1113,37 @@ impl Indexer {
         }
     }
 
+    /// 添加或更新卡片文档，加密卡片只索引标题
+    pub fn index_card(&self, card: &Card) -> Result<(), String> {
+        self.index_doc_with_type(
+            &card.id,
+            &card.title,
+            card_body(card),
+            &card.tags,
+            card.path.as_deref().unwrap_or(""),
+            card.modified_at,
+            Some(card.card_type.as_str()),
+        )
+    }
+
+    /// 添加或更新文献源文档：标题、作者和简介可被全文检索
+    pub fn index_source(&self, source: &Source) -> Result<(), String> {
+        let content = [source.author.as_deref(), source.description.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.index_doc_with_type(
+            &source.id,
+            &source.title,
+            &content,
+            &source.tags,
+            source.url.as_deref().unwrap_or(""),
+            source.updated_at,
+            Some(SOURCE_DOC_TYPE),
+        )
+    }
+
     /// 删除文档
     pub fn delete_doc(&self, id_val: &str) -> Result<(), String> {
         let mut index_writer: IndexWriter<TantivyDocument> =
@@ -597,6 +1172,24 @@ impl Indexer {
         Ok(None)
     }
 
+    /// 获取索引中所有文档的 ID 及其修改时间 (用于完整性检查)
+    pub fn all_doc_mtimes(&self) -> Result<HashMap<String, i64>, String> {
+        let searcher = self.reader.searcher();
+        let addresses = searcher
+            .search(&AllQuery, &DocSetCollector)
+            .map_err(|e| e.to_string())?;
+
+        let mut docs = HashMap::new();
+        for doc_address in addresses {
+            let doc: TantivyDocument = searcher.doc(doc_address).map_err(|e| e.to_string())?;
+            if let Some(id) = doc.get_first(self.id).and_then(|v| v.as_str()) {
+                let mtime = doc.get_first(self.modified_at).and_then(|v| v.as_i64()).unwrap_or(0);
+                docs.insert(id.to_string(), mtime);
+            }
+        }
+        Ok(docs)
+    }
+
     /// 按标签搜索
     pub fn search_by_tag(&self, tag: &str, limit: usize) -> Result<Vec<SearchResult>, String> {
         let searcher = self.reader.searcher();
@@ -645,7 +1238,7 @@ impl Indexer {
             });
         }
 
-        Ok(results)
+        Ok(dedup_results(results))
     }
 
     /// 按卡片类型搜索
@@ -700,6 +1293,475 @@ impl Indexer {
             });
         }
 
-        Ok(results)
+        Ok(dedup_results(results))
+    }
+
+    /// 索引维护：同一 ID 有多个文档时只保留修改时间最新的一个，返回删除的文档数
+    pub fn dedup_index(&self) -> Result<usize, String> {
+        let searcher = self.reader.searcher();
+        let addresses = searcher
+            .search(&AllQuery, &DocSetCollector)
+            .map_err(|e| e.to_string())?;
+
+        let mut by_id: HashMap<String, Vec<TantivyDocument>> = HashMap::new();
+        for doc_address in addresses {
+            let doc: TantivyDocument = searcher.doc(doc_address).map_err(|e| e.to_string())?;
+            if let Some(id) = doc.get_first(self.id).and_then(|v| v.as_str()) {
+                by_id.entry(id.to_string()).or_default().push(doc);
+            }
+        }
+
+        let mut removed = 0;
+        let mut index_writer: IndexWriter<TantivyDocument> =
+            self.index.writer(50_000_000).map_err(|e| e.to_string())?;
+        for (id, docs) in by_id.into_iter().filter(|(_, docs)| docs.len() > 1) {
+            removed += docs.len() - 1;
+            let newest = docs
+                .into_iter()
+                .max_by_key(|doc| doc.get_first(self.modified_at).and_then(|v| v.as_i64()).unwrap_or(0))
+                .expect("duplicate group is non-empty");
+            // tantivy 只能按词项删除，先删掉该 ID 的全部文档再写回最新的一个
+            index_writer.delete_term(Term::from_field_text(self.id, &id));
+            index_writer.add_document(newest).map_err(|e| e.to_string())?;
+        }
+        if removed > 0 {
+            index_writer.commit().map_err(|e| e.to_string())?;
+        }
+        Ok(removed)
+    }
+
+    /// 统计文档数、段数、磁盘占用和各字段词项数
+    pub fn stats(&self) -> Result<IndexStats, String> {
+        let searcher = self.reader.searcher();
+        let indexed_fields = [
+            ("id", self.id),
+            ("title", self.title),
+            ("content", self.content),
+            ("tags", self.tags),
+            ("path", self.path),
+            ("card_type", self.card_type),
+        ];
+
+        let mut num_deleted_docs = 0;
+        let mut field_terms: BTreeMap<String, u64> = BTreeMap::new();
+        for segment_reader in searcher.segment_readers() {
+            num_deleted_docs += segment_reader.num_deleted_docs() as u64;
+            for (name, field) in indexed_fields {
+                let terms = segment_reader
+                    .inverted_index(field)
+                    .map_err(|e| e.to_string())?
+                    .terms()
+                    .num_terms() as u64;
+                *field_terms.entry(name.to_string()).or_default() += terms;
+            }
+        }
+
+        let size_bytes = std::fs::read_dir(&self.index_path)
+            .map_err(|e| e.to_string())?
+            .flatten()
+            .filter_map(|entry| entry.metadata().ok())
+            .filter(|meta| meta.is_file())
+            .map(|meta| meta.len())
+            .sum();
+
+        Ok(IndexStats {
+            num_docs: searcher.num_docs(),
+            num_deleted_docs,
+            num_segments: searcher.segment_readers().len(),
+            size_bytes,
+            field_terms,
+            tokenizer: self.tokenizer,
+        })
+    }
+
+    /// 索引维护：把所有段合并为一个，清除已删除文档的墓碑并回收旧段文件
+    pub fn optimize(&self) -> Result<(), String> {
+        let segment_ids = self.index.searchable_segment_ids().map_err(|e| e.to_string())?;
+        let mut index_writer: IndexWriter<TantivyDocument> =
+            self.index.writer(50_000_000).map_err(|e| e.to_string())?;
+        if !segment_ids.is_empty() {
+            index_writer.merge(&segment_ids).wait().map_err(|e| e.to_string())?;
+        }
+        index_writer.garbage_collect_files().wait().map_err(|e| e.to_string())?;
+        index_writer.wait_merging_threads().map_err(|e| e.to_string())?;
+        self.reader.reload().map_err(|e| e.to_string())
+    }
+}
+
+/// 卡片写入索引的正文。索引字段都会存储到磁盘，加密卡片解锁后的正文明文不能写入
+fn card_body(card: &Card) -> &str {
+    if card.encrypted {
+        ""
+    } else {
+        &card.plain_text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_count_segmented_words_mixed_scripts() {
+        assert_eq!(count_segmented_words(""), 0);
+        assert_eq!(count_segmented_words("Hello, world! It's fine."), 4);
+        assert_eq!(count_segmented_words("我来到北京清华大学"), 4);
+        // 中英混排：中文按分词计，英文按空白计，标点不计
+        assert_eq!(count_segmented_words("我来到北京清华大学，to study Rust."), 7);
+        assert_eq!(count_segmented_words("用Rust写笔记"), count_segmented_words("用 Rust 写笔记"));
+        assert_eq!(count_segmented_words("—— ！"), 0);
+    }
+
+    #[test]
+    fn test_index_cards_batch() {
+        let dir = tempdir().unwrap();
+        let indexer = Indexer::new(dir.path()).unwrap();
+        indexer.index_doc("gone", "gone", "rust", &[], "", 1).unwrap();
+        let cards: Vec<Card> = (0..200)
+            .map(|i| {
+                serde_json::from_value(serde_json::json!({
+                    "id": format!("c{}", i),
+                    "title": format!("note {}", i),
+                    "tags": [],
+                    "type": "permanent",
+                    "content": "",
+                    "plainText": "rust batch",
+                    "preview": null,
+                    "createdAt": 0,
+                    "modifiedAt": i
+                }))
+                .unwrap()
+            })
+            .collect();
+
+        assert_eq!(indexer.index_cards_batch(&cards, &["gone".to_string()]).unwrap(), 200);
+        indexer.reader.reload().unwrap();
+        let mtimes = indexer.all_doc_mtimes().unwrap();
+        assert_eq!(mtimes.len(), 200);
+        assert!(!mtimes.contains_key("gone"));
+    }
+
+    #[test]
+    fn test_index_cards_batch_skips_encrypted_body() {
+        let dir = tempdir().unwrap();
+        let indexer = Indexer::new(dir.path()).unwrap();
+        let card: Card = serde_json::from_value(serde_json::json!({
+            "id": "diary",
+            "title": "Diary",
+            "tags": [],
+            "type": "fleeting",
+            "content": "",
+            "plainText": "secret diary",
+            "preview": null,
+            "createdAt": 0,
+            "modifiedAt": 1,
+            "encrypted": true
+        }))
+        .unwrap();
+
+        indexer.index_cards_batch(&[card], &[]).unwrap();
+        indexer.reader.reload().unwrap();
+        assert_eq!(indexer.search("Diary", 10).unwrap().len(), 1);
+        assert!(indexer.search("secret", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_index_stats() {
+        let dir = tempdir().unwrap();
+        let indexer = Indexer::new(dir.path()).unwrap();
+        indexer.index_doc("a", "rust", "ownership borrow", &["lang".to_string()], "", 1).unwrap();
+        indexer.index_doc("b", "go", "goroutine", &[], "", 2).unwrap();
+        indexer.index_doc("a", "rust", "ownership", &[], "", 3).unwrap();
+        indexer.reader.reload().unwrap();
+
+        let stats = indexer.stats().unwrap();
+        assert_eq!(stats.num_docs, 2);
+        assert_eq!(stats.num_deleted_docs, 1);
+        assert!(stats.num_segments >= 1);
+        assert!(stats.size_bytes > 0);
+        assert!(stats.field_terms["content"] >= 3);
+        assert_eq!(stats.field_terms["tags"], 1);
+    }
+
+    #[test]
+    fn test_whitespace_tokenizer_terms() {
+        let mut analyzer = TokenizerKind::Whitespace.analyzer();
+        let mut stream = analyzer.token_stream("Hello, World  wide-web");
+        let mut terms = Vec::new();
+        while stream.advance() {
+            terms.push(stream.token().text.clone());
+        }
+        assert_eq!(terms, vec!["hello,", "world", "wide-web"]);
+
+        let dir = tempdir().unwrap();
+        let indexer = Indexer::with_tokenizer(dir.path(), TokenizerKind::Whitespace).unwrap();
+        indexer.index_doc("a", "Notes", "wide-web ownership", &[], "", 1).unwrap();
+        indexer.reader.reload().unwrap();
+        assert_eq!(indexer.search("ownership", 10).unwrap().len(), 1);
+        drop(indexer);
+
+        // 重新打开时沿用索引记录的分词器，而不是调用方请求的
+        let reopened = Indexer::with_tokenizer(dir.path(), TokenizerKind::Jieba).unwrap();
+        assert_eq!(reopened.tokenizer(), TokenizerKind::Whitespace);
+        drop(reopened);
+        let recreated = Indexer::recreate(dir.path(), TokenizerKind::Default).unwrap();
+        assert_eq!(recreated.stats().unwrap().tokenizer, TokenizerKind::Default);
+    }
+
+    #[test]
+    fn test_search_modified_range() {
+        let dir = tempdir().unwrap();
+        let indexer = Indexer::new(dir.path()).unwrap();
+        indexer.index_doc("old", "rust old", "rust notes", &[], "", 1_000).unwrap();
+        indexer.index_doc("mid", "rust mid", "rust notes", &[], "", 2_000).unwrap();
+        indexer.index_doc("new", "rust new", "rust notes", &[], "", 3_000).unwrap();
+        indexer.reader.reload().unwrap();
+
+        let filter = SearchFilter {
+            modified_after: Some(1_500),
+            modified_before: Some(2_500),
+            ..Default::default()
+        };
+        let results = indexer.search_with_filter("rust", 10, &filter).unwrap();
+        let ids: Vec<_> = results.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["mid"]);
+
+        let filter = SearchFilter {
+            modified_after: Some(2_000),
+            ..Default::default()
+        };
+        let results = indexer.search_with_filter("rust", 10, &filter).unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.id != "old"));
+    }
+
+    #[test]
+    fn test_search_tags_include_exclude_without_query() {
+        let dir = tempdir().unwrap();
+        let indexer = Indexer::new(dir.path()).unwrap();
+        let physics = vec!["physics".to_string()];
+        let both = vec!["physics".to_string(), "draft".to_string()];
+        indexer.index_doc("a", "a", "energy", &physics, "", 1_000).unwrap();
+        indexer.index_doc("b", "b", "energy", &both, "", 1_000).unwrap();
+        indexer.index_doc("c", "c", "energy", &[], "", 1_000).unwrap();
+        indexer.reader.reload().unwrap();
+
+        let filter = SearchFilter {
+            tags_include: vec!["physics".to_string()],
+            tags_exclude: vec!["draft".to_string()],
+            ..Default::default()
+        };
+        let results = indexer.search_with_filter("", 10, &filter).unwrap();
+        let ids: Vec<_> = results.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["a"]);
+    }
+
+    #[test]
+    fn test_search_with_synonyms() {
+        let dir = tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("synonyms.json"),
+            r#"{ "ML": ["machine learning", "机器学习"] }"#,
+        )
+        .unwrap();
+        let indexer = Indexer::new(&dir.path().join("index")).unwrap();
+        indexer
+            .index_doc("a", "Intro", "basics of machine learning", &[], "", 1_000)
+            .unwrap();
+        indexer.reader.reload().unwrap();
+
+        let filter = SearchFilter::default();
+        assert!(indexer.search_with_filter("ML", 10, &filter).unwrap().is_empty());
+        let results = indexer.search_with_synonyms("ML", 10, &filter).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, "a");
+    }
+
+    #[test]
+    fn test_snippet_in_long_cjk_paragraph() {
+        let dir = tempdir().unwrap();
+        let indexer = Indexer::new(dir.path()).unwrap();
+
+        let content = format!("{}卡片盒笔记法{}", "知识管理".repeat(30), "时间管理".repeat(30));
+        let snippet = indexer.generate_snippet(&content, "卡片盒").unwrap();
+        assert!(snippet.starts_with("...") && snippet.ends_with("..."));
+        assert!(snippet.contains("理<mark>卡片盒</mark>笔记法"));
+
+        // 小写化后字节变长的字符不能让高亮错位
+        let snippet = indexer.generate_snippet("İİİ Rust 与 rust", "rust").unwrap();
+        assert_eq!(snippet, "İİİ <mark>Rust</mark> 与 <mark>rust</mark>");
+    }
+
+    #[test]
+    fn test_split_phrases() {
+        let (phrases, free) = split_phrases(r#"foo "bar baz" qux "知识管理""#);
+        assert_eq!(phrases, vec!["bar baz", "知识管理"]);
+        assert_eq!(free, "foo qux");
+
+        let (phrases, free) = split_phrases(r#"open "quote"#);
+        assert!(phrases.is_empty());
+        assert_eq!(free, "open quote");
+    }
+
+    #[test]
+    fn test_phrase_search_requires_adjacent_terms() {
+        let dir = tempdir().unwrap();
+        let indexer = Indexer::new(dir.path()).unwrap();
+        indexer.index_doc("adjacent", "one", "foo bar baz", &[], "", 1).unwrap();
+        indexer.index_doc("reversed", "two", "qux baz bar", &[], "", 1).unwrap();
+        indexer.index_doc("phrase-only", "three", "bar baz alone", &[], "", 1).unwrap();
+        indexer.index_doc("mixed", "four", "qux and then bar baz", &[], "", 1).unwrap();
+        indexer.reader.reload().unwrap();
+
+        let filter = SearchFilter::default();
+        let ids = |query: &str| {
+            let mut ids: Vec<String> = indexer
+                .search_with_filter(query, 10, &filter)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.id)
+                .collect();
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(ids(r#""bar baz""#), vec!["adjacent", "mixed", "phrase-only"]);
+        assert_eq!(ids(r#"foo "bar baz" qux"#), vec!["adjacent", "mixed"]);
+        assert!(ids(r#""baz foo""#).is_empty());
+    }
+
+    #[test]
+    fn test_title_match_ranks_above_body_match() {
+        let dir = tempdir().unwrap();
+        let indexer = Indexer::new(dir.path()).unwrap();
+        indexer
+            .index_doc("body", "Notes", "tantivy tantivy tantivy is mentioned here", &[], "", 1_000)
+            .unwrap();
+        indexer
+            .index_doc("title", "Tantivy", "a search library written in rust", &[], "", 1_000)
+            .unwrap();
+        indexer.reader.reload().unwrap();
+
+        let results = indexer.search_with_snippets("tantivy", 10).unwrap();
+        let ids: Vec<_> = results.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["title", "body"]);
+
+        // 正文权重调高后正文多次提及的卡片排在前面
+        let vault = tempdir().unwrap();
+        crate::vault::write_setting(
+            vault.path(),
+            FIELD_BOOSTS_SETTING,
+            serde_json::json!({ "title": 1.0, "content": 10.0 }),
+        )
+        .unwrap();
+        let boosts = FieldBoosts::from_vault(vault.path());
+        assert_eq!((boosts.title, boosts.content), (1.0, 10.0));
+        let indexer = indexer.with_field_boosts(boosts);
+        let results = indexer.search_with_snippets("tantivy", 10).unwrap();
+        assert_eq!(results[0].id, "body");
+    }
+
+    #[test]
+    fn test_fuzzy_distance() {
+        let dir = tempdir().unwrap();
+        let indexer = Indexer::new(dir.path()).unwrap();
+        indexer
+            .index_doc("a", "Notes", "the zettelkasten method", &[], "", 1_000)
+            .unwrap();
+        indexer.reader.reload().unwrap();
+
+        // 两处替换
+        let typo = "zettalkastan";
+        let one = FuzzyOptions { distance: FuzzyDistance::Fixed(1), prefix: false };
+        let two = FuzzyOptions { distance: FuzzyDistance::Fixed(2), prefix: false };
+        assert!(indexer.fuzzy_search(typo, 10, &one).unwrap().is_empty());
+        assert_eq!(indexer.fuzzy_search(typo, 10, &two).unwrap().len(), 1);
+
+        let auto = FuzzyOptions { distance: FuzzyDistance::Auto, prefix: false };
+        assert_eq!(indexer.fuzzy_search(typo, 10, &auto).unwrap().len(), 1);
+        assert_eq!(FuzzyDistance::Auto.for_term("the"), 0);
+        assert_eq!(FuzzyDistance::Fixed(5).for_term("zettel"), FuzzyDistance::MAX);
+
+        let prefix = FuzzyOptions { distance: FuzzyDistance::Fixed(0), prefix: true };
+        assert_eq!(indexer.fuzzy_search("zettel", 10, &prefix).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_sources_share_index_with_cards() {
+        let dir = tempdir().unwrap();
+        let indexer = Indexer::new(dir.path()).unwrap();
+        indexer
+            .index_doc_with_type("card", "Reading notes", "about gödel", &[], "", 1_000, Some("permanent"))
+            .unwrap();
+        let source: Source = serde_json::from_value(serde_json::json!({
+            "id": "src",
+            "type": "book",
+            "title": "Gödel, Escher, Bach",
+            "author": "Douglas Hofstadter",
+            "url": null,
+            "cover": null,
+            "description": null,
+            "tags": [],
+            "progress": 0,
+            "lastReadAt": null,
+            "metadata": null,
+            "noteIds": [],
+            "createdAt": 1000,
+            "updatedAt": 1000
+        }))
+        .unwrap();
+        indexer.index_source(&source).unwrap();
+        indexer.reader.reload().unwrap();
+
+        let results = indexer.search_with_snippets("hofstadter", 10).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].card_type.as_deref(), Some(SOURCE_DOC_TYPE));
+
+        let filter = SearchFilter {
+            card_type: Some(SOURCE_DOC_TYPE.to_string()),
+            ..Default::default()
+        };
+        let ids: Vec<_> = indexer
+            .search_with_filter("gödel", 10, &filter)
+            .unwrap()
+            .into_iter()
+            .map(|r| r.id)
+            .collect();
+        assert_eq!(ids, vec!["src"]);
+
+        indexer.delete_doc("src").unwrap();
+        indexer.reader.reload().unwrap();
+        assert!(indexer.search_with_snippets("hofstadter", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_duplicate_docs_yield_single_result() {
+        let dir = tempdir().unwrap();
+        let indexer = Indexer::new(dir.path()).unwrap();
+        indexer
+            .index_doc("card", "zettel", "zettel body", &[], "cards/old.json", 1_000)
+            .unwrap();
+
+        // 模拟索引漂移：不删除旧文档直接再写入一份
+        let mut writer: IndexWriter<TantivyDocument> = indexer.index.writer(50_000_000).unwrap();
+        let mut doc = TantivyDocument::default();
+        doc.add_text(indexer.id, "card");
+        doc.add_text(indexer.title, "zettel");
+        doc.add_text(indexer.content, "zettel body");
+        doc.add_text(indexer.path, "cards/new.json");
+        doc.add_i64(indexer.modified_at, 2_000);
+        writer.add_document(doc).unwrap();
+        writer.commit().unwrap();
+        indexer.reader.reload().unwrap();
+
+        assert_eq!(indexer.search_with_snippets("zettel", 10).unwrap().len(), 1);
+        let fuzzy = indexer.fuzzy_search("zettel", 10, &FuzzyOptions::default()).unwrap();
+        assert_eq!(fuzzy.len(), 1);
+
+        assert_eq!(indexer.dedup_index().unwrap(), 1);
+        indexer.reader.reload().unwrap();
+        assert_eq!(indexer.all_doc_mtimes().unwrap()["card"], 2_000);
+        assert_eq!(indexer.dedup_index().unwrap(), 0);
     }
 }
@@ -1,29 +1,72 @@
 //! Card 应用服务层
 //! 封装 Card 相关的业务逻辑，协调 CardRepository 和其他服务
 
+use crate::crypto::{self, KeyParams, VaultKey};
 use crate::database::CardRepository;
+use crate::database::ConfigRepository;
+use crate::database::HighlightRepository;
 use crate::database::SourceRepository;
-use crate::error::AppResult;
-use crate::models::{Card, CardType, CreateCardRequest, UpdateCardRequest};
+use crate::db::extract_text_recursive;
+use crate::error::{AppError, AppResult};
+use crate::graph::LinkResolver;
+use crate::models::{
+    Backlink, Card, CardChange, CardChangeOp, CardListItem, CardListSort, CardMatch, CardType,
+    CardRevision, CardTemplate, CreateCardRequest, FindOptions, Reference, ReferenceKind, ReplaceResult, ResolvedReference,
+    ReviewState, TextChange, TrashedCard, TypeChangeResult, UnlinkedMention, UpdateCardRequest,
+};
 use crate::search::Indexer;
+use crate::storage;
+use regex::{NoExpand, Regex};
 use serde_json::Value as JsonValue;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
 /// Card 应用服务
 pub struct CardService {
     card_repo: Arc<CardRepository>,
     source_repo: Arc<SourceRepository>,
+    /// 用于解析正文中引用的高亮
+    highlight_repo: Arc<HighlightRepository>,
+    /// 用于读取旧版 Markdown 卡片
+    vault_path: Option<PathBuf>,
+    /// 保存加密卡片的密钥参数
+    config_repo: Arc<ConfigRepository>,
+    /// 解锁后的密钥，只保存在内存中
+    key: Mutex<Option<VaultKey>>,
+    /// 旧版 Markdown 卡片的解析缓存，避免每次读取都重新解析整个 vault
+    markdown_cache: Mutex<storage::MarkdownCardCache>,
 }
 
+/// 配置表中保存密钥参数的键
+const ENCRYPTION_CONFIG_KEY: &str = "card_encryption";
+
+/// 配置表中保存历史版本数量上限的键
+const REVISION_LIMIT_CONFIG_KEY: &str = "card_revision_limit";
+
+/// 每张卡片默认保留的历史版本数量
+const DEFAULT_REVISION_LIMIT: usize = 20;
+
 impl CardService {
-    pub fn new(card_repo: Arc<CardRepository>, source_repo: Arc<SourceRepository>) -> Self {
+    pub fn new(
+        card_repo: Arc<CardRepository>,
+        source_repo: Arc<SourceRepository>,
+        highlight_repo: Arc<HighlightRepository>,
+        config_repo: Arc<ConfigRepository>,
+        vault_path: Option<PathBuf>,
+    ) -> Self {
         Self {
             card_repo,
             source_repo,
+            highlight_repo,
+            vault_path,
+            config_repo,
+            key: Mutex::new(None),
+            markdown_cache: Mutex::new(storage::MarkdownCardCache::default()),
         }
     }
 
-    /// 获取所有卡片
+    /// 获取所有卡片（包含尚未导入数据库的旧版 Markdown 卡片）
     pub async fn get_all(&self) -> AppResult<Vec<Card>> {
         let mut cards = self.card_repo.get_all().await?;
         // 为每个卡片生成虚拟路径
@@ -31,11 +74,55 @@ impl CardService {
             if card.path.is_none() {
                 card.path = Some(card.generate_path());
             }
+            self.reveal(card);
+        }
+
+        // 数据库中已有同 ID 卡片时以数据库为准，已移入回收站的也不再从 Markdown 读出
+        if let Some(vault_path) = &self.vault_path {
+            let mut known: HashSet<String> = cards.iter().map(|c| c.id.clone()).collect();
+            known.extend(self.card_repo.get_trashed_ids().await?);
+            let markdown_cards = self.markdown_cache.lock().unwrap().cards(vault_path);
+            cards.extend(
+                markdown_cards
+                    .into_iter()
+                    .filter(|c| !known.contains(&c.id)),
+            );
+        }
+        Ok(cards)
+    }
+
+    /// ID 以指定前缀开头的卡片 ID（包含旧版 Markdown 卡片，不含回收站）
+    pub async fn get_ids_with_prefix(&self, prefix: &str) -> AppResult<Vec<String>> {
+        let mut ids = self.card_repo.get_ids_with_prefix(prefix).await?;
+        if let Some(vault_path) = &self.vault_path {
+            let mut known: HashSet<String> = ids.iter().cloned().collect();
+            known.extend(self.card_repo.get_trashed_ids().await?);
+            let markdown_cards = self.markdown_cache.lock().unwrap().cards(vault_path);
+            ids.extend(
+                markdown_cards
+                    .into_iter()
+                    .map(|c| c.id)
+                    .filter(|id| id.starts_with(prefix) && !known.contains(id)),
+            );
+        }
+        Ok(ids)
+    }
+
+    /// 获取所有卡片，`include_archived` 为 false 时跳过已归档卡片
+    pub async fn get_all_filtered(&self, include_archived: bool) -> AppResult<Vec<Card>> {
+        let mut cards = self.get_all().await?;
+        if !include_archived {
+            cards.retain(|c| !c.archived);
         }
         Ok(cards)
     }
 
-    /// 获取单个卡片
+    /// 已归档卡片的 ID，用于从搜索结果中排除
+    pub async fn get_archived_ids(&self) -> AppResult<HashSet<String>> {
+        Ok(self.card_repo.get_archived_ids().await?.into_iter().collect())
+    }
+
+    /// 获取单个卡片，数据库中没有时回退到旧版 Markdown 卡片
     pub async fn get_by_id(&self, id: &str) -> AppResult<Option<Card>> {
         if id.contains("..") {
             return Err(crate::error::AppError::InvalidInput("Invalid card ID".to_string()));
@@ -45,10 +132,19 @@ impl CardService {
             if c.path.is_none() {
                 c.path = Some(c.generate_path());
             }
+            self.reveal(c);
+        }
+        if card.is_none() && !self.is_trashed(id).await? {
+            card = self.get_markdown_card(id);
         }
         Ok(card)
     }
 
+    /// 卡片是否在回收站中
+    async fn is_trashed(&self, id: &str) -> AppResult<bool> {
+        Ok(self.card_repo.get_trashed_ids().await?.iter().any(|t| t == id))
+    }
+
     /// 通过路径获取卡片（兼容旧 API）
     pub async fn get_by_path(&self, path: &str) -> AppResult<Option<Card>> {
         let id = if let Some(id) = path
@@ -56,12 +152,90 @@ impl CardService {
             .and_then(|p| p.strip_suffix(".json"))
         {
             id
+        } else if path.ends_with(".md") {
+            Path::new(path).file_stem().and_then(|s| s.to_str()).unwrap_or(path)
         } else {
             path
         };
         self.get_by_id(id).await
     }
 
+    fn get_markdown_card(&self, id: &str) -> Option<Card> {
+        self.vault_path
+            .as_deref()
+            .and_then(|vault_path| self.markdown_cache.lock().unwrap().card(vault_path, id))
+    }
+
+    /// 旧版 Markdown 卡片首次被修改时导入数据库（保留原 ID，源文件不动）
+    async fn import_markdown_card(&self, id: &str) -> AppResult<()> {
+        if self.card_repo.get_by_id(id).await?.is_some() || self.is_trashed(id).await? {
+            return Ok(());
+        }
+        if let Some(card) = self.get_markdown_card(id) {
+            self.card_repo
+                .create(CreateCardRequest {
+                    id: Some(card.id),
+                    title: card.title,
+                    card_type: card.card_type,
+                    content: card.content,
+                    tags: card.tags,
+                    aliases: card.aliases,
+                    source_id: card.source_id,
+                })
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// 批量导入外部卡片并保留原 ID，数据库或回收站中已有的 ID 跳过
+    ///
+    /// 返回与输入顺序一致的结果：`Ok(true)` 已创建，`Ok(false)` 已跳过；新卡片一次提交写入索引
+    pub async fn import_cards(
+        &self,
+        cards: Vec<Card>,
+        indexer: Option<&Mutex<Option<Indexer>>>,
+        on_change: Option<&(dyn Fn(CardChange) + Sync)>,
+    ) -> AppResult<Vec<AppResult<bool>>> {
+        let trashed: HashSet<String> = self.card_repo.get_trashed_ids().await?.into_iter().collect();
+        let mut results = Vec::with_capacity(cards.len());
+        let mut created = Vec::new();
+        for card in cards {
+            if trashed.contains(&card.id) || self.card_repo.get_by_id(&card.id).await?.is_some() {
+                results.push(Ok(false));
+                continue;
+            }
+            let req = CreateCardRequest {
+                id: Some(card.id),
+                title: card.title,
+                card_type: card.card_type,
+                content: card.content,
+                tags: card.tags,
+                aliases: card.aliases,
+                source_id: None,
+            };
+            match self.card_repo.create(req).await {
+                Ok(mut card) => {
+                    if card.path.is_none() {
+                        card.path = Some(card.generate_path());
+                    }
+                    created.push(card);
+                    results.push(Ok(true));
+                }
+                Err(e) => results.push(Err(e)),
+            }
+        }
+
+        if let Some(indexer) = indexer {
+            if let Ok(Some(idx)) = indexer.lock().as_deref() {
+                idx.index_cards_batch(&created, &[]).ok();
+            }
+        }
+        for card in &created {
+            notify(on_change, CardChangeOp::Created, &card.id, Some(&card.card_type));
+        }
+        Ok(results)
+    }
+
     /// 创建卡片
     pub async fn create(
         &self,
@@ -70,6 +244,7 @@ impl CardService {
         content: Option<&str>,
         source_id: Option<&str>,
         indexer: Option<&Mutex<Option<Indexer>>>,
+        on_change: Option<&(dyn Fn(CardChange) + Sync)>,
     ) -> AppResult<Card> {
         // 验证输入
         if title.trim().is_empty() {
@@ -108,22 +283,9 @@ impl CardService {
         }
 
         // 更新搜索索引
-        if let Some(indexer) = indexer {
-            if let Ok(Some(idx)) = indexer.lock().as_deref() {
-                let path = card.path.as_ref().map(|p| p.as_str()).unwrap_or("");
-                idx.index_doc_with_type(
-                    &card.id,
-                    &card.title,
-                    &card.plain_text,
-                    &card.tags,
-                    path,
-                    card.modified_at,
-                    Some(card.card_type.as_str()),
-                )
-                .ok();
-            }
-        }
+        index_card(&card, indexer);
 
+        notify(on_change, CardChangeOp::Created, &card.id, Some(&card.card_type));
         Ok(card)
     }
 
@@ -136,15 +298,40 @@ impl CardService {
         tags: Option<Vec<String>>,
         card_type: Option<CardType>,
         indexer: Option<&Mutex<Option<Indexer>>>,
+        on_change: Option<&(dyn Fn(CardChange) + Sync)>,
     ) -> AppResult<Card> {
         if id.contains("..") {
             return Err(crate::error::AppError::InvalidInput("Invalid card ID".to_string()));
         }
 
+        self.import_markdown_card(id).await?;
+
+        // 加密卡片的正文先加密再单独写入；未解锁时拒绝修改正文，
+        // 已经是密文的内容（前端拿到的旧数据）视为未修改
+        let content = content.filter(|c| !crypto::is_encrypted(c));
+        let previous = match (content, &self.vault_path) {
+            (None, None) => None,
+            _ => self.card_repo.get_by_id(id).await?,
+        };
+        let encrypted = content.is_some() && previous.as_ref().is_some_and(|c| c.encrypted);
+
+        // 有实际改动时才保存修改前的版本；加密正文无法比较，视为已修改。
+        // previous 直接取自数据库，加密卡片的历史版本里保存的是密文
+        let revision = previous.filter(|prev| {
+            title.is_some_and(|t| t != prev.title)
+                || content.is_some_and(|c| prev.encrypted || c != prev.content)
+                || tags.as_ref().is_some_and(|t| *t != prev.tags)
+                || card_type.as_ref().is_some_and(|t| *t != prev.card_type)
+        });
+        let sealed = match content {
+            Some(c) if encrypted => Some(self.seal(c)?),
+            _ => None,
+        };
+
         // 创建更新请求（links 将在 db.rs 的 update_card 中从 content 提取）
         let req = UpdateCardRequest {
             title: title.map(String::from),
-            content: content.map(String::from),
+            content: if encrypted { None } else { content.map(String::from) },
             tags,
             card_type,
             aliases: None,
@@ -156,6 +343,22 @@ impl CardService {
             .update(id, req)
             .await?
             .ok_or_else(|| crate::error::AppError::NotFound("Card not found".to_string()))?;
+        if let Some(sealed) = sealed {
+            card = self
+                .card_repo
+                .set_encrypted(id, &sealed, true)
+                .await?
+                .ok_or_else(|| AppError::NotFound("Card not found".to_string()))?;
+        }
+        self.reveal(&mut card);
+
+        // 历史版本只是辅助数据，写入失败不影响本次修改
+        if let (Some(vault_path), Some(revision)) = (&self.vault_path, revision) {
+            let keep = self.revision_limit().await?;
+            if let Err(e) = storage::write_card_revision(vault_path, &revision, keep) {
+                eprintln!("Failed to save revision of card {}: {}", id, e);
+            }
+        }
 
         // 生成虚拟路径
         if card.path.is_none() {
@@ -163,22 +366,9 @@ impl CardService {
         }
 
         // 更新搜索索引
-        if let Some(indexer) = indexer {
-            if let Ok(Some(idx)) = indexer.lock().as_deref() {
-                let path = card.path.as_ref().map(|p| p.as_str()).unwrap_or("");
-                idx.index_doc_with_type(
-                    &card.id,
-                    &card.title,
-                    &card.plain_text,
-                    &card.tags,
-                    path,
-                    card.modified_at,
-                    Some(card.card_type.as_str()),
-                )
-                .ok();
-            }
-        }
+        index_card(&card, indexer);
 
+        notify(on_change, CardChangeOp::Updated, &card.id, Some(&card.card_type));
         Ok(card)
     }
 
@@ -187,11 +377,20 @@ impl CardService {
         &self,
         id: &str,
         indexer: Option<&Mutex<Option<Indexer>>>,
+        on_change: Option<&(dyn Fn(CardChange) + Sync)>,
     ) -> AppResult<()> {
         if id.contains("..") {
             return Err(crate::error::AppError::InvalidInput("Invalid card ID".to_string()));
         }
 
+        // 旧版 Markdown 卡片先导入数据库，才能移入回收站
+        self.import_markdown_card(id).await?;
+
+        // 删除前记下类型，供变更通知使用
+        let card_type = match on_change {
+            Some(_) => self.card_repo.get_by_id(id).await?.map(|c| c.card_type),
+            None => None,
+        };
         self.card_repo.delete(id).await?;
 
         // 更新搜索索引
@@ -201,8 +400,1100 @@ impl CardService {
             }
         }
 
+        notify(on_change, CardChangeOp::Deleted, id, card_type.as_ref());
         Ok(())
     }
+
+    /// 从回收站恢复卡片，重新加入搜索索引
+    pub async fn restore(
+        &self,
+        id: &str,
+        indexer: Option<&Mutex<Option<Indexer>>>,
+        on_change: Option<&(dyn Fn(CardChange) + Sync)>,
+    ) -> AppResult<Option<Card>> {
+        if id.contains("..") {
+            return Err(crate::error::AppError::InvalidInput("Invalid card ID".to_string()));
+        }
+        let Some(mut card) = self.card_repo.restore(id).await? else {
+            return Ok(None);
+        };
+        if card.path.is_none() {
+            card.path = Some(card.generate_path());
+        }
+        self.reveal(&mut card);
+        index_card(&card, indexer);
+
+        notify(on_change, CardChangeOp::Created, &card.id, Some(&card.card_type));
+        Ok(Some(card))
+    }
+
+    /// 获取回收站中的卡片
+    pub async fn get_trashed(&self) -> AppResult<Vec<TrashedCard>> {
+        self.card_repo.get_trashed().await
+    }
+
+    /// 永久删除回收站中超过指定天数的卡片及其历史版本；旧版 Markdown 卡片的源文件一并删除，
+    /// 否则卡片会从 vault 中重新读出
+    pub async fn purge_trashed(&self, older_than_days: u32) -> AppResult<u64> {
+        let ids = self.card_repo.purge_trashed(older_than_days).await?;
+        if let Some(vault_path) = &self.vault_path {
+            for id in &ids {
+                if let Err(e) = storage::delete_card_revisions(vault_path, id) {
+                    eprintln!("Failed to delete revisions of card {}: {}", id, e);
+                }
+            }
+            storage::delete_markdown_cards(vault_path, &ids).map_err(AppError::Storage)?;
+        }
+        Ok(ids.len() as u64)
+    }
+
+    /// 列出可用的卡片模板
+    pub fn list_templates(&self) -> AppResult<Vec<CardTemplate>> {
+        let vault_path = self.vault_path.as_deref().ok_or(AppError::VaultPathNotSet)?;
+        Ok(storage::list_templates(vault_path))
+    }
+
+    /// 按今天的日期和卡片标题渲染模板，返回 TipTap JSON 字符串
+    pub fn render_template(&self, template_id: &str, title: &str) -> AppResult<String> {
+        let vault_path = self.vault_path.as_deref().ok_or(AppError::VaultPathNotSet)?;
+        let template = storage::read_template(vault_path, template_id)
+            .ok_or_else(|| AppError::NotFound(format!("Template not found: {}", template_id)))?;
+        let today = chrono::Local::now().date_naive();
+        Ok(storage::render_template(&template, title, today).to_string())
+    }
+
+    /// 将卡片导出为带 frontmatter 的 Markdown
+    pub async fn export_markdown(&self, id: &str) -> AppResult<String> {
+        let card = self
+            .get_by_id(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Card not found".to_string()))?;
+        if card.encrypted && !self.is_unlocked() {
+            return Err(AppError::Locked);
+        }
+        Ok(storage::card_to_markdown(&card))
+    }
+
+    /// 可导出为 Markdown 的卡片；未解锁时跳过加密卡片
+    pub async fn get_exportable(&self) -> AppResult<Vec<Card>> {
+        let unlocked = self.is_unlocked();
+        Ok(self
+            .get_all()
+            .await?
+            .into_iter()
+            .filter(|card| unlocked || !card.encrypted)
+            .collect())
+    }
+
+    /// 获取卡片的历史版本（新的在前）
+    pub async fn get_revisions(&self, id: &str) -> AppResult<Vec<CardRevision>> {
+        if id.contains("..") {
+            return Err(AppError::InvalidInput("Invalid card ID".to_string()));
+        }
+        let Some(vault_path) = &self.vault_path else {
+            return Ok(Vec::new());
+        };
+        Ok(storage::read_card_revisions(vault_path, id)
+            .into_iter()
+            .map(|card| CardRevision {
+                timestamp: card.modified_at,
+                title: card.title,
+                word_count: card.word_count,
+            })
+            .collect())
+    }
+
+    /// 将卡片恢复到指定的历史版本；恢复前的内容同样会保存为历史版本
+    pub async fn restore_revision(
+        &self,
+        id: &str,
+        timestamp: i64,
+        indexer: Option<&Mutex<Option<Indexer>>>,
+        on_change: Option<&(dyn Fn(CardChange) + Sync)>,
+    ) -> AppResult<Card> {
+        if id.contains("..") {
+            return Err(AppError::InvalidInput("Invalid card ID".to_string()));
+        }
+        let revision = self
+            .vault_path
+            .as_deref()
+            .and_then(|vault_path| storage::read_card_revision(vault_path, id, timestamp))
+            .ok_or_else(|| AppError::NotFound("Revision not found".to_string()))?;
+        let content = if revision.encrypted {
+            self.open(&revision.content)?
+        } else {
+            revision.content
+        };
+        self.update(
+            id,
+            Some(&revision.title),
+            Some(&content),
+            Some(revision.tags),
+            Some(revision.card_type),
+            indexer,
+            on_change,
+        )
+        .await
+    }
+
+    /// 每张卡片保留的历史版本数量
+    pub async fn revision_limit(&self) -> AppResult<usize> {
+        Ok(self
+            .config_repo
+            .get(REVISION_LIMIT_CONFIG_KEY)
+            .await?
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_REVISION_LIMIT))
+    }
+
+    /// 设置每张卡片保留的历史版本数量，0 表示不保存
+    pub async fn set_revision_limit(&self, limit: usize) -> AppResult<()> {
+        self.config_repo
+            .set(REVISION_LIMIT_CONFIG_KEY, &limit.to_string())
+            .await
+    }
+
+    /// 在所有卡片中重命名标签（已有新标签的卡片去重），返回受影响的卡片数量
+    pub async fn rename_tag(
+        &self,
+        old: &str,
+        new: &str,
+        indexer: Option<&Mutex<Option<Indexer>>>,
+        on_change: Option<&(dyn Fn(CardChange) + Sync)>,
+    ) -> AppResult<usize> {
+        let new = new.trim();
+        if new.is_empty() {
+            return Err(AppError::InvalidInput("标签不能为空".to_string()));
+        }
+        self.rewrite_tags(old, Some(new), indexer, on_change).await
+    }
+
+    /// 从所有卡片中移除标签，返回受影响的卡片数量
+    pub async fn delete_tag(
+        &self,
+        tag: &str,
+        indexer: Option<&Mutex<Option<Indexer>>>,
+        on_change: Option<&(dyn Fn(CardChange) + Sync)>,
+    ) -> AppResult<usize> {
+        self.rewrite_tags(tag, None, indexer, on_change).await
+    }
+
+    async fn rewrite_tags(
+        &self,
+        from: &str,
+        into: Option<&str>,
+        indexer: Option<&Mutex<Option<Indexer>>>,
+        on_change: Option<&(dyn Fn(CardChange) + Sync)>,
+    ) -> AppResult<usize> {
+        let changed = self.card_repo.merge_tags(&[from.to_string()], into).await?;
+        let mut cards = Vec::with_capacity(changed.len());
+        for id in &changed {
+            // 回收站中的卡片不在索引中，get_by_id 返回 None 时跳过
+            let Some(mut card) = self.card_repo.get_by_id(id).await? else {
+                continue;
+            };
+            if card.path.is_none() {
+                card.path = Some(card.generate_path());
+            }
+            self.reveal(&mut card);
+            cards.push(card);
+        }
+
+        // 一次提交更新全部受影响卡片的索引
+        if let Some(indexer) = indexer {
+            if let Ok(Some(idx)) = indexer.lock().as_deref() {
+                idx.index_cards_batch(&cards, &[]).ok();
+            }
+        }
+        for card in &cards {
+            notify(on_change, CardChangeOp::Updated, &card.id, Some(&card.card_type));
+        }
+        Ok(changed.len())
+    }
+
+    /// 设置卡片置顶状态
+    pub async fn set_pinned(
+        &self,
+        id: &str,
+        pinned: bool,
+        on_change: Option<&(dyn Fn(CardChange) + Sync)>,
+    ) -> AppResult<Card> {
+        if id.contains("..") {
+            return Err(crate::error::AppError::InvalidInput("Invalid card ID".to_string()));
+        }
+        self.import_markdown_card(id).await?;
+
+        let mut card = self
+            .card_repo
+            .set_pinned(id, pinned)
+            .await?
+            .ok_or_else(|| crate::error::AppError::NotFound("Card not found".to_string()))?;
+        if card.path.is_none() {
+            card.path = Some(card.generate_path());
+        }
+        self.reveal(&mut card);
+        notify(on_change, CardChangeOp::Updated, &card.id, Some(&card.card_type));
+        Ok(card)
+    }
+
+    /// 加入或移出间隔重复复习；已在复习中的卡片再次加入时保留原进度
+    pub async fn set_review_enabled(
+        &self,
+        id: &str,
+        enabled: bool,
+        on_change: Option<&(dyn Fn(CardChange) + Sync)>,
+    ) -> AppResult<Card> {
+        let card = self.get_existing_for_review(id).await?;
+        let review = match (enabled, card.review) {
+            (false, _) => None,
+            (true, Some(review)) => Some(review),
+            (true, None) => Some(ReviewState::new(chrono::Utc::now().timestamp_millis())),
+        };
+        let card = self.save_review(id, review.as_ref()).await?;
+        notify(on_change, CardChangeOp::Updated, &card.id, Some(&card.card_type));
+        Ok(card)
+    }
+
+    /// 记录一次复习评分（0-5），按 SM-2 计算下次复习时间；未加入复习的卡片会自动加入
+    pub async fn review(
+        &self,
+        id: &str,
+        grade: u8,
+        on_change: Option<&(dyn Fn(CardChange) + Sync)>,
+    ) -> AppResult<Card> {
+        if grade > 5 {
+            return Err(crate::error::AppError::InvalidInput(format!("Grade must be 0-5, got {}", grade)));
+        }
+        let card = self.get_existing_for_review(id).await?;
+        let now = chrono::Utc::now().timestamp_millis();
+        let review = card.review.unwrap_or_else(|| ReviewState::new(now)).next(grade, now);
+        let card = self.save_review(id, Some(&review)).await?;
+        notify(on_change, CardChangeOp::Updated, &card.id, Some(&card.card_type));
+        Ok(card)
+    }
+
+    /// 获取当前到期需要复习的卡片
+    pub async fn get_due(&self) -> AppResult<Vec<CardListItem>> {
+        self.card_repo.get_due(chrono::Utc::now().timestamp_millis()).await
+    }
+
+    async fn get_existing_for_review(&self, id: &str) -> AppResult<Card> {
+        if id.contains("..") {
+            return Err(crate::error::AppError::InvalidInput("Invalid card ID".to_string()));
+        }
+        self.import_markdown_card(id).await?;
+        self.card_repo
+            .get_by_id(id)
+            .await?
+            .ok_or_else(|| crate::error::AppError::NotFound("Card not found".to_string()))
+    }
+
+    async fn save_review(&self, id: &str, review: Option<&ReviewState>) -> AppResult<Card> {
+        let mut card = self
+            .card_repo
+            .set_review(id, review)
+            .await?
+            .ok_or_else(|| crate::error::AppError::NotFound("Card not found".to_string()))?;
+        if card.path.is_none() {
+            card.path = Some(card.generate_path());
+        }
+        self.reveal(&mut card);
+        Ok(card)
+    }
+
+    /// 批量修改卡片类型：数据库在单个事务中更新，搜索索引一次提交，返回每个 ID 的结果
+    ///
+    /// 数据库更新失败时整体回滚并返回错误，不会出现部分卡片已修改的状态
+    pub async fn batch_change_type(
+        &self,
+        ids: &[String],
+        new_type: CardType,
+        indexer: Option<&Mutex<Option<Indexer>>>,
+        on_change: Option<&(dyn Fn(CardChange) + Sync)>,
+    ) -> AppResult<Vec<TypeChangeResult>> {
+        let mut valid = Vec::new();
+        for id in ids {
+            if !id.contains("..") && !valid.contains(id) {
+                self.import_markdown_card(id).await?;
+                valid.push(id.clone());
+            }
+        }
+
+        let updated = self.card_repo.set_type_batch(&valid, &new_type).await?;
+
+        let mut cards = Vec::with_capacity(updated.len());
+        for id in &updated {
+            if let Some(mut card) = self.card_repo.get_by_id(id).await? {
+                card.path = Some(card.generate_path());
+                cards.push(card);
+            }
+        }
+        if let Some(indexer) = indexer {
+            if let Ok(Some(idx)) = indexer.lock().as_deref() {
+                idx.index_cards_batch(&cards, &[]).ok();
+            }
+        }
+        for card in &cards {
+            notify(on_change, CardChangeOp::Updated, &card.id, Some(&card.card_type));
+        }
+
+        Ok(ids
+            .iter()
+            .map(|id| {
+                let error = if id.contains("..") {
+                    Some("Invalid card ID".to_string())
+                } else if !updated.contains(id) {
+                    Some("Card not found".to_string())
+                } else {
+                    None
+                };
+                TypeChangeResult {
+                    id: id.clone(),
+                    success: error.is_none(),
+                    error,
+                }
+            })
+            .collect())
+    }
+
+    /// 获取置顶卡片
+    pub async fn get_pinned(&self) -> AppResult<Vec<Card>> {
+        let mut cards = self.card_repo.get_pinned().await?;
+        for card in &mut cards {
+            if card.path.is_none() {
+                card.path = Some(card.generate_path());
+            }
+            self.reveal(card);
+        }
+        Ok(cards)
+    }
+
+    /// 获取卡片列表（含字数，不加载正文）
+    pub async fn get_list(
+        &self,
+        sort: CardListSort,
+        include_archived: bool,
+    ) -> AppResult<Vec<CardListItem>> {
+        self.card_repo.get_list(sort, include_archived).await
+    }
+
+    /// 归档或取消归档卡片；归档只影响默认列表，链接保持不变
+    pub async fn set_archived(
+        &self,
+        id: &str,
+        archived: bool,
+        on_change: Option<&(dyn Fn(CardChange) + Sync)>,
+    ) -> AppResult<Card> {
+        if id.contains("..") {
+            return Err(crate::error::AppError::InvalidInput("Invalid card ID".to_string()));
+        }
+        self.import_markdown_card(id).await?;
+
+        let mut card = self
+            .card_repo
+            .set_archived(id, archived)
+            .await?
+            .ok_or_else(|| crate::error::AppError::NotFound("Card not found".to_string()))?;
+        if card.path.is_none() {
+            card.path = Some(card.generate_path());
+        }
+        self.reveal(&mut card);
+        notify(on_change, CardChangeOp::Updated, &card.id, Some(&card.card_type));
+        Ok(card)
+    }
+
+    /// 是否已设置加密口令
+    pub async fn has_passphrase(&self) -> AppResult<bool> {
+        Ok(self.config_repo.get(ENCRYPTION_CONFIG_KEY).await?.is_some())
+    }
+
+    /// 首次设置加密口令，需要输入两次确认；设置后即处于解锁状态
+    pub async fn set_passphrase(&self, passphrase: &str, confirmation: &str) -> AppResult<()> {
+        if passphrase.is_empty() {
+            return Err(AppError::InvalidInput("口令不能为空".to_string()));
+        }
+        if passphrase != confirmation {
+            return Err(AppError::InvalidInput("两次输入的口令不一致".to_string()));
+        }
+        if self.has_passphrase().await? {
+            return Err(AppError::InvalidInput("已设置过口令".to_string()));
+        }
+
+        // Argon2 派生密钥比较耗时，放到阻塞线程中执行
+        let passphrase = passphrase.to_string();
+        let (key, params) = tokio::task::spawn_blocking(move || VaultKey::create(&passphrase))
+            .await
+            .map_err(|e| AppError::Storage(e.to_string()))??;
+        self.config_repo
+            .set(ENCRYPTION_CONFIG_KEY, &serde_json::to_string(&params)?)
+            .await?;
+        *self.key.lock().unwrap() = Some(key);
+        Ok(())
+    }
+
+    /// 用口令解锁加密卡片，需要先通过 `set_passphrase` 设置口令。加密卡片的正文不进入搜索索引
+    pub async fn unlock(
+        &self,
+        passphrase: &str,
+        indexer: Option<&Mutex<Option<Indexer>>>,
+    ) -> AppResult<()> {
+        if passphrase.is_empty() {
+            return Err(AppError::InvalidInput("口令不能为空".to_string()));
+        }
+
+        let params = match self.config_repo.get(ENCRYPTION_CONFIG_KEY).await? {
+            Some(json) => serde_json::from_str::<KeyParams>(&json)?,
+            None => return Err(AppError::InvalidInput("尚未设置口令".to_string())),
+        };
+        let passphrase = passphrase.to_string();
+        let key = tokio::task::spawn_blocking(move || VaultKey::unlock(&passphrase, &params))
+            .await
+            .map_err(|e| AppError::Storage(e.to_string()))??;
+
+        *self.key.lock().unwrap() = Some(key);
+        self.reindex_encrypted(indexer).await
+    }
+
+    /// 锁定：丢弃并清零内存中的密钥
+    pub async fn lock(&self, indexer: Option<&Mutex<Option<Indexer>>>) -> AppResult<()> {
+        // take 后立即 drop，VaultKey 在 Drop 中清零
+        drop(self.key.lock().unwrap().take());
+        self.reindex_encrypted(indexer).await
+    }
+
+    /// 是否已解锁
+    pub fn is_unlocked(&self) -> bool {
+        self.key.lock().unwrap().is_some()
+    }
+
+    /// 加密或解密单张卡片的正文，需要先解锁
+    pub async fn set_encrypted(
+        &self,
+        id: &str,
+        encrypted: bool,
+        indexer: Option<&Mutex<Option<Indexer>>>,
+        on_change: Option<&(dyn Fn(CardChange) + Sync)>,
+    ) -> AppResult<Card> {
+        if id.contains("..") {
+            return Err(AppError::InvalidInput("Invalid card ID".to_string()));
+        }
+        self.import_markdown_card(id).await?;
+
+        let card = self
+            .card_repo
+            .get_by_id(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Card not found".to_string()))?;
+        let content = match (card.encrypted, encrypted) {
+            (false, true) => self.seal(&card.content)?,
+            (true, false) => self.open(&card.content)?,
+            _ => card.content,
+        };
+
+        let mut card = self
+            .card_repo
+            .set_encrypted(id, &content, encrypted)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Card not found".to_string()))?;
+        card.path = Some(card.generate_path());
+        self.reveal(&mut card);
+        index_card(&card, indexer);
+
+        // 加密前的历史版本是明文，加密后一并删除
+        if let (Some(vault_path), true) = (&self.vault_path, encrypted) {
+            storage::delete_card_revisions(vault_path, id).map_err(AppError::Storage)?;
+        }
+
+        notify(on_change, CardChangeOp::Updated, &card.id, Some(&card.card_type));
+        Ok(card)
+    }
+
+    fn seal(&self, content: &str) -> AppResult<String> {
+        let key = self.key.lock().unwrap();
+        Ok(key.as_ref().ok_or(AppError::Locked)?.encrypt(content)?)
+    }
+
+    fn open(&self, content: &str) -> AppResult<String> {
+        let key = self.key.lock().unwrap();
+        Ok(key.as_ref().ok_or(AppError::Locked)?.decrypt(content)?)
+    }
+
+    /// 解密加密卡片的正文；未解锁或解密失败时清空正文，密文不离开后端
+    fn reveal(&self, card: &mut Card) {
+        if !card.encrypted {
+            return;
+        }
+        match self.open(&card.content) {
+            Ok(content) => {
+                card.plain_text =
+                    crate::db::extract_plain_text_from_json(&content).unwrap_or_default();
+                card.content = content;
+            }
+            Err(_) => {
+                card.content = String::new();
+                card.plain_text = String::new();
+            }
+        }
+    }
+
+    /// 重建加密卡片的索引（只含标题），清除旧版本写入索引的正文明文
+    async fn reindex_encrypted(&self, indexer: Option<&Mutex<Option<Indexer>>>) -> AppResult<()> {
+        let Some(indexer) = indexer else {
+            return Ok(());
+        };
+        let mut cards = Vec::new();
+        for mut card in self.card_repo.get_all().await? {
+            if card.encrypted {
+                card.path = Some(card.generate_path());
+                card.plain_text = String::new();
+                cards.push(card);
+            }
+        }
+        if let Ok(Some(idx)) = indexer.lock().as_deref() {
+            idx.index_cards_batch(&cards, &[]).ok();
+        }
+        Ok(())
+    }
+
+    /// 全库查找：只在 TipTap 文本节点中匹配
+    pub async fn find_in_cards(&self, query: &str, options: &FindOptions) -> AppResult<Vec<CardMatch>> {
+        let matcher = build_matcher(query, options)?;
+        let mut results = Vec::new();
+
+        for card in self.card_repo.get_all().await? {
+            let Ok(json) = serde_json::from_str::<JsonValue>(&card.content) else {
+                continue;
+            };
+            let mut texts = Vec::new();
+            collect_text_nodes(&json, &mut texts);
+
+            let mut match_count = 0;
+            let mut snippets = Vec::new();
+            for text in texts {
+                for m in matcher.find_iter(text) {
+                    match_count += 1;
+                    if snippets.len() < MAX_SNIPPETS_PER_CARD {
+                        snippets.push(context_snippet(text, m.start(), m.end(), SNIPPET_CONTEXT_CHARS));
+                    }
+                }
+            }
+
+            if match_count > 0 {
+                results.push(CardMatch {
+                    card_id: card.id,
+                    title: card.title,
+                    match_count,
+                    snippets,
+                });
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// 全库替换：只修改文本节点的 text，不触碰链接 href 等结构
+    ///
+    /// `card_ids` 为空时作用于所有命中的卡片；`dry_run` 时只返回预览
+    pub async fn replace_in_cards(
+        &self,
+        query: &str,
+        replacement: &str,
+        options: &FindOptions,
+        card_ids: Option<&[String]>,
+        dry_run: bool,
+        indexer: Option<&Mutex<Option<Indexer>>>,
+        on_change: Option<&(dyn Fn(CardChange) + Sync)>,
+    ) -> AppResult<Vec<ReplaceResult>> {
+        let matcher = build_matcher(query, options)?;
+        let cards = match card_ids {
+            Some(ids) => {
+                let mut cards = Vec::new();
+                for id in ids {
+                    if let Some(card) = self.card_repo.get_by_id(id).await? {
+                        cards.push(card);
+                    }
+                }
+                cards
+            }
+            None => self.card_repo.get_all().await?,
+        };
+
+        let mut results = Vec::new();
+        for card in cards {
+            let Ok(mut json) = serde_json::from_str::<JsonValue>(&card.content) else {
+                continue;
+            };
+            let mut changes = Vec::new();
+            let replacements =
+                replace_text_nodes(&mut json, &matcher, replacement, options.regex, &mut changes);
+            if replacements == 0 {
+                continue;
+            }
+
+            if !dry_run {
+                let content = serde_json::to_string(&json)?;
+                self.update(&card.id, None, Some(&content), None, None, indexer, on_change)
+                    .await?;
+            }
+
+            results.push(ReplaceResult {
+                card_id: card.id,
+                title: card.title,
+                replacements,
+                changes,
+                applied: !dry_run,
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// 重命名卡片，并把其他卡片中按旧标题指向它的 wikiLink 改为新标题，返回被改写的卡片 ID
+    ///
+    /// 只改写 href 或显示标题与旧标题完全相同、且按重命名前的规则解析到该卡片的链接；
+    /// 通过 ID 或别名的链接本身不受影响。加密卡片无法改写，会被跳过
+    pub async fn rename(
+        &self,
+        id: &str,
+        new_title: &str,
+        indexer: Option<&Mutex<Option<Indexer>>>,
+        on_change: Option<&(dyn Fn(CardChange) + Sync)>,
+    ) -> AppResult<Vec<String>> {
+        let new_title = new_title.trim();
+        if new_title.is_empty() {
+            return Err(AppError::InvalidInput("Title cannot be empty".to_string()));
+        }
+        self.import_markdown_card(id).await?;
+        let cards = self.card_repo.get_all().await?;
+        let old_title = cards
+            .iter()
+            .find(|c| c.id == id)
+            .map(|c| c.title.clone())
+            .ok_or_else(|| AppError::NotFound(format!("Card not found: {}", id)))?;
+        if old_title == new_title {
+            return Ok(Vec::new());
+        }
+
+        // 重命名前解析，避免旧标题在改名后落到其他卡片上
+        let items: Vec<CardListItem> = cards.iter().cloned().map(Into::into).collect();
+        let resolver = LinkResolver::new(&items);
+        let is_target = |link: &str| resolver.resolve(link).as_deref() == Some(id);
+
+        self.update(id, Some(new_title), None, None, None, indexer, on_change)
+            .await?;
+
+        let mut updated = Vec::new();
+        for other in cards {
+            // 旧数据的 links 可能缺少 wikiLink，直接检查正文
+            if other.id == id || other.encrypted {
+                continue;
+            }
+            let Ok(mut json) = serde_json::from_str::<JsonValue>(&other.content) else {
+                continue;
+            };
+            if rewrite_wiki_links(&mut json, &is_target, &old_title, new_title) == 0 {
+                continue;
+            }
+            let content = serde_json::to_string(&json)?;
+            self.update(&other.id, None, Some(&content), None, None, indexer, on_change)
+                .await?;
+            updated.push(other.id);
+        }
+
+        Ok(updated)
+    }
+
+    /// 查找未链接提及：其他卡片中以纯文本出现的本卡片标题或别名
+    ///
+    /// 不区分大小写，按词边界匹配；已在链接内的文本会被跳过
+    pub async fn find_unlinked_mentions(&self, card_id: &str) -> AppResult<Vec<UnlinkedMention>> {
+        let card = self
+            .card_repo
+            .get_by_id(card_id)
+            .await?
+            .ok_or_else(|| crate::error::AppError::NotFound(format!("Card not found: {}", card_id)))?;
+
+        let Some(matcher) = build_mention_matcher(&card.title, &card.aliases) else {
+            return Ok(Vec::new());
+        };
+
+        let mut results = Vec::new();
+        for other in self.card_repo.get_all().await? {
+            if other.id == card.id {
+                continue;
+            }
+            let Ok(json) = serde_json::from_str::<JsonValue>(&other.content) else {
+                continue;
+            };
+            let mut texts = Vec::new();
+            collect_unlinked_text_nodes(&json, &mut texts);
+
+            let mut found = 0;
+            for text in texts {
+                for m in matcher.find_iter(text) {
+                    if found == MAX_SNIPPETS_PER_CARD {
+                        break;
+                    }
+                    found += 1;
+                    results.push(UnlinkedMention {
+                        card_id: other.id.clone(),
+                        title: other.title.clone(),
+                        matched_text: m.as_str().to_string(),
+                        snippet: context_snippet(text, m.start(), m.end(), SNIPPET_CONTEXT_CHARS),
+                    });
+                }
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// 查找链接到该卡片的其他卡片，附带每处链接周围的文字（链接按 ID → 别名 → 标题解析）
+    pub async fn get_backlinks_with_context(&self, card_id: &str) -> AppResult<Vec<Backlink>> {
+        let cards = self.card_repo.get_all().await?;
+        let items: Vec<CardListItem> = cards.iter().cloned().map(Into::into).collect();
+        let resolver = LinkResolver::new(&items);
+        let is_target = |link: &str| resolver.resolve(link).as_deref() == Some(card_id);
+
+        let mut results = Vec::new();
+        for other in &cards {
+            if other.id == card_id || !other.links.iter().any(|link| is_target(link)) {
+                continue;
+            }
+            let Ok(json) = serde_json::from_str::<JsonValue>(&other.content) else {
+                continue;
+            };
+            let mut snippets = Vec::new();
+            collect_link_contexts(&json, &is_target, SNIPPET_CONTEXT_CHARS, &mut snippets);
+            results.extend(snippets.into_iter().take(MAX_SNIPPETS_PER_CARD).map(|snippet| {
+                Backlink {
+                    card_id: other.id.clone(),
+                    title: other.title.clone(),
+                    snippet,
+                }
+            }));
+        }
+
+        Ok(results)
+    }
+
+    /// 解析卡片正文中的 reference 节点，返回各目标（文献源、高亮、卡片）的展示信息
+    pub async fn resolve_references(&self, card_id: &str) -> AppResult<Vec<ResolvedReference>> {
+        let card = self
+            .card_repo
+            .get_by_id(card_id)
+            .await?
+            .ok_or_else(|| crate::error::AppError::NotFound(format!("Card not found: {}", card_id)))?;
+
+        let mut resolved = Vec::new();
+        for Reference { kind, id } in extract_references_from_json(&card.content) {
+            let (title, detail) = match kind {
+                ReferenceKind::Card => match self.card_repo.get_by_id(&id).await? {
+                    Some(target) => (Some(target.title), Some(target.card_type.as_str().to_string())),
+                    None => (None, None),
+                },
+                ReferenceKind::Source => match self.source_repo.get_by_id(&id).await? {
+                    Some(source) => (Some(source.title), source.author),
+                    None => (None, None),
+                },
+                ReferenceKind::Highlight => match self.highlight_repo.get_by_id(&id).await? {
+                    Some(highlight) => {
+                        let source_title = self
+                            .source_repo
+                            .get_by_id(&highlight.source_id)
+                            .await?
+                            .map(|s| s.title);
+                        (Some(highlight.content), source_title)
+                    }
+                    None => (None, None),
+                },
+            };
+            resolved.push(ResolvedReference {
+                kind,
+                id,
+                exists: title.is_some(),
+                title,
+                detail,
+            });
+        }
+
+        Ok(resolved)
+    }
+}
+
+/// 更新卡片的搜索索引（索引失败不影响数据写入）
+fn index_card(card: &Card, indexer: Option<&Mutex<Option<Indexer>>>) {
+    if let Some(indexer) = indexer {
+        if let Ok(Some(idx)) = indexer.lock().as_deref() {
+            idx.index_card(card).ok();
+        }
+    }
+}
+
+/// 调用方提供了回调时发出卡片变更通知
+fn notify(
+    on_change: Option<&(dyn Fn(CardChange) + Sync)>,
+    op: CardChangeOp,
+    id: &str,
+    card_type: Option<&CardType>,
+) {
+    if let Some(on_change) = on_change {
+        on_change(CardChange {
+            op,
+            id: id.to_string(),
+            card_type: card_type.cloned(),
+        });
+    }
+}
+
+/// 每张卡片最多返回的上下文片段数
+const MAX_SNIPPETS_PER_CARD: usize = 5;
+
+/// 上下文片段两侧保留的字符数
+const SNIPPET_CONTEXT_CHARS: usize = 30;
+
+/// 图谱反向链接上下文两侧保留的字符数（合计约 100 字符）
+const BACKLINK_CONTEXT_CHARS: usize = 50;
+
+/// 根据查找选项构造匹配器，字面量查询会被转义
+fn build_matcher(query: &str, options: &FindOptions) -> AppResult<Regex> {
+    if query.is_empty() {
+        return Err(crate::error::AppError::InvalidInput("Query cannot be empty".to_string()));
+    }
+    let mut pattern = if options.regex {
+        query.to_string()
+    } else {
+        regex::escape(query)
+    };
+    if options.whole_word {
+        pattern = format!(r"\b(?:{})\b", pattern);
+    }
+    if !options.case_sensitive {
+        pattern = format!("(?i){}", pattern);
+    }
+    Regex::new(&pattern).map_err(|e| crate::error::AppError::InvalidInput(e.to_string()))
+}
+
+/// 收集所有文本节点的 text
+fn collect_text_nodes<'a>(node: &'a JsonValue, texts: &mut Vec<&'a str>) {
+    if node.get("type").and_then(|t| t.as_str()) == Some("text") {
+        if let Some(text) = node.get("text").and_then(|t| t.as_str()) {
+            texts.push(text);
+        }
+    }
+    if let Some(children) = node.get("content").and_then(|c| c.as_array()) {
+        for child in children {
+            collect_text_nodes(child, texts);
+        }
+    }
+}
+
+/// 构造标题/别名的提及匹配器，长的词优先以免被较短的别名截断
+///
+/// 词边界只加在 ASCII 字母数字的一侧，中文等无空格分词的标题可以出现在句中
+fn build_mention_matcher(title: &str, aliases: &[String]) -> Option<Regex> {
+    let mut terms: Vec<&str> = std::iter::once(title)
+        .chain(aliases.iter().map(String::as_str))
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .collect();
+    if terms.is_empty() {
+        return None;
+    }
+    terms.sort_by_key(|t| std::cmp::Reverse(t.chars().count()));
+    terms.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+
+    let alternatives: Vec<String> = terms
+        .iter()
+        .map(|term| {
+            let lead = term.starts_with(|c: char| c.is_ascii_alphanumeric());
+            let tail = term.ends_with(|c: char| c.is_ascii_alphanumeric());
+            format!(
+                "{}{}{}",
+                if lead { r"\b" } else { "" },
+                regex::escape(term),
+                if tail { r"\b" } else { "" }
+            )
+        })
+        .collect();
+    Regex::new(&format!("(?i)(?:{})", alternatives.join("|"))).ok()
+}
+
+/// 收集未处于链接中的文本节点（跳过 wikiLink 节点和带 link mark 的文本）
+fn collect_unlinked_text_nodes<'a>(node: &'a JsonValue, texts: &mut Vec<&'a str>) {
+    match node.get("type").and_then(|t| t.as_str()) {
+        Some("wikiLink") => return,
+        Some("text") => {
+            let linked = node
+                .get("marks")
+                .and_then(|m| m.as_array())
+                .is_some_and(|marks| {
+                    marks
+                        .iter()
+                        .any(|mark| mark.get("type").and_then(|t| t.as_str()) == Some("link"))
+                });
+            if !linked {
+                if let Some(text) = node.get("text").and_then(|t| t.as_str()) {
+                    texts.push(text);
+                }
+            }
+        }
+        _ => {}
+    }
+    if let Some(children) = node.get("content").and_then(|c| c.as_array()) {
+        for child in children {
+            collect_unlinked_text_nodes(child, texts);
+        }
+    }
+}
+
+/// 解析卡片正文，为每处指向目标的 wikiLink 或 reference 节点返回所在段落约 100 字符的上下文
+///
+/// 同一段落或同一卡片中多次链接到目标时各自返回一条
+pub fn link_contexts(content: &str, is_target: &dyn Fn(&str) -> bool) -> Vec<String> {
+    let Ok(json) = serde_json::from_str::<JsonValue>(content) else {
+        return Vec::new();
+    };
+    let mut snippets = Vec::new();
+    collect_link_contexts(&json, is_target, BACKLINK_CONTEXT_CHARS, &mut snippets);
+    snippets
+}
+
+/// 为指向目标的每个 wikiLink 或 reference 生成所在文本块的上下文片段，链接渲染为 `[[标题]]`
+///
+/// wikiLink 按 href 判断，reference 按目标 ID 判断；其他行内节点只取其文字
+fn collect_link_contexts(
+    node: &JsonValue,
+    is_target: &dyn Fn(&str) -> bool,
+    context_chars: usize,
+    snippets: &mut Vec<String>,
+) {
+    let Some(children) = node.get("content").and_then(|c| c.as_array()) else {
+        return;
+    };
+    let is_textblock = children.iter().any(|child| {
+        matches!(
+            child.get("type").and_then(|t| t.as_str()),
+            Some("text" | "wikiLink" | "reference")
+        )
+    });
+    if !is_textblock {
+        for child in children {
+            collect_link_contexts(child, is_target, context_chars, snippets);
+        }
+        return;
+    }
+
+    let mut text = String::new();
+    let mut hits = Vec::new();
+    for child in children {
+        let node_type = child.get("type").and_then(|t| t.as_str());
+        match node_type {
+            Some("text") => text.push_str(child.get("text").and_then(|t| t.as_str()).unwrap_or("")),
+            Some("wikiLink" | "reference") => {
+                let attrs = child.get("attrs");
+                let key = if node_type == Some("wikiLink") { "href" } else { "id" };
+                let target = attrs.and_then(|a| a.get(key)).and_then(|h| h.as_str()).unwrap_or("");
+                let label = attrs
+                    .and_then(|a| a.get("title"))
+                    .and_then(|t| t.as_str())
+                    .filter(|t| !t.is_empty())
+                    .unwrap_or(target);
+                let start = text.len();
+                text.push_str(&format!("[[{}]]", label));
+                if !target.is_empty() && is_target(target) {
+                    hits.push((start, text.len()));
+                }
+            }
+            Some("hardBreak") => text.push(' '),
+            _ => extract_text_recursive(child, &mut text),
+        }
+    }
+    for (start, end) in hits {
+        snippets.push(context_snippet(&text, start, end, context_chars));
+    }
+}
+
+/// 将指向目标的 wikiLink 中等于旧标题的 href 和显示标题改为新标题，返回改写的属性数
+fn rewrite_wiki_links(
+    node: &mut JsonValue,
+    is_target: &dyn Fn(&str) -> bool,
+    old_title: &str,
+    new_title: &str,
+) -> usize {
+    let mut count = 0;
+    if node.get("type").and_then(|t| t.as_str()) == Some("wikiLink") {
+        if let Some(attrs) = node.get_mut("attrs").and_then(|a| a.as_object_mut()) {
+            let href = attrs.get("href").and_then(|h| h.as_str()).unwrap_or("").to_string();
+            if is_target(&href) {
+                for key in ["href", "title"] {
+                    if attrs.get(key).and_then(|v| v.as_str()) == Some(old_title) {
+                        attrs.insert(key.to_string(), JsonValue::String(new_title.to_string()));
+                        count += 1;
+                    }
+                }
+            }
+        }
+    }
+    if let Some(children) = node.get_mut("content").and_then(|c| c.as_array_mut()) {
+        for child in children {
+            count += rewrite_wiki_links(child, is_target, old_title, new_title);
+        }
+    }
+    count
+}
+
+/// 在文本节点中执行替换，返回替换次数
+///
+/// 非正则模式下替换串按字面量处理，不展开 `$1` 等捕获组引用
+fn replace_text_nodes(
+    node: &mut JsonValue,
+    matcher: &Regex,
+    replacement: &str,
+    expand: bool,
+    changes: &mut Vec<TextChange>,
+) -> usize {
+    let mut count = 0;
+    if node.get("type").and_then(|t| t.as_str()) == Some("text") {
+        if let Some(JsonValue::String(text)) = node.get_mut("text") {
+            let hits = matcher.find_iter(text).count();
+            if hits > 0 {
+                let replaced = if expand {
+                    matcher.replace_all(text, replacement).into_owned()
+                } else {
+                    matcher.replace_all(text, NoExpand(replacement)).into_owned()
+                };
+                changes.push(TextChange {
+                    before: text.clone(),
+                    after: replaced.clone(),
+                });
+                *text = replaced;
+                count += hits;
+            }
+        }
+    }
+    if let Some(children) = node.get_mut("content").and_then(|c| c.as_array_mut()) {
+        for child in children {
+            count += replace_text_nodes(child, matcher, replacement, expand, changes);
+        }
+    }
+    count
+}
+
+/// 截取命中位置附近的上下文，两侧各保留 `context_chars` 个字符
+fn context_snippet(text: &str, start: usize, end: usize, context_chars: usize) -> String {
+    let prefix: String = text[..start]
+        .chars()
+        .rev()
+        .take(context_chars)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    let suffix: String = text[end..].chars().take(context_chars).collect();
+    let lead = if prefix.len() < start { "..." } else { "" };
+    let tail = if suffix.len() < text.len() - end { "..." } else { "" };
+    format!("{}{}{}{}{}", lead, prefix, &text[start..end], suffix, tail)
 }
 
 // 辅助函数：从 TipTap JSON 中提取链接
@@ -217,6 +1508,14 @@ fn extract_links_from_json(content: &str) -> Vec<String> {
 fn extract_links_recursive(node: &JsonValue, links: &mut Vec<String>) {
     if let Some(obj) = node.as_object() {
         if let Some(node_type) = obj.get("type").and_then(|t| t.as_str()) {
+            // 指向卡片的 reference 节点与 wiki link 一样计入出链
+            if node_type == "reference" {
+                if let Some(Reference { kind: ReferenceKind::Card, id }) = reference_from_node(node) {
+                    if !links.contains(&id) {
+                        links.push(id);
+                    }
+                }
+            }
             if node_type == "wikiLink" {
                 if let Some(attrs) = obj.get("attrs").and_then(|a| a.as_object()) {
                     if let Some(href) = attrs.get("href").and_then(|h| h.as_str()) {
@@ -236,3 +1535,526 @@ fn extract_links_recursive(node: &JsonValue, links: &mut Vec<String>) {
     }
 }
 
+
+/// 解析 reference 节点的 attrs，kind 或 id 无效时返回 None
+fn reference_from_node(node: &JsonValue) -> Option<Reference> {
+    let attrs = node.get("attrs")?;
+    let kind = serde_json::from_value(attrs.get("kind")?.clone()).ok()?;
+    let id = attrs.get("id")?.as_str().filter(|id| !id.is_empty())?;
+    Some(Reference {
+        kind,
+        id: id.to_string(),
+    })
+}
+
+/// 从 TipTap JSON 中提取 reference 节点（按出现顺序去重）
+pub fn extract_references_from_json(content: &str) -> Vec<Reference> {
+    fn walk(node: &JsonValue, references: &mut Vec<Reference>) {
+        if node.get("type").and_then(|t| t.as_str()) == Some("reference") {
+            if let Some(reference) = reference_from_node(node) {
+                if !references.contains(&reference) {
+                    references.push(reference);
+                }
+            }
+        }
+        if let Some(children) = node.get("content").and_then(|c| c.as_array()) {
+            for child in children {
+                walk(child, references);
+            }
+        }
+    }
+
+    let mut references = Vec::new();
+    if let Ok(json) = serde_json::from_str::<JsonValue>(content) {
+        walk(&json, &mut references);
+    }
+    references
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_only_touches_text_nodes() {
+        let mut json: JsonValue = serde_json::json!({
+            "type": "doc",
+            "content": [{
+                "type": "paragraph",
+                "content": [
+                    {"type": "text", "text": "Zettel and zettel"},
+                    {"type": "text", "text": "zettel", "marks": [{"type": "link", "attrs": {"href": "card://zettel"}}]}
+                ]
+            }]
+        });
+        let matcher = build_matcher("zettel", &FindOptions::default()).unwrap();
+        let mut changes = Vec::new();
+        let count = replace_text_nodes(&mut json, &matcher, "note", false, &mut changes);
+
+        assert_eq!(count, 3);
+        let nodes = &json["content"][0]["content"];
+        assert_eq!(nodes[0]["text"], "note and note");
+        assert_eq!(nodes[1]["text"], "note");
+        assert_eq!(nodes[1]["marks"][0]["attrs"]["href"], "card://zettel");
+    }
+
+    #[test]
+    fn test_collect_link_contexts() {
+        let json: JsonValue = serde_json::json!({
+            "type": "doc",
+            "content": [
+                {"type": "paragraph", "content": [
+                    {"type": "text", "text": "See "},
+                    {"type": "wikiLink", "attrs": {"href": "target", "title": "Target"}},
+                    {"type": "text", "text": " for details"}
+                ]},
+                {"type": "blockquote", "content": [
+                    {"type": "paragraph", "content": [
+                        {"type": "wikiLink", "attrs": {"href": "other", "title": "Other"}},
+                        {"type": "text", "text": " and "},
+                        {"type": "wikiLink", "attrs": {"href": "Target Alias"}}
+                    ]}
+                ]}
+            ]
+        });
+        let mut snippets = Vec::new();
+        collect_link_contexts(
+            &json,
+            &|href| href == "target" || href == "Target Alias",
+            SNIPPET_CONTEXT_CHARS,
+            &mut snippets,
+        );
+
+        assert_eq!(snippets, vec!["See [[Target]] for details", "[[Other]] and [[Target Alias]]"]);
+    }
+
+    #[test]
+    fn test_link_contexts_one_per_occurrence() {
+        let long = "x".repeat(80);
+        let content = serde_json::json!({
+            "type": "doc",
+            "content": [
+                {"type": "paragraph", "content": [
+                    {"type": "text", "text": format!("{} before ", long)},
+                    {"type": "wikiLink", "attrs": {"href": "Target", "title": "Target"}},
+                    {"type": "text", "text": " then "},
+                    {"type": "reference", "attrs": {"kind": "card", "id": "t1", "title": "Again"}}
+                ]},
+                {"type": "paragraph", "content": [
+                    {"type": "wikiLink", "attrs": {"href": "elsewhere"}}
+                ]}
+            ]
+        })
+        .to_string();
+        let contexts = link_contexts(&content, &|target| target == "Target" || target == "t1");
+
+        assert_eq!(contexts.len(), 2);
+        assert_eq!(
+            contexts[0],
+            format!("...{} before [[Target]] then [[Again]]", "x".repeat(42))
+        );
+        assert!(contexts[1].ends_with("before [[Target]] then [[Again]]"));
+        assert!(link_contexts("not json", &|_| true).is_empty());
+    }
+
+    #[test]
+    fn test_literal_query_is_escaped() {
+        let matcher = build_matcher("a.b", &FindOptions::default()).unwrap();
+        assert!(matcher.is_match("a.b"));
+        assert!(!matcher.is_match("axb"));
+        assert!(build_matcher("(", &FindOptions { regex: true, ..Default::default() }).is_err());
+    }
+
+    #[test]
+    fn test_mention_matcher_respects_word_boundaries() {
+        let matcher = build_mention_matcher("Zettel", &["卡片盒".to_string()]).unwrap();
+        assert!(matcher.is_match("a zettel note"));
+        assert!(!matcher.is_match("zettelkasten"));
+        assert!(matcher.is_match("这是卡片盒笔记法"));
+        assert!(build_mention_matcher("  ", &[]).is_none());
+    }
+
+    #[test]
+    fn test_unlinked_text_skips_links() {
+        let json: JsonValue = serde_json::json!({
+            "type": "doc",
+            "content": [{
+                "type": "paragraph",
+                "content": [
+                    {"type": "text", "text": "plain zettel"},
+                    {"type": "text", "text": "zettel", "marks": [{"type": "link", "attrs": {"href": "card://zettel"}}]},
+                    {"type": "wikiLink", "attrs": {"href": "zettel"}, "content": [{"type": "text", "text": "zettel"}]}
+                ]
+            }]
+        });
+        let mut texts = Vec::new();
+        collect_unlinked_text_nodes(&json, &mut texts);
+        assert_eq!(texts, vec!["plain zettel"]);
+    }
+
+    #[test]
+    fn test_extract_references() {
+        let content = serde_json::json!({
+            "type": "doc",
+            "content": [{
+                "type": "paragraph",
+                "content": [
+                    {"type": "reference", "attrs": {"kind": "source", "id": "s1"}},
+                    {"type": "reference", "attrs": {"kind": "card", "id": "c1"}},
+                    {"type": "reference", "attrs": {"kind": "source", "id": "s1"}},
+                    {"type": "reference", "attrs": {"kind": "note", "id": "x"}},
+                    {"type": "wikiLink", "attrs": {"href": "c2"}}
+                ]
+            }]
+        })
+        .to_string();
+
+        let references = extract_references_from_json(&content);
+        assert_eq!(
+            references,
+            vec![
+                Reference { kind: ReferenceKind::Source, id: "s1".to_string() },
+                Reference { kind: ReferenceKind::Card, id: "c1".to_string() },
+            ]
+        );
+        assert_eq!(extract_links_from_json(&content), vec!["c1", "c2"]);
+    }
+
+    #[tokio::test]
+    async fn test_card_mutations_notify_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Arc::new(crate::db::Database::open(&dir.path().join("zentri.db")).await.unwrap());
+        let service = CardService::new(
+            Arc::new(CardRepository::new(db.clone())),
+            Arc::new(SourceRepository::new(db.clone())),
+            Arc::new(HighlightRepository::new(db.clone())),
+            Arc::new(ConfigRepository::new(db)),
+            None,
+        );
+        let changes = Mutex::new(Vec::new());
+        let notify = |change: CardChange| changes.lock().unwrap().push(change);
+
+        let card = service
+            .create(CardType::Permanent, "Note", None, None, None, Some(&notify))
+            .await
+            .unwrap();
+        service
+            .update(&card.id, Some("Renamed"), None, None, None, None, Some(&notify))
+            .await
+            .unwrap();
+        service.set_pinned(&card.id, true, Some(&notify)).await.unwrap();
+        service.set_review_enabled(&card.id, true, Some(&notify)).await.unwrap();
+        service.review(&card.id, 4, Some(&notify)).await.unwrap();
+        service.delete(&card.id, None, Some(&notify)).await.unwrap();
+
+        let changes = changes.into_inner().unwrap();
+        let ops: Vec<CardChangeOp> = changes.iter().map(|c| c.op).collect();
+        assert_eq!(
+            ops,
+            vec![
+                CardChangeOp::Created,
+                CardChangeOp::Updated,
+                CardChangeOp::Updated,
+                CardChangeOp::Updated,
+                CardChangeOp::Updated,
+                CardChangeOp::Deleted
+            ]
+        );
+        assert!(changes.iter().all(|c| c.id == card.id && c.card_type == Some(CardType::Permanent)));
+    }
+
+    #[tokio::test]
+    async fn test_rename_rewrites_inbound_wiki_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Arc::new(crate::db::Database::open(&dir.path().join("zentri.db")).await.unwrap());
+        let service = CardService::new(
+            Arc::new(CardRepository::new(db.clone())),
+            Arc::new(SourceRepository::new(db.clone())),
+            Arc::new(HighlightRepository::new(db.clone())),
+            Arc::new(ConfigRepository::new(db)),
+            None,
+        );
+        let link_doc = |links: &[(&str, &str)]| {
+            let nodes: Vec<JsonValue> = links
+                .iter()
+                .map(|(href, title)| serde_json::json!({"type": "wikiLink", "attrs": {"href": href, "title": title}}))
+                .collect();
+            serde_json::json!({"type": "doc", "content": [{"type": "paragraph", "content": nodes}]}).to_string()
+        };
+
+        let target = service
+            .create(CardType::Permanent, "Old", None, None, None, None)
+            .await
+            .unwrap();
+        service
+            .create(CardType::Permanent, "Older", None, None, None, None)
+            .await
+            .unwrap();
+        let by_title = service
+            .create(CardType::Permanent, "A", Some(link_doc(&[("Old", "Old"), ("Older", "Older")]).as_str()), None, None, None)
+            .await
+            .unwrap();
+        let by_id = service
+            .create(CardType::Permanent, "B", Some(link_doc(&[(target.id.as_str(), "Old")]).as_str()), None, None, None)
+            .await
+            .unwrap();
+        let unrelated = service
+            .create(CardType::Permanent, "C", Some(link_doc(&[("Older", "Older")]).as_str()), None, None, None)
+            .await
+            .unwrap();
+
+        let mut updated = service.rename(&target.id, "New", None, None).await.unwrap();
+        updated.sort();
+        let mut expected = vec![by_title.id.clone(), by_id.id.clone()];
+        expected.sort();
+        assert_eq!(updated, expected);
+
+        let by_title = service.get_by_id(&by_title.id).await.unwrap().unwrap();
+        assert_eq!(by_title.content, link_doc(&[("New", "New"), ("Older", "Older")]));
+        assert_eq!(by_title.links, vec!["New", "Older"]);
+        let by_id = service.get_by_id(&by_id.id).await.unwrap().unwrap();
+        assert_eq!(by_id.content, link_doc(&[(target.id.as_str(), "New")]));
+        let unrelated = service.get_by_id(&unrelated.id).await.unwrap().unwrap();
+        assert_eq!(unrelated.content, link_doc(&[("Older", "Older")]));
+        assert_eq!(service.get_by_id(&target.id).await.unwrap().unwrap().title, "New");
+        assert!(service.rename(&target.id, "  ", None, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_restore_card_revision() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Arc::new(crate::db::Database::open(&dir.path().join("zentri.db")).await.unwrap());
+        let service = CardService::new(
+            Arc::new(CardRepository::new(db.clone())),
+            Arc::new(SourceRepository::new(db.clone())),
+            Arc::new(HighlightRepository::new(db.clone())),
+            Arc::new(ConfigRepository::new(db)),
+            Some(dir.path().to_path_buf()),
+        );
+        // 历史版本以修改时间命名，两次修改之间留出间隔
+        let pause = || std::thread::sleep(std::time::Duration::from_millis(5));
+
+        let card = service
+            .create(CardType::Permanent, "First", Some("first"), None, None, None)
+            .await
+            .unwrap();
+        pause();
+        service
+            .update(&card.id, Some("Second"), Some("second"), None, None, None, None)
+            .await
+            .unwrap();
+        pause();
+        service
+            .update(&card.id, Some("Third"), Some("third"), None, None, None, None)
+            .await
+            .unwrap();
+
+        let revisions = service.get_revisions(&card.id).await.unwrap();
+        let titles: Vec<&str> = revisions.iter().map(|r| r.title.as_str()).collect();
+        assert_eq!(titles, vec!["Second", "First"]);
+
+        pause();
+        let restored = service
+            .restore_revision(&card.id, revisions[1].timestamp, None, None)
+            .await
+            .unwrap();
+        assert_eq!(restored.title, "First");
+        assert_eq!(restored.content, "first");
+        assert_eq!(service.get_revisions(&card.id).await.unwrap()[0].title, "Third");
+
+        service.set_revision_limit(1).await.unwrap();
+        pause();
+        service
+            .update(&card.id, None, Some("fourth"), None, None, None, None)
+            .await
+            .unwrap();
+        let revisions = service.get_revisions(&card.id).await.unwrap();
+        assert_eq!(revisions.len(), 1);
+        assert_eq!(revisions[0].title, "First");
+        assert!(service.restore_revision(&card.id, 0, None, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_purge_deletes_legacy_markdown_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Arc::new(crate::db::Database::open(&dir.path().join("zentri.db")).await.unwrap());
+        let service = CardService::new(
+            Arc::new(CardRepository::new(db.clone())),
+            Arc::new(SourceRepository::new(db.clone())),
+            Arc::new(HighlightRepository::new(db.clone())),
+            Arc::new(ConfigRepository::new(db)),
+            Some(dir.path().to_path_buf()),
+        );
+        let file = dir.path().join("20_Slipbox").join("legacy.md");
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        std::fs::write(&file, "# Legacy\n\nOld note").unwrap();
+        let ids = |cards: Vec<Card>| cards.into_iter().map(|c| c.id).collect::<Vec<_>>();
+        assert!(ids(service.get_all().await.unwrap()).contains(&"legacy".to_string()));
+
+        service.delete("legacy", None, None).await.unwrap();
+        assert!(!ids(service.get_all().await.unwrap()).contains(&"legacy".to_string()));
+
+        // 永久删除后源文件也被删除，卡片不会从 vault 中重新读出
+        assert_eq!(service.purge_trashed(0).await.unwrap(), 1);
+        assert!(!file.exists());
+        assert!(!ids(service.get_all().await.unwrap()).contains(&"legacy".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_import_cards_keeps_ids_and_skips_existing() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Arc::new(crate::db::Database::open(&dir.path().join("zentri.db")).await.unwrap());
+        let service = CardService::new(
+            Arc::new(CardRepository::new(db.clone())),
+            Arc::new(SourceRepository::new(db.clone())),
+            Arc::new(HighlightRepository::new(db.clone())),
+            Arc::new(ConfigRepository::new(db)),
+            None,
+        );
+        let src = tempfile::tempdir().unwrap();
+        std::fs::write(src.path().join("entropy.md"), "See [[gibbs]].").unwrap();
+        std::fs::write(src.path().join("gibbs.md"), "# Gibbs").unwrap();
+        let note = |name: &str| {
+            crate::storage::read_obsidian_note(src.path(), &format!("{}.md", name), &Default::default()).unwrap()
+        };
+        let changes = Mutex::new(Vec::new());
+        let record = |change: CardChange| changes.lock().unwrap().push(change.id);
+
+        let results = service
+            .import_cards(vec![note("entropy"), note("gibbs")], None, Some(&record))
+            .await
+            .unwrap();
+        assert!(results.iter().all(|r| matches!(r, Ok(true))));
+        assert!(service.get_by_id("entropy").await.unwrap().is_some());
+
+        // 重复导入和回收站中的卡片都跳过
+        service.delete("gibbs", None, None).await.unwrap();
+        let results = service
+            .import_cards(vec![note("entropy"), note("gibbs")], None, Some(&record))
+            .await
+            .unwrap();
+        assert!(results.iter().all(|r| matches!(r, Ok(false))));
+        assert_eq!(*changes.lock().unwrap(), vec!["entropy".to_string(), "gibbs".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_get_ids_with_prefix_includes_markdown_cards() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Arc::new(crate::db::Database::open(&dir.path().join("zentri.db")).await.unwrap());
+        let service = CardService::new(
+            Arc::new(CardRepository::new(db.clone())),
+            Arc::new(SourceRepository::new(db.clone())),
+            Arc::new(HighlightRepository::new(db.clone())),
+            Arc::new(ConfigRepository::new(db)),
+            Some(dir.path().to_path_buf()),
+        );
+        let src = dir.path().join("00_Inbox");
+        std::fs::create_dir_all(&src).unwrap();
+        for name in ["daily-2024-01-01", "daily-2024-01-02", "idea"] {
+            std::fs::write(src.join(format!("{}.md", name)), "text").unwrap();
+        }
+        let note = |name: &str| {
+            crate::storage::read_obsidian_note(&src, &format!("{}.md", name), &Default::default()).unwrap()
+        };
+        service.import_cards(vec![note("daily-2024-01-02")], None, None).await.unwrap();
+        service.delete("daily-2024-01-01", None, None).await.unwrap();
+        std::fs::write(src.join("daily-2024-01-03.md"), "text").unwrap();
+
+        let mut ids = service.get_ids_with_prefix("daily-").await.unwrap();
+        ids.sort();
+        assert_eq!(ids, vec!["daily-2024-01-02", "daily-2024-01-03"]);
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_card_history_has_no_plaintext() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Arc::new(crate::db::Database::open(&dir.path().join("zentri.db")).await.unwrap());
+        let service = CardService::new(
+            Arc::new(CardRepository::new(db.clone())),
+            Arc::new(SourceRepository::new(db.clone())),
+            Arc::new(HighlightRepository::new(db.clone())),
+            Arc::new(ConfigRepository::new(db)),
+            Some(dir.path().to_path_buf()),
+        );
+        let pause = || std::thread::sleep(std::time::Duration::from_millis(5));
+        let history = dir.path().join(".zentri").join("history");
+
+        let card = service
+            .create(CardType::Fleeting, "Diary", Some("secret diary"), None, None, None)
+            .await
+            .unwrap();
+        pause();
+        service
+            .update(&card.id, None, Some("secret diary v2"), None, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(service.get_revisions(&card.id).await.unwrap().len(), 1);
+
+        // 加密后删除明文历史，之后的版本只保存密文
+        service.set_passphrase("passphrase", "passphrase").await.unwrap();
+        service.set_encrypted(&card.id, true, None, None).await.unwrap();
+        assert!(!history.join(&card.id).exists());
+        pause();
+        service
+            .update(&card.id, Some("Journal"), None, None, None, None, None)
+            .await
+            .unwrap();
+        let revisions = storage::read_card_revisions(dir.path(), &card.id);
+        assert_eq!(revisions.len(), 1);
+        assert!(crypto::is_encrypted(&revisions[0].content));
+
+        // 清理回收站时删除历史目录
+        service.delete(&card.id, None, None).await.unwrap();
+        assert_eq!(service.purge_trashed(0).await.unwrap(), 1);
+        assert!(!history.join(&card.id).exists());
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_card_requires_unlock() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Arc::new(crate::db::Database::open(&dir.path().join("zentri.db")).await.unwrap());
+        let service = CardService::new(
+            Arc::new(CardRepository::new(db.clone())),
+            Arc::new(SourceRepository::new(db.clone())),
+            Arc::new(HighlightRepository::new(db.clone())),
+            Arc::new(ConfigRepository::new(db.clone())),
+            None,
+        );
+        let content = r#"{"type":"doc","content":[{"type":"paragraph","content":[{"type":"text","text":"secret diary"}]}]}"#;
+        let card = service
+            .create(CardType::Fleeting, "Diary", Some(content), None, None, None)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            service.set_encrypted(&card.id, true, None, None).await,
+            Err(AppError::Locked)
+        ));
+        // 首次解锁不会把输入当作新口令，需要先确认设置
+        assert!(service.unlock("passphrase", None).await.is_err());
+        assert!(service.set_passphrase("passphrase", "passphrase!").await.is_err());
+        service.set_passphrase("passphrase", "passphrase").await.unwrap();
+        assert!(service.set_passphrase("other", "other").await.is_err());
+        let card = service.set_encrypted(&card.id, true, None, None).await.unwrap();
+        assert!(card.encrypted);
+        assert_eq!(card.content, content);
+
+        let stored = db.get_card(&card.id).await.unwrap().unwrap();
+        assert!(crypto::is_encrypted(&stored.content));
+        assert!(stored.plain_text.is_empty() && stored.preview.is_none());
+
+        service.lock(None).await.unwrap();
+        let locked = service.get_by_id(&card.id).await.unwrap().unwrap();
+        assert_eq!(locked.title, "Diary");
+        assert!(locked.content.is_empty());
+        assert!(matches!(
+            service.update(&card.id, None, Some(content), None, None, None, None).await,
+            Err(AppError::Locked)
+        ));
+
+        assert!(service.unlock("wrong", None).await.is_err());
+        service.unlock("passphrase", None).await.unwrap();
+        let card = service.get_by_id(&card.id).await.unwrap().unwrap();
+        assert_eq!(card.plain_text, "secret diary");
+    }
+}
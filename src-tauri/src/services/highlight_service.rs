@@ -3,8 +3,8 @@
 
 use crate::commands::highlights::SourceBacklink;
 use crate::database::HighlightRepository;
-use crate::error::AppResult;
-use crate::models::{CreateHighlightRequest, Highlight, UpdateHighlightRequest};
+use crate::error::{AppError, AppResult};
+use crate::models::{CreateHighlightRequest, Highlight, Source, UpdateHighlightRequest};
 use std::sync::Arc;
 
 /// Highlight 应用服务
@@ -19,6 +19,9 @@ impl HighlightService {
 
     /// 创建高亮
     pub async fn create(&self, req: CreateHighlightRequest) -> AppResult<Highlight> {
+        if let Some(position) = &req.position {
+            position.validate_image_path().map_err(AppError::InvalidInput)?;
+        }
         self.repo.create(req).await
     }
 
@@ -32,6 +35,21 @@ impl HighlightService {
         self.repo.get_all().await
     }
 
+    /// 按原文和笔记全文搜索高亮，结果按相关度排序
+    pub async fn search(&self, query: &str, source_id: Option<&str>) -> AppResult<Vec<Highlight>> {
+        self.repo.search(query, source_id).await
+    }
+
+    /// 按阅读顺序获取文献源的高亮（页码优先，其次创建时间）
+    pub async fn get_in_reading_order(&self, source_id: &str) -> AppResult<Vec<Highlight>> {
+        let mut highlights = self.repo.get_by_source(source_id).await?;
+        highlights.sort_by_key(|h| {
+            let page = h.position.as_ref().and_then(|p| p.page).unwrap_or(i32::MAX);
+            (page, h.created_at)
+        });
+        Ok(highlights)
+    }
+
     /// 获取单个高亮
     pub async fn get_by_id(&self, id: &str) -> AppResult<Option<Highlight>> {
         self.repo.get_by_id(id).await
@@ -39,6 +57,9 @@ impl HighlightService {
 
     /// 更新高亮
     pub async fn update(&self, id: &str, req: UpdateHighlightRequest) -> AppResult<Option<Highlight>> {
+        if let Some(position) = &req.position {
+            position.validate_image_path().map_err(AppError::InvalidInput)?;
+        }
         self.repo.update(id, req).await
     }
 
@@ -58,3 +79,126 @@ impl HighlightService {
     }
 }
 
+/// 摘要 Prompt 中高亮内容的最大字符数，避免超出模型上下文
+const MAX_SUMMARY_INPUT_CHARS: usize = 12_000;
+
+/// 构建高亮摘要 Prompt，超出长度的高亮会被截断
+pub fn build_summary_prompt(source_title: &str, highlights: &[Highlight]) -> String {
+    let mut prompt = format!(
+        "你是一个阅读助手。以下是读者在《{}》中按阅读顺序摘录的高亮及批注。\n\n",
+        source_title
+    );
+
+    let mut used = 0;
+    for (i, highlight) in highlights.iter().enumerate() {
+        let mut entry = format!("[{}] {}\n", i + 1, highlight.content.trim());
+        if let Some(note) = highlight.note.as_deref().filter(|n| !n.trim().is_empty()) {
+            entry.push_str(&format!("    批注：{}\n", note.trim()));
+        }
+        used += entry.chars().count();
+        if used > MAX_SUMMARY_INPUT_CHARS {
+            prompt.push_str("（其余高亮已省略）\n");
+            break;
+        }
+        prompt.push_str(&entry);
+    }
+
+    prompt.push_str("\n请综合这些高亮，用简洁的几段话提炼核心观点和它们之间的联系，不要逐条复述。");
+    prompt
+}
+
+/// 由高亮生成的卡片标题最多保留的字符数
+const PROMOTED_TITLE_CHARS: usize = 30;
+
+/// 高亮生成卡片的标题：取高亮首行，过长时截断
+pub fn promoted_card_title(highlight: &Highlight) -> String {
+    let first_line = highlight.content.trim().lines().next().unwrap_or("").trim();
+    if first_line.chars().count() > PROMOTED_TITLE_CHARS {
+        let truncated: String = first_line.chars().take(PROMOTED_TITLE_CHARS).collect();
+        format!("{}…", truncated)
+    } else {
+        first_line.to_string()
+    }
+}
+
+/// 高亮的出处引用，如 "—— 作者，《标题》，第 12 页"
+pub fn highlight_citation(highlight: &Highlight, source: &Source) -> String {
+    let mut parts = Vec::new();
+    if let Some(author) = source.author.as_deref().filter(|a| !a.trim().is_empty()) {
+        parts.push(author.trim().to_string());
+    }
+    parts.push(format!("《{}》", source.title));
+    if let Some(position) = &highlight.position {
+        if let Some(chapter) = position.chapter.as_deref().filter(|c| !c.trim().is_empty()) {
+            parts.push(chapter.trim().to_string());
+        }
+        if let Some(page) = position.page {
+            parts.push(format!("第 {} 页", page));
+        }
+    }
+    format!("—— {}", parts.join("，"))
+}
+
+/// 构建高亮生成卡片的 TipTap 内容：高亮原文作为引用块，其后附出处
+pub fn build_promoted_card_content(highlight: &Highlight, source: &Source) -> String {
+    let quote: Vec<serde_json::Value> = highlight
+        .content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            serde_json::json!({
+                "type": "paragraph",
+                "content": [{ "type": "text", "text": line }]
+            })
+        })
+        .collect();
+    serde_json::json!({
+        "type": "doc",
+        "content": [
+            { "type": "blockquote", "content": quote },
+            {
+                "type": "paragraph",
+                "content": [{ "type": "text", "text": highlight_citation(highlight, source) }]
+            }
+        ]
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{HighlightPosition, SourceType};
+
+    #[test]
+    fn test_build_promoted_card_content() {
+        let source: Source = serde_json::from_value(serde_json::json!({
+            "id": "s1", "type": SourceType::Book, "title": "思考，快与慢", "author": "卡尼曼",
+            "url": null, "cover": null, "description": null, "tags": [], "progress": 0,
+            "lastReadAt": null, "metadata": null, "noteIds": [], "createdAt": 0, "updatedAt": 0
+        }))
+        .unwrap();
+        let highlight = Highlight {
+            id: "h1".to_string(),
+            source_id: "s1".to_string(),
+            card_id: None,
+            content: "系统一快速而直觉\n系统二缓慢而理性".to_string(),
+            note: None,
+            annotation_type: None,
+            position: Some(HighlightPosition {
+                page: Some(12),
+                ..Default::default()
+            }),
+            color: None,
+            created_at: 0,
+        };
+
+        assert_eq!(promoted_card_title(&highlight), "系统一快速而直觉");
+        let doc: serde_json::Value =
+            serde_json::from_str(&build_promoted_card_content(&highlight, &source)).unwrap();
+        assert_eq!(doc["content"][0]["type"], "blockquote");
+        assert_eq!(doc["content"][0]["content"].as_array().unwrap().len(), 2);
+        assert_eq!(doc["content"][1]["content"][0]["text"], "—— 卡尼曼，《思考，快与慢》，第 12 页");
+    }
+}
@@ -41,15 +41,21 @@ impl Services {
         let bookmark_repo = Arc::new(BookmarkRepository::new(db.clone()));
         let web_snapshot_repo = Arc::new(WebSnapshotRepository::new(db.clone(), vault_path.clone()));
         let card_repo = Arc::new(CardRepository::new(db.clone()));
-        let _config_repo = Arc::new(ConfigRepository::new(db.clone()));
+        let config_repo = Arc::new(ConfigRepository::new(db.clone()));
 
         Self {
             source: SourceService::new(source_repo.clone()),
             highlight: HighlightService::new(highlight_repo.clone()),
             bookmark: BookmarkService::new(bookmark_repo.clone()),
-            card: CardService::new(card_repo.clone(), source_repo.clone()),
+            card: CardService::new(
+                card_repo.clone(),
+                source_repo.clone(),
+                highlight_repo.clone(),
+                config_repo.clone(),
+                vault_path.clone(),
+            ),
             book: BookService::new(db.clone()),
-            web_reader: WebReaderService::new(web_snapshot_repo.clone()),
+            web_reader: WebReaderService::new(web_snapshot_repo.clone(), config_repo.clone()),
         }
     }
 }
@@ -2,9 +2,13 @@
 //! 封装 Source 相关的业务逻辑
 
 use crate::database::SourceRepository;
-use crate::error::AppResult;
-use crate::models::{CreateSourceRequest, Source, UpdateSourceRequest};
-use std::sync::Arc;
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    reading_minutes, CreateSourceRequest, ReadingListFilter, ReadingListSort, Source, SourceFilter,
+    UpdateSourceRequest,
+};
+use crate::search::Indexer;
+use std::sync::{Arc, Mutex};
 
 /// Source 应用服务
 pub struct SourceService {
@@ -17,8 +21,14 @@ impl SourceService {
     }
 
     /// 创建文献源
-    pub async fn create(&self, req: CreateSourceRequest) -> AppResult<Source> {
-        self.repo.create(req).await
+    pub async fn create(
+        &self,
+        req: CreateSourceRequest,
+        indexer: Option<&Mutex<Option<Indexer>>>,
+    ) -> AppResult<Source> {
+        let source = self.repo.create(req).await?;
+        index_source(&source, indexer);
+        Ok(source)
     }
 
     /// 获取所有文献源
@@ -31,30 +41,289 @@ impl SourceService {
         self.repo.get_paginated(offset, limit).await
     }
 
+    /// 按类型、语言和内容格式过滤文献源
+    pub async fn get_filtered(&self, filter: &SourceFilter) -> AppResult<Vec<Source>> {
+        self.repo.get_filtered(filter).await
+    }
+
     /// 获取文献源总数
     pub async fn get_count(&self) -> AppResult<usize> {
         self.repo.get_count().await
     }
 
+    /// 获取最近阅读且未读完的文献源
+    pub async fn get_continue_reading(&self, limit: usize) -> AppResult<Vec<Source>> {
+        self.repo.get_continue_reading(limit).await
+    }
+
+    /// 按标签、类型和进度生成排好序的阅读清单
+    pub async fn get_reading_list(&self, filter: &ReadingListFilter) -> AppResult<Vec<Source>> {
+        let sources = self.repo.get_all().await?;
+        Ok(build_reading_list(sources, filter))
+    }
+
     /// 获取单个文献源
     pub async fn get_by_id(&self, id: &str) -> AppResult<Option<Source>> {
         self.repo.get_by_id(id).await
     }
 
     /// 更新文献源
-    pub async fn update(&self, id: &str, req: UpdateSourceRequest) -> AppResult<Option<Source>> {
-        self.repo.update(id, req).await
+    pub async fn update(
+        &self,
+        id: &str,
+        req: UpdateSourceRequest,
+        indexer: Option<&Mutex<Option<Indexer>>>,
+    ) -> AppResult<Option<Source>> {
+        let source = self.repo.update(id, req).await?;
+        if let Some(source) = &source {
+            index_source(source, indexer);
+        }
+        Ok(source)
     }
 
-    /// 删除文献源（包含关联数据清理）
-    pub async fn delete(&self, id: &str) -> AppResult<()> {
+    /// 删除文献源（移入回收站，关联的高亮和书签保留）
+    pub async fn delete(&self, id: &str, indexer: Option<&Mutex<Option<Indexer>>>) -> AppResult<()> {
+        self.repo.delete(id).await?;
+        remove_from_index(id, indexer);
+        Ok(())
+    }
+
+    /// 修改文献源加入资料库的途径（manual / epub / web / pdf / bibtex）
+    pub async fn set_origin(&self, id: &str, origin: &str) -> AppResult<()> {
+        self.repo.set_origin(id, origin).await
+    }
+
+    /// 永久删除文献源（包含关联数据清理）
+    pub async fn hard_delete(
+        &self,
+        id: &str,
+        indexer: Option<&Mutex<Option<Indexer>>>,
+    ) -> AppResult<()> {
         // 删除操作会自动级联删除关联的高亮和书签（通过外键约束）
-        self.repo.delete(id).await
+        self.repo.hard_delete(id).await?;
+        remove_from_index(id, indexer);
+        Ok(())
+    }
+
+    /// 从回收站恢复文献源
+    pub async fn restore(
+        &self,
+        id: &str,
+        indexer: Option<&Mutex<Option<Indexer>>>,
+    ) -> AppResult<Option<Source>> {
+        let source = self.repo.restore(id).await?;
+        if let Some(source) = &source {
+            index_source(source, indexer);
+        }
+        Ok(source)
+    }
+
+    /// 获取回收站中的文献源
+    pub async fn get_trashed(&self) -> AppResult<Vec<Source>> {
+        self.repo.get_trashed().await
+    }
+
+    /// 永久删除回收站中超过指定天数的文献源
+    pub async fn purge_trashed(&self, older_than_days: u32) -> AppResult<u64> {
+        self.repo.purge_trashed(older_than_days).await
     }
 
     /// 添加笔记到文献源
     pub async fn add_note(&self, source_id: &str, note_id: &str) -> AppResult<()> {
         self.repo.add_note(source_id, note_id).await
     }
+
+    /// 从文献源移除笔记
+    pub async fn remove_note(&self, source_id: &str, note_id: &str) -> AppResult<()> {
+        self.repo.remove_note(source_id, note_id).await
+    }
+
+    /// 列出文献源标签及使用次数
+    pub async fn list_tags(&self) -> AppResult<Vec<(String, usize)>> {
+        self.repo.get_tag_counts().await
+    }
+
+    /// 重命名文献源标签，返回受影响的文献源数量
+    pub async fn rename_tag(
+        &self,
+        old: &str,
+        new: &str,
+        indexer: Option<&Mutex<Option<Indexer>>>,
+    ) -> AppResult<usize> {
+        self.merge_tags(&[old.to_string()], new, indexer).await
+    }
+
+    /// 将多个文献源标签合并为一个，返回受影响的文献源数量
+    pub async fn merge_tags(
+        &self,
+        from: &[String],
+        into: &str,
+        indexer: Option<&Mutex<Option<Indexer>>>,
+    ) -> AppResult<usize> {
+        let into = into.trim();
+        if into.is_empty() {
+            return Err(AppError::InvalidInput("标签不能为空".to_string()));
+        }
+
+        let changed = self.repo.merge_tags(from, into).await?;
+        for id in &changed {
+            // 回收站中的文献源不在索引中，get_by_id 返回 None 时跳过
+            if let Some(source) = self.repo.get_by_id(id).await? {
+                index_source(&source, indexer);
+            }
+        }
+        Ok(changed.len())
+    }
 }
 
+/// 从文献源列表中筛选并排序出阅读清单
+pub fn build_reading_list(sources: Vec<Source>, filter: &ReadingListFilter) -> Vec<Source> {
+    let wanted: Vec<String> = filter
+        .tags
+        .iter()
+        .map(|t| normalize_tag(t))
+        .filter(|t| !t.is_empty())
+        .collect();
+
+    let mut list: Vec<Source> = sources
+        .into_iter()
+        .filter(|s| s.deleted_at.is_none())
+        .filter(|s| filter.source_type.as_ref().is_none_or(|t| &s.source_type == t))
+        .filter(|s| filter.min_progress.is_none_or(|min| s.progress >= min))
+        .filter(|s| filter.max_progress.is_none_or(|max| s.progress <= max))
+        .filter(|s| {
+            let tags: Vec<String> = s.tags.iter().map(|t| normalize_tag(t)).collect();
+            wanted.iter().all(|w| tags.contains(w))
+        })
+        .collect();
+
+    match filter.sort {
+        ReadingListSort::Shortest => list.sort_by(|a, b| {
+            // 篇幅未知的排在最后，篇幅相同按标题
+            match (source_length(a), source_length(b)) {
+                (Some(x), Some(y)) => x.cmp(&y),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => std::cmp::Ordering::Equal,
+            }
+            .then_with(|| a.title.to_lowercase().cmp(&b.title.to_lowercase()))
+        }),
+        ReadingListSort::RecentlyAdded => list.sort_by(|a, b| b.created_at.cmp(&a.created_at)),
+        ReadingListSort::RecentlyRead => list.sort_by(|a, b| b.last_read_at.cmp(&a.last_read_at)),
+        ReadingListSort::Progress => list.sort_by(|a, b| b.progress.cmp(&a.progress)),
+        ReadingListSort::Title => {
+            list.sort_by(|a, b| a.title.to_lowercase().cmp(&b.title.to_lowercase()))
+        }
+    }
+
+    if let Some(limit) = filter.limit {
+        list.truncate(limit);
+    }
+    list
+}
+
+fn normalize_tag(tag: &str) -> String {
+    tag.trim().trim_start_matches('#').to_lowercase()
+}
+
+/// 估算页数时每页的词数
+const WORDS_PER_PAGE: usize = 300;
+
+/// 文献源篇幅，统一换算为预计阅读分钟数：
+/// 网页按阅读时间或词数，书籍等按页数，音视频按时长
+fn source_length(source: &Source) -> Option<usize> {
+    let meta = source.metadata.as_ref()?;
+    let positive = |n: Option<i32>| n.filter(|n| *n > 0).map(|n| n as usize);
+    meta.reading_minutes
+        .filter(|m| *m > 0)
+        .or_else(|| meta.word_count.filter(|w| *w > 0).map(reading_minutes))
+        .or_else(|| positive(meta.page_count).map(|pages| reading_minutes(pages * WORDS_PER_PAGE)))
+        .or_else(|| positive(meta.duration))
+}
+
+/// 更新文献源的搜索索引（索引失败不影响数据写入）
+fn index_source(source: &Source, indexer: Option<&Mutex<Option<Indexer>>>) {
+    if let Some(indexer) = indexer {
+        if let Ok(Some(idx)) = indexer.lock().as_deref() {
+            idx.index_source(source).ok();
+        }
+    }
+}
+
+fn remove_from_index(id: &str, indexer: Option<&Mutex<Option<Indexer>>>) {
+    if let Some(indexer) = indexer {
+        if let Ok(Some(idx)) = indexer.lock().as_deref() {
+            idx.delete_doc(id).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SourceType;
+
+    fn source(
+        id: &str,
+        tags: &[&str],
+        progress: i32,
+        pages: Option<i32>,
+        created_at: i64,
+    ) -> Source {
+        serde_json::from_value(serde_json::json!({
+            "id": id, "type": SourceType::Book, "title": id, "author": null,
+            "url": null, "cover": null, "description": null, "tags": tags, "progress": progress,
+            "lastReadAt": null, "metadata": { "pageCount": pages }, "noteIds": [],
+            "createdAt": created_at, "updatedAt": created_at
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_build_reading_list() {
+        let sources = vec![
+            source("long", &["to-read"], 0, Some(800), 1),
+            source("short", &["To-Read"], 0, Some(120), 2),
+            source("unknown", &["to-read"], 0, None, 3),
+            source("started", &["to-read"], 40, Some(50), 4),
+            source("other", &["fiction"], 0, Some(10), 5),
+        ];
+
+        let filter = ReadingListFilter {
+            tags: vec!["#to-read".to_string()],
+            max_progress: Some(0),
+            sort: ReadingListSort::Shortest,
+            ..Default::default()
+        };
+        let ids: Vec<_> = build_reading_list(sources.clone(), &filter)
+            .into_iter()
+            .map(|s| s.id)
+            .collect();
+        assert_eq!(ids, ["short", "long", "unknown"]);
+
+        // 网页按词数、音视频按时长，与书籍页数换算到同一单位比较
+        let mut article = source("article", &[], 0, None, 6);
+        article.metadata.as_mut().unwrap().word_count = Some(2_500);
+        let mut podcast = source("podcast", &[], 0, None, 7);
+        podcast.metadata.as_mut().unwrap().duration = Some(90);
+        let filter = ReadingListFilter {
+            sort: ReadingListSort::Shortest,
+            ..Default::default()
+        };
+        let ids: Vec<_> = build_reading_list(vec![sources[0].clone(), sources[1].clone(), article, podcast], &filter)
+            .into_iter()
+            .map(|s| s.id)
+            .collect();
+        assert_eq!(ids, ["article", "podcast", "short", "long"]);
+
+        let filter = ReadingListFilter {
+            limit: Some(2),
+            ..Default::default()
+        };
+        let ids: Vec<_> = build_reading_list(sources, &filter)
+            .into_iter()
+            .map(|s| s.id)
+            .collect();
+        assert_eq!(ids, ["other", "started"]);
+    }
+}
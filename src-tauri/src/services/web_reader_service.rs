@@ -1,29 +1,62 @@
 //! WebReader 应用服务层
 //! 封装网页阅读器相关的业务逻辑
 
-use crate::database::WebSnapshotRepository;
-use crate::web_reader::{self, FetchResult, WebSnapshot, WebpageMetadata};
+use crate::database::{ConfigRepository, WebSnapshotRepository};
+use crate::models::{reading_minutes_at, WORDS_PER_MINUTE};
+use crate::web_reader::{self, FetchResult, FetchedDocument, SimplifyLevel, WebSnapshot, WebpageMetadata};
 use std::sync::Arc;
 use uuid::Uuid;
 
 /// WebReader 应用服务
 pub struct WebReaderService {
     repo: Arc<WebSnapshotRepository>,
+    config_repo: Arc<ConfigRepository>,
 }
 
+/// 配置表中保存阅读速度（词/分钟）的键
+const READING_SPEED_CONFIG_KEY: &str = "reading_words_per_minute";
+
 impl WebReaderService {
-    pub fn new(repo: Arc<WebSnapshotRepository>) -> Self {
-        Self { repo }
+    pub fn new(repo: Arc<WebSnapshotRepository>, config_repo: Arc<ConfigRepository>) -> Self {
+        Self { repo, config_repo }
+    }
+
+    /// 估算阅读时间使用的阅读速度（词/分钟）
+    pub async fn reading_speed(&self) -> Result<usize, String> {
+        Ok(self
+            .config_repo
+            .get(READING_SPEED_CONFIG_KEY)
+            .await
+            .map_err(|e| e.to_string())?
+            .and_then(|v| v.parse().ok())
+            .filter(|&wpm| wpm > 0)
+            .unwrap_or(WORDS_PER_MINUTE))
+    }
+
+    /// 设置阅读速度（词/分钟），只影响之后保存的快照
+    pub async fn set_reading_speed(&self, words_per_minute: usize) -> Result<(), String> {
+        if words_per_minute == 0 {
+            return Err("Reading speed must be greater than 0".to_string());
+        }
+        self.config_repo
+            .set(READING_SPEED_CONFIG_KEY, &words_per_minute.to_string())
+            .await
+            .map_err(|e| e.to_string())
     }
 
-    /// 抓取并清洗网页（完整内容）
-    pub fn fetch_webpage(&self, url: &str) -> Result<FetchResult, String> {
-        web_reader::fetch_and_clean(url).map_err(|e| e.to_string())
+    /// 抓取并清洗网页（完整内容），可选再做一次精简
+    pub async fn fetch_webpage(&self, url: &str, simplify: Option<SimplifyLevel>) -> Result<FetchResult, String> {
+        web_reader::fetch_and_clean(url, simplify).await.map_err(|e| e.to_string())
+    }
+
+    /// 抓取链接，区分网页和 PDF
+    pub async fn fetch_document(&self, url: &str) -> Result<FetchedDocument, String> {
+        web_reader::fetch_document(url).await.map_err(|e| e.to_string())
     }
 
     /// 快速获取网页元数据（用于表单自动填充）
-    pub fn fetch_metadata(&self, url: &str) -> Result<WebpageMetadata, String> {
-        web_reader::fetch_webpage_metadata(url).map_err(|e| e.to_string())
+    pub async fn fetch_metadata(&self, url: &str) -> Result<WebpageMetadata, String> {
+        web_reader::fetch_webpage_metadata(url).await.map_err(|e| e.to_string())
     }
 
     /// 保存网页快照
@@ -37,6 +70,8 @@ impl WebReaderService {
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_millis() as i64;
+        let word_count = crate::search::count_segmented_words(&fetch_result.text_content);
+        let reading_minutes = reading_minutes_at(word_count, self.reading_speed().await?);
 
         let snapshot = WebSnapshot {
             id: Uuid::new_v4().to_string(),
@@ -48,6 +83,9 @@ impl WebReaderService {
             content: fetch_result.content,
             text_content: fetch_result.text_content,
             excerpt: fetch_result.excerpt,
+            published_at: fetch_result.published_at,
+            word_count,
+            reading_minutes,
             created_at: now,
         };
 
@@ -60,6 +98,18 @@ impl WebReaderService {
         Ok(snapshot)
     }
 
+    /// 替换快照的 HTML 内容（如图片本地化后）并重新保存
+    pub async fn update_snapshot_content(&self, mut snapshot: WebSnapshot, content: String) -> Result<WebSnapshot, String> {
+        snapshot.content = content;
+        self.repo.save(&snapshot).await.map_err(|e| e.to_string())?;
+        Ok(snapshot)
+    }
+
+    /// 按网页地址查找已有快照
+    pub async fn find_snapshot_by_url(&self, url: &str) -> Result<Option<WebSnapshot>, String> {
+        self.repo.find_by_url(url).await.map_err(|e| e.to_string())
+    }
+
     /// 获取网页快照
     pub async fn get_snapshot(&self, source_id: &str) -> Result<Option<WebSnapshot>, String> {
         self.repo.get_by_source(source_id).await.map_err(|e| e.to_string())
@@ -4,11 +4,16 @@ use crate::ai::AIManager;
 use crate::crdt::CrdtManager;
 use crate::db::Database;
 use crate::graph::GraphEngine;
+use crate::models::CardChange;
 use crate::search::Indexer;
 use crate::services::Services;
-use crate::watcher::VaultWatcher;
+use crate::watcher::{VaultWatcher, WatcherStatus};
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+/// 卡片变更事件名
+pub const CARD_CHANGED_EVENT: &str = "card-changed";
 
 /// 应用全局状态
 pub struct AppState {
@@ -22,26 +27,36 @@ pub struct AppState {
     pub indexer: Mutex<Option<Indexer>>,
     /// 文件监听器
     pub watcher: Mutex<Option<VaultWatcher>>,
+    /// 文件监听器状态（记录初始化/运行错误）
+    pub watcher_status: Mutex<WatcherStatus>,
     /// CRDT 管理器 (协作编辑)
     pub crdt: Mutex<Option<Arc<CrdtManager>>>,
     /// 图谱引擎 (增强版)
     pub graph_engine: Mutex<Option<Arc<GraphEngine>>>,
     /// AI 管理器
     pub ai_manager: Mutex<Option<Arc<AIManager>>>,
+    /// 卡片变更通道（发送端）
+    card_changes: UnboundedSender<CardChange>,
+    /// 卡片变更通道（接收端），启动时由转发任务取走
+    card_change_rx: Mutex<Option<UnboundedReceiver<CardChange>>>,
 }
 
 impl AppState {
     /// 创建新的应用状态（无 vault 时）
     pub fn new_empty() -> Self {
+        let (card_changes, card_change_rx) = unbounded_channel();
         Self {
             db: Mutex::new(None),
             services: Mutex::new(None),
             vault_path: Mutex::new(None),
             indexer: Mutex::new(None),
             watcher: Mutex::new(None),
+            watcher_status: Mutex::new(WatcherStatus::default()),
             crdt: Mutex::new(None),
             graph_engine: Mutex::new(None),
             ai_manager: Mutex::new(None),
+            card_changes,
+            card_change_rx: Mutex::new(Some(card_change_rx)),
         }
     }
 
@@ -64,15 +79,23 @@ impl AppState {
             .ok()
             .map(Arc::new);
 
+        let (card_changes, card_change_rx) = unbounded_channel();
         Self {
             db: Mutex::new(Some(db)),
             services: Mutex::new(Some(services)),
             vault_path: Mutex::new(Some(vault_path)),
             indexer: Mutex::new(indexer),
+            watcher_status: Mutex::new(if watcher.is_some() {
+                WatcherStatus::running()
+            } else {
+                WatcherStatus::default()
+            }),
             watcher: Mutex::new(watcher),
             crdt: Mutex::new(crdt),
             graph_engine: Mutex::new(graph_engine),
             ai_manager: Mutex::new(ai_manager),
+            card_changes,
+            card_change_rx: Mutex::new(Some(card_change_rx)),
         }
     }
 
@@ -88,6 +111,33 @@ impl AppState {
         *self.graph_engine.lock().unwrap() = Some(new_graph);
     }
 
+    /// 设置文件监听器并同步状态，初始化失败时返回错误信息
+    pub fn set_watcher(&self, watcher: Result<VaultWatcher, String>) -> Option<String> {
+        match watcher {
+            Ok(w) => {
+                *self.watcher.lock().unwrap() = Some(w);
+                *self.watcher_status.lock().unwrap() = WatcherStatus::running();
+                None
+            }
+            Err(e) => {
+                eprintln!("Warning: Failed to initialize file watcher: {}", e);
+                *self.watcher.lock().unwrap() = None;
+                *self.watcher_status.lock().unwrap() = WatcherStatus::failed(e.clone());
+                Some(e)
+            }
+        }
+    }
+
+    /// 发出卡片变更通知（接收端已关闭时忽略）
+    pub fn notify_card_change(&self, change: CardChange) {
+        self.card_changes.send(change).ok();
+    }
+
+    /// 取走卡片变更接收端，只能取一次
+    pub fn take_card_change_receiver(&self) -> Option<UnboundedReceiver<CardChange>> {
+        self.card_change_rx.lock().unwrap().take()
+    }
+
     /// 获取服务层（如果已初始化）
     pub fn get_services(&self) -> Option<Arc<Services>> {
         self.services.lock().unwrap().clone()
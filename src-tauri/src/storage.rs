@@ -1,5 +1,5 @@
 //! 数据存储模块
-//! 使用纯 JSON 文件存储 Canvas 等非数据库数据
+//! 使用纯 JSON 文件存储 Canvas 等非数据库数据，并兼容读取旧版 Markdown 卡片
 
 use std::fs;
 use std::path::Path;
@@ -7,6 +7,9 @@ use std::path::Path;
 /// Canvas 目录名称
 const DIR_CANVASES: &str = "40_Canvases";
 
+/// 用户模板目录名称（位于 .zentri 下）
+const DIR_TEMPLATES: &str = "templates";
+
 /// 生成短 ID (类似 nanoid)
 fn generate_id() -> String {
     use std::time::{SystemTime, UNIX_EPOCH};
@@ -29,6 +32,9 @@ pub fn ensure_vault_structure(vault_path: &Path) -> Result<(), String> {
     let migrations_dir = zentri_dir.join("migrations");
     fs::create_dir_all(&migrations_dir).map_err(|e| e.to_string())?;
 
+    // 创建用户模板目录
+    fs::create_dir_all(zentri_dir.join(DIR_TEMPLATES)).map_err(|e| e.to_string())?;
+
     // 创建 sources 目录及其子目录
     for dir in ["pdf", "epub", "web"] {
         let path = vault_path.join("sources").join(dir);
@@ -38,7 +44,7 @@ pub fn ensure_vault_structure(vault_path: &Path) -> Result<(), String> {
     }
 
     // 创建 attachments 目录及其子目录
-    for dir in ["images", "files"] {
+    for dir in ["images", "files", "highlights"] {
         let path = vault_path.join("attachments").join(dir);
         if !path.exists() {
             fs::create_dir_all(&path).map_err(|e| e.to_string())?;
@@ -219,4 +225,1126 @@ pub fn delete_canvas(data_path: &Path, id: &str) -> Result<(), String> {
     Ok(())
 }
 
-// Card 相关函数已全部移除，Card 现在存储在数据库中
+// -----------------------------------------------------------------------------
+// Card Templates
+// -----------------------------------------------------------------------------
+// 用户模板为 .zentri/templates/<id>.json 中的 TipTap JSON，与内置模板同 ID 时覆盖内置模板
+use crate::models::CardTemplate;
+
+/// 日记模板 ID
+pub const DAILY_TEMPLATE_ID: &str = "daily";
+
+/// 内置模板：(ID, 名称, TipTap JSON)
+const BUILTIN_TEMPLATES: &[(&str, &str, &str)] = &[
+    (
+        DAILY_TEMPLATE_ID,
+        "日记",
+        r#"{"type":"doc","content":[
+            {"type":"heading","attrs":{"level":1},"content":[{"type":"text","text":"{{date:%Y年%m月%d日 %A}}"}]},
+            {"type":"heading","attrs":{"level":2},"content":[{"type":"text","text":"今日待办"}]},
+            {"type":"taskList","content":[{"type":"taskItem","attrs":{"checked":false},"content":[{"type":"paragraph"}]}]},
+            {"type":"heading","attrs":{"level":2},"content":[{"type":"text","text":"笔记"}]},
+            {"type":"paragraph"}
+        ]}"#,
+    ),
+    (
+        "meeting",
+        "会议记录",
+        r#"{"type":"doc","content":[
+            {"type":"heading","attrs":{"level":1},"content":[{"type":"text","text":"{{title}}"}]},
+            {"type":"paragraph","content":[{"type":"text","text":"日期：{{date}}"}]},
+            {"type":"heading","attrs":{"level":2},"content":[{"type":"text","text":"参会人"}]},
+            {"type":"bulletList","content":[{"type":"listItem","content":[{"type":"paragraph"}]}]},
+            {"type":"heading","attrs":{"level":2},"content":[{"type":"text","text":"议题"}]},
+            {"type":"paragraph"},
+            {"type":"heading","attrs":{"level":2},"content":[{"type":"text","text":"行动项"}]},
+            {"type":"taskList","content":[{"type":"taskItem","attrs":{"checked":false},"content":[{"type":"paragraph"}]}]}
+        ]}"#,
+    ),
+    (
+        "book",
+        "读书笔记",
+        r#"{"type":"doc","content":[
+            {"type":"heading","attrs":{"level":1},"content":[{"type":"text","text":"{{title}}"}]},
+            {"type":"heading","attrs":{"level":2},"content":[{"type":"text","text":"核心观点"}]},
+            {"type":"paragraph"},
+            {"type":"heading","attrs":{"level":2},"content":[{"type":"text","text":"摘录"}]},
+            {"type":"blockquote","content":[{"type":"paragraph"}]},
+            {"type":"heading","attrs":{"level":2},"content":[{"type":"text","text":"我的想法"}]},
+            {"type":"paragraph"}
+        ]}"#,
+    ),
+    (
+        "zettel",
+        "永久笔记",
+        r#"{"type":"doc","content":[
+            {"type":"paragraph"},
+            {"type":"heading","attrs":{"level":2},"content":[{"type":"text","text":"相关卡片"}]},
+            {"type":"bulletList","content":[{"type":"listItem","content":[{"type":"paragraph"}]}]},
+            {"type":"heading","attrs":{"level":2},"content":[{"type":"text","text":"来源"}]},
+            {"type":"paragraph"}
+        ]}"#,
+    ),
+];
+
+/// 列出所有模板（内置模板在前，用户模板按 ID 排序）
+pub fn list_templates(vault_path: &Path) -> Vec<CardTemplate> {
+    let user_ids = user_template_ids(vault_path);
+    let mut templates: Vec<CardTemplate> = BUILTIN_TEMPLATES
+        .iter()
+        .map(|(id, name, _)| CardTemplate {
+            id: id.to_string(),
+            name: name.to_string(),
+            builtin: !user_ids.iter().any(|u| u == id),
+        })
+        .collect();
+    templates.extend(
+        user_ids
+            .into_iter()
+            .filter(|id| !BUILTIN_TEMPLATES.iter().any(|(builtin, _, _)| builtin == id))
+            .map(|id| CardTemplate {
+                name: id.clone(),
+                id,
+                builtin: false,
+            }),
+    );
+    templates
+}
+
+/// 读取模板内容，用户模板优先于内置模板
+pub fn read_template(vault_path: &Path, id: &str) -> Option<serde_json::Value> {
+    if id.contains("..") || id.contains('/') || id.contains('\\') {
+        return None;
+    }
+    let path = vault_path.join(".zentri").join(DIR_TEMPLATES).join(format!("{}.json", id));
+    if let Ok(content) = fs::read_to_string(path) {
+        if let Ok(template) = serde_json::from_str(&content) {
+            return Some(template);
+        }
+    }
+    BUILTIN_TEMPLATES
+        .iter()
+        .find(|(builtin, _, _)| *builtin == id)
+        .and_then(|(_, _, content)| serde_json::from_str(content).ok())
+}
+
+/// 替换模板中所有字符串里的占位符：`{{title}}`、`{{date}}`（YYYY-MM-DD）、`{{date:格式}}`（chrono 格式）
+///
+/// 不认识的占位符和无效的日期格式原样保留
+pub fn render_template(template: &serde_json::Value, title: &str, date: chrono::NaiveDate) -> serde_json::Value {
+    match template {
+        serde_json::Value::String(text) => serde_json::Value::String(render_placeholders(text, title, date)),
+        serde_json::Value::Array(items) => {
+            serde_json::Value::Array(items.iter().map(|item| render_template(item, title, date)).collect())
+        }
+        serde_json::Value::Object(map) => serde_json::Value::Object(
+            map.iter()
+                .map(|(key, value)| (key.clone(), render_template(value, title, date)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+fn render_placeholders(text: &str, title: &str, date: chrono::NaiveDate) -> String {
+    use chrono::format::{Item, StrftimeItems};
+
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let key = rest[start + 2..start + 2 + len].trim();
+        let value = match key {
+            "title" => Some(title.to_string()),
+            "date" => Some(date.format("%Y-%m-%d").to_string()),
+            _ => key
+                .strip_prefix("date:")
+                .filter(|fmt| !StrftimeItems::new(fmt).any(|item| matches!(item, Item::Error)))
+                .map(|fmt| date.format(fmt).to_string()),
+        };
+        out.push_str(&rest[..start]);
+        match value {
+            Some(value) => out.push_str(&value),
+            None => out.push_str(&rest[start..start + 2 + len + 2]),
+        }
+        rest = &rest[start + 2 + len + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn user_template_ids(vault_path: &Path) -> Vec<String> {
+    let mut ids: Vec<String> = fs::read_dir(vault_path.join(".zentri").join(DIR_TEMPLATES))
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| {
+                    let path = entry.path();
+                    if path.extension().map(|e| e == "json").unwrap_or(false) {
+                        Some(path.file_stem()?.to_string_lossy().to_string())
+                    } else {
+                        None
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    ids.sort();
+    ids
+}
+
+// -----------------------------------------------------------------------------
+// Card Revisions
+// -----------------------------------------------------------------------------
+// 卡片每次修改前的版本保存在 .zentri/history/<id>/<modified_at>.json
+
+fn revision_dir(vault_path: &Path, id: &str) -> std::path::PathBuf {
+    vault_path.join(".zentri").join("history").join(id)
+}
+
+/// 保存卡片修改前的版本，只保留最近 `keep` 个；`keep` 为 0 时不保存
+pub fn write_card_revision(vault_path: &Path, card: &crate::models::Card, keep: usize) -> Result<(), String> {
+    if keep == 0 {
+        return Ok(());
+    }
+
+    let dir_path = revision_dir(vault_path, &card.id);
+    fs::create_dir_all(&dir_path).map_err(|e| e.to_string())?;
+
+    let path = dir_path.join(format!("{}.json", card.modified_at));
+    let content = serde_json::to_string_pretty(card).map_err(|e| e.to_string())?;
+    fs::write(&path, content).map_err(|e| e.to_string())?;
+
+    for timestamp in list_revision_timestamps(&dir_path).into_iter().skip(keep) {
+        fs::remove_file(dir_path.join(format!("{}.json", timestamp))).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// 删除卡片的全部历史版本
+pub fn delete_card_revisions(vault_path: &Path, id: &str) -> Result<(), String> {
+    let dir_path = revision_dir(vault_path, id);
+    if dir_path.exists() {
+        fs::remove_dir_all(&dir_path).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// 读取卡片的所有历史版本（新的在前）
+pub fn read_card_revisions(vault_path: &Path, id: &str) -> Vec<crate::models::Card> {
+    let dir_path = revision_dir(vault_path, id);
+    list_revision_timestamps(&dir_path)
+        .into_iter()
+        .filter_map(|timestamp| read_card_revision(vault_path, id, timestamp))
+        .collect()
+}
+
+/// 读取卡片的单个历史版本
+pub fn read_card_revision(vault_path: &Path, id: &str, timestamp: i64) -> Option<crate::models::Card> {
+    let path = revision_dir(vault_path, id).join(format!("{}.json", timestamp));
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// 历史目录中的版本时间戳（新的在前）
+fn list_revision_timestamps(dir_path: &Path) -> Vec<i64> {
+    let mut timestamps: Vec<i64> = fs::read_dir(dir_path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| {
+                    let path = entry.path();
+                    if path.extension().map(|e| e == "json").unwrap_or(false) {
+                        path.file_stem()?.to_str()?.parse().ok()
+                    } else {
+                        None
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    timestamps.sort_unstable_by(|a, b| b.cmp(a));
+    timestamps
+}
+
+// -----------------------------------------------------------------------------
+// Legacy Markdown Cards
+// -----------------------------------------------------------------------------
+// Card 现在存储在数据库中；旧版 vault 中的 .md 卡片只读，编辑时再导入数据库
+use crate::models::{count_words, Card, CardType, Frontmatter};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// 不属于卡片的顶层目录
+const NON_CARD_DIRS: &[&str] = &["sources", "attachments", "derived", "canvases", "assets"];
+
+/// 读取 vault 中所有旧版 Markdown 卡片（ID 为文件名）
+pub fn read_markdown_cards(vault_path: &Path) -> Vec<Card> {
+    MarkdownCardCache::default().cards(vault_path)
+}
+
+/// 按 ID 读取旧版 Markdown 卡片
+pub fn read_markdown_card(vault_path: &Path, id: &str) -> Option<Card> {
+    MarkdownCardCache::default().card(vault_path, id)
+}
+
+/// 旧版 Markdown 卡片的解析缓存：扫描时只读取文件修改时间，新增或修改过的文件才重新解析
+#[derive(Default)]
+pub struct MarkdownCardCache {
+    entries: HashMap<PathBuf, (i64, Card)>,
+}
+
+impl MarkdownCardCache {
+    /// 所有旧版 Markdown 卡片（新修改的在前）
+    pub fn cards(&mut self, vault_path: &Path) -> Vec<Card> {
+        let mut entries = HashMap::new();
+        for path in markdown_card_files(vault_path) {
+            let modified = file_modified_millis(&path);
+            let entry = match self.entries.remove(&path) {
+                Some(entry) if entry.0 == modified => entry,
+                _ => match read_markdown_file(vault_path, &path, modified) {
+                    Some(card) => (modified, card),
+                    None => continue,
+                },
+            };
+            entries.insert(path, entry);
+        }
+        self.entries = entries;
+
+        let mut cards: Vec<Card> = self.entries.values().map(|(_, card)| card.clone()).collect();
+        cards.sort_by(|a, b| b.modified_at.cmp(&a.modified_at));
+        cards
+    }
+
+    /// 按 ID 读取，缓存中的文件未修改时不再扫描 vault
+    pub fn card(&mut self, vault_path: &Path, id: &str) -> Option<Card> {
+        let cached = self.entries.iter().find(|(_, (_, card))| card.id == id);
+        if let Some((path, (modified, card))) = cached {
+            if path.exists() && file_modified_millis(path) == *modified {
+                return Some(card.clone());
+            }
+        }
+        self.cards(vault_path).into_iter().find(|card| card.id == id)
+    }
+}
+
+/// 可能存放旧版卡片的 .md 文件：跳过隐藏目录和文献、附件等非卡片目录
+fn markdown_card_files(vault_path: &Path) -> Vec<PathBuf> {
+    walkdir::WalkDir::new(vault_path)
+        .into_iter()
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            if entry.depth() == 0 {
+                return true;
+            }
+            if name.starts_with('.') {
+                return false;
+            }
+            !(entry.depth() == 1 && entry.file_type().is_dir() && NON_CARD_DIRS.contains(&name.as_ref()))
+        })
+        .flatten()
+        .filter(|entry| {
+            entry.file_type().is_file() && entry.path().extension().is_some_and(|e| e == "md")
+        })
+        .map(|entry| entry.into_path())
+        .collect()
+}
+
+fn file_modified_millis(path: &Path) -> i64 {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_else(current_timestamp)
+}
+
+/// 删除指定 ID 的旧版 Markdown 卡片源文件。卡片永久删除后源文件若还在，会被重新当作卡片读出
+pub fn delete_markdown_cards(vault_path: &Path, ids: &[String]) -> Result<(), String> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+    for card in read_markdown_cards(vault_path) {
+        if let (true, Some(path)) = (ids.contains(&card.id), &card.path) {
+            fs::remove_file(vault_path.join(path)).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+fn read_markdown_file(vault_path: &Path, path: &Path, modified: i64) -> Option<Card> {
+    let text = fs::read_to_string(path).ok()?;
+    let relative = path
+        .strip_prefix(vault_path)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/");
+    Some(parse_markdown_card(&relative, &text, modified, &HashMap::new()))
+}
+
+// -----------------------------------------------------------------------------
+// Obsidian Import
+// -----------------------------------------------------------------------------
+
+/// 导入时按图片处理的附件扩展名
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "svg", "bmp"];
+
+/// 扫描到的 Obsidian vault 文件，路径均相对于 vault 根目录
+#[derive(Debug, Default)]
+pub struct ObsidianScan {
+    pub notes: Vec<String>,
+    pub attachments: Vec<String>,
+}
+
+/// 扫描 Obsidian vault，跳过 .obsidian、.trash 等隐藏目录
+pub fn scan_obsidian_vault(src_dir: &Path) -> ObsidianScan {
+    let mut scan = ObsidianScan::default();
+    let entries = walkdir::WalkDir::new(src_dir)
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || !entry.file_name().to_string_lossy().starts_with('.'))
+        .flatten()
+        .filter(|entry| entry.file_type().is_file());
+    for entry in entries {
+        let relative = entry
+            .path()
+            .strip_prefix(src_dir)
+            .unwrap_or(entry.path())
+            .to_string_lossy()
+            .replace('\\', "/");
+        if entry.path().extension().is_some_and(|e| e == "md") {
+            scan.notes.push(relative);
+        } else {
+            scan.attachments.push(relative);
+        }
+    }
+    scan.notes.sort();
+    scan.attachments.sort();
+    scan
+}
+
+/// 复制附件的结果
+#[derive(Debug, Default)]
+pub struct ObsidianAttachments {
+    /// 源 vault 中的相对路径 -> 相对目标 vault 的新路径
+    pub paths: HashMap<String, String>,
+    /// 实际复制的文件数（内容相同、此前已导入的附件不再复制）
+    pub copied: usize,
+}
+
+/// 将附件复制到 attachments/images（图片）或 attachments/files
+///
+/// 目标目录已有同名文件时：内容相同视为已导入，直接复用；否则追加序号
+pub fn copy_obsidian_attachments(
+    src_dir: &Path,
+    vault_path: &Path,
+    attachments: &[String],
+) -> Result<ObsidianAttachments, String> {
+    let mut result = ObsidianAttachments::default();
+    for relative in attachments {
+        let src = src_dir.join(relative);
+        let Some(file_name) = src.file_name().map(|n| n.to_string_lossy().to_string()) else {
+            continue;
+        };
+        let is_image = src
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.as_str()));
+        let dir = Path::new("attachments").join(if is_image { "images" } else { "files" });
+        fs::create_dir_all(vault_path.join(&dir)).map_err(|e| e.to_string())?;
+
+        let stem = src.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        let ext = src.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+        let mut dest_name = file_name;
+        let mut n = 1;
+        let mut imported = false;
+        while vault_path.join(&dir).join(&dest_name).exists() {
+            if same_content(&src, &vault_path.join(&dir).join(&dest_name)) {
+                imported = true;
+                break;
+            }
+            dest_name = format!("{}-{}{}", stem, n, ext);
+            n += 1;
+        }
+        if !imported {
+            fs::copy(&src, vault_path.join(&dir).join(&dest_name)).map_err(|e| format!("{}: {}", relative, e))?;
+            result.copied += 1;
+        }
+        result
+            .paths
+            .insert(relative.clone(), dir.join(&dest_name).to_string_lossy().replace('\\', "/"));
+    }
+    Ok(result)
+}
+
+fn same_content(a: &Path, b: &Path) -> bool {
+    let same_len = match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.len() == b.len(),
+        _ => false,
+    };
+    same_len && matches!((fs::read(a), fs::read(b)), (Ok(a), Ok(b)) if a == b)
+}
+
+/// 按 Obsidian 的规则解析附件嵌入：带路径的目标按相对路径匹配（相对 vault 根目录或笔记所在目录），
+/// 只有文件名时优先笔记所在目录，其次路径最短的同名文件
+fn resolve_attachment<'a>(
+    attachments: &'a HashMap<String, String>,
+    note_path: &str,
+    target: &str,
+) -> Option<&'a str> {
+    let note_dir = Path::new(note_path).parent().unwrap_or(Path::new(""));
+    if target.contains('/') {
+        let from_note = note_dir.join(target).to_string_lossy().replace('\\', "/");
+        return attachments
+            .get(target)
+            .or_else(|| attachments.get(&from_note))
+            .map(String::as_str);
+    }
+    attachments
+        .iter()
+        .filter(|(path, _)| Path::new(path).file_name().is_some_and(|n| n.to_string_lossy() == target))
+        .min_by_key(|(path, _)| {
+            let in_note_dir = Path::new(path).parent() == Some(note_dir);
+            (!in_note_dir, path.matches('/').count(), path.as_str())
+        })
+        .map(|(_, dest)| dest.as_str())
+}
+
+/// 读取并转换一篇 Obsidian 笔记，`attachments` 为 copy_obsidian_attachments 返回的路径映射
+pub fn read_obsidian_note(
+    src_dir: &Path,
+    relative_path: &str,
+    attachments: &HashMap<String, String>,
+) -> Result<Card, String> {
+    let path = src_dir.join(relative_path);
+    let text = fs::read_to_string(&path).map_err(|e| format!("{}: {}", relative_path, e))?;
+    let modified = fs::metadata(&path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_else(current_timestamp);
+    Ok(parse_markdown_card(relative_path, &text, modified, attachments))
+}
+
+/// 将 Markdown 文本（可带 YAML frontmatter）转换为 Card
+///
+/// 正文转换为 TipTap JSON：`#` 标题转为 heading，`-`/`1.` 列表转为 bulletList/orderedList，空行分段，`[[目标]]` 转为 card:// 链接，
+/// `![[附件]]` 转为指向 `attachments` 中对应路径的链接
+fn parse_markdown_card(
+    relative_path: &str,
+    text: &str,
+    file_modified: i64,
+    attachments: &HashMap<String, String>,
+) -> Card {
+    let (frontmatter, body) = split_frontmatter(text);
+    let stem = Path::new(relative_path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let card_type = frontmatter
+        .card_type
+        .as_deref()
+        .map(CardType::from_str)
+        .unwrap_or_else(|| infer_card_type(relative_path));
+    let created_at = frontmatter.created.as_deref().and_then(parse_timestamp).unwrap_or(file_modified);
+    let modified_at = frontmatter.modified.as_deref().and_then(parse_timestamp).unwrap_or(file_modified);
+
+    let mut blocks = Vec::new();
+    let mut plain_text = Vec::new();
+    let mut links = Vec::new();
+    let mut title_from_heading = None;
+    for block in body.split("\n\n").map(str::trim).filter(|b| !b.is_empty()) {
+        if let Some((list_type, items)) = markdown_list_items(block) {
+            let items: Vec<serde_json::Value> = items
+                .into_iter()
+                .map(|item| {
+                    let (inline, text_only) = markdown_inline_nodes(item, &mut links, relative_path, attachments);
+                    plain_text.push(text_only);
+                    serde_json::json!({
+                        "type": "listItem",
+                        "content": [{ "type": "paragraph", "content": inline }]
+                    })
+                })
+                .collect();
+            blocks.push(serde_json::json!({ "type": list_type, "content": items }));
+            continue;
+        }
+
+        let level = block.chars().take_while(|c| *c == '#').count();
+        let (node_type, text) = if (1..=6).contains(&level) && block[level..].starts_with(' ') {
+            ("heading", block[level..].trim())
+        } else {
+            ("paragraph", block)
+        };
+        if node_type == "heading" && level == 1 && title_from_heading.is_none() {
+            title_from_heading = Some(text.to_string());
+        }
+
+        let (inline, text_only) = markdown_inline_nodes(text, &mut links, relative_path, attachments);
+        let mut node = serde_json::json!({ "type": node_type, "content": inline });
+        if node_type == "heading" {
+            node["attrs"] = serde_json::json!({ "level": level });
+        }
+        blocks.push(node);
+        plain_text.push(text_only);
+    }
+
+    let plain_text = plain_text.join("\n");
+    let preview: String = plain_text.chars().take(200).collect();
+    let title = frontmatter
+        .title
+        .or(title_from_heading)
+        .unwrap_or_else(|| stem.clone());
+
+    Card {
+        id: stem,
+        path: Some(relative_path.to_string()),
+        title,
+        tags: frontmatter.tags,
+        card_type,
+        content: serde_json::json!({ "type": "doc", "content": blocks }).to_string(),
+        word_count: count_words(&plain_text),
+        preview: (!preview.is_empty()).then_some(preview),
+        plain_text,
+        created_at,
+        modified_at,
+        aliases: frontmatter.aliases,
+        links,
+        source_id: frontmatter.source_id,
+        pinned: false,
+        archived: false,
+        review: None,
+        encrypted: false,
+    }
+}
+
+/// 拆分 YAML frontmatter 和正文；frontmatter 无法解析时按正文处理
+fn split_frontmatter(text: &str) -> (Frontmatter, &str) {
+    let text = text.trim_start_matches('\u{feff}');
+    if let Some(rest) = text.strip_prefix("---\n").or_else(|| text.strip_prefix("---\r\n")) {
+        if let Some(end) = rest.find("\n---") {
+            let yaml = &rest[..end];
+            let body = rest[end + 4..].trim_start_matches(['\r', '\n']);
+            if let Ok(frontmatter) = serde_yaml::from_str::<Frontmatter>(yaml) {
+                return (frontmatter, body);
+            }
+        }
+    }
+    (Frontmatter::default(), text)
+}
+
+/// 每一行都是列表项时返回列表类型（bulletList / orderedList）和各项文本；嵌套列表展平为同一层
+fn markdown_list_items(block: &str) -> Option<(&'static str, Vec<&str>)> {
+    let lines: Vec<&str> = block.lines().map(str::trim_start).collect();
+    let bullets: Option<Vec<&str>> = lines
+        .iter()
+        .map(|line| ["- ", "* ", "+ "].iter().find_map(|marker| line.strip_prefix(marker)))
+        .collect();
+    if let Some(items) = bullets {
+        return Some(("bulletList", items));
+    }
+    let ordered: Option<Vec<&str>> = lines
+        .iter()
+        .map(|line| {
+            let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+            (digits > 0).then(|| line[digits..].strip_prefix(". ")).flatten()
+        })
+        .collect();
+    ordered.map(|items| ("orderedList", items))
+}
+
+/// 将一段文本中的 `[[目标|显示文本]]` 转为带链接标记的文本节点
+///
+/// `![[文件.png]]` 这类附件嵌入不计入卡片链接，链接到附件复制后的路径（未知附件保留原文件名）
+fn markdown_inline_nodes(
+    text: &str,
+    links: &mut Vec<String>,
+    note_path: &str,
+    attachments: &HashMap<String, String>,
+) -> (Vec<serde_json::Value>, String) {
+    let mut nodes = Vec::new();
+    let mut plain = String::new();
+    let mut rest = text;
+
+    let push_text = |nodes: &mut Vec<serde_json::Value>, t: &str| {
+        if !t.is_empty() {
+            nodes.push(serde_json::json!({ "type": "text", "text": t }));
+        }
+    };
+
+    while let Some(start) = rest.find("[[") {
+        let Some(len) = rest[start + 2..].find("]]") else {
+            break;
+        };
+        let inner = &rest[start + 2..start + 2 + len];
+        let (target, label) = inner.split_once('|').unwrap_or((inner, inner));
+        let (target, label) = (target.trim(), label.trim());
+        let embedded_file = rest[..start].ends_with('!')
+            && Path::new(target).extension().is_some_and(|e| e != "md");
+        let before = if embedded_file { &rest[..start - 1] } else { &rest[..start] };
+
+        push_text(&mut nodes, before);
+        plain.push_str(before);
+        if embedded_file {
+            let href = resolve_attachment(attachments, note_path, target).unwrap_or(target);
+            nodes.push(serde_json::json!({
+                "type": "text",
+                "text": label,
+                "marks": [{ "type": "link", "attrs": { "href": href } }]
+            }));
+            plain.push_str(label);
+        } else if !target.is_empty() {
+            nodes.push(serde_json::json!({
+                "type": "text",
+                "text": label,
+                "marks": [{ "type": "link", "attrs": { "href": format!("card://{}", target) } }]
+            }));
+            plain.push_str(label);
+            if !links.iter().any(|l| l == target) {
+                links.push(target.to_string());
+            }
+        }
+        rest = &rest[start + 2 + len + 2..];
+    }
+    push_text(&mut nodes, rest);
+    plain.push_str(rest);
+
+    (nodes, plain)
+}
+
+/// 根据所在目录推断卡片类型：先认 Zentri 自己的目录（如 10_Literature/），
+/// 再按常见的 Obsidian 目录名猜测（Inbox、References、Zettelkasten、Projects 等），最近的目录优先
+pub fn infer_card_type(relative_path: &str) -> CardType {
+    let dirs: Vec<&str> = relative_path.split('/').collect();
+    for dir in dirs.iter().rev().skip(1) {
+        match *dir {
+            "00_Inbox" => return CardType::Fleeting,
+            "10_Literature" => return CardType::Literature,
+            "20_Slipbox" => return CardType::Permanent,
+            "30_Projects" => return CardType::Project,
+            _ => {}
+        }
+    }
+
+    const HINTS: &[(&[&str], CardType)] = &[
+        (&["inbox", "fleeting", "daily", "journal", "scratch"], CardType::Fleeting),
+        (&["literature", "reference", "source", "reading", "book", "clipping"], CardType::Literature),
+        (&["zettel", "permanent", "slipbox", "evergreen", "concept"], CardType::Permanent),
+        (&["project"], CardType::Project),
+    ];
+    for dir in dirs.iter().rev().skip(1) {
+        let dir = dir.to_lowercase();
+        for (keywords, card_type) in HINTS {
+            if keywords.iter().any(|k| dir.contains(k)) {
+                return card_type.clone();
+            }
+        }
+    }
+    CardType::Fleeting
+}
+
+/// 解析 frontmatter 中的时间（RFC 3339 或 YYYY-MM-DD）
+fn parse_timestamp(value: &str) -> Option<i64> {
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(value.trim()) {
+        return Some(dt.timestamp_millis());
+    }
+    chrono::NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|dt| dt.and_utc().timestamp_millis())
+}
+
+// -----------------------------------------------------------------------------
+// Markdown Export
+// -----------------------------------------------------------------------------
+// 导出为 `<类型目录>/<id>.md`，与 Obsidian 导入的约定一致，导出的目录可以再导入
+
+/// 将卡片导出到目标目录，返回导出的数量
+pub fn export_markdown_cards(dest: &Path, cards: &[Card]) -> Result<usize, String> {
+    for card in cards {
+        let relative = Path::new(&card.generate_path())
+            .strip_prefix("cards")
+            .map(|p| p.with_extension("md"))
+            .map_err(|e| e.to_string())?;
+        let path = dest.join(relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        fs::write(&path, card_to_markdown(card)).map_err(|e| format!("{}: {}", card.id, e))?;
+    }
+    Ok(cards.len())
+}
+
+/// 将卡片转换为带 YAML frontmatter 的 Markdown
+pub fn card_to_markdown(card: &Card) -> String {
+    let format_time = |ms: i64| chrono::DateTime::from_timestamp_millis(ms).map(|dt| dt.to_rfc3339());
+    let frontmatter = Frontmatter {
+        title: Some(card.title.clone()),
+        tags: card.tags.clone(),
+        card_type: Some(card.card_type.as_str().to_string()),
+        aliases: card.aliases.clone(),
+        created: format_time(card.created_at),
+        modified: format_time(card.modified_at),
+        source_id: card.source_id.clone(),
+    };
+    let yaml = serde_yaml::to_string(&frontmatter).unwrap_or_default();
+    // 非 JSON 的正文（早期的纯文本卡片）原样输出
+    let body = match serde_json::from_str::<serde_json::Value>(&card.content) {
+        Ok(doc) => blocks_markdown(node_children(&doc)).join("\n\n"),
+        Err(_) => card.content.clone(),
+    };
+    format!("---\n{}---\n\n{}\n", yaml, body)
+}
+
+fn node_type(node: &serde_json::Value) -> &str {
+    node.get("type").and_then(|t| t.as_str()).unwrap_or("")
+}
+
+fn node_attr<'a>(node: &'a serde_json::Value, name: &str) -> Option<&'a serde_json::Value> {
+    node.get("attrs").and_then(|attrs| attrs.get(name))
+}
+
+fn node_children(node: &serde_json::Value) -> &[serde_json::Value] {
+    node.get("content").and_then(|c| c.as_array()).map(Vec::as_slice).unwrap_or(&[])
+}
+
+fn blocks_markdown(nodes: &[serde_json::Value]) -> Vec<String> {
+    nodes.iter().map(block_markdown).filter(|b| !b.is_empty()).collect()
+}
+
+/// 转换块级节点；不认识的节点只输出其中的文本
+fn block_markdown(node: &serde_json::Value) -> String {
+    let children = node_children(node);
+    match node_type(node) {
+        "paragraph" => inline_markdown(children),
+        "heading" => {
+            let level = node_attr(node, "level").and_then(|l| l.as_u64()).unwrap_or(1).clamp(1, 6);
+            format!("{} {}", "#".repeat(level as usize), inline_markdown(children))
+        }
+        "bulletList" | "orderedList" | "taskList" => list_markdown(node),
+        "blockquote" => prefix_lines(&blocks_markdown(children).join("\n\n"), "> ", "> "),
+        "codeBlock" => {
+            let language = node_attr(node, "language").and_then(|l| l.as_str()).unwrap_or("");
+            let mut code = String::new();
+            crate::db::extract_text_recursive(node, &mut code);
+            format!("```{}\n{}\n```", language, code)
+        }
+        "horizontalRule" => "---".to_string(),
+        _ => {
+            let mut text = String::new();
+            crate::db::extract_text_recursive(node, &mut text);
+            text
+        }
+    }
+}
+
+/// 转换列表，嵌套列表按列表标记的宽度缩进
+fn list_markdown(node: &serde_json::Value) -> String {
+    let start = node_attr(node, "start").and_then(|s| s.as_u64()).unwrap_or(1);
+    node_children(node)
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let marker = match node_type(node) {
+                "orderedList" => format!("{}. ", start + i as u64),
+                "taskList" if node_attr(item, "checked").and_then(|c| c.as_bool()).unwrap_or(false) => {
+                    "- [x] ".to_string()
+                }
+                "taskList" => "- [ ] ".to_string(),
+                _ => "- ".to_string(),
+            };
+            let body = blocks_markdown(node_children(item)).join("\n");
+            prefix_lines(&body, &marker, &" ".repeat(marker.len()))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 第一行加 `first` 前缀，其余行加 `rest` 前缀（空行不留尾随空格）
+fn prefix_lines(text: &str, first: &str, rest: &str) -> String {
+    text.split('\n')
+        .enumerate()
+        .map(|(i, line)| {
+            let prefix = if i == 0 { first } else { rest };
+            if line.is_empty() {
+                prefix.trim_end().to_string()
+            } else {
+                format!("{}{}", prefix, line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 转换行内节点：卡片链接写成 `[[标题]]`，其他链接写成 `[文本](地址)`
+fn inline_markdown(nodes: &[serde_json::Value]) -> String {
+    let mut out = String::new();
+    for node in nodes {
+        match node_type(node) {
+            "text" => out.push_str(&text_markdown(node)),
+            "wikiLink" => {
+                let title = node_attr(node, "title")
+                    .and_then(|t| t.as_str())
+                    .filter(|t| !t.is_empty())
+                    .or_else(|| node_attr(node, "href").and_then(|h| h.as_str()))
+                    .unwrap_or("");
+                out.push_str(&format!("[[{}]]", title));
+            }
+            "hardBreak" => out.push_str("  \n"),
+            "image" => {
+                let alt = node_attr(node, "alt").and_then(|a| a.as_str()).unwrap_or("");
+                let src = node_attr(node, "src").and_then(|s| s.as_str()).unwrap_or("");
+                out.push_str(&format!("![{}]({})", alt, src));
+            }
+            _ => crate::db::extract_text_recursive(node, &mut out),
+        }
+    }
+    out
+}
+
+fn text_markdown(node: &serde_json::Value) -> String {
+    let mut text = node.get("text").and_then(|t| t.as_str()).unwrap_or("").to_string();
+    let marks = node.get("marks").and_then(|m| m.as_array()).map(Vec::as_slice).unwrap_or(&[]);
+    for mark in marks {
+        text = match node_type(mark) {
+            "bold" => format!("**{}**", text),
+            "italic" => format!("*{}*", text),
+            "strike" => format!("~~{}~~", text),
+            "code" => format!("`{}`", text),
+            _ => text,
+        };
+    }
+    // 链接包在最外层
+    let href = marks
+        .iter()
+        .find(|mark| node_type(mark) == "link")
+        .and_then(|mark| node_attr(mark, "href"))
+        .and_then(|h| h.as_str());
+    match href {
+        Some(href) => match href.strip_prefix("card://") {
+            Some(target) if target == text => format!("[[{}]]", target),
+            Some(target) => format!("[[{}|{}]]", target, text),
+            None => format!("[{}]({})", text, href),
+        },
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown_card_cache_reparses_modified_files() {
+        let vault = tempfile::tempdir().unwrap();
+        let path = vault.path().join("20_Slipbox").join("note.md");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "# One").unwrap();
+        fs::create_dir_all(vault.path().join("sources")).unwrap();
+        fs::write(vault.path().join("sources").join("book.md"), "# Book").unwrap();
+
+        let mut cache = MarkdownCardCache::default();
+        let cards = cache.cards(vault.path());
+        assert_eq!(cards.len(), 1);
+        assert_eq!(cards[0].title, "One");
+
+        // 修改时间变化后重新解析
+        fs::write(&path, "# Two").unwrap();
+        let later = std::time::SystemTime::now() + std::time::Duration::from_secs(10);
+        fs::File::options().write(true).open(&path).unwrap().set_modified(later).unwrap();
+        assert_eq!(cache.card(vault.path(), "note").unwrap().title, "Two");
+
+        fs::remove_file(&path).unwrap();
+        assert!(cache.card(vault.path(), "note").is_none());
+    }
+
+    #[test]
+    fn test_parse_markdown_card() {
+        let text = "---\ntitle: Entropy\ntags: [physics]\naliases: [Disorder]\ncreated: 2024-01-02\n---\n\n# Heading\n\nSee [[thermo|Thermodynamics]] and [[gibbs]].";
+        let card = parse_markdown_card("20_Slipbox/entropy.md", text, 42, &HashMap::new());
+
+        assert_eq!(card.id, "entropy");
+        assert_eq!(card.title, "Entropy");
+        assert_eq!(card.card_type, CardType::Permanent);
+        assert_eq!(card.tags, vec!["physics"]);
+        assert_eq!(card.aliases, vec!["Disorder"]);
+        assert_eq!(card.links, vec!["thermo", "gibbs"]);
+        assert_eq!(card.modified_at, 42);
+        assert_ne!(card.created_at, 42);
+        assert_eq!(card.plain_text, "Heading\nSee Thermodynamics and gibbs.");
+
+        let json: serde_json::Value = serde_json::from_str(&card.content).unwrap();
+        assert_eq!(json["content"][0]["type"], "heading");
+        assert_eq!(json["content"][1]["content"][1]["marks"][0]["attrs"]["href"], "card://thermo");
+    }
+
+    #[test]
+    fn test_parse_markdown_lists() {
+        let text = "Steps:\n\n1. Read [[paper]]\n2. Take notes\n\n- one\n  - nested\n* two";
+        let card = parse_markdown_card("ideas.md", text, 0, &HashMap::new());
+        assert_eq!(card.links, vec!["paper"]);
+        assert_eq!(card.plain_text, "Steps:\nRead paper\nTake notes\none\nnested\ntwo");
+
+        let json: serde_json::Value = serde_json::from_str(&card.content).unwrap();
+        assert_eq!(json["content"][0]["type"], "paragraph");
+        assert_eq!(json["content"][1]["type"], "orderedList");
+        assert_eq!(json["content"][1]["content"].as_array().unwrap().len(), 2);
+        assert_eq!(json["content"][1]["content"][0]["content"][0]["content"][1]["marks"][0]["attrs"]["href"], "card://paper");
+        assert_eq!(json["content"][2]["type"], "bulletList");
+        assert_eq!(json["content"][2]["content"].as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_markdown_without_frontmatter_uses_heading_title() {
+        let card = parse_markdown_card("notes/idea.md", "# Big Idea\n\nbody", 0, &HashMap::new());
+        assert_eq!(card.title, "Big Idea");
+        assert_eq!(card.card_type, CardType::Fleeting);
+    }
+
+    #[test]
+    fn test_card_to_markdown() {
+        let content = serde_json::json!({
+            "type": "doc",
+            "content": [
+                {"type": "heading", "attrs": {"level": 2}, "content": [{"type": "text", "text": "Entropy"}]},
+                {"type": "bulletList", "content": [
+                    {"type": "listItem", "content": [
+                        {"type": "paragraph", "content": [
+                            {"type": "text", "text": "See "},
+                            {"type": "wikiLink", "attrs": {"href": "thermo", "title": "Thermodynamics"}}
+                        ]},
+                        {"type": "orderedList", "content": [
+                            {"type": "listItem", "content": [
+                                {"type": "paragraph", "content": [{"type": "text", "text": "bold", "marks": [{"type": "bold"}]}]}
+                            ]}
+                        ]}
+                    ]},
+                    {"type": "listItem", "content": [
+                        {"type": "paragraph", "content": [{"type": "text", "text": "gibbs", "marks": [{"type": "link", "attrs": {"href": "card://gibbs"}}]}]}
+                    ]}
+                ]},
+                {"type": "callout", "content": [{"type": "paragraph", "content": [{"type": "text", "text": "kept as text"}]}]}
+            ]
+        });
+        let mut card = parse_markdown_card("20_Slipbox/entropy.md", "", 0, &HashMap::new());
+        card.title = "Entropy".to_string();
+        card.tags = vec!["physics".to_string()];
+        card.aliases = vec!["Disorder".to_string()];
+        card.content = content.to_string();
+
+        let markdown = card_to_markdown(&card);
+        let (frontmatter, body) = split_frontmatter(&markdown);
+        assert_eq!(frontmatter.title.as_deref(), Some("Entropy"));
+        assert_eq!(frontmatter.card_type.as_deref(), Some("permanent"));
+        assert_eq!(frontmatter.tags, vec!["physics"]);
+        assert_eq!(frontmatter.aliases, vec!["Disorder"]);
+        assert!(frontmatter.source_id.is_none());
+        assert_eq!(
+            body,
+            "## Entropy\n\n- See [[Thermodynamics]]\n  1. **bold**\n- [[gibbs]]\n\nkept as text\n"
+        );
+    }
+
+    #[test]
+    fn test_render_template_placeholders() {
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+        let template = serde_json::json!({
+            "type": "doc",
+            "content": [
+                {"type": "heading", "content": [{"type": "text", "text": "{{ title }} — {{date}}"}]},
+                {"type": "paragraph", "content": [{"type": "text", "text": "{{date:%Y/%m/%d}} {{date:%Q}} {{unknown}} {{open"}]}
+            ]
+        });
+        let rendered = render_template(&template, "Weekly Sync", date);
+        assert_eq!(rendered["content"][0]["content"][0]["text"], "Weekly Sync — 2024-03-05");
+        assert_eq!(rendered["content"][1]["content"][0]["text"], "2024/03/05 {{date:%Q}} {{unknown}} {{open");
+        assert_eq!(rendered["type"], "doc");
+    }
+
+    #[test]
+    fn test_user_template_overrides_builtin() {
+        let vault = tempfile::tempdir().unwrap();
+        assert!(read_template(vault.path(), "meeting").is_some());
+        assert!(read_template(vault.path(), "../meeting").is_none());
+
+        let dir = vault.path().join(".zentri").join(DIR_TEMPLATES);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("meeting.json"), r#"{"type":"doc","content":[]}"#).unwrap();
+        fs::write(dir.join("recipe.json"), r#"{"type":"doc","content":[]}"#).unwrap();
+
+        assert_eq!(read_template(vault.path(), "meeting").unwrap()["content"], serde_json::json!([]));
+        let templates = list_templates(vault.path());
+        let ids: Vec<&str> = templates.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, vec![DAILY_TEMPLATE_ID, "meeting", "book", "zettel", "recipe"]);
+        assert!(!templates[1].builtin);
+        assert!(templates[0].builtin);
+    }
+
+    #[test]
+    fn test_infer_card_type_from_obsidian_folders() {
+        assert_eq!(infer_card_type("Zettelkasten/entropy.md"), CardType::Permanent);
+        assert_eq!(infer_card_type("References/Books/dune.md"), CardType::Literature);
+        assert_eq!(infer_card_type("Projects/Active/plan.md"), CardType::Project);
+        assert_eq!(infer_card_type("Daily Notes/2024-01-01.md"), CardType::Fleeting);
+        assert_eq!(infer_card_type("idea.md"), CardType::Fleeting);
+        // 最近的目录优先
+        assert_eq!(infer_card_type("Projects/References/paper.md"), CardType::Literature);
+    }
+
+    #[test]
+    fn test_import_obsidian_vault_files() {
+        let src = tempfile::tempdir().unwrap();
+        let vault = tempfile::tempdir().unwrap();
+        fs::create_dir_all(src.path().join(".obsidian")).unwrap();
+        fs::write(src.path().join(".obsidian/app.json"), "{}").unwrap();
+        fs::create_dir_all(src.path().join("Zettelkasten")).unwrap();
+        fs::write(
+            src.path().join("Zettelkasten/entropy.md"),
+            "See [[gibbs]].\n\n![[diagram.png]]",
+        )
+        .unwrap();
+        fs::create_dir_all(src.path().join("assets")).unwrap();
+        fs::write(src.path().join("assets/diagram.png"), b"png").unwrap();
+        // 不同目录下的同名附件，笔记所在目录优先
+        fs::write(src.path().join("Zettelkasten/diagram.png"), b"local").unwrap();
+        fs::create_dir_all(vault.path().join("attachments/images")).unwrap();
+        fs::write(vault.path().join("attachments/images/diagram.png"), b"existing").unwrap();
+
+        let scan = scan_obsidian_vault(src.path());
+        assert_eq!(scan.notes, vec!["Zettelkasten/entropy.md"]);
+        assert_eq!(scan.attachments, vec!["Zettelkasten/diagram.png", "assets/diagram.png"]);
+
+        let copied = copy_obsidian_attachments(src.path(), vault.path(), &scan.attachments).unwrap();
+        assert_eq!(copied.copied, 2);
+        assert_eq!(copied.paths["Zettelkasten/diagram.png"], "attachments/images/diagram-1.png");
+        assert_eq!(copied.paths["assets/diagram.png"], "attachments/images/diagram-2.png");
+        assert_eq!(fs::read(vault.path().join("attachments/images/diagram-1.png")).unwrap(), b"local");
+        assert_eq!(fs::read(vault.path().join("attachments/images/diagram-2.png")).unwrap(), b"png");
+
+        // 重复导入复用已复制的文件
+        let again = copy_obsidian_attachments(src.path(), vault.path(), &scan.attachments).unwrap();
+        assert_eq!(again.copied, 0);
+        assert_eq!(again.paths, copied.paths);
+        assert!(!vault.path().join("attachments/images/diagram-3.png").exists());
+
+        let card = read_obsidian_note(src.path(), &scan.notes[0], &copied.paths).unwrap();
+        assert_eq!(card.id, "entropy");
+        assert_eq!(card.card_type, CardType::Permanent);
+        assert_eq!(card.links, vec!["gibbs"]);
+        let json: serde_json::Value = serde_json::from_str(&card.content).unwrap();
+        let embed = &json["content"][1]["content"][0];
+        assert_eq!(embed["text"], "diagram.png");
+        assert_eq!(embed["marks"][0]["attrs"]["href"], "attachments/images/diagram-1.png");
+        assert_eq!(
+            resolve_attachment(&copied.paths, "Zettelkasten/entropy.md", "assets/diagram.png"),
+            Some("attachments/images/diagram-2.png")
+        );
+        assert_eq!(
+            resolve_attachment(&copied.paths, "index.md", "diagram.png"),
+            Some("attachments/images/diagram-1.png")
+        );
+    }
+}
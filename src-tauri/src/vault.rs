@@ -13,7 +13,10 @@ pub struct VaultLock {
 
 impl VaultLock {
     /// 尝试获取 vault 锁
-    pub fn try_lock(vault_path: &Path) -> Result<Self, String> {
+    ///
+    /// 锁文件已存在时读取其中的 PID：该进程已不在运行（上次异常退出）则清理后重试一次。
+    /// `force` 为 true 时无论持有者是否存活都强制接管（界面上的"回收锁"）
+    pub fn try_lock(vault_path: &Path, force: bool) -> Result<Self, String> {
         let lock_file = vault_path.join(".zentri").join("lock");
         
         // 确保 .zentri 目录存在
@@ -22,19 +25,23 @@ impl VaultLock {
         }
 
         // 尝试创建锁文件（独占模式）
-        let file = fs::OpenOptions::new()
-            .write(true)
-            .create_new(true)
-            .open(&lock_file)
-            .map_err(|e| {
-                if e.kind() == io::ErrorKind::AlreadyExists {
-                    format!("Vault is already locked. Another instance may be accessing this vault.")
-                } else {
-                    format!("Failed to create lock file: {}", e)
-                }
-            })?;
-
-        // 写入进程 ID 到锁文件（用于调试）
+        let file = match Self::create_lock_file(&lock_file) {
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists && (force || Self::is_stale(&lock_file)) => {
+                fs::remove_file(&lock_file)
+                    .map_err(|e| format!("Failed to remove stale lock file: {}", e))?;
+                Self::create_lock_file(&lock_file)
+            }
+            result => result,
+        }
+        .map_err(|e| {
+            if e.kind() == io::ErrorKind::AlreadyExists {
+                "Vault is already locked. Another instance may be accessing this vault.".to_string()
+            } else {
+                format!("Failed to create lock file: {}", e)
+            }
+        })?;
+
+        // 写入进程 ID 到锁文件（用于判断锁是否失效）
         let pid = std::process::id();
         writeln!(&file, "{}", pid).map_err(|e| format!("Failed to write to lock file: {}", e))?;
 
@@ -44,6 +51,25 @@ impl VaultLock {
         })
     }
 
+    fn create_lock_file(lock_file: &Path) -> io::Result<fs::File> {
+        fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(lock_file)
+    }
+
+    /// 锁文件中记录的进程已不在运行；读不出 PID（如写入前崩溃）也视为失效
+    fn is_stale(lock_file: &Path) -> bool {
+        match fs::read_to_string(lock_file) {
+            Ok(content) => match content.trim().parse::<u32>() {
+                Ok(pid) => !process_alive(pid),
+                Err(_) => true,
+            },
+            // 读取失败时保守处理，不删除别人的锁
+            Err(_) => false,
+        }
+    }
+
     /// 检查锁是否存在（不获取锁）
     pub fn is_locked(vault_path: &Path) -> bool {
         let lock_file = vault_path.join(".zentri").join("lock");
@@ -61,6 +87,39 @@ impl VaultLock {
     }
 }
 
+/// 检查进程是否仍在运行；无法判断时按仍在运行处理
+fn process_alive(pid: u32) -> bool {
+    if pid == std::process::id() {
+        return true;
+    }
+
+    #[cfg(unix)]
+    {
+        // kill -0 只检查进程是否存在，不发送信号
+        std::process::Command::new("kill")
+            .args(["-0", &pid.to_string()])
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(true)
+    }
+
+    #[cfg(windows)]
+    {
+        std::process::Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {}", pid), "/NH", "/FO", "CSV"])
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).contains(&format!("\"{}\"", pid)))
+            .unwrap_or(true)
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    {
+        true
+    }
+}
+
 impl Drop for VaultLock {
     fn drop(&mut self) {
         // 自动清理锁文件
@@ -80,6 +139,19 @@ pub fn copy_migrations_to_vault(vault_path: &Path) -> Result<(), String> {
         ("002_add_highlight_type.sql", include_str!("../migrations/002_add_highlight_type.sql")),
         ("003_add_vectors.sql", include_str!("../migrations/003_add_vectors.sql")),
         ("004_add_cards.sql", include_str!("../migrations/004_add_cards.sql")),
+        ("005_add_source_deleted_at.sql", include_str!("../migrations/005_add_source_deleted_at.sql")),
+        ("006_add_card_pinned.sql", include_str!("../migrations/006_add_card_pinned.sql")),
+        ("007_add_source_index_meta.sql", include_str!("../migrations/007_add_source_index_meta.sql")),
+        ("008_add_card_word_count.sql", include_str!("../migrations/008_add_card_word_count.sql")),
+        ("009_add_chat_sessions.sql", include_str!("../migrations/009_add_chat_sessions.sql")),
+        ("010_add_card_archived.sql", include_str!("../migrations/010_add_card_archived.sql")),
+        ("011_add_source_origin.sql", include_str!("../migrations/011_add_source_origin.sql")),
+        ("012_add_card_review.sql", include_str!("../migrations/012_add_card_review.sql")),
+        ("013_add_card_encrypted.sql", include_str!("../migrations/013_add_card_encrypted.sql")),
+        ("014_add_highlights_fts.sql", include_str!("../migrations/014_add_highlights_fts.sql")),
+        ("015_add_card_deleted_at.sql", include_str!("../migrations/015_add_card_deleted_at.sql")),
+        ("016_add_web_snapshot_published_at.sql", include_str!("../migrations/016_add_web_snapshot_published_at.sql")),
+        ("017_add_web_snapshot_reading_time.sql", include_str!("../migrations/017_add_web_snapshot_reading_time.sql")),
     ];
 
     for (filename, content) in migrations_content.iter() {
@@ -101,3 +173,116 @@ pub fn get_config_path(vault_path: &Path) -> PathBuf {
     vault_path.join(".zentri").join("config.json")
 }
 
+/// 读取 vault 配置中 `settings` 下的某项设置，配置不存在或无法解析时返回 None
+pub fn read_setting(vault_path: &Path, key: &str) -> Option<serde_json::Value> {
+    let content = fs::read_to_string(get_config_path(vault_path)).ok()?;
+    let config: serde_json::Value = serde_json::from_str(&content).ok()?;
+    config.get("settings")?.get(key).cloned()
+}
+
+/// 写入 vault 配置中 `settings` 下的某项设置，保留其余配置项
+pub fn write_setting(vault_path: &Path, key: &str, value: serde_json::Value) -> Result<(), String> {
+    let path = get_config_path(vault_path);
+    let mut config = fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .filter(|config| config.is_object())
+        .unwrap_or_else(|| serde_json::json!({}));
+    let settings = config
+        .as_object_mut()
+        .unwrap()
+        .entry("settings")
+        .or_insert_with(|| serde_json::json!({}));
+    if !settings.is_object() {
+        *settings = serde_json::json!({});
+    }
+    settings.as_object_mut().unwrap().insert(key.to_string(), value);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let content = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+    fs::write(&path, content).map_err(|e| format!("Failed to write vault config: {}", e))
+}
+
+/// 检查目录是否已经是 vault
+pub fn is_vault(path: &Path) -> bool {
+    path.join(".zentri").is_dir() || get_database_path(path).exists()
+}
+
+/// 在空目录中初始化新 vault：创建标准目录结构、默认配置和迁移文件
+pub fn initialize_vault(vault_path: &Path) -> Result<(), String> {
+    if is_vault(vault_path) {
+        return Err(format!("Folder already contains a vault: {}", vault_path.display()));
+    }
+
+    crate::storage::ensure_vault_structure(vault_path)
+        .map_err(|e| format!("Failed to create vault structure: {}", e))?;
+
+    let config = serde_json::json!({
+        "version": 1,
+        "dataVersion": "2.0.0",
+        "createdAt": crate::storage::current_timestamp(),
+        "settings": {
+            "defaultCardType": "fleeting",
+            "autoSaveInterval": 5000
+        }
+    });
+    let content = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+    fs::write(get_config_path(vault_path), content)
+        .map_err(|e| format!("Failed to write vault config: {}", e))?;
+
+    copy_migrations_to_vault(vault_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_initialize_vault_refuses_existing() {
+        let dir = tempdir().unwrap();
+        assert!(!is_vault(dir.path()));
+
+        initialize_vault(dir.path()).unwrap();
+        assert!(dir.path().join("sources/epub").is_dir());
+        assert!(dir.path().join("attachments/images").is_dir());
+        assert!(dir.path().join(".zentri/templates").is_dir());
+        assert!(get_config_path(dir.path()).exists());
+        assert!(dir.path().join(".zentri/migrations/004_add_cards.sql").exists());
+
+        assert!(initialize_vault(dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_lock_held_by_live_process() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join(".zentri")).unwrap();
+        fs::write(dir.path().join(".zentri/lock"), format!("{}\n", std::process::id())).unwrap();
+
+        assert!(VaultLock::try_lock(dir.path(), false).is_err());
+        let lock = VaultLock::try_lock(dir.path(), true).unwrap();
+        drop(lock);
+        assert!(!VaultLock::is_locked(dir.path()));
+    }
+
+    #[test]
+    fn test_stale_lock_is_reclaimed() {
+        let dir = tempdir().unwrap();
+        // 启动一个立即退出的子进程，取其 PID 作为已失效的持有者
+        let mut child = std::process::Command::new(std::env::current_exe().unwrap())
+            .arg("--list")
+            .stdout(std::process::Stdio::null())
+            .spawn()
+            .unwrap();
+        let dead_pid = child.id();
+        child.wait().unwrap();
+        fs::create_dir_all(dir.path().join(".zentri")).unwrap();
+        fs::write(dir.path().join(".zentri/lock"), format!("{}\n", dead_pid)).unwrap();
+
+        let _lock = VaultLock::try_lock(dir.path(), false).unwrap();
+        let content = fs::read_to_string(dir.path().join(".zentri/lock")).unwrap();
+        assert_eq!(content.trim(), std::process::id().to_string());
+    }
+}
@@ -4,6 +4,7 @@
 use notify::event::{CreateKind, ModifyKind, RemoveKind, RenameMode};
 use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use notify_debouncer_mini::{new_debouncer, DebouncedEvent, Debouncer};
+use serde::Serialize;
 use std::path::{Path, PathBuf};
 use std::sync::mpsc::{channel, Receiver};
 use std::time::Duration;
@@ -19,16 +20,116 @@ pub enum FileChange {
     Renamed(PathBuf, PathBuf),
 }
 
+/// 一轮轮询的索引变更：同一 ID 多次变化时以最后一次为准
+#[derive(Debug, Default, PartialEq)]
+pub struct ChangeBatch {
+    /// 需要重新索引的 ID
+    pub upserted: Vec<String>,
+    /// 需要从索引删除的 ID
+    pub removed: Vec<String>,
+}
+
+impl ChangeBatch {
+    fn upsert(&mut self, id: &str) {
+        self.removed.retain(|r| r != id);
+        if !self.upserted.iter().any(|u| u == id) {
+            self.upserted.push(id.to_string());
+        }
+    }
+
+    fn remove(&mut self, id: &str) {
+        self.upserted.retain(|u| u != id);
+        if !self.removed.iter().any(|r| r == id) {
+            self.removed.push(id.to_string());
+        }
+    }
+}
+
+/// 将文件变更按文件名（卡片 ID）合并为一批索引操作
+pub fn batch_changes(changes: &[FileChange]) -> ChangeBatch {
+    let id_of = |path: &Path| path.file_stem().and_then(|s| s.to_str()).map(String::from);
+    let mut batch = ChangeBatch::default();
+    for change in changes {
+        match change {
+            FileChange::Modified(path) => {
+                if let Some(id) = id_of(path) {
+                    batch.upsert(&id);
+                }
+            }
+            FileChange::Removed(path) => {
+                if let Some(id) = id_of(path) {
+                    batch.remove(&id);
+                }
+            }
+            FileChange::Renamed(old_path, new_path) => {
+                if let Some(id) = id_of(old_path) {
+                    batch.remove(&id);
+                }
+                if let Some(id) = id_of(new_path) {
+                    batch.upsert(&id);
+                }
+            }
+        }
+    }
+    batch
+}
+
+/// 文件监听器运行状态
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatcherStatus {
+    /// 监听器是否在运行
+    pub active: bool,
+    /// 最近一次错误
+    pub last_error: Option<String>,
+}
+
+impl WatcherStatus {
+    /// 正常运行
+    pub fn running() -> Self {
+        Self { active: true, last_error: None }
+    }
+
+    /// 初始化失败
+    pub fn failed(error: String) -> Self {
+        Self { active: false, last_error: Some(error) }
+    }
+}
+
+/// 默认监听的文件扩展名：旧版 Markdown 卡片和 JSON 卡片
+pub const DEFAULT_EXTENSIONS: &[&str] = &["md", "json"];
+
+/// 是否为需要处理的文件：扩展名在监听列表中，且不是原子写入时的临时文件（如 `.json.tmp`）
+fn is_watched_file(path: &Path, extensions: &[String]) -> bool {
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return false;
+    };
+    if name.ends_with(".tmp") {
+        return false;
+    }
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| extensions.iter().any(|x| x.eq_ignore_ascii_case(e)))
+        .unwrap_or(false)
+}
+
 /// 文件监听器
 pub struct VaultWatcher {
     _watcher: RecommendedWatcher,
     receiver: Receiver<Result<Event, notify::Error>>,
     vault_path: PathBuf,
+    /// 监听的文件扩展名（不含点）
+    extensions: Vec<String>,
 }
 
 impl VaultWatcher {
-    /// 创建新的文件监听器
+    /// 创建新的文件监听器，监听 `DEFAULT_EXTENSIONS` 中的文件
     pub fn new(vault_path: &Path) -> Result<Self, String> {
+        Self::with_extensions(vault_path, DEFAULT_EXTENSIONS)
+    }
+
+    /// 创建只监听指定扩展名的文件监听器
+    pub fn with_extensions(vault_path: &Path, extensions: &[&str]) -> Result<Self, String> {
         let (tx, rx) = channel();
         
         let mut watcher = RecommendedWatcher::new(
@@ -46,63 +147,56 @@ impl VaultWatcher {
             _watcher: watcher,
             receiver: rx,
             vault_path: vault_path.to_path_buf(),
+            extensions: extensions.iter().map(|e| e.trim_start_matches('.').to_string()).collect(),
         })
     }
     
-    /// 获取待处理的文件变更（非阻塞）
-    pub fn poll_changes(&self) -> Vec<FileChange> {
+    /// 获取待处理的文件变更（非阻塞），同时返回监听过程中出现的错误
+    pub fn poll_changes(&self) -> (Vec<FileChange>, Vec<String>) {
         let mut changes = Vec::new();
+        let mut errors = Vec::new();
         
         // 非阻塞地获取所有待处理的事件
         while let Ok(result) = self.receiver.try_recv() {
-            if let Ok(event) = result {
-                if let Some(change) = self.process_event(event) {
-                    changes.push(change);
+            match result {
+                Ok(event) => {
+                    if let Some(change) = self.process_event(event) {
+                        changes.push(change);
+                    }
                 }
+                Err(e) => errors.push(e.to_string()),
             }
         }
         
         // 去重：对于同一文件的多次修改，只保留一次
-        self.deduplicate_changes(changes)
+        (self.deduplicate_changes(changes), errors)
     }
     
     /// 处理单个事件
     fn process_event(&self, event: Event) -> Option<FileChange> {
-        // 只处理 .md 文件
-        let paths: Vec<_> = event.paths.iter()
-            .filter(|p| {
-                p.extension().map(|e| e == "md").unwrap_or(false) &&
-                !self.is_hidden_path(p)
-            })
-            .cloned()
-            .collect();
-        
-        if paths.is_empty() {
-            return None;
+        let accepted = |p: &PathBuf| is_watched_file(p, &self.extensions) && !self.is_hidden_path(p);
+
+        // 重命名需要分别判断新旧路径：临时文件改名为正式文件时只有新路径需要处理
+        if let EventKind::Modify(ModifyKind::Name(RenameMode::Both)) = event.kind {
+            return match (event.paths.first(), event.paths.get(1)) {
+                (Some(from), Some(to)) => match (accepted(from), accepted(to)) {
+                    (true, true) => Some(FileChange::Renamed(from.clone(), to.clone())),
+                    (false, true) => Some(FileChange::Modified(to.clone())),
+                    (true, false) => Some(FileChange::Removed(from.clone())),
+                    (false, false) => None,
+                },
+                _ => None,
+            };
         }
-        
+
+        let path = event.paths.iter().find(|p| accepted(p))?.clone();
         match event.kind {
             EventKind::Create(CreateKind::File) |
             EventKind::Modify(ModifyKind::Data(_)) |
-            EventKind::Modify(ModifyKind::Any) => {
-                paths.first().map(|p| FileChange::Modified(p.clone()))
-            }
-            EventKind::Remove(RemoveKind::File) => {
-                paths.first().map(|p| FileChange::Removed(p.clone()))
-            }
-            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
-                if paths.len() >= 2 {
-                    Some(FileChange::Renamed(paths[0].clone(), paths[1].clone()))
-                } else {
-                    None
-                }
-            }
-            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
-                paths.first().map(|p| FileChange::Removed(p.clone()))
-            }
-            EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
-                paths.first().map(|p| FileChange::Modified(p.clone()))
-            }
+            EventKind::Modify(ModifyKind::Any) |
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)) => Some(FileChange::Modified(path)),
+            EventKind::Remove(RemoveKind::File) |
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => Some(FileChange::Removed(path)),
             _ => None
         }
     }
@@ -189,10 +283,10 @@ impl DebouncedVaultWatcher {
             if let Ok(events) = result {
                 for event in events {
                     let path = event.path;
-                    // 只处理 .md 文件，且不在隐藏目录
-                    if path.extension().map(|e| e == "md").unwrap_or(false) 
-                        && !self.is_hidden_path(&path) 
-                    {
+                    // 只处理监听的文件类型，且不在隐藏目录
+                    let extensions: Vec<String> =
+                        DEFAULT_EXTENSIONS.iter().map(|e| e.to_string()).collect();
+                    if is_watched_file(&path, &extensions) && !self.is_hidden_path(&path) {
                         if !paths.contains(&path) {
                             paths.push(path);
                         }
@@ -238,5 +332,76 @@ mod tests {
         let watcher = VaultWatcher::new(dir.path());
         assert!(watcher.is_ok());
     }
-}
 
+    #[test]
+    fn test_watcher_creation_fails_for_missing_path() {
+        let dir = tempdir().unwrap();
+        let watcher = VaultWatcher::new(&dir.path().join("missing"));
+        assert!(watcher.is_err());
+    }
+
+    #[test]
+    fn test_json_card_produces_modified_change() {
+        let dir = tempdir().unwrap();
+        let cards_dir = dir.path().join("cards").join("permanent");
+        fs::create_dir_all(&cards_dir).unwrap();
+        let watcher = VaultWatcher::new(dir.path()).unwrap();
+
+        fs::write(cards_dir.join("note.json.tmp"), "{}").unwrap();
+        fs::write(cards_dir.join("note.json"), "{}").unwrap();
+
+        let mut changes = Vec::new();
+        for _ in 0..50 {
+            std::thread::sleep(Duration::from_millis(100));
+            changes.extend(watcher.poll_changes().0);
+            if !changes.is_empty() {
+                break;
+            }
+        }
+        assert!(changes
+            .iter()
+            .any(|c| matches!(c, FileChange::Modified(p) if p.ends_with("permanent/note.json"))));
+        assert!(!changes.iter().any(|c| matches!(
+            c,
+            FileChange::Modified(p) | FileChange::Removed(p) if p.to_string_lossy().ends_with(".tmp")
+        )));
+    }
+
+    #[test]
+    fn test_temp_file_rename_becomes_modified() {
+        let dir = tempdir().unwrap();
+        let watcher = VaultWatcher::with_extensions(dir.path(), &["json"]).unwrap();
+        let tmp = dir.path().join("cards/a.json.tmp");
+        let card = dir.path().join("cards/a.json");
+
+        let event = Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both)))
+            .add_path(tmp.clone())
+            .add_path(card.clone());
+        assert!(matches!(watcher.process_event(event), Some(FileChange::Modified(p)) if p == card));
+
+        let event = Event::new(EventKind::Create(CreateKind::File)).add_path(tmp);
+        assert!(watcher.process_event(event).is_none());
+        let event = Event::new(EventKind::Create(CreateKind::File))
+            .add_path(dir.path().join("notes/a.md"));
+        assert!(watcher.process_event(event).is_none());
+    }
+
+    #[test]
+    fn test_batch_changes_collapses_repeated_paths() {
+        let changes: Vec<FileChange> = (0..500)
+            .map(|i| FileChange::Modified(PathBuf::from(format!("cards/{}.json", i % 100))))
+            .chain([
+                FileChange::Removed(PathBuf::from("cards/3.json")),
+                FileChange::Renamed(PathBuf::from("cards/4.json"), PathBuf::from("cards/new.json")),
+                FileChange::Modified(PathBuf::from("cards/3.json")),
+            ])
+            .collect();
+
+        let batch = batch_changes(&changes);
+        assert_eq!(batch.upserted.len(), 100);
+        assert!(batch.upserted.contains(&"3".to_string()));
+        assert!(batch.upserted.contains(&"new".to_string()));
+        assert!(!batch.upserted.contains(&"4".to_string()));
+        assert_eq!(batch.removed, vec!["4".to_string()]);
+    }
+}
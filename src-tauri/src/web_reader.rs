@@ -1,9 +1,11 @@
 //! 网页阅读器模块 - 网页抓取与清洗
 //!
-//! 使用 readability 提取网页正文，生成干净的阅读模式内容
+//! 使用 readability 提取网页正文，生成干净的阅读模式内容；
+//! 指向 PDF 的链接会下载原文件并提取纯文本
 
 use serde::{Deserialize, Serialize};
 use std::io::Cursor;
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -17,8 +19,17 @@ pub enum WebReaderError {
     ExtractionFailed,
     #[error("URL 解析失败: {0}")]
     UrlError(#[from] url::ParseError),
+    #[error("文件过大: 超过 {0} 字节上限")]
+    TooLarge(u64),
+    #[error("PDF 解析失败: {0}")]
+    PdfError(String),
+    #[error("读取响应失败: {0}")]
+    IoError(#[from] std::io::Error),
 }
 
+/// 通过链接下载的 PDF 大小上限
+pub const MAX_PDF_BYTES: u64 = 50 * 1024 * 1024;
+
 /// 网页元数据（用于快速填充表单）
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -43,6 +54,15 @@ pub struct WebSnapshot {
     pub content: String,        // 清洗后的 HTML
     pub text_content: String,   // 纯文本（用于搜索索引）
     pub excerpt: Option<String>,
+    /// 文章发布时间（毫秒），页面未声明时为 None
+    #[serde(default)]
+    pub published_at: Option<i64>,
+    /// 正文词数（中文按分词计）
+    #[serde(default)]
+    pub word_count: usize,
+    /// 按配置的阅读速度估算的阅读时间（分钟）
+    #[serde(default)]
+    pub reading_minutes: usize,
     pub created_at: i64,
 }
 
@@ -57,42 +77,418 @@ pub struct FetchResult {
     pub text_content: String,   // 纯文本
     pub excerpt: Option<String>,
     pub word_count: usize,
+    /// 页面声明的语言（<html lang>）
+    #[serde(default)]
+    pub language: Option<String>,
+    /// 文章发布时间（毫秒）
+    #[serde(default)]
+    pub published_at: Option<i64>,
+}
+
+/// 阅读模式二次精简的力度，级别越高移除的元素越多
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SimplifyLevel {
+    /// 只移除脚本、表单、导航、侧栏等明显不属于正文的结构
+    Light,
+    /// 额外移除分享、订阅、相关推荐、评论等模块
+    Normal,
+    /// 额外移除页眉页脚、按钮、广告位和图片说明
+    Aggressive,
+}
+
+impl SimplifyLevel {
+    /// 当前级别需要移除的元素选择器（包含所有较低级别）
+    fn selectors(self) -> Vec<&'static str> {
+        const LIGHT: &[&str] = &[
+            "script", "style", "noscript", "iframe", "form", "nav", "aside", "[role=navigation]",
+        ];
+        const NORMAL: &[&str] = &[
+            "[class*=share]", "[class*=newsletter]", "[class*=related]", "[class*=subscribe]",
+            "[class*=social]", "[class*=comment]", "[id*=share]", "[id*=newsletter]",
+            "[id*=related]", "[id*=comment]",
+        ];
+        const AGGRESSIVE: &[&str] = &[
+            "header", "footer", "button", "figcaption", "[class*=promo]", "[class*=advert]",
+            "[class*=sidebar]", "[class*=recommend]", "[id*=sidebar]",
+        ];
+
+        let mut selectors = LIGHT.to_vec();
+        if self != SimplifyLevel::Light {
+            selectors.extend_from_slice(NORMAL);
+        }
+        if self == SimplifyLevel::Aggressive {
+            selectors.extend_from_slice(AGGRESSIVE);
+        }
+        selectors
+    }
+}
+
+/// 链接抓取到的文档：网页正文或 PDF 原文件
+pub enum FetchedDocument {
+    Html(FetchResult),
+    Pdf {
+        /// PDF 原始字节，由调用方保存到 sources/pdf
+        bytes: Vec<u8>,
+        /// 从 PDF 提取的文本，title 取自文件名
+        fetch_result: FetchResult,
+    },
 }
 
-/// 抓取并清洗网页内容
-pub fn fetch_and_clean(url: &str) -> Result<FetchResult, WebReaderError> {
+/// 网页快照中下载到本地的图片总大小上限
+pub const MAX_SNAPSHOT_IMAGE_BYTES: u64 = 20 * 1024 * 1024;
+
+/// 抓取网页正文的超时
+const PAGE_TIMEOUT: Duration = Duration::from_secs(30);
+/// 获取元数据只用于表单自动填充，超时更短
+const METADATA_TIMEOUT: Duration = Duration::from_secs(15);
+/// PDF 可能较大，给下载留更多时间
+const DOCUMENT_TIMEOUT: Duration = Duration::from_secs(120);
+
+fn http_client(timeout: Duration) -> Result<reqwest::Client, WebReaderError> {
+    Ok(reqwest::Client::builder()
+        .user_agent("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
+        .timeout(timeout)
+        .build()?)
+}
+
+/// 下载网页 HTML，超时（包括连接和读取响应体）时返回 NetworkError
+async fn fetch_html(url: &str, timeout: Duration) -> Result<String, WebReaderError> {
+    Ok(http_client(timeout)?.get(url).send().await?.text().await?)
+}
+
+/// 抓取并清洗网页内容，指定 simplify 时对正文再做一次精简
+pub async fn fetch_and_clean(url: &str, simplify: Option<SimplifyLevel>) -> Result<FetchResult, WebReaderError> {
     // 解析 URL
     let parsed_url = url::Url::parse(url)?;
     
     // 获取网页 HTML
-    let client = reqwest::blocking::Client::builder()
-        .user_agent("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
-        .timeout(std::time::Duration::from_secs(30))
-        .build()?;
-    
-    let response = client.get(url).send()?;
-    let html = response.text()?;
+    let html = fetch_html(url, PAGE_TIMEOUT).await?;
+    let result = clean_html_blocking(html, parsed_url).await?;
+    Ok(match simplify {
+        Some(level) => simplify_result(result, level),
+        None => result,
+    })
+}
+
+/// 抓取链接，根据 Content-Type（或 .pdf 后缀）区分网页和 PDF
+pub async fn fetch_document(url: &str) -> Result<FetchedDocument, WebReaderError> {
+    let parsed_url = url::Url::parse(url)?;
+
+    let mut response = http_client(DOCUMENT_TIMEOUT)?.get(url).send().await?;
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+
+    if !is_pdf(content_type.as_deref(), &parsed_url) {
+        let html = response.text().await?;
+        return clean_html_blocking(html, parsed_url).await.map(FetchedDocument::Html);
+    }
+
+    if response.content_length().is_some_and(|len| len > MAX_PDF_BYTES) {
+        return Err(WebReaderError::TooLarge(MAX_PDF_BYTES));
+    }
+    // Content-Length 可能缺失或不可信，读取时同样限制大小
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        bytes.extend_from_slice(&chunk);
+        if bytes.len() as u64 > MAX_PDF_BYTES {
+            return Err(WebReaderError::TooLarge(MAX_PDF_BYTES));
+        }
+    }
+
+    // PDF 文本提取是 CPU 密集操作，不占用异步运行时线程
+    let (bytes, text_content) = tokio::task::spawn_blocking(move || {
+        let text = pdf_extract::extract_text_from_mem(&bytes);
+        (bytes, text)
+    })
+    .await
+    .map_err(|e| WebReaderError::PdfError(e.to_string()))?;
+    let text_content = text_content.map_err(|e| WebReaderError::PdfError(e.to_string()))?;
+    let word_count = crate::search::count_segmented_words(&text_content);
+    let excerpt = text_content
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(200)
+        .collect();
+
+    let fetch_result = FetchResult {
+        title: pdf_title(&parsed_url),
+        author: None,
+        site_name: Some(parsed_url.host_str().unwrap_or("").to_string()),
+        content: text_to_html(&text_content),
+        text_content,
+        excerpt: Some(excerpt),
+        word_count,
+        language: None,
+        published_at: None,
+    };
+    Ok(FetchedDocument::Pdf { bytes, fetch_result })
+}
+
+/// 不影响页面内容的跟踪参数（`utm_*` 之外）
+const TRACKING_PARAMS: &[&str] = &["fbclid", "gclid", "dclid", "msclkid", "mc_cid", "mc_eid", "igshid", "yclid"];
+
+/// 规范化网页地址用于去重：去掉片段、跟踪参数（utm_* 等）和路径末尾的斜杠；无法解析时原样返回
+pub fn normalize_url(url: &str) -> String {
+    let Ok(mut parsed) = url::Url::parse(url.trim()) else {
+        return url.trim().to_string();
+    };
+    parsed.set_fragment(None);
+
+    let kept: Vec<(String, String)> = parsed
+        .query_pairs()
+        .filter(|(key, _)| {
+            let key = key.to_lowercase();
+            !key.starts_with("utm_") && !TRACKING_PARAMS.contains(&key.as_str())
+        })
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    if kept.is_empty() {
+        parsed.set_query(None);
+    } else {
+        parsed.query_pairs_mut().clear().extend_pairs(kept);
+    }
+
+    let path = parsed.path().to_string();
+    if path.len() > 1 && path.ends_with('/') {
+        parsed.set_path(path.trim_end_matches('/'));
+    }
+    parsed.to_string()
+}
+
+/// 下载 HTML 中引用的图片到 `dir`，并把 `<img src>` 改写为 `{link_prefix}/文件名`
+///
+/// 相对地址按 `base_url` 解析；data: URI 和非 http(s) 地址保持不变；下载失败的图片保留远程地址；
+/// 图片总大小超过 MAX_SNAPSHOT_IMAGE_BYTES 后不再下载。本地化的图片会移除 srcset，避免浏览器仍加载远程图片
+pub async fn localize_images(
+    html: &str,
+    base_url: &str,
+    dir: &std::path::Path,
+    link_prefix: &str,
+) -> Result<String, WebReaderError> {
+    localize_images_with_limit(html, base_url, dir, link_prefix, MAX_SNAPSHOT_IMAGE_BYTES).await
+}
+
+async fn localize_images_with_limit(
+    html: &str,
+    base_url: &str,
+    dir: &std::path::Path,
+    link_prefix: &str,
+    max_bytes: u64,
+) -> Result<String, WebReaderError> {
+    let base_url = url::Url::parse(base_url)?;
+    // 先按解析器的格式重新序列化，之后才能按属性原文替换
+    let (mut html, sources) = {
+        let fragment = scraper::Html::parse_fragment(html);
+        let selector = scraper::Selector::parse("img[src]").map_err(|e| WebReaderError::ParseError(e.to_string()))?;
+        let mut sources: Vec<String> = Vec::new();
+        for img in fragment.select(&selector) {
+            let src = img.value().attr("src").unwrap_or("").to_string();
+            if !src.trim().is_empty() && !src.starts_with("data:") && !sources.contains(&src) {
+                sources.push(src);
+            }
+        }
+        (fragment.root_element().inner_html(), sources)
+    };
+
+    let client = http_client(PAGE_TIMEOUT)?;
+    let mut total: u64 = 0;
+    let mut localized = 0;
+    for (i, src) in sources.iter().enumerate() {
+        let Ok(url) = base_url.join(src.trim()) else {
+            continue;
+        };
+        if url.scheme() != "http" && url.scheme() != "https" {
+            continue;
+        }
+        let Some((bytes, extension)) = download_image(&client, &url, max_bytes - total).await else {
+            continue;
+        };
+        total += bytes.len() as u64;
+
+        std::fs::create_dir_all(dir)?;
+        let filename = format!("{}.{}", i + 1, extension);
+        std::fs::write(dir.join(&filename), &bytes)?;
+        let escaped = src.replace('&', "&amp;").replace('"', "&quot;").replace('\u{a0}', "&nbsp;");
+        html = html.replace(
+            &format!(r#"src="{}""#, escaped),
+            &format!(r#"src="{}/{}""#, link_prefix, filename),
+        );
+        localized += 1;
+    }
+
+    if localized > 0 {
+        let srcset = regex::Regex::new(r#"\s(?:data-)?srcset="[^"]*""#).expect("valid srcset regex");
+        html = srcset.replace_all(&html, "").into_owned();
+    }
+    Ok(html)
+}
+
+/// 下载单张图片，返回内容和文件扩展名；失败、不是图片或超过剩余额度时返回 None
+async fn download_image(client: &reqwest::Client, url: &url::Url, remaining: u64) -> Option<(Vec<u8>, String)> {
+    let mut response = client.get(url.clone()).send().await.ok()?.error_for_status().ok()?;
+    if response.content_length().is_some_and(|len| len > remaining) {
+        return None;
+    }
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.split(';').next().unwrap_or("").trim().to_lowercase());
+    if content_type.as_deref().is_some_and(|t| !t.starts_with("image/")) {
+        return None;
+    }
+
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await.ok()? {
+        bytes.extend_from_slice(&chunk);
+        if bytes.len() as u64 > remaining {
+            return None;
+        }
+    }
+
+    const EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "svg", "avif", "bmp", "ico"];
+    let from_url = std::path::Path::new(url.path())
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .filter(|e| EXTENSIONS.contains(&e.as_str()));
+    let extension = from_url.unwrap_or_else(|| {
+        match content_type.as_deref() {
+            Some("image/jpeg") => "jpg",
+            Some("image/gif") => "gif",
+            Some("image/webp") => "webp",
+            Some("image/svg+xml") => "svg",
+            Some("image/avif") => "avif",
+            _ => "png",
+        }
+        .to_string()
+    });
+    Some((bytes, extension))
+}
+
+/// readability 基于同步的 Read 接口解析，放到阻塞线程池中执行
+async fn clean_html_blocking(html: String, parsed_url: url::Url) -> Result<FetchResult, WebReaderError> {
+    tokio::task::spawn_blocking(move || clean_html(&html, &parsed_url))
+        .await
+        .map_err(|e| WebReaderError::ParseError(e.to_string()))?
+}
+
+/// 使用 readability 提取正文
+fn clean_html(html: &str, parsed_url: &url::Url) -> Result<FetchResult, WebReaderError> {
+    let language = extract_html_language(html);
+    let (author, published_at) = {
+        let document = scraper::Html::parse_document(html);
+        (extract_author(&document), extract_published_at(&document))
+    };
     
     // 使用 readability 提取正文
     let mut cursor = Cursor::new(html.as_bytes());
-    let extracted = readability::extractor::extract(&mut cursor, &parsed_url)
+    let extracted = readability::extractor::extract(&mut cursor, parsed_url)
         .map_err(|e| WebReaderError::ParseError(e.to_string()))?;
     
     // 提取纯文本用于搜索
     let text_content = extract_text_from_html(&extracted.content);
-    let word_count = text_content.chars().filter(|c| !c.is_whitespace()).count();
+    let word_count = crate::search::count_segmented_words(&text_content);
     
     Ok(FetchResult {
         title: extracted.title,
-        author: None, // readability 不直接提供作者，可以后续用 scraper 提取
+        author,
         site_name: Some(parsed_url.host_str().unwrap_or("").to_string()),
         content: extracted.content,
         text_content,
         excerpt: Some(extracted.text.chars().take(200).collect()),
         word_count,
+        language,
+        published_at,
     })
 }
 
+/// 精简正文后重新计算纯文本和字数
+fn simplify_result(result: FetchResult, level: SimplifyLevel) -> FetchResult {
+    let content = simplify(&result.content, level);
+    let text_content = extract_text_from_html(&content);
+    let word_count = crate::search::count_segmented_words(&text_content);
+    FetchResult {
+        content,
+        text_content,
+        word_count,
+        ..result
+    }
+}
+
+/// 移除 readability 残留的表单、导航、分享栏等杂项，返回精简后的 HTML 片段
+pub fn simplify(html: &str, level: SimplifyLevel) -> String {
+    use scraper::{Html, Selector};
+
+    let mut fragment = Html::parse_fragment(html);
+    let mut junk = Vec::new();
+    for css in level.selectors() {
+        let Ok(selector) = Selector::parse(css) else {
+            continue;
+        };
+        junk.extend(fragment.select(&selector).map(|el| el.id()));
+    }
+
+    // 嵌套的杂项元素可能被重复选中，detach 已脱离的节点是无害的
+    for id in junk {
+        if let Some(mut node) = fragment.tree.get_mut(id) {
+            node.detach();
+        }
+    }
+    fragment.root_element().inner_html()
+}
+
+/// 响应是否为 PDF：优先看 Content-Type，服务器返回通用二进制类型时再看 URL 后缀
+fn is_pdf(content_type: Option<&str>, url: &url::Url) -> bool {
+    let mime = content_type
+        .and_then(|ct| ct.split(';').next())
+        .map(|ct| ct.trim().to_lowercase());
+    match mime.as_deref() {
+        Some("application/pdf") | Some("application/x-pdf") => true,
+        Some(m) if m.starts_with("text/") => false,
+        _ => url.path().to_lowercase().ends_with(".pdf"),
+    }
+}
+
+/// 以 URL 中的文件名作为 PDF 标题
+fn pdf_title(url: &url::Url) -> String {
+    url.path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            let name = name.strip_suffix(".pdf").or_else(|| name.strip_suffix(".PDF")).unwrap_or(name);
+            name.replace(['-', '_'], " ")
+        })
+        .unwrap_or_else(|| url.host_str().unwrap_or("Untitled").to_string())
+}
+
+/// 将纯文本按空行分段，转为阅读模式可用的 HTML
+fn text_to_html(text: &str) -> String {
+    text.split("\n\n")
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(|p| format!("<p>{}</p>", ammonia::clean_text(p)))
+        .collect()
+}
+
+/// 读取 <html lang="..."> 声明的语言
+fn extract_html_language(html: &str) -> Option<String> {
+    use scraper::{Html, Selector};
+
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("html[lang]").ok()?;
+    document
+        .select(&selector)
+        .next()
+        .and_then(|el| el.value().attr("lang"))
+        .and_then(crate::models::SourceMetadata::normalize_language)
+}
+
 /// 从 HTML 中提取纯文本
 fn extract_text_from_html(html: &str) -> String {
     use scraper::{Html, Selector};
@@ -114,58 +510,248 @@ fn extract_text_from_html(html: &str) -> String {
     text_parts.join("\n")
 }
 
-/// 将清洗后的 HTML 转换为简化的 Markdown 格式
+/// 转换 Markdown 时整体跳过的元素
+const MARKDOWN_SKIP_TAGS: &[&str] = &["head", "script", "style", "noscript", "template", "iframe", "svg"];
+
+/// 按块处理的元素，其余元素都按行内内容处理
+const MARKDOWN_BLOCK_TAGS: &[&str] = &[
+    "html", "body", "main", "article", "section", "div", "header", "footer", "nav", "aside", "figure",
+    "figcaption", "table", "thead", "tbody", "tr", "td", "th", "p", "h1", "h2", "h3", "h4", "h5", "h6",
+    "ul", "ol", "li", "blockquote", "pre", "hr", "dl", "dt", "dd",
+];
+
+/// 将清洗后的 HTML 转换为 Markdown
+///
+/// 遍历 DOM 保留标题、段落、（嵌套）有序/无序列表、引用、代码块、粗体/斜体、链接和图片；
+/// 不认识的元素只保留其中的文字，残缺的 HTML 由解析器补全
 pub fn html_to_markdown(html: &str) -> String {
-    use scraper::{Html, Selector};
-    
-    let document = Html::parse_document(html);
-    let mut markdown = String::new();
-    
-    // 简化的转换逻辑
-    let selectors = [
-        ("h1", "# "),
-        ("h2", "## "),
-        ("h3", "### "),
-        ("h4", "#### "),
-        ("p", ""),
-        ("blockquote", "> "),
-        ("li", "- "),
-    ];
-    
-    for (tag, prefix) in selectors {
-        if let Ok(selector) = Selector::parse(tag) {
-            for element in document.select(&selector) {
-                let text: String = element.text().collect();
-                let trimmed = text.trim();
-                if !trimmed.is_empty() {
-                    markdown.push_str(prefix);
-                    markdown.push_str(trimmed);
-                    markdown.push_str("\n\n");
+    let document = scraper::Html::parse_document(html);
+    let mut markdown = markdown_blocks(document.root_element()).join("\n\n");
+    if !markdown.is_empty() {
+        markdown.push('\n');
+    }
+    markdown
+}
+
+/// 转换容器元素的子节点：连续的行内内容合成一段，块级子元素各自成块
+fn markdown_blocks(element: scraper::ElementRef) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut inline = String::new();
+    for child in element.children() {
+        match child.value() {
+            scraper::Node::Text(text) => inline.push_str(&collapse_whitespace(text)),
+            scraper::Node::Element(el) => {
+                let Some(child) = scraper::ElementRef::wrap(child) else {
+                    continue;
+                };
+                if MARKDOWN_SKIP_TAGS.contains(&el.name()) {
+                    continue;
+                }
+                if MARKDOWN_BLOCK_TAGS.contains(&el.name()) {
+                    push_paragraph(&mut blocks, &inline);
+                    inline.clear();
+                    blocks.extend(markdown_block(child));
+                } else {
+                    inline.push_str(&markdown_inline(child));
                 }
             }
+            _ => {}
         }
     }
-    
-    markdown
+    push_paragraph(&mut blocks, &inline);
+    blocks
+}
+
+fn markdown_block(element: scraper::ElementRef) -> Vec<String> {
+    let name = element.value().name();
+    match name {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let text = finish_inline(&markdown_inline_children(element));
+            if text.is_empty() {
+                return Vec::new();
+            }
+            let level = name[1..].parse().unwrap_or(1);
+            vec![format!("{} {}", "#".repeat(level), text.replace("  \n", " "))]
+        }
+        "ul" | "ol" => {
+            let list = markdown_list(element);
+            if list.is_empty() { Vec::new() } else { vec![list] }
+        }
+        "li" => vec![prefix_markdown_lines(&markdown_blocks(element).join("\n"), "- ", "  ")],
+        "blockquote" => {
+            let quote = markdown_blocks(element).join("\n\n");
+            if quote.is_empty() { Vec::new() } else { vec![prefix_markdown_lines(&quote, "> ", "> ")] }
+        }
+        "pre" => {
+            let code: String = element.text().collect();
+            let language = element
+                .children()
+                .filter_map(scraper::ElementRef::wrap)
+                .find(|child| child.value().name() == "code")
+                .and_then(|code| {
+                    code.value()
+                        .classes()
+                        .find_map(|class| class.strip_prefix("language-").or_else(|| class.strip_prefix("lang-")))
+                        .map(str::to_string)
+                })
+                .unwrap_or_default();
+            vec![format!("```{}\n{}\n```", language, code.trim_end_matches('\n'))]
+        }
+        "hr" => vec!["---".to_string()],
+        _ => markdown_blocks(element),
+    }
+}
+
+/// 转换列表；列表项中的段落和嵌套列表按列表标记的宽度缩进
+fn markdown_list(element: scraper::ElementRef) -> String {
+    let ordered = element.value().name() == "ol";
+    let start: usize = element.value().attr("start").and_then(|s| s.trim().parse().ok()).unwrap_or(1);
+    element
+        .children()
+        .filter_map(scraper::ElementRef::wrap)
+        .filter(|child| child.value().name() == "li")
+        .enumerate()
+        .map(|(i, item)| {
+            let marker = if ordered { format!("{}. ", start + i) } else { "- ".to_string() };
+            let body = markdown_blocks(item).join("\n");
+            prefix_markdown_lines(&body, &marker, &" ".repeat(marker.len()))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn markdown_inline(element: scraper::ElementRef) -> String {
+    let inner = || markdown_inline_children(element);
+    match element.value().name() {
+        "strong" | "b" => wrap_inline(&inner(), "**"),
+        "em" | "i" => wrap_inline(&inner(), "*"),
+        "del" | "s" | "strike" => wrap_inline(&inner(), "~~"),
+        "code" => {
+            let code: String = element.text().collect();
+            if code.is_empty() { code } else { format!("`{}`", code) }
+        }
+        "a" => {
+            let text = inner();
+            match element.value().attr("href").map(str::trim) {
+                Some(href) if !href.is_empty() && !href.starts_with("javascript:") => {
+                    let label = text.trim();
+                    let label = if label.is_empty() { href } else { label };
+                    format!("{}[{}]({}){}", leading_space(&text), label, href, trailing_space(&text))
+                }
+                _ => text,
+            }
+        }
+        "img" => match element.value().attr("src") {
+            Some(src) if !src.trim().is_empty() => {
+                format!("![{}]({})", element.value().attr("alt").unwrap_or("").trim(), src.trim())
+            }
+            _ => String::new(),
+        },
+        "br" => "\n".to_string(),
+        name if MARKDOWN_SKIP_TAGS.contains(&name) => String::new(),
+        _ => inner(),
+    }
+}
+
+/// 把元素的所有子节点（包括块级元素）都当作行内内容转换
+fn markdown_inline_children(element: scraper::ElementRef) -> String {
+    let mut out = String::new();
+    for child in element.children() {
+        match child.value() {
+            scraper::Node::Text(text) => out.push_str(&collapse_whitespace(text)),
+            scraper::Node::Element(_) => {
+                if let Some(child) = scraper::ElementRef::wrap(child) {
+                    out.push_str(&markdown_inline(child));
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// 用标记包住文本，首尾空白留在标记外面（`** bold **` 不是合法的 Markdown）
+fn wrap_inline(text: &str, marker: &str) -> String {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return text.to_string();
+    }
+    format!("{}{}{}{}{}", leading_space(text), marker, trimmed, marker, trailing_space(text))
+}
+
+fn leading_space(text: &str) -> &'static str {
+    if text.starts_with(char::is_whitespace) { " " } else { "" }
+}
+
+fn trailing_space(text: &str) -> &'static str {
+    if text.ends_with(char::is_whitespace) { " " } else { "" }
+}
+
+/// 将连续空白（含换行）压缩为一个空格
+fn collapse_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut last_space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !last_space {
+                out.push(' ');
+            }
+            last_space = true;
+        } else {
+            out.push(c);
+            last_space = false;
+        }
+    }
+    out
+}
+
+/// 整理行内内容：`<br>` 产生的换行转为 Markdown 硬换行，每行去掉首尾和重复空格
+fn finish_inline(text: &str) -> String {
+    text.split('\n')
+        .map(|line| line.split(' ').filter(|word| !word.is_empty()).collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("  \n")
+}
+
+fn push_paragraph(blocks: &mut Vec<String>, inline: &str) {
+    let paragraph = finish_inline(inline);
+    if !paragraph.is_empty() {
+        blocks.push(paragraph);
+    }
+}
+
+/// 第一行加 `first` 前缀，其余行加 `rest` 前缀（空行不留尾随空格）
+fn prefix_markdown_lines(text: &str, first: &str, rest: &str) -> String {
+    text.split('\n')
+        .enumerate()
+        .map(|(i, line)| {
+            let prefix = if i == 0 { first } else { rest };
+            if line.is_empty() {
+                prefix.trim_end().to_string()
+            } else {
+                format!("{}{}", prefix, line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 /// 快速获取网页元数据（不进行完整内容提取）
-pub fn fetch_webpage_metadata(url: &str) -> Result<WebpageMetadata, WebReaderError> {
-    use scraper::{Html, Selector};
-    
+pub async fn fetch_webpage_metadata(url: &str) -> Result<WebpageMetadata, WebReaderError> {
     // 解析 URL
     let parsed_url = url::Url::parse(url)?;
     
     // 获取网页 HTML
-    let client = reqwest::blocking::Client::builder()
-        .user_agent("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
-        .timeout(std::time::Duration::from_secs(15))
-        .build()?;
-    
-    let response = client.get(url).send()?;
-    let html = response.text()?;
-    
-    let document = Html::parse_document(&html);
+    let html = fetch_html(url, METADATA_TIMEOUT).await?;
+    Ok(parse_webpage_metadata(&html, &parsed_url))
+}
+
+/// 从 HTML 中提取元数据（scraper 的文档不能跨 await 持有，单独放在同步函数中）
+fn parse_webpage_metadata(html: &str, parsed_url: &url::Url) -> WebpageMetadata {
+    use scraper::{Html, Selector};
+
+    let document = Html::parse_document(html);
     
     // 提取标题
     let title = extract_meta_content(&document, "og:title")
@@ -179,9 +765,7 @@ pub fn fetch_webpage_metadata(url: &str) -> Result<WebpageMetadata, WebReaderErr
         .unwrap_or_else(|| "Untitled".to_string());
     
     // 提取作者
-    let author = extract_meta_content(&document, "author")
-        .or_else(|| extract_meta_content(&document, "og:article:author"))
-        .or_else(|| extract_meta_content(&document, "twitter:creator"));
+    let author = extract_author(&document);
     
     // 提取站点名称
     let site_name = extract_meta_content(&document, "og:site_name")
@@ -193,15 +777,15 @@ pub fn fetch_webpage_metadata(url: &str) -> Result<WebpageMetadata, WebReaderErr
         .or_else(|| extract_meta_content(&document, "twitter:description"));
     
     // 提取 favicon
-    let favicon = extract_favicon(&document, &parsed_url);
+    let favicon = extract_favicon(&document, parsed_url);
     
-    Ok(WebpageMetadata {
+    WebpageMetadata {
         title,
         author,
         site_name,
         description,
         favicon,
-    })
+    }
 }
 
 /// 从 meta 标签提取内容
@@ -237,6 +821,77 @@ fn extract_meta_content(document: &scraper::Html, name: &str) -> Option<String>
     None
 }
 
+/// 提取作者：meta author / article:author，其次 JSON-LD 的 author，最后 og:article:author / twitter:creator
+fn extract_author(document: &scraper::Html) -> Option<String> {
+    extract_meta_content(document, "author")
+        .or_else(|| extract_meta_content(document, "article:author"))
+        .or_else(|| json_ld_field(document, "author").and_then(|author| json_ld_names(&author)))
+        .or_else(|| extract_meta_content(document, "og:article:author"))
+        .or_else(|| extract_meta_content(document, "twitter:creator"))
+}
+
+/// 提取发布时间：article:published_time，其次 JSON-LD 的 datePublished，最后 meta date
+fn extract_published_at(document: &scraper::Html) -> Option<i64> {
+    extract_meta_content(document, "article:published_time")
+        .and_then(|value| parse_published_at(&value))
+        .or_else(|| {
+            json_ld_field(document, "datePublished")
+                .and_then(|value| value.as_str().and_then(parse_published_at))
+        })
+        .or_else(|| extract_meta_content(document, "date").and_then(|value| parse_published_at(&value)))
+}
+
+/// 在页面所有 JSON-LD 块中查找第一个带有指定字段的对象（支持数组和 @graph）
+fn json_ld_field(document: &scraper::Html, field: &str) -> Option<serde_json::Value> {
+    fn find(value: &serde_json::Value, field: &str) -> Option<serde_json::Value> {
+        match value {
+            serde_json::Value::Object(map) => map
+                .get(field)
+                .cloned()
+                .or_else(|| map.get("@graph").and_then(|graph| find(graph, field))),
+            serde_json::Value::Array(items) => items.iter().find_map(|item| find(item, field)),
+            _ => None,
+        }
+    }
+
+    let selector = scraper::Selector::parse(r#"script[type="application/ld+json"]"#).ok()?;
+    document.select(&selector).find_map(|script| {
+        let json: serde_json::Value = serde_json::from_str(&script.text().collect::<String>()).ok()?;
+        find(&json, field)
+    })
+}
+
+/// JSON-LD 的 author 可以是字符串、{ name } 对象或它们的数组，多位作者用逗号连接
+fn json_ld_names(author: &serde_json::Value) -> Option<String> {
+    let names: Vec<String> = match author {
+        serde_json::Value::Array(items) => items.iter().filter_map(json_ld_names).collect(),
+        serde_json::Value::String(name) => vec![name.trim().to_string()],
+        serde_json::Value::Object(map) => map
+            .get("name")
+            .and_then(|name| name.as_str())
+            .map(|name| vec![name.trim().to_string()])
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    let names: Vec<String> = names.into_iter().filter(|name| !name.is_empty()).collect();
+    (!names.is_empty()).then(|| names.join(", "))
+}
+
+/// 解析发布时间（RFC 3339、不带时区的日期时间或 YYYY-MM-DD，后两者按 UTC 处理）
+fn parse_published_at(value: &str) -> Option<i64> {
+    let value = value.trim();
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Some(dt.timestamp_millis());
+    }
+    if let Ok(dt) = chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S") {
+        return Some(dt.and_utc().timestamp_millis());
+    }
+    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|dt| dt.and_utc().timestamp_millis())
+}
+
 /// 提取 favicon
 fn extract_favicon(document: &scraper::Html, base_url: &url::Url) -> Option<String> {
     use scraper::Selector;
@@ -287,5 +942,287 @@ mod tests {
         assert!(text.contains("标题"));
         assert!(text.contains("这是一段正文内容"));
     }
-}
 
+    #[test]
+    fn test_extract_html_language() {
+        let html = r#"<html lang="en_us"><body><p>Hello</p></body></html>"#;
+        assert_eq!(extract_html_language(html).as_deref(), Some("en-US"));
+        assert_eq!(extract_html_language("<html><body></body></html>"), None);
+    }
+
+    #[test]
+    fn test_is_pdf() {
+        let pdf_url = url::Url::parse("https://example.com/papers/attention-is-all.pdf").unwrap();
+        let page_url = url::Url::parse("https://example.com/article").unwrap();
+        assert!(is_pdf(Some("application/pdf; charset=binary"), &page_url));
+        assert!(is_pdf(Some("application/octet-stream"), &pdf_url));
+        assert!(is_pdf(None, &pdf_url));
+        assert!(!is_pdf(Some("text/html"), &pdf_url));
+        assert!(!is_pdf(None, &page_url));
+        assert_eq!(pdf_title(&pdf_url), "attention is all");
+    }
+
+    /// 在本地端口上启动只处理一个连接的 HTTP 服务，`response` 为 None 时接受连接但不回应
+    async fn mock_server(response: Option<&'static str>) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 1024];
+            let _ = socket.read(&mut request).await;
+            match response {
+                Some(body) => {
+                    let reply = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    socket.write_all(reply.as_bytes()).await.unwrap();
+                }
+                None => tokio::time::sleep(Duration::from_secs(10)).await,
+            }
+        });
+        format!("http://{}/article", addr)
+    }
+
+    /// 按请求路径返回 PNG 图片的 HTTP 服务（处理任意数量的连接）
+    async fn image_server() -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut request = [0u8; 1024];
+                    let n = socket.read(&mut request).await.unwrap_or(0);
+                    let path = String::from_utf8_lossy(&request[..n])
+                        .split_whitespace()
+                        .nth(1)
+                        .unwrap_or("/")
+                        .to_string();
+                    let body = format!("PNG:{}", path);
+                    let reply = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: image/png\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let _ = socket.write_all(reply.as_bytes()).await;
+                });
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_localize_snapshot_images() {
+        let server = image_server().await;
+        let vault = tempfile::tempdir().unwrap();
+        let dir = vault.path().join("attachments/web/snap");
+        let html = format!(
+            r#"<p>Intro</p><img src="{server}/img/a.png?x=1&amp;y=2" srcset="{server}/img/a@2x.png 2x"><figure><img src="/photos/b" alt="B"></figure><img src="data:image/png;base64,AAAA">"#
+        );
+
+        let localized = localize_images(&html, &format!("{}/post/1", server), &dir, "attachments/web/snap")
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(dir.join("1.png")).unwrap(), b"PNG:/img/a.png?x=1&y=2");
+        assert_eq!(std::fs::read(dir.join("2.png")).unwrap(), b"PNG:/photos/b");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+        assert!(localized.contains(r#"src="attachments/web/snap/1.png""#));
+        assert!(localized.contains(r#"src="attachments/web/snap/2.png""#));
+        assert!(localized.contains(r#"src="data:image/png;base64,AAAA""#));
+        assert!(!localized.contains("srcset"));
+        assert!(!localized.contains(&server));
+
+        // 超过总大小上限的图片保留远程地址
+        let capped_dir = vault.path().join("attachments/web/capped");
+        let capped = localize_images_with_limit(&html, &format!("{}/post/1", server), &capped_dir, "local", 30)
+            .await
+            .unwrap();
+        assert!(capped.contains(r#"src="local/1.png""#));
+        assert!(capped.contains(r#"src="/photos/b""#));
+        assert_eq!(std::fs::read_dir(&capped_dir).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_webpage_metadata_from_mock_server() {
+        let url = mock_server(Some(
+            r#"<html><head><title>Fallback</title><meta property="og:title" content="Async Fetching"><meta name="description" content="No blocking"></head><body></body></html>"#,
+        ))
+        .await;
+        let metadata = fetch_webpage_metadata(&url).await.unwrap();
+        assert_eq!(metadata.title, "Async Fetching");
+        assert_eq!(metadata.description.as_deref(), Some("No blocking"));
+        assert_eq!(metadata.site_name.as_deref(), Some("127.0.0.1"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_html_respects_timeout() {
+        let url = mock_server(None).await;
+        let started = std::time::Instant::now();
+        let err = fetch_html(&url, Duration::from_millis(200)).await.unwrap_err();
+        assert!(matches!(err, WebReaderError::NetworkError(ref e) if e.is_timeout()));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_normalize_url_variants() {
+        let canonical = normalize_url("https://example.com/post/1");
+        for variant in [
+            "https://Example.com/post/1/",
+            "https://example.com/post/1#comments",
+            "https://example.com/post/1?utm_source=feed&utm_medium=rss",
+            "https://example.com/post/1/?fbclid=abc&UTM_Campaign=x#top",
+            "  https://example.com/post/1  ",
+        ] {
+            assert_eq!(normalize_url(variant), canonical, "{}", variant);
+        }
+
+        // 有意义的参数保留，去掉跟踪参数后仍与同参数的地址一致
+        let with_id = normalize_url("https://example.com/post?id=2&utm_campaign=z");
+        assert_eq!(with_id, normalize_url("https://example.com/post/?id=2"));
+        assert_ne!(with_id, normalize_url("https://example.com/post?id=3"));
+        assert_eq!(normalize_url("https://example.com/"), "https://example.com/");
+        assert_eq!(normalize_url("not a url"), "not a url");
+    }
+
+    #[test]
+    fn test_extract_author_and_published_at() {
+        let html = r#"
+            <html><head>
+                <title>Post</title>
+                <script type="application/ld+json">{"@context": "https://schema.org", "@type": "WebSite", "name": "Blog"}</script>
+                <script type="application/ld+json">
+                    {"@context": "https://schema.org", "@graph": [
+                        {"@type": "BlogPosting", "headline": "Post",
+                         "author": [{"@type": "Person", "name": "Ada Lovelace"}, {"@type": "Person", "name": "Charles Babbage"}],
+                         "datePublished": "2024-03-05T08:30:00+08:00"}
+                    ]}
+                </script>
+                <meta name="twitter:creator" content="@someone">
+            </head><body><p>Body</p></body></html>
+        "#;
+        let document = scraper::Html::parse_document(html);
+        assert_eq!(extract_author(&document).as_deref(), Some("Ada Lovelace, Charles Babbage"));
+        assert_eq!(
+            extract_published_at(&document),
+            Some(chrono::DateTime::parse_from_rfc3339("2024-03-05T00:30:00Z").unwrap().timestamp_millis())
+        );
+
+        // meta 标签优先于 JSON-LD
+        let html = r#"<html><head>
+            <meta property="article:author" content="Grace Hopper">
+            <meta property="article:published_time" content="2023-01-02">
+            <script type="application/ld+json">{"author": "Someone Else", "datePublished": "2020-01-01"}</script>
+        </head><body></body></html>"#;
+        let document = scraper::Html::parse_document(html);
+        assert_eq!(extract_author(&document).as_deref(), Some("Grace Hopper"));
+        assert_eq!(extract_published_at(&document), parse_published_at("2023-01-02T00:00:00"));
+
+        let document = scraper::Html::parse_document("<html><head><meta name=\"twitter:creator\" content=\"@handle\"></head></html>");
+        assert_eq!(extract_author(&document).as_deref(), Some("@handle"));
+        assert_eq!(extract_published_at(&document), None);
+    }
+
+    #[test]
+    fn test_html_to_markdown() {
+        let html = r#"
+            <article>
+                <h2>Getting <em>Started</em></h2>
+                <p>Read the <a href="https://example.com/docs">official <strong>docs</strong></a> first.<br>Then continue.</p>
+                <ul>
+                    <li>Install
+                        <ol><li>Download</li><li>Run <code>setup</code></li></ol>
+                    </li>
+                    <li>Configure</li>
+                </ul>
+                <p><img src="/img/diagram.png" alt="Diagram"></p>
+                <pre><code class="language-rust">fn main() {
+    println!("hi");
+}</code></pre>
+                <blockquote><p>Quoted text</p></blockquote>
+                <script>track()</script>
+                <p>Broken <b>markup
+            </article>
+        "#;
+        assert_eq!(
+            html_to_markdown(html),
+            "## Getting *Started*\n\n\
+             Read the [official **docs**](https://example.com/docs) first.  \nThen continue.\n\n\
+             - Install\n  1. Download\n  2. Run `setup`\n- Configure\n\n\
+             ![Diagram](/img/diagram.png)\n\n\
+             ```rust\nfn main() {\n    println!(\"hi\");\n}\n```\n\n\
+             > Quoted text\n\n\
+             Broken **markup**\n"
+        );
+        assert_eq!(html_to_markdown(""), "");
+    }
+
+    const CLUTTERED: &str = r##"
+        <nav><a href="/">首页</a></nav>
+        <header><h2>站点名称</h2></header>
+        <article>
+            <h1>正文标题</h1>
+            <p>第一段正文。</p>
+            <div class="post-share-bar"><a href="#">分享到微博</a></div>
+            <figure><img src="a.png"><figcaption>图片说明</figcaption></figure>
+            <p>第二段正文。</p>
+            <form><input type="email"><button>订阅</button></form>
+            <section class="newsletter-signup"><p>订阅我们的周报</p></section>
+            <ul class="related-posts"><li>相关文章</li></ul>
+        </article>
+        <aside>侧栏广告</aside>
+        <footer>版权所有</footer>
+        <script>track()</script>
+    "##;
+
+    #[test]
+    fn test_simplify_levels() {
+        let light = simplify(CLUTTERED, SimplifyLevel::Light);
+        assert!(light.contains("第一段正文") && light.contains("第二段正文"));
+        assert!(!light.contains("首页"));
+        assert!(!light.contains("侧栏广告"));
+        assert!(!light.contains("<form"));
+        assert!(!light.contains("track()"));
+        assert!(light.contains("分享到微博"));
+
+        let normal = simplify(CLUTTERED, SimplifyLevel::Normal);
+        assert!(normal.contains("第二段正文"));
+        assert!(!normal.contains("分享到微博"));
+        assert!(!normal.contains("订阅我们的周报"));
+        assert!(!normal.contains("相关文章"));
+        assert!(normal.contains("版权所有"));
+
+        let aggressive = simplify(CLUTTERED, SimplifyLevel::Aggressive);
+        assert!(aggressive.contains("正文标题") && aggressive.contains("第一段正文"));
+        assert!(aggressive.contains("<img"));
+        assert!(!aggressive.contains("版权所有"));
+        assert!(!aggressive.contains("站点名称"));
+        assert!(!aggressive.contains("图片说明"));
+    }
+
+    #[test]
+    fn test_simplify_result_recounts_words() {
+        let result = FetchResult {
+            title: "标题".to_string(),
+            author: None,
+            site_name: None,
+            content: CLUTTERED.to_string(),
+            text_content: extract_text_from_html(CLUTTERED),
+            excerpt: None,
+            word_count: 0,
+            language: None,
+            published_at: None,
+        };
+        let simplified = simplify_result(result, SimplifyLevel::Aggressive);
+        assert!(!simplified.text_content.contains("版权所有"));
+        assert_eq!(
+            simplified.word_count,
+            crate::search::count_segmented_words(&simplified.text_content)
+        );
+        assert_eq!(simplified.title, "标题");
+    }
+}
@@ -15,6 +15,16 @@ export interface ServerStatus {
   model_path: string | null;
 }
 
+/**
+ * 模型下载进度（`model-download-progress` 事件，约每 250ms 一次）
+ */
+export interface ModelDownloadProgress {
+  model_id: string;
+  downloaded: number;
+  total: number;
+  percent: number;
+}
+
 export interface ModelInfo {
   id: string;
   name: string;
@@ -71,6 +81,21 @@ export async function downloadModel(modelId: string): Promise<string> {
   return await safeInvoke<string>("ai_download_model", { modelId });
 }
 
+/**
+ * 取消进行中的模型下载，已下载的部分保留，再次下载时续传
+ * @returns 是否有进行中的下载被取消
+ */
+export async function cancelDownload(modelId: string): Promise<boolean> {
+  return await safeInvoke<boolean>("ai_cancel_download", { modelId });
+}
+
+/**
+ * 重新校验已下载的模型文件（大小和 SHA-256），校验失败时抛出错误
+ */
+export async function verifyModel(modelId: string): Promise<void> {
+  return await safeInvoke<void>("ai_verify_model", { modelId });
+}
+
 /**
  * 设置活动模型
  */
@@ -2,7 +2,7 @@
  * Card API 模块
  */
 import { invoke } from "@tauri-apps/api/core";
-import type { CardFull, UpdateCardRequest } from "./types";
+import type { CardFull, CardTemplate, UpdateCardRequest } from "./types";
 import type { Card, CardType } from "@/types";
 
 /**
@@ -27,16 +27,25 @@ export async function get(id: string): Promise<Card | null> {
 export async function create(
   cardType: CardType,
   title: string,
-  sourceId?: string
+  sourceId?: string,
+  templateId?: string
 ): Promise<Card> {
   const card = await invoke<CardFull>("create_card", {
     cardType,
     title,
     sourceId,
+    templateId,
   });
   return normalizeCardFull(card);
 }
 
+/**
+ * 列出可用的卡片模板
+ */
+export async function listTemplates(): Promise<CardTemplate[]> {
+  return invoke<CardTemplate[]>("list_templates");
+}
+
 /**
  * 更新卡片
  */
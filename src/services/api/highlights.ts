@@ -77,6 +77,8 @@ export interface SourceBacklink {
   highlightContent: string;
   page?: number;
   cfi?: string;
+  /** 笔记正文中引用处所在段落的上下文 */
+  context?: string;
 }
 
 /**
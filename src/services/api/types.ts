@@ -23,6 +23,13 @@ export interface CardFull extends CardListItem {
   content: string;
 }
 
+export interface CardTemplate {
+  id: string;
+  name: string;
+  /** 是否为未被用户模板覆盖的内置模板 */
+  builtin: boolean;
+}
+
 export interface UpdateCardRequest {
   title?: string;
   content?: string;
@@ -115,7 +122,7 @@ export interface GraphNode {
 
 export interface GraphData {
   nodes: GraphNode[];
-  links: Array<{ source: string; target: string }>;
+  links: Array<{ source: string; target: string; weight: number }>; // weight: 两卡片间的链接总数
   clusterCount: number;    // 连通分量数量
   orphanCount: number;     // 孤立节点数量
 }
@@ -15,6 +15,7 @@ export interface FetchResult {
   textContent: string;    // 纯文本
   excerpt?: string;
   wordCount: number;
+  publishedAt?: number; // 文章发布时间（毫秒）
 }
 
 /**
@@ -48,15 +49,40 @@ export async function fetchWebpageMetadata(url: string): Promise<WebpageMetadata
 export async function saveSnapshot(
   sourceId: string,
   url: string,
-  fetchResult: FetchResult
+  fetchResult: FetchResult,
+  downloadImages?: boolean,
+  forceNew?: boolean
 ): Promise<WebSnapshot> {
   return await invoke<WebSnapshot>("save_web_snapshot", {
     sourceId,
     url,
     fetchResult,
+    downloadImages,
+    forceNew,
   });
 }
 
+/**
+ * 按网页地址查找已保存的快照（忽略跟踪参数、片段和末尾斜杠）
+ */
+export async function findSnapshotByUrl(url: string): Promise<WebSnapshot | null> {
+  return await invoke<WebSnapshot | null>("find_web_snapshot_by_url", { url });
+}
+
+/**
+ * 获取估算阅读时间使用的阅读速度（词/分钟）
+ */
+export async function getReadingSpeed(): Promise<number> {
+  return await invoke<number>("get_reading_speed");
+}
+
+/**
+ * 设置阅读速度（词/分钟），只影响之后保存的网页
+ */
+export async function setReadingSpeed(wordsPerMinute: number): Promise<void> {
+  await invoke("set_reading_speed", { wordsPerMinute });
+}
+
 /**
  * 获取网页快照
  */
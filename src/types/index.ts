@@ -61,6 +61,8 @@ export interface Source {
     lastPage?: number; // 上次阅读到的页码（向后兼容，新数据优先使用 lastCfi）
     lastCfi?: string; // 精确位置标识（CFI 或等效），用于精确恢复阅读位置
     duration?: number; // 视频/播客时长（秒）
+    wordCount?: number; // 正文词数（目前仅网页）
+    readingMinutes?: number; // 预计阅读时间（分钟）
   };
   // 关联的文献笔记 ID 列表
   noteIds: string[];
@@ -116,6 +118,9 @@ export interface WebSnapshot {
   content: string; // 清洗后的 HTML 内容
   textContent: string; // 纯文本内容（用于搜索）
   excerpt?: string;
+  publishedAt?: number; // 文章发布时间（毫秒）
+  wordCount: number; // 正文词数（中文按分词计）
+  readingMinutes: number; // 预计阅读时间（分钟）
   createdAt: number;
 }
 